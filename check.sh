@@ -2,6 +2,10 @@
 # This scripts runs various CI-like checks in a convenient way.
 set -eux
 
+# Everything after this runs offline, from the versions in Cargo.lock
+cargo fetch --locked
+export CARGO_NET_OFFLINE=true
+
 cargo check --quiet --workspace --all-targets
 cargo check --quiet --workspace --all-features --lib --target wasm32-unknown-unknown
 cargo fmt --all -- --check
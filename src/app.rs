@@ -46,6 +46,9 @@ impl App {
             user_config,
             ..Default::default()
         };
+        if let Some(session) = &args.session {
+            model.actions.push(Action::LoadSession(session.clone()));
+        }
         let mut open_files = |files: &[ReadConfig]| {
             for file_read_config in files {
                 model
@@ -63,9 +66,24 @@ impl App {
                     trace!("Open command");
                     open_files(files);
                 }
-                args::Commands::Diff { file1, file2 } => {
+                args::Commands::Diff { file1, file2, .. } => {
                     trace!("Diff command");
-                    // todo!("diff files");
+                    model.actions.push(Action::OpenDiff {
+                        file_a: file1.clone(),
+                        file_b: file2.clone(),
+                    });
+                }
+                args::Commands::Info { .. } => {
+                    // Handled in main without starting the app
+                    trace!("Info command");
+                }
+                args::Commands::Export { .. } => {
+                    // Handled in main without starting the app
+                    trace!("Export command");
+                }
+                args::Commands::Render { .. } => {
+                    // Handled in main without starting the app
+                    trace!("Render command");
                 }
             },
         }
@@ -3,8 +3,13 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 use crate::{
-    audio::sample,
-    wav::{self, ReadConfig},
+    audio::sample::{
+        self,
+        convert::{NoiseShaping, RequantizeConfig, Rounding},
+    },
+    model::ruler::TimeUnit,
+    render::RenderConfig,
+    wav::{self, ReadConfig, write::WavFormat},
 };
 
 /// Command line arguments for the application.
@@ -34,6 +39,10 @@ pub struct Args {
         default_value = None
     )]
     pub files: Vec<ReadConfig>,
+
+    /// Session file to restore, with its files, tracks, zoom and selection
+    #[arg(long)]
+    pub session: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug, PartialEq)]
@@ -53,9 +62,168 @@ pub enum Commands {
         /// Second file to compare
         #[arg(value_parser = clap::value_parser!(ReadConfig))]
         file2: ReadConfig,
+
+        #[command(flatten)]
+        report: DiffReportArgs,
+    },
+    /// Print information about one or more WAV files, without opening a window
+    Info {
+        /// Files to describe
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Print the information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write channels and a sample range of a WAV file to a new WAV file, without opening a
+    /// window
+    Export {
+        /// File to export, with optional channel and range specifications.
+        /// The channels are written in the given order.
+        #[arg(value_parser = clap::value_parser!(ReadConfig))]
+        file: ReadConfig,
+
+        /// Path of the new file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Sample format of the new file. Default: the format of the input file
+        #[arg(long, value_enum)]
+        format: Option<WavFormat>,
+
+        #[command(flatten)]
+        requantize: RequantizeArgs,
+    },
+    /// Draw the waveforms of WAV files with time and value rulers to a PNG or SVG image, without
+    /// opening a window
+    Render {
+        /// Files to draw, with optional channel and range specifications.
+        /// All channels are drawn below each other.
+        #[arg(required = true, value_parser = clap::value_parser!(ReadConfig))]
+        files: Vec<ReadConfig>,
+
+        /// Path of the image, the extension .png or .svg picks the format
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        render: RenderArgs,
     },
 }
 
+/// Size and ranges of a rendered image.
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub struct RenderArgs {
+    /// Width of the image in pixels
+    #[arg(long, default_value_t = 1200)]
+    pub width: u32,
+
+    /// Height of the image in pixels
+    #[arg(long, default_value_t = 600)]
+    pub height: u32,
+
+    /// Start of the drawn time range in seconds. Default: the start of the files
+    #[arg(long, allow_negative_numbers = true)]
+    pub start: Option<f64>,
+
+    /// End of the drawn time range in seconds. Default: the end of the longest channel
+    #[arg(long)]
+    pub end: Option<f64>,
+
+    /// Lowest drawn sample value, full scale is -1.0
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    pub min_value: f64,
+
+    /// Highest drawn sample value, full scale is 1.0
+    #[arg(long, default_value_t = 1.0, allow_negative_numbers = true)]
+    pub max_value: f64,
+
+    /// Unit of the time ruler
+    #[arg(long, value_enum, default_value_t = TimeUnit::Samples)]
+    pub unit: TimeUnit,
+
+    /// Use the colours of the light theme
+    #[arg(long)]
+    pub light: bool,
+}
+
+impl From<&RenderArgs> for RenderConfig {
+    fn from(args: &RenderArgs) -> Self {
+        Self {
+            width: args.width,
+            height: args.height,
+            start_s: args.start,
+            end_s: args.end,
+            val_rng: sample::ValRange {
+                min: args.min_value,
+                max: args.max_value,
+            },
+            time_unit: args.unit,
+            light: args.light,
+        }
+    }
+}
+
+/// How samples are requantized when exporting to an int format with a lower bit depth.
+#[derive(clap::Args, Debug, Default, Clone, Copy, PartialEq)]
+pub struct RequantizeArgs {
+    /// Rounding to the lower bit depth
+    #[arg(long, value_enum, default_value_t = Rounding::Round)]
+    pub rounding: Rounding,
+
+    /// Noise shaping filter for the requantization error
+    #[arg(long, value_enum, default_value_t = NoiseShaping::None)]
+    pub noise_shaping: NoiseShaping,
+
+    /// Seed of the dither, the same seed gives the same file
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+impl From<&RequantizeArgs> for RequantizeConfig {
+    fn from(args: &RequantizeArgs) -> Self {
+        Self {
+            rounding: args.rounding,
+            noise_shaping: args.noise_shaping,
+            seed: args.seed,
+        }
+    }
+}
+
+/// Options for comparing two files without opening a window.
+#[derive(clap::Args, Debug, Default, PartialEq)]
+pub struct DiffReportArgs {
+    /// Print a report per channel pair instead of opening a window.
+    /// Exits with 1 when the files differ more than the tolerance, 2 on errors.
+    #[arg(long)]
+    pub report: bool,
+
+    /// Output format of the report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text, requires = "report")]
+    pub format: ReportFormat,
+
+    /// Max allowed absolute difference in LSBs, used when both files are int
+    #[arg(long, default_value_t = 0.0, requires = "report")]
+    pub tolerance_lsb: f64,
+
+    /// Max allowed absolute difference in dBFS, used when a file is float.
+    /// Default: no difference allowed
+    #[arg(long, allow_negative_numbers = true, requires = "report")]
+    pub tolerance_db: Option<f64>,
+
+    /// Min required SNR in dB, with the first file as the signal
+    #[arg(long, allow_negative_numbers = true, requires = "report")]
+    pub min_snr_db: Option<f64>,
+}
+
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 fn parse_sample_ix_range(s: &str) -> Result<sample::OptIxRange> {
     let parse_bound = |s: &str| -> Result<Option<sample::Ix>> {
         if s.is_empty() {
@@ -154,6 +322,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -171,6 +340,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -188,6 +358,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -209,6 +380,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -230,6 +402,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -251,6 +424,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -272,6 +446,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -293,6 +468,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
@@ -310,6 +486,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: Some(Commands::Open {
                     files: vec![
                         ReadConfig {
@@ -339,6 +516,7 @@ mod tests {
             args,
             Args {
                 log_level: None,
+                session: None,
                 command: Some(Commands::Diff {
                     file1: ReadConfig {
                         filepath: PathBuf::from("file1.wav"),
@@ -350,12 +528,183 @@ mod tests {
                         ch_ixs: Some(vec![1]),
                         sample_range: sample::OptIxRange::default(),
                     },
+                    report: DiffReportArgs::default(),
                 }),
                 files: vec![]
             }
         );
     }
 
+    #[test]
+    fn test_parse_args_diff_report() {
+        let args = Args::parse_from([
+            "wavalyze",
+            "diff",
+            "out.wav",
+            "golden.wav",
+            "--report",
+            "--format",
+            "json",
+            "--tolerance-lsb",
+            "1",
+            "--tolerance-db",
+            "-120",
+        ]);
+        let Some(Commands::Diff { report, .. }) = args.command else {
+            panic!("expected diff command");
+        };
+        assert_eq!(
+            report,
+            DiffReportArgs {
+                report: true,
+                format: ReportFormat::Json,
+                tolerance_lsb: 1.0,
+                tolerance_db: Some(-120.0),
+                min_snr_db: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_args_diff_tolerance_requires_report() {
+        for (flag, value) in [
+            ("--format", "json"),
+            ("--tolerance-lsb", "1"),
+            ("--tolerance-db", "1"),
+            ("--min-snr-db", "1"),
+        ] {
+            let args = ["wavalyze", "diff", "out.wav", "golden.wav", flag, value];
+            assert!(Args::try_parse_from(args).is_err(), "{flag}");
+        }
+        assert!(Args::try_parse_from(["wavalyze", "diff", "out.wav", "golden.wav"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_args_info_json() {
+        let args = Args::parse_from(["wavalyze", "info", "a.wav", "b.wav", "--json"]);
+        assert_eq!(
+            args.command,
+            Some(Commands::Info {
+                files: vec![PathBuf::from("a.wav"), PathBuf::from("b.wav")],
+                json: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_args_session() {
+        let args = Args::parse_from(["wavalyze", "--session", "debug.toml", "extra.wav"]);
+        assert_eq!(args.session, Some(PathBuf::from("debug.toml")));
+        assert_eq!(args.files, vec![ReadConfig::new("extra.wav")]);
+    }
+
+    #[test]
+    fn test_parse_args_info_requires_file() {
+        assert!(Args::try_parse_from(["wavalyze", "info"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_export() {
+        let args = Args::parse_from([
+            "wavalyze",
+            "export",
+            "capture.wav:1,0:48000-96000",
+            "-o",
+            "cut.wav",
+            "--format",
+            "int16",
+        ]);
+        assert_eq!(
+            args.command,
+            Some(Commands::Export {
+                file: ReadConfig {
+                    filepath: PathBuf::from("capture.wav"),
+                    ch_ixs: Some(vec![1, 0]),
+                    sample_range: sample::OptIxRange {
+                        start: Some(48000),
+                        end: Some(96000),
+                    },
+                },
+                output: PathBuf::from("cut.wav"),
+                format: Some(WavFormat::Int16),
+                requantize: RequantizeArgs::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_args_export_dither() {
+        let args = Args::parse_from([
+            "wavalyze",
+            "export",
+            "master.wav",
+            "-o",
+            "vector.wav",
+            "--format",
+            "int16",
+            "--rounding",
+            "tpdf",
+            "--noise-shaping",
+            "second-order",
+            "--seed",
+            "42",
+        ]);
+        let Some(Commands::Export { requantize, .. }) = args.command else {
+            panic!("expected export command");
+        };
+        assert_eq!(
+            RequantizeConfig::from(&requantize),
+            RequantizeConfig {
+                rounding: Rounding::Tpdf,
+                noise_shaping: NoiseShaping::SecondOrder,
+                seed: 42,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_args_render() {
+        let args = Args::parse_from([
+            "wavalyze",
+            "render",
+            "a.wav:0",
+            "b.wav",
+            "-o",
+            "plot.png",
+            "--width",
+            "800",
+            "--start",
+            "0.5",
+            "--min-value",
+            "-0.25",
+            "--unit",
+            "seconds",
+        ]);
+        let Some(Commands::Render {
+            files,
+            output,
+            render,
+        }) = args.command
+        else {
+            panic!("expected render command");
+        };
+        assert_eq!(files.len(), 2);
+        assert_eq!(output, PathBuf::from("plot.png"));
+        assert_eq!(
+            RenderConfig::from(&render),
+            RenderConfig {
+                width: 800,
+                start_s: Some(0.5),
+                val_rng: sample::ValRange {
+                    min: -0.25,
+                    max: 1.0,
+                },
+                time_unit: TimeUnit::Seconds,
+                ..Default::default()
+            }
+        );
+    }
+
     #[test]
     fn test_parse_invalid_range_reversed() {
         let result = Args::try_parse_from(["wavalyze", "song.wav:5000-1000"]);
@@ -381,6 +730,7 @@ mod tests {
             args,
             Args {
                 log_level: Some("debug".to_string()),
+                session: None,
                 command: None,
                 files: vec![ReadConfig {
                     filepath: PathBuf::from("song.wav"),
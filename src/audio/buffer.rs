@@ -14,6 +14,7 @@ pub struct Buffer<T: Sample> {
 #[derive(Debug, PartialEq, Clone)]
 pub enum BufferE {
     F32(Buffer<f32>),
+    F64(Buffer<f64>),
     I32(Buffer<i32>),
     I16(Buffer<i16>),
 }
@@ -52,6 +53,11 @@ impl<T: Sample> Buffer<T> {
         self.data.len()
     }
 
+    /// Memory held by the samples
+    pub fn size_in_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<T>()
+    }
+
     pub fn duration_s(&self) -> f64 {
         self.data.len() as f64 / self.sample_rate as f64
     }
@@ -96,6 +102,83 @@ impl<T: Sample> Buffer<T> {
     }
 }
 
+impl BufferE {
+    pub fn sample_rate(&self) -> u32 {
+        match self {
+            BufferE::F32(buffer) => buffer.sample_rate,
+            BufferE::F64(buffer) => buffer.sample_rate,
+            BufferE::I32(buffer) => buffer.sample_rate,
+            BufferE::I16(buffer) => buffer.sample_rate,
+        }
+    }
+
+    pub fn bit_depth(&self) -> u16 {
+        match self {
+            BufferE::F32(buffer) => buffer.bit_depth,
+            BufferE::F64(buffer) => buffer.bit_depth,
+            BufferE::I32(buffer) => buffer.bit_depth,
+            BufferE::I16(buffer) => buffer.bit_depth,
+        }
+    }
+
+    pub fn nr_samples(&self) -> usize {
+        match self {
+            BufferE::F32(buffer) => buffer.nr_samples(),
+            BufferE::F64(buffer) => buffer.nr_samples(),
+            BufferE::I32(buffer) => buffer.nr_samples(),
+            BufferE::I16(buffer) => buffer.nr_samples(),
+        }
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            BufferE::F32(buffer) => buffer.size_in_bytes(),
+            BufferE::F64(buffer) => buffer.size_in_bytes(),
+            BufferE::I32(buffer) => buffer.size_in_bytes(),
+            BufferE::I16(buffer) => buffer.size_in_bytes(),
+        }
+    }
+
+    /// Buffer with the sample rate, bit depth and sample type of this one, but no samples
+    pub fn without_samples(&self) -> Self {
+        match self {
+            BufferE::F32(buffer) => BufferE::F32(Buffer::new(buffer.sample_rate, buffer.bit_depth)),
+            BufferE::F64(buffer) => BufferE::F64(Buffer::new(buffer.sample_rate, buffer.bit_depth)),
+            BufferE::I32(buffer) => BufferE::I32(Buffer::new(buffer.sample_rate, buffer.bit_depth)),
+            BufferE::I16(buffer) => BufferE::I16(Buffer::new(buffer.sample_rate, buffer.bit_depth)),
+        }
+    }
+
+    /// Sample value at `sample_ix` normalized to [-1.0, 1.0] full scale
+    pub fn norm_value(&self, sample_ix: usize) -> Option<f64> {
+        match self {
+            BufferE::F32(buffer) => buffer.get(sample_ix).map(|v| v.to_norm(buffer.bit_depth)),
+            BufferE::F64(buffer) => buffer.get(sample_ix).map(|v| v.to_norm(buffer.bit_depth)),
+            BufferE::I32(buffer) => buffer.get(sample_ix).map(|v| v.to_norm(buffer.bit_depth)),
+            BufferE::I16(buffer) => buffer.get(sample_ix).map(|v| v.to_norm(buffer.bit_depth)),
+        }
+    }
+
+    /// Sample values in `ix_rng` normalized to [-1.0, 1.0] full scale, the range is clamped to
+    /// the buffer
+    pub fn norm_values(&self, ix_rng: std::ops::Range<usize>) -> Vec<f64> {
+        fn norm_values<T: Sample>(buffer: &Buffer<T>, ix_rng: std::ops::Range<usize>) -> Vec<f64> {
+            let end = ix_rng.end.min(buffer.len());
+            let start = ix_rng.start.min(end);
+            buffer[start..end]
+                .iter()
+                .map(|v| v.to_norm(buffer.bit_depth))
+                .collect()
+        }
+        match self {
+            BufferE::F32(buffer) => norm_values(buffer, ix_rng),
+            BufferE::F64(buffer) => norm_values(buffer, ix_rng),
+            BufferE::I32(buffer) => norm_values(buffer, ix_rng),
+            BufferE::I16(buffer) => norm_values(buffer, ix_rng),
+        }
+    }
+}
+
 /// Use deref to access the underlying buffer
 /// Impies Indexing and iterator support (not IntoIterator! Deref takes a reference)
 impl<T: Sample> Deref for Buffer<T> {
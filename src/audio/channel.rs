@@ -1,5 +1,6 @@
 pub mod id;
 pub mod layout;
+pub mod mask;
 
 pub use super::channel::id::Id;
 pub use super::channel::layout::Layout;
@@ -0,0 +1,143 @@
+//! Mapping of the WAVE_FORMAT_EXTENSIBLE `dwChannelMask` speaker positions to our channel ids.
+
+use super::{Id, Layout};
+
+pub const SPEAKER_FRONT_LEFT: u32 = 0x1;
+pub const SPEAKER_FRONT_RIGHT: u32 = 0x2;
+pub const SPEAKER_FRONT_CENTER: u32 = 0x4;
+pub const SPEAKER_LOW_FREQUENCY: u32 = 0x8;
+pub const SPEAKER_BACK_LEFT: u32 = 0x10;
+pub const SPEAKER_BACK_RIGHT: u32 = 0x20;
+pub const SPEAKER_FRONT_LEFT_OF_CENTER: u32 = 0x40;
+pub const SPEAKER_FRONT_RIGHT_OF_CENTER: u32 = 0x80;
+pub const SPEAKER_BACK_CENTER: u32 = 0x100;
+pub const SPEAKER_SIDE_LEFT: u32 = 0x200;
+pub const SPEAKER_SIDE_RIGHT: u32 = 0x400;
+pub const SPEAKER_TOP_CENTER: u32 = 0x800;
+pub const SPEAKER_TOP_FRONT_LEFT: u32 = 0x1000;
+pub const SPEAKER_TOP_FRONT_CENTER: u32 = 0x2000;
+pub const SPEAKER_TOP_FRONT_RIGHT: u32 = 0x4000;
+pub const SPEAKER_TOP_BACK_LEFT: u32 = 0x8000;
+pub const SPEAKER_TOP_BACK_CENTER: u32 = 0x10000;
+pub const SPEAKER_TOP_BACK_RIGHT: u32 = 0x20000;
+
+/// Speaker position of a single WAVE channel mask bit.
+///
+/// NOTE: WAVE 'back' speakers are our surrounds when the mask has no 'side' speakers, e.g. 5.1
+/// with mask `0x3F`. When both are present (7.1, `0x63F`), sides are surrounds and backs are backs.
+fn id_from_speaker_bit(bit: u32, has_sides: bool) -> Option<Id> {
+    let id = match bit {
+        SPEAKER_FRONT_LEFT => Id::Left,
+        SPEAKER_FRONT_RIGHT => Id::Right,
+        SPEAKER_FRONT_CENTER => Id::Center,
+        SPEAKER_LOW_FREQUENCY => Id::LFE,
+        SPEAKER_BACK_LEFT if has_sides => Id::LeftBack,
+        SPEAKER_BACK_LEFT => Id::LeftSurround,
+        SPEAKER_BACK_RIGHT if has_sides => Id::RightBack,
+        SPEAKER_BACK_RIGHT => Id::RightSurround,
+        SPEAKER_FRONT_LEFT_OF_CENTER => Id::LeftCenter,
+        SPEAKER_FRONT_RIGHT_OF_CENTER => Id::RightCenter,
+        SPEAKER_BACK_CENTER => Id::CenterSurround,
+        SPEAKER_SIDE_LEFT => Id::LeftSurround,
+        SPEAKER_SIDE_RIGHT => Id::RightSurround,
+        SPEAKER_TOP_CENTER => Id::Top,
+        SPEAKER_TOP_FRONT_LEFT => Id::HeightLeft,
+        SPEAKER_TOP_FRONT_CENTER => Id::HeightCenter,
+        SPEAKER_TOP_FRONT_RIGHT => Id::HeightRight,
+        SPEAKER_TOP_BACK_LEFT => Id::HeightLeftSurround,
+        SPEAKER_TOP_BACK_CENTER => Id::HeightCenterSurround,
+        SPEAKER_TOP_BACK_RIGHT => Id::HeightRightSurround,
+        _ => return None,
+    };
+    Some(id)
+}
+
+/// Channel ids in file channel order for a WAVE channel mask.
+///
+/// Channels are stored in order of increasing mask bit. Reserved bits (like SPEAKER_ALL) are
+/// ignored, so the result can hold fewer ids than the number of set bits.
+pub fn ids_from_wave_channel_mask(channel_mask: u32) -> Vec<Id> {
+    let has_sides = channel_mask & (SPEAKER_SIDE_LEFT | SPEAKER_SIDE_RIGHT) != 0;
+    (0..u32::BITS)
+        .map(|bit_ix| 1u32 << bit_ix)
+        .filter(|bit| channel_mask & bit != 0)
+        .map_while(|bit| id_from_speaker_bit(bit, has_sides))
+        .collect()
+}
+
+impl Layout {
+    pub fn from_ids<'a>(ids: impl IntoIterator<Item = &'a Id>) -> Self {
+        ids.into_iter()
+            .fold(Layout::NONE, |layout, id| layout | Layout::from(*id))
+    }
+}
+
+impl From<Id> for Layout {
+    fn from(id: Id) -> Self {
+        Layout::from_bits_truncate(1 << id as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereo_mask() {
+        assert_eq!(ids_from_wave_channel_mask(0x3), vec![Id::Left, Id::Right]);
+        assert_eq!(
+            Layout::from_ids(&ids_from_wave_channel_mask(0x3)),
+            Layout::LAYOUT_2_0
+        );
+    }
+
+    #[test]
+    fn back_speakers_are_surrounds_without_sides() {
+        let ids = ids_from_wave_channel_mask(0x3F);
+        assert_eq!(
+            ids,
+            vec![
+                Id::Left,
+                Id::Right,
+                Id::Center,
+                Id::LFE,
+                Id::LeftSurround,
+                Id::RightSurround
+            ]
+        );
+        assert_eq!(Layout::from_ids(&ids), Layout::LAYOUT_5_1);
+        assert_eq!(
+            Layout::from_ids(&ids_from_wave_channel_mask(0x60F)),
+            Layout::LAYOUT_5_1
+        );
+    }
+
+    #[test]
+    fn seven_one_four_mask() {
+        let ids = ids_from_wave_channel_mask(0x2D63F);
+        assert_eq!(
+            ids,
+            vec![
+                Id::Left,
+                Id::Right,
+                Id::Center,
+                Id::LFE,
+                Id::LeftBack,
+                Id::RightBack,
+                Id::LeftSurround,
+                Id::RightSurround,
+                Id::HeightLeft,
+                Id::HeightRight,
+                Id::HeightLeftSurround,
+                Id::HeightRightSurround,
+            ]
+        );
+        assert_eq!(Layout::from_ids(&ids), Layout::LAYOUT_7_1_4H);
+    }
+
+    #[test]
+    fn reserved_bits_are_ignored() {
+        assert_eq!(ids_from_wave_channel_mask(0x8000_0004), vec![Id::Center]);
+        assert!(ids_from_wave_channel_mask(0).is_empty());
+    }
+}
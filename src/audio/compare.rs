@@ -0,0 +1,133 @@
+//! Sample by sample comparison of two buffers.
+
+use crate::audio::{buffer::BufferE, db};
+use anyhow::Result;
+
+/// Differences between a buffer A and a buffer B, in normalized full scale values.
+///
+/// Where one buffer is longer than the other, the missing samples of the shorter one count as
+/// silence, like the A−B residual of a diff track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub nr_samples_a: usize,
+    pub nr_samples_b: usize,
+    /// Index of the first sample where A and B differ
+    pub first_mismatch: Option<usize>,
+    /// Number of samples where A and B differ
+    pub nr_mismatches: usize,
+    /// Largest |A−B|
+    pub max_abs_diff: f64,
+    /// Signal to noise ratio in dB, with A as the signal and A−B as the noise.
+    /// Infinite when A and B are equal.
+    pub snr_db: f64,
+}
+
+impl Comparison {
+    pub fn new(buffer_a: &BufferE, buffer_b: &BufferE) -> Result<Self> {
+        anyhow::ensure!(
+            buffer_a.sample_rate() == buffer_b.sample_rate(),
+            "Can't compare buffers with different sample rates: {} Hz and {} Hz",
+            buffer_a.sample_rate(),
+            buffer_b.sample_rate()
+        );
+        let nr_samples_a = buffer_a.nr_samples();
+        let nr_samples_b = buffer_b.nr_samples();
+
+        let mut first_mismatch = None;
+        let mut nr_mismatches = 0;
+        let mut max_abs_diff: f64 = 0.0;
+        let mut signal_energy = 0.0;
+        let mut noise_energy = 0.0;
+        for ix in 0..nr_samples_a.max(nr_samples_b) {
+            let a = buffer_a.norm_value(ix).unwrap_or(0.0);
+            let b = buffer_b.norm_value(ix).unwrap_or(0.0);
+            let diff = a - b;
+            if diff != 0.0 {
+                first_mismatch.get_or_insert(ix);
+                nr_mismatches += 1;
+                max_abs_diff = max_abs_diff.max(diff.abs());
+            }
+            signal_energy += a * a;
+            noise_energy += diff * diff;
+        }
+
+        let snr_db = if noise_energy == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (signal_energy / noise_energy).log10()
+        };
+
+        Ok(Self {
+            nr_samples_a,
+            nr_samples_b,
+            first_mismatch,
+            nr_mismatches,
+            max_abs_diff,
+            snr_db,
+        })
+    }
+
+    /// Largest |A−B| in LSBs of an integer sample type with the given bit depth
+    pub fn max_abs_diff_lsb(&self, bit_depth: u16) -> f64 {
+        let bit_depth = bit_depth.clamp(1, 32) as i32;
+        self.max_abs_diff * 2.0_f64.powi(bit_depth - 1)
+    }
+
+    /// Largest |A−B| in dB full scale, -inf when A and B are equal
+    pub fn max_abs_diff_db(&self) -> f64 {
+        db::gain_to_db_f64(self.max_abs_diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::Buffer;
+
+    fn buffer_i16(data: &[i16]) -> BufferE {
+        let mut buffer = Buffer::<i16>::new(48_000, 16);
+        buffer.data = data.to_vec();
+        BufferE::I16(buffer)
+    }
+
+    #[test]
+    fn equal_buffers_have_no_mismatches() {
+        let a = buffer_i16(&[1, 2, 3]);
+
+        let comparison = Comparison::new(&a, &a).unwrap();
+
+        assert_eq!(comparison.first_mismatch, None);
+        assert_eq!(comparison.nr_mismatches, 0);
+        assert_eq!(comparison.max_abs_diff_lsb(16), 0.0);
+        assert_eq!(comparison.snr_db, f64::INFINITY);
+        assert_eq!(comparison.max_abs_diff_db(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn mismatches_are_counted_in_lsb() {
+        let a = buffer_i16(&[100, 200, 300, 400]);
+        let b = buffer_i16(&[100, 203, 300, 399]);
+
+        let comparison = Comparison::new(&a, &b).unwrap();
+
+        assert_eq!(comparison.first_mismatch, Some(1));
+        assert_eq!(comparison.nr_mismatches, 2);
+        assert_eq!(comparison.max_abs_diff_lsb(16), 3.0);
+        // signal: 100² + 200² + 300² + 400², noise: 3² + 1²
+        let snr_db = 10.0 * (300_000.0_f64 / 10.0).log10();
+        assert!((comparison.snr_db - snr_db).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_samples_count_as_silence() {
+        let a = buffer_i16(&[100, 0]);
+        let b = buffer_i16(&[100, 0, 0, 5]);
+
+        let comparison = Comparison::new(&a, &b).unwrap();
+
+        assert_eq!(comparison.nr_samples_a, 2);
+        assert_eq!(comparison.nr_samples_b, 4);
+        assert_eq!(comparison.first_mismatch, Some(3));
+        assert_eq!(comparison.nr_mismatches, 1);
+    }
+}
@@ -0,0 +1,167 @@
+//! Lag between two signals from the peak of their cross-correlation.
+
+use crate::math::fft::{Complex, fft, ifft};
+use anyhow::Result;
+
+/// Longer signals are cut to their first this many samples, which keeps the FFTs of the
+/// correlation at 2^22 points.
+pub const MAX_NR_SAMPLES: usize = 1 << 21;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lag {
+    /// Delay of `other` with respect to `reference` in samples, with sub-sample precision:
+    /// `other[n + samples] ≈ reference[n]`
+    pub samples: f64,
+    /// Normalized correlation at the lag, 1.0 for identical signals and negative when `other`
+    /// is inverted
+    pub peak: f64,
+}
+
+impl Lag {
+    pub fn ms(&self, sample_rate: u32) -> f64 {
+        self.samples * 1000.0 / sample_rate as f64
+    }
+}
+
+/// Estimate the lag of `other` with respect to `reference` from the largest absolute value of
+/// their cross-correlation, refined by fitting a parabola through the peak and its neighbours.
+///
+/// `max_lag` limits the search to lags within ± that many samples.
+pub fn estimate_lag(reference: &[f64], other: &[f64], max_lag: Option<usize>) -> Result<Lag> {
+    let reference = &reference[..reference.len().min(MAX_NR_SAMPLES)];
+    let other = &other[..other.len().min(MAX_NR_SAMPLES)];
+    anyhow::ensure!(
+        !reference.is_empty() && !other.is_empty(),
+        "Can't correlate an empty signal"
+    );
+    let energy = |values: &[f64]| values.iter().map(|value| value * value).sum::<f64>();
+    let norm = (energy(reference) * energy(other)).sqrt();
+    anyhow::ensure!(norm > 0.0, "Can't correlate silence");
+
+    // zero pad so the circular correlation has no wrap around
+    let len = (reference.len() + other.len()).next_power_of_two();
+    let to_complex = |values: &[f64]| {
+        let mut data = vec![Complex::ZERO; len];
+        for (value, sample) in data.iter_mut().zip(values) {
+            value.re = *sample;
+        }
+        data
+    };
+    let mut spectrum_ref = to_complex(reference);
+    let mut spectrum_other = to_complex(other);
+    fft(&mut spectrum_ref)?;
+    fft(&mut spectrum_other)?;
+    let mut correlation: Vec<Complex> = spectrum_ref
+        .iter()
+        .zip(&spectrum_other)
+        .map(|(r, o)| r.conj() * *o)
+        .collect();
+    ifft(&mut correlation)?;
+
+    // lag k is at index k, negative lags wrap around to the end
+    let lag_of = |ix: usize| {
+        if ix < len / 2 {
+            ix as i64
+        } else {
+            ix as i64 - len as i64
+        }
+    };
+    let max_lag = max_lag.map_or(i64::MAX, |max_lag| max_lag as i64);
+    let (peak_ix, _) = correlation
+        .iter()
+        .enumerate()
+        .filter(|(ix, _)| lag_of(*ix).abs() <= max_lag)
+        .map(|(ix, value)| (ix, value.re.abs()))
+        .fold((0, f64::MIN), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+
+    let value = |ix: usize| correlation[ix % len].re;
+    let (prev, peak, next) = (value(peak_ix + len - 1), value(peak_ix), value(peak_ix + 1));
+    let curvature = prev - 2.0 * peak + next;
+    let fraction = if curvature != 0.0 {
+        (0.5 * (prev - next) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+
+    Ok(Lag {
+        samples: lag_of(peak_ix) as f64 + fraction,
+        peak: peak / norm,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise-like signal, so the correlation has a single clear peak
+    fn signal(nr_samples: usize) -> Vec<f64> {
+        let mut state: u32 = 12345;
+        (0..nr_samples)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as f64 / 32768.0 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_delay_and_advance() {
+        let reference = signal(4000);
+        let delayed: Vec<f64> = std::iter::repeat_n(0.0, 37)
+            .chain(reference.iter().copied())
+            .collect();
+
+        let lag = estimate_lag(&reference, &delayed, None).unwrap();
+        assert!((lag.samples - 37.0).abs() < 0.1, "{lag:?}");
+        assert!(lag.peak > 0.99, "{lag:?}");
+
+        let lag = estimate_lag(&delayed, &reference, None).unwrap();
+        assert!((lag.samples + 37.0).abs() < 0.1, "{lag:?}");
+    }
+
+    #[test]
+    fn inverted_signal_has_negative_peak() {
+        let reference = signal(2000);
+        let inverted: Vec<f64> = reference.iter().map(|value| -0.5 * value).collect();
+
+        let lag = estimate_lag(&reference, &inverted, None).unwrap();
+
+        assert!(lag.samples.abs() < 0.1, "{lag:?}");
+        assert!((lag.peak + 1.0).abs() < 1e-6, "{lag:?}");
+    }
+
+    #[test]
+    fn refines_to_sub_sample_lag() {
+        // a low frequency sine delayed by a quarter sample
+        let sine = |delay: f64| -> Vec<f64> {
+            (0..4096)
+                .map(|ix| (std::f64::consts::TAU * (ix as f64 - delay) / 200.0).sin())
+                .collect()
+        };
+
+        let lag = estimate_lag(&sine(0.0), &sine(10.25), Some(50)).unwrap();
+
+        assert!((lag.samples - 10.25).abs() < 0.1, "{lag:?}");
+    }
+
+    #[test]
+    fn silence_is_an_error() {
+        assert!(estimate_lag(&[0.0; 16], &signal(16), None).is_err());
+        assert!(estimate_lag(&[], &signal(16), None).is_err());
+    }
+
+    #[test]
+    fn lag_in_ms() {
+        let lag = Lag {
+            samples: 48.0,
+            peak: 1.0,
+        };
+        assert_eq!(lag.ms(48_000), 1.0);
+    }
+}
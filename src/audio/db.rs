@@ -8,6 +8,12 @@ pub fn gain_to_db(gain: f32) -> f32 {
     gain.log10() / factor
 }
 
+/// `gain_to_db` for f64 sample values, which can be far below the smallest f32
+pub fn gain_to_db_f64(gain: f64) -> f64 {
+    let factor = 0.05;
+    gain.log10() / factor
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,6 +43,12 @@ mod tests {
         assert!(act_db.is_sign_negative());
     }
 
+    #[test]
+    fn test_gain_to_db_f64_below_f32_range() {
+        let act_db = gain_to_db_f64(1e-60);
+        assert!((act_db + 1200.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_gain_to_db_exact() {
         let act_db = gain_to_db(1.0);
@@ -9,22 +9,41 @@ use crate::{
     wav::{
         file2::File,
         read::{ReadConfig, read_to_file},
+        stream::{StreamedBuffer, StreamedChannel},
     },
 };
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use rayon::prelude::*;
 use slotmap::{SecondaryMap, SlotMap, new_key_type};
+use std::sync::Arc;
 
 new_key_type! { pub struct BufferId; }
 
-pub type Buffers = SlotMap<BufferId, BufferE>;
+/// Buffers are shared, so work on them can be moved off the UI thread without copying
+pub type Buffers = SlotMap<BufferId, Arc<BufferE>>;
 pub type Thumbnails = SecondaryMap<BufferId, ThumbnailE>;
+pub type Streams = SecondaryMap<BufferId, StreamedBuffer>;
+
+/// Why the samples of a streamed buffer can't be used, shown where they would be
+pub const STREAMED_BUFFER_HINT: &str =
+    "Streamed from disk, reload it with a higher stream threshold to use its samples";
+
+/// Memory held by the `AudioManager`, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub buffers: usize,
+    pub thumbnails: usize,
+    /// Samples of streamed buffers read around the visible range
+    pub streamed: usize,
+}
 
 /// Manages audio buffers and their associated thumbnails
 #[derive(Debug, Clone, Default)]
 pub struct AudioManager {
     pub buffers: Buffers,
     pub thumbnails: Thumbnails,
+    /// Buffers streamed from disk, their entry in `buffers` has no samples
+    pub streams: Streams,
 }
 
 impl AudioManager {
@@ -56,9 +75,74 @@ impl AudioManager {
         Ok(file)
     }
 
+    /// Bytes held by all buffers and by all thumbnails
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            buffers: self
+                .buffers
+                .values()
+                .map(|buffer| buffer.size_in_bytes())
+                .sum(),
+            thumbnails: self
+                .thumbnails
+                .values()
+                .map(ThumbnailE::size_in_bytes)
+                .sum(),
+            streamed: self
+                .streams
+                .values()
+                .enumerate()
+                .filter(|(ix, stream)| {
+                    // count sources shared by several channels once
+                    !self
+                        .streams
+                        .values()
+                        .take(*ix)
+                        .any(|other| Arc::ptr_eq(&other.source, &stream.source))
+                })
+                .map(|(_, stream)| stream.source.size_in_bytes())
+                .sum(),
+        }
+    }
+
+    pub fn is_streamed(&self, buffer_id: BufferId) -> bool {
+        self.streams.contains_key(buffer_id)
+    }
+
+    /// Whether samples of streamed buffers are being read from disk
+    pub fn is_reading_streams(&self) -> bool {
+        self.streams
+            .values()
+            .any(|stream| stream.source.is_reading())
+    }
+
+    /// Changes when a read of streamed samples finishes, views of streamed buffers drawn before
+    /// may show their thumbnail instead
+    pub fn nr_stream_reads(&self) -> u64 {
+        self.streams
+            .values()
+            .map(|stream| stream.source.nr_reads())
+            .sum()
+    }
+
+    /// Add a channel that is streamed from disk, with its thumbnail
+    pub fn insert_streamed(&mut self, channel: StreamedChannel) -> BufferId {
+        let buffer_id = self.buffers.insert(Arc::new(channel.format));
+        self.thumbnails.insert(buffer_id, channel.thumbnail);
+        self.streams.insert(buffer_id, channel.buffer);
+        buffer_id
+    }
+
     pub fn remove_buffer(&mut self, buffer_id: BufferId) {
         self.buffers.remove(buffer_id);
         self.thumbnails.remove(buffer_id);
+        self.streams.remove(buffer_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.buffers.clear();
+        self.thumbnails.clear();
+        self.streams.clear();
     }
 
     pub fn remove_buffers_from_file(&mut self, file: &File) {
@@ -68,11 +152,50 @@ impl AudioManager {
     }
 
     pub fn get_buffer(&self, buffer_id: BufferId) -> Result<&BufferE> {
+        self.get_shared_buffer(buffer_id)
+            .map(|buffer| buffer.as_ref())
+    }
+
+    /// Shared handle to a buffer, e.g. to process it on a worker thread. Fails for streamed
+    /// buffers, their samples are not in memory.
+    pub fn get_shared_buffer(&self, buffer_id: BufferId) -> Result<&Arc<BufferE>> {
+        if self.streams.contains_key(buffer_id) {
+            bail!(
+                "Buffer {:?} is streamed from disk, open it with a higher stream threshold",
+                buffer_id
+            );
+        }
+        self.get_buffer_format(buffer_id)
+    }
+
+    /// Buffer to get the sample rate, bit depth and sample type from, streamed buffers included.
+    /// Don't use its samples, a streamed buffer has none.
+    pub fn get_buffer_format(&self, buffer_id: BufferId) -> Result<&Arc<BufferE>> {
         self.buffers
             .get(buffer_id)
             .with_context(|| format!("Buffer {:?} not found", buffer_id))
     }
 
+    /// Rectangle containing the whole buffer, streamed buffers included
+    pub fn get_sample_rect(&self, buffer_id: BufferId) -> Result<SampleRect> {
+        let mut sample_rect = SampleRect::from_buffere(self.get_buffer_format(buffer_id)?);
+        if let Some(stream) = self.streams.get(buffer_id) {
+            sample_rect.set_ix_rng(sample::FracIxRange {
+                start: 0.0,
+                end: stream.nr_samples() as f64,
+            });
+        }
+        Ok(sample_rect)
+    }
+
+    /// Normalized value of a sample. For a streamed buffer only when it was read to be shown.
+    pub fn norm_value(&self, buffer_id: BufferId, sample_ix: usize) -> Result<Option<f64>> {
+        match self.streams.get(buffer_id) {
+            Some(stream) => Ok(stream.read_value(sample_ix)),
+            None => Ok(self.get_buffer(buffer_id)?.norm_value(sample_ix)),
+        }
+    }
+
     pub fn get_sample_view(
         &self,
         buffer_id: BufferId,
@@ -86,6 +209,36 @@ impl AudioManager {
             .and_then(|thumbnail| thumbnail.get_smallest_samples_per_pixel())
             .map(|spp| spp as f32);
         if thumbnail_spp.is_none() || thumbnail_spp.unwrap() > target_spp {
+            if let Some(stream) = self.streams.get(buffer_id) {
+                // Only read the visible samples, the view is relative to the first one read.
+                // Until they are read the finest level of the thumbnail is shown.
+                let Some((buffere, start)) = stream.read(sample::IxRange {
+                    start: sample_rect.ix_rng.start.floor() as sample::Ix - 1,
+                    end: sample_rect.ix_rng.end.ceil() as sample::Ix + 1,
+                }) else {
+                    let level_data = thumbnail
+                        .zip(thumbnail_spp)
+                        .and_then(|(thumbnail, spp)| thumbnail.get_level_data(spp))
+                        .ok_or(anyhow!("level_data not found"))?;
+                    return sample::View::from_level_data_e(
+                        &level_data,
+                        sample_rect,
+                        screen_rect,
+                        display_scale,
+                    );
+                };
+                let mut window_rect = sample_rect;
+                window_rect.set_ix_rng(sample::FracIxRange {
+                    start: sample_rect.ix_rng.start - start as f64,
+                    end: sample_rect.ix_rng.end - start as f64,
+                });
+                return sample::View::from_buffere(
+                    &buffere,
+                    window_rect,
+                    screen_rect,
+                    display_scale,
+                );
+            }
             let buffere = self.get_buffer(buffer_id)?;
             sample::View::from_buffere(buffere, sample_rect, screen_rect, display_scale)
         } else {
@@ -1,16 +1,21 @@
 pub mod buffer_pool;
 pub mod channel;
+pub mod compare;
 // pub mod channel_id;
 // pub mod channel_mask;
 pub mod buffer;
-// pub mod cross_correlation;
+pub mod cross_correlation;
 pub mod db;
 pub mod manager;
 pub mod rms;
 pub mod sample;
 pub mod sample_rect2;
+pub mod spectrogram;
+pub mod spectrum;
+pub mod stats;
 pub mod thumbnail;
 pub mod util;
+pub mod window;
 
 // pub type SampleIx = i64;
 pub type SampleIx = f64;
@@ -48,3 +48,169 @@ pub fn flt2pcm32(val: f32) -> i32 {
 pub fn pcm322flt(val: i32) -> f64 {
     val as f64 / float2pcm_factor(32) as f64
 }
+
+/// How a value is brought to a PCM value when the bit depth is reduced
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Rounding {
+    /// Drop the bits below the LSB, rounds towards minus infinity
+    Truncate,
+    /// Round to the nearest value
+    #[default]
+    Round,
+    /// Add triangular (TPDF) dither of ±1 LSB, then round. Decorrelates the quantisation error
+    /// from the signal at the cost of a slightly higher noise floor.
+    Tpdf,
+}
+
+impl Rounding {
+    pub const ALL: [Rounding; 3] = [Rounding::Truncate, Rounding::Round, Rounding::Tpdf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rounding::Truncate => "truncate",
+            Rounding::Round => "round",
+            Rounding::Tpdf => "TPDF dither",
+        }
+    }
+}
+
+/// Error feedback filter that moves the quantisation noise to high frequencies
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NoiseShaping {
+    #[default]
+    None,
+    /// Noise transfer function 1 - z^-1, +6 dB/octave
+    FirstOrder,
+    /// Noise transfer function (1 - z^-1)^2, +12 dB/octave
+    SecondOrder,
+}
+
+impl NoiseShaping {
+    pub const ALL: [NoiseShaping; 3] = [
+        NoiseShaping::None,
+        NoiseShaping::FirstOrder,
+        NoiseShaping::SecondOrder,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseShaping::None => "none",
+            NoiseShaping::FirstOrder => "1st order",
+            NoiseShaping::SecondOrder => "2nd order",
+        }
+    }
+
+    /// Weights of the previous errors, most recent first
+    fn coefficients(self) -> [f64; 2] {
+        match self {
+            NoiseShaping::None => [0.0, 0.0],
+            NoiseShaping::FirstOrder => [1.0, 0.0],
+            NoiseShaping::SecondOrder => [2.0, -1.0],
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RequantizeConfig {
+    pub rounding: Rounding,
+    pub noise_shaping: NoiseShaping,
+    /// Seed of the dither, the same seed gives the same output
+    pub seed: u64,
+}
+
+/// Normalized values to PCM values of a lower resolution, one per channel as noise shaping
+/// keeps the errors of the previous samples.
+#[derive(Debug, Clone)]
+pub struct Requantizer {
+    bit_depth: u32,
+    rounding: Rounding,
+    coefficients: [f64; 2],
+    rng: crate::math::rng::Rng,
+    /// Quantisation errors in LSBs, most recent first
+    errors: [f64; 2],
+}
+
+impl Requantizer {
+    /// Channels get different dither for the same seed, so it isn't correlated between them
+    pub fn new(bit_depth: u32, config: &RequantizeConfig, ch_ix: usize) -> Self {
+        Self {
+            bit_depth,
+            rounding: config.rounding,
+            coefficients: config.noise_shaping.coefficients(),
+            rng: crate::math::rng::Rng::new(config.seed.wrapping_add(ch_ix as u64)),
+            errors: [0.0; 2],
+        }
+    }
+
+    pub fn requantize(&mut self, val: f64) -> i32 {
+        let factor = float2pcm_factor(self.bit_depth) as f64;
+        let shaped = val * factor
+            - self.coefficients[0] * self.errors[0]
+            - self.coefficients[1] * self.errors[1];
+        let quantized = match self.rounding {
+            Rounding::Truncate => shaped.floor(),
+            Rounding::Round => shaped.round(),
+            Rounding::Tpdf => (shaped + self.rng.next_f64() - self.rng.next_f64()).round(),
+        };
+        // The error of the unclipped value, clipping would make the feedback blow up
+        self.errors = [quantized - shaped, self.errors[0]];
+        quantized.clamp(-factor, factor - 1.0) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requantize(config: &RequantizeConfig, values: &[f64]) -> Vec<i32> {
+        let mut requantizer = Requantizer::new(16, config, 0);
+        values
+            .iter()
+            .map(|&value| requantizer.requantize(value))
+            .collect()
+    }
+
+    #[test]
+    fn truncate_and_round() {
+        let lsb = pcm2float_factor(16);
+        let values = [0.4 * lsb, 0.6 * lsb, -0.4 * lsb, 2.0, -2.0];
+        let truncate = RequantizeConfig {
+            rounding: Rounding::Truncate,
+            ..Default::default()
+        };
+        assert_eq!(requantize(&truncate, &values), [0, 0, -1, 32767, -32768]);
+        let round = RequantizeConfig::default();
+        assert_eq!(requantize(&round, &values), [0, 1, 0, 32767, -32768]);
+    }
+
+    #[test]
+    fn tpdf_dither_is_reproducible_and_unbiased() {
+        let config = RequantizeConfig {
+            rounding: Rounding::Tpdf,
+            seed: 1234,
+            ..Default::default()
+        };
+        let values = vec![0.25 * pcm2float_factor(16); 20_000];
+        let dithered = requantize(&config, &values);
+        assert_eq!(dithered, requantize(&config, &values));
+        assert!(dithered.iter().all(|value| (-1..=1).contains(value)));
+        // The mean of the dithered signal is the value below the LSB
+        let mean = dithered.iter().sum::<i32>() as f64 / dithered.len() as f64;
+        assert!((mean - 0.25).abs() < 0.02, "mean {mean}");
+        let other_seed = RequantizeConfig { seed: 1, ..config };
+        assert_ne!(dithered, requantize(&other_seed, &values));
+    }
+
+    #[test]
+    fn noise_shaping_keeps_the_average_and_moves_the_error_up() {
+        let config = RequantizeConfig {
+            noise_shaping: NoiseShaping::FirstOrder,
+            ..Default::default()
+        };
+        let values = vec![0.25 * pcm2float_factor(16); 8];
+        // Error feedback turns a constant 1/4 LSB into a pattern with that average
+        assert_eq!(requantize(&config, &values), [0, 1, 0, 0, 0, 1, 0, 0]);
+        // Rounding alone loses it
+        assert_eq!(requantize(&RequantizeConfig::default(), &values), [0; 8]);
+    }
+}
@@ -15,7 +15,7 @@ impl IxTrait for OptIx {}
 
 // Used for representing user-specified ranges where omission means from the start or to the end of
 // the buffer
-#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct OptIxRange {
     pub start: Option<Ix>,
     pub end: Option<Ix>,
@@ -117,6 +117,9 @@ impl View {
             BufferE::F32(buffer) => {
                 View::from_buffer(buffer, sample_rect, screen_rect, display_scale)
             }
+            BufferE::F64(buffer) => {
+                View::from_buffer(buffer, sample_rect, screen_rect, display_scale)
+            }
             BufferE::I32(buffer) => {
                 View::from_buffer(buffer, sample_rect, screen_rect, display_scale)
             }
@@ -231,6 +234,9 @@ impl View {
             LevelDataERef::F32(level_data) => {
                 Self::from_level_data(level_data, sample_rect, screen_rect, display_scale)
             }
+            LevelDataERef::F64(level_data) => {
+                Self::from_level_data(level_data, sample_rect, screen_rect, display_scale)
+            }
             LevelDataERef::I32(level_data) => {
                 Self::from_level_data(level_data, sample_rect, screen_rect, display_scale)
             }
@@ -253,12 +259,9 @@ impl View {
             .ok_or_else(|| anyhow!("val_rng is missing"))?;
         ensure!(!val_rng.is_empty(), "val_rng is empty");
 
-        // target zoom level
+        // target zoom level, a coarser level is stretched, e.g. the thumbnail of a streamed buffer
+        // shown until its samples are read
         let samples_per_pixel = sample_rect.width() / screen_rect.width();
-        ensure!(
-            samples_per_pixel >= level_data.samples_per_pixel as f32,
-            "samples_per_pixel too small"
-        );
 
         // Get visible range of min/max sample indices present in the level_data
         let start_ix = sample_rect.ix_rng.start.max(0.0).ceil();
@@ -16,6 +16,7 @@ impl SampleRect {
     pub fn from_buffere(buffer: &BufferE) -> Self {
         let nr_samples = match buffer {
             BufferE::F32(buffer) => buffer.nr_samples(),
+            BufferE::F64(buffer) => buffer.nr_samples(),
             BufferE::I32(buffer) => buffer.nr_samples(),
             BufferE::I16(buffer) => buffer.nr_samples(),
         };
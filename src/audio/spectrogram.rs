@@ -0,0 +1,342 @@
+//! Time-frequency levels of a buffer, computed in tiles of columns per zoom level.
+//!
+//! Like the min/max `LevelData` of a `Thumbnail`, a zoom level is identified by its number of
+//! samples per column, which is the hop size times a power of two. Zoomed out views use the
+//! coarser levels so the work per screen stays bounded, and panning only needs the tiles that
+//! scroll into view.
+
+use crate::audio::{buffer::BufferE, spectrum::power_spectrum, window::WindowFunction};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+pub const FFT_SIZES: [usize; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+/// Number of columns per tile
+pub const TILE_NR_COLUMNS: usize = 256;
+
+/// A column wider than the hop averages at most this many frames, evenly spread over the column
+pub const MAX_NR_FRAMES_PER_COLUMN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Analysis {
+    /// Power of two, see `FFT_SIZES`
+    pub fft_size: usize,
+    /// Samples between columns of the finest zoom level
+    pub hop: usize,
+    pub window: WindowFunction,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self {
+            fft_size: 2048,
+            hop: 512,
+            window: WindowFunction::default(),
+        }
+    }
+}
+
+impl Analysis {
+    pub fn nr_bins(&self) -> usize {
+        self.fft_size / 2 + 1
+    }
+
+    /// Zoom level for the given samples per pixel: the largest hop·2^k that is not larger, so a
+    /// pixel never skips a column, or the hop itself when zoomed in further.
+    pub fn samples_per_column(&self, samples_per_pixel: f64) -> u64 {
+        let mut samples_per_column = self.hop.max(1) as u64;
+        while (samples_per_column * 2) as f64 <= samples_per_pixel {
+            samples_per_column *= 2;
+        }
+        samples_per_column
+    }
+
+    /// Nearest bin of a frequency, None outside of 0 Hz up to Nyquist
+    pub fn frequency_bin(&self, frequency: f64, sample_rate: u32) -> Option<usize> {
+        let bin = (frequency * self.fft_size as f64 / sample_rate as f64).round();
+        (bin >= 0.0 && (bin as usize) < self.nr_bins()).then_some(bin as usize)
+    }
+}
+
+/// A tile of one zoom level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileKey {
+    pub samples_per_column: u64,
+    pub tile_ix: usize,
+}
+
+impl TileKey {
+    /// The tile holding the column that contains `sample_ix`, and the column index
+    pub fn for_sample_ix(samples_per_column: u64, sample_ix: u64) -> (Self, usize) {
+        let column = (sample_ix / samples_per_column) as usize;
+        let key = Self {
+            samples_per_column,
+            tile_ix: column / TILE_NR_COLUMNS,
+        };
+        (key, column)
+    }
+
+    pub fn first_column(&self) -> usize {
+        self.tile_ix * TILE_NR_COLUMNS
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub key: TileKey,
+    pub nr_bins: usize,
+    /// Level in dBFS per column and bin, column after column: a full scale sine on a bin reads
+    /// 0 dB
+    pub levels_db: Vec<f32>,
+}
+
+impl Tile {
+    pub fn new(buffer: &BufferE, analysis: Analysis, key: TileKey) -> Result<Self> {
+        let fft_size = analysis.fft_size;
+        anyhow::ensure!(
+            fft_size.is_power_of_two() && fft_size >= 2,
+            "FFT size must be a power of two, got {fft_size}"
+        );
+        anyhow::ensure!(analysis.hop > 0, "Hop size must be larger than zero");
+        let nr_samples = buffer.nr_samples() as u64;
+        let spc = key.samples_per_column;
+        let nr_columns = nr_samples.div_ceil(spc) as usize;
+        let first_column = key.first_column();
+        anyhow::ensure!(
+            first_column < nr_columns,
+            "Tile {} is beyond the last column {}",
+            key.tile_ix,
+            nr_columns
+        );
+        let columns = first_column..(first_column + TILE_NR_COLUMNS).min(nr_columns);
+
+        let window = analysis.window.coefficients(fft_size);
+        let window_sum: f64 = window.iter().sum();
+        let nr_frames = (spc / analysis.hop as u64).clamp(1, MAX_NR_FRAMES_PER_COLUMN as u64);
+
+        let columns = columns
+            .into_par_iter()
+            .map(|column| {
+                let column_start = column as u64 * spc;
+                let mut power = vec![0.0; analysis.nr_bins()];
+                for frame_ix in 0..nr_frames {
+                    let center = column_start + (2 * frame_ix + 1) * spc / (2 * nr_frames);
+                    let samples = frame(buffer, center as i64 - fft_size as i64 / 2, fft_size);
+                    let frame_power = power_spectrum(&samples, &window, window_sum, fft_size)?;
+                    for (acc, value) in power.iter_mut().zip(frame_power) {
+                        *acc += value;
+                    }
+                }
+                Ok(power
+                    .into_iter()
+                    .map(|power| (10.0 * (power / nr_frames as f64).log10()) as f32)
+                    .collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            key,
+            nr_bins: analysis.nr_bins(),
+            levels_db: columns.concat(),
+        })
+    }
+
+    pub fn nr_columns(&self) -> usize {
+        self.levels_db.len() / self.nr_bins
+    }
+
+    /// Level of a bin in the given absolute column, None when the column is not in this tile
+    pub fn level(&self, column: usize, bin: usize) -> Option<f32> {
+        let column = column.checked_sub(self.key.first_column())?;
+        if column >= self.nr_columns() || bin >= self.nr_bins {
+            return None;
+        }
+        Some(self.levels_db[column * self.nr_bins + bin])
+    }
+}
+
+/// `len` normalized samples starting at `start`, zero outside of the buffer
+fn frame(buffer: &BufferE, start: i64, len: usize) -> Vec<f64> {
+    let mut samples = vec![0.0; len];
+    let nr_samples = buffer.nr_samples() as i64;
+    let begin = start.clamp(0, nr_samples);
+    let end = (start + len as i64).clamp(0, nr_samples);
+    if begin < end {
+        let offset = (begin - start) as usize;
+        let values = buffer.norm_values(begin as usize..end as usize);
+        samples[offset..offset + values.len()].copy_from_slice(&values);
+    }
+    samples
+}
+
+/// Tiles of one buffer for one analysis, the least recently used tiles are dropped when the
+/// cache is full.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrogram {
+    pub analysis: Analysis,
+    max_nr_tiles: usize,
+    tiles: HashMap<TileKey, (Tile, u64)>,
+    /// Incremented on every use, to find the least recently used tile
+    clock: u64,
+}
+
+impl Spectrogram {
+    /// A few screens worth of tiles, about 270 MB with the largest FFT size
+    pub const DEFAULT_MAX_NR_TILES: usize = 64;
+
+    pub fn new(analysis: Analysis) -> Self {
+        Self::with_max_nr_tiles(analysis, Self::DEFAULT_MAX_NR_TILES)
+    }
+
+    pub fn with_max_nr_tiles(analysis: Analysis, max_nr_tiles: usize) -> Self {
+        Self {
+            analysis,
+            max_nr_tiles: max_nr_tiles.max(1),
+            tiles: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn nr_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn contains(&self, key: TileKey) -> bool {
+        self.tiles.contains_key(&key)
+    }
+
+    /// Get a tile and mark it as recently used
+    pub fn get(&mut self, key: TileKey) -> Option<&Tile> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.tiles.get_mut(&key).map(|(tile, last_used)| {
+            *last_used = clock;
+            &*tile
+        })
+    }
+
+    pub fn insert(&mut self, tile: Tile) {
+        if self.tiles.len() >= self.max_nr_tiles
+            && !self.tiles.contains_key(&tile.key)
+            && let Some(oldest) = self
+                .tiles
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key)
+        {
+            self.tiles.remove(&oldest);
+        }
+        self.clock += 1;
+        self.tiles.insert(tile.key, (tile, self.clock));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::Buffer;
+
+    fn sine(frequency: f64, nr_samples: usize) -> BufferE {
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = (0..nr_samples)
+            .map(|ix| {
+                let phase = std::f64::consts::TAU * frequency * ix as f64 / 48_000.0;
+                phase.sin() as f32
+            })
+            .collect();
+        BufferE::F32(buffer)
+    }
+
+    #[test]
+    fn zoom_level_is_hop_times_power_of_two() {
+        let analysis = Analysis {
+            hop: 256,
+            ..Default::default()
+        };
+
+        assert_eq!(analysis.samples_per_column(0.5), 256);
+        assert_eq!(analysis.samples_per_column(256.0), 256);
+        assert_eq!(analysis.samples_per_column(511.0), 256);
+        assert_eq!(analysis.samples_per_column(512.0), 512);
+        assert_eq!(analysis.samples_per_column(5000.0), 4096);
+    }
+
+    #[test]
+    fn tile_key_for_sample_ix() {
+        let (key, column) = TileKey::for_sample_ix(100, 30_050);
+
+        assert_eq!(column, 300);
+        assert_eq!(key.tile_ix, 1);
+        assert_eq!(key.first_column(), TILE_NR_COLUMNS);
+    }
+
+    #[test]
+    fn full_scale_sine_on_a_bin_reads_zero_db() {
+        // bin 64 of a 1024 point FFT at 48 kHz
+        let buffer = sine(3000.0, 48_000);
+        let analysis = Analysis {
+            fft_size: 1024,
+            hop: 256,
+            window: WindowFunction::Hann,
+        };
+        let key = TileKey {
+            samples_per_column: 1024,
+            tile_ix: 0,
+        };
+
+        let tile = Tile::new(&buffer, analysis, key).unwrap();
+
+        assert_eq!(tile.nr_columns(), 47);
+        let level = tile.level(20, 64).unwrap();
+        assert!(level.abs() < 0.01, "{level}");
+        assert!(tile.level(20, 256).unwrap() < -100.0);
+        assert!(tile.level(47, 64).is_none());
+    }
+
+    #[test]
+    fn last_tile_is_partial_and_beyond_is_an_error() {
+        let buffer = sine(1000.0, 1000);
+        let analysis = Analysis {
+            fft_size: 256,
+            hop: 2,
+            window: WindowFunction::Hann,
+        };
+        let key = |tile_ix| TileKey {
+            samples_per_column: 2,
+            tile_ix,
+        };
+
+        let tile = Tile::new(&buffer, analysis, key(1)).unwrap();
+
+        assert_eq!(tile.nr_columns(), 500 - TILE_NR_COLUMNS);
+        assert!(Tile::new(&buffer, analysis, key(2)).is_err());
+    }
+
+    #[test]
+    fn cache_drops_least_recently_used_tile() {
+        let buffer = sine(1000.0, 4096);
+        let analysis = Analysis {
+            fft_size: 256,
+            hop: 256,
+            window: WindowFunction::Hann,
+        };
+        let key = |samples_per_column| TileKey {
+            samples_per_column,
+            tile_ix: 0,
+        };
+        let mut spectrogram = Spectrogram::with_max_nr_tiles(analysis, 2);
+
+        for spc in [256, 512] {
+            spectrogram.insert(Tile::new(&buffer, analysis, key(spc)).unwrap());
+        }
+        assert!(spectrogram.get(key(256)).is_some());
+        spectrogram.insert(Tile::new(&buffer, analysis, key(1024)).unwrap());
+
+        assert_eq!(spectrogram.nr_tiles(), 2);
+        assert!(spectrogram.contains(key(256)));
+        assert!(!spectrogram.contains(key(512)));
+        assert!(spectrogram.contains(key(1024)));
+    }
+}
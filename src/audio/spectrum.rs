@@ -0,0 +1,273 @@
+//! Magnitude spectrum of a range of samples.
+
+use crate::{
+    audio::{buffer::BufferE, window::WindowFunction},
+    math::fft::{Complex, fft},
+};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::ops::Range;
+use strum_macros::EnumIter;
+
+pub const FFT_SIZES: [usize; 9] = [256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536];
+
+/// Longer ranges spread this many frames evenly instead of overlapping them by half, to bound
+/// the work for long selections.
+pub const MAX_NR_FRAMES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+pub enum Averaging {
+    /// One frame in the middle of the range
+    Off,
+    /// Mean power over the frames
+    #[default]
+    Mean,
+    /// Max power per bin over the frames
+    PeakHold,
+}
+
+impl Averaging {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Averaging::Off => "off",
+            Averaging::Mean => "mean",
+            Averaging::PeakHold => "peak hold",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumConfig {
+    /// Power of two, see `FFT_SIZES`
+    pub fft_size: usize,
+    pub window: WindowFunction,
+    pub averaging: Averaging,
+}
+
+impl Default for SpectrumConfig {
+    fn default() -> Self {
+        Self {
+            fft_size: 4096,
+            window: WindowFunction::default(),
+            averaging: Averaging::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    /// Level per bin from 0 Hz up to Nyquist, in dBFS: a full scale sine on a bin reads 0 dB
+    pub levels_db: Vec<f64>,
+    pub nr_frames: usize,
+}
+
+impl Spectrum {
+    /// Spectrum of the samples in `ix_rng`, which is clamped to the buffer.
+    ///
+    /// A range shorter than the FFT size is windowed as a whole and zero padded.
+    pub fn new(buffer: &BufferE, ix_rng: Range<usize>, config: SpectrumConfig) -> Result<Self> {
+        let fft_size = config.fft_size;
+        anyhow::ensure!(
+            fft_size.is_power_of_two() && fft_size >= 2,
+            "FFT size must be a power of two, got {fft_size}"
+        );
+        let end = ix_rng.end.min(buffer.nr_samples());
+        let start = ix_rng.start.min(end);
+        anyhow::ensure!(start < end, "Can't compute the spectrum of an empty range");
+
+        let frame_len = (end - start).min(fft_size);
+        let window = config.window.coefficients(frame_len);
+        let window_sum: f64 = window.iter().sum();
+        let frame_starts = frame_starts(start..end, frame_len, config.averaging);
+
+        let powers = frame_starts
+            .par_iter()
+            .map(|&frame_start| {
+                let samples = buffer.norm_values(frame_start..frame_start + frame_len);
+                power_spectrum(&samples, &window, window_sum, fft_size)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let nr_bins = fft_size / 2 + 1;
+        let mut power = vec![0.0; nr_bins];
+        for frame_power in &powers {
+            for (acc, value) in power.iter_mut().zip(frame_power) {
+                match config.averaging {
+                    Averaging::Off | Averaging::Mean => *acc += value,
+                    Averaging::PeakHold => *acc = acc.max(*value),
+                }
+            }
+        }
+        if config.averaging != Averaging::PeakHold {
+            for value in power.iter_mut() {
+                *value /= powers.len() as f64;
+            }
+        }
+
+        Ok(Self {
+            sample_rate: buffer.sample_rate(),
+            fft_size,
+            levels_db: power.iter().map(|power| 10.0 * power.log10()).collect(),
+            nr_frames: powers.len(),
+        })
+    }
+
+    pub fn nyquist(&self) -> f64 {
+        self.sample_rate as f64 / 2.0
+    }
+
+    pub fn bin_frequency(&self, bin: usize) -> f64 {
+        bin as f64 * self.sample_rate as f64 / self.fft_size as f64
+    }
+
+    /// Fractional bin index of a frequency
+    pub fn frequency_bin(&self, frequency: f64) -> f64 {
+        frequency * self.fft_size as f64 / self.sample_rate as f64
+    }
+
+    /// Level of the bin nearest to `frequency`
+    pub fn level_at(&self, frequency: f64) -> Option<f64> {
+        let bin = self.frequency_bin(frequency).round();
+        if bin < 0.0 {
+            return None;
+        }
+        self.levels_db.get(bin as usize).copied()
+    }
+
+    /// Highest level of the bins in the frequency range, or the nearest bin when the range
+    /// falls in between bins, so narrow peaks stay visible when drawing many bins per pixel
+    pub fn max_level_in(&self, frequencies: Range<f64>) -> Option<f64> {
+        let first = self.frequency_bin(frequencies.start).ceil().max(0.0) as usize;
+        let last = (self.frequency_bin(frequencies.end).floor().max(0.0) as usize)
+            .min(self.levels_db.len().saturating_sub(1));
+        if first > last {
+            return self.level_at((frequencies.start + frequencies.end) / 2.0);
+        }
+        self.levels_db[first..=last]
+            .iter()
+            .copied()
+            .reduce(f64::max)
+    }
+}
+
+/// Start indices of the frames to analyze
+fn frame_starts(ix_rng: Range<usize>, frame_len: usize, averaging: Averaging) -> Vec<usize> {
+    let last_start = ix_rng.end - frame_len;
+    let span = last_start - ix_rng.start;
+    if span == 0 {
+        return vec![ix_rng.start];
+    }
+    if averaging == Averaging::Off {
+        return vec![ix_rng.start + span / 2];
+    }
+    let hop = (frame_len / 2).max(1);
+    let nr_frames = (span.div_ceil(hop) + 1).min(MAX_NR_FRAMES);
+    (0..nr_frames)
+        .map(|frame_ix| ix_rng.start + frame_ix * span / (nr_frames - 1))
+        .collect()
+}
+
+/// Power per bin of one windowed and zero padded frame, scaled to amplitude² of a sine
+pub fn power_spectrum(
+    samples: &[f64],
+    window: &[f64],
+    window_sum: f64,
+    fft_size: usize,
+) -> Result<Vec<f64>> {
+    let mut data = vec![Complex::ZERO; fft_size];
+    for ((value, sample), weight) in data.iter_mut().zip(samples).zip(window) {
+        value.re = sample * weight;
+    }
+    fft(&mut data)?;
+
+    let nr_bins = fft_size / 2 + 1;
+    Ok(data[..nr_bins]
+        .iter()
+        .enumerate()
+        .map(|(bin, value)| {
+            // energy of the negative frequencies is folded onto the positive ones
+            let scale = if bin == 0 || bin == fft_size / 2 {
+                1.0
+            } else {
+                2.0
+            };
+            (value.norm() * scale / window_sum).powi(2)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::Buffer;
+
+    fn sine(frequency: f64, amplitude: f64, nr_samples: usize) -> BufferE {
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = (0..nr_samples)
+            .map(|ix| {
+                let phase = std::f64::consts::TAU * frequency * ix as f64 / 48_000.0;
+                (amplitude * phase.sin()) as f32
+            })
+            .collect();
+        BufferE::F32(buffer)
+    }
+
+    #[test]
+    fn full_scale_sine_on_a_bin_reads_zero_db() {
+        // bin 64 of a 1024 point FFT at 48 kHz
+        let buffer = sine(3000.0, 1.0, 8192);
+
+        for window in [
+            WindowFunction::Hann,
+            WindowFunction::BlackmanHarris,
+            WindowFunction::FlatTop,
+        ] {
+            let config = SpectrumConfig {
+                fft_size: 1024,
+                window,
+                averaging: Averaging::Mean,
+            };
+            let spectrum = Spectrum::new(&buffer, 0..8192, config).unwrap();
+            let level = spectrum.level_at(3000.0).unwrap();
+            assert!(level.abs() < 0.01, "{window:?}: {level}");
+            assert!(spectrum.level_at(12000.0).unwrap() < -100.0);
+        }
+    }
+
+    #[test]
+    fn flat_top_reads_amplitude_between_bins() {
+        let buffer = sine(3023.4375, 0.5, 4096);
+        let config = SpectrumConfig {
+            fft_size: 1024,
+            window: WindowFunction::FlatTop,
+            averaging: Averaging::Mean,
+        };
+
+        let spectrum = Spectrum::new(&buffer, 0..4096, config).unwrap();
+
+        let level = spectrum.max_level_in(2950.0..3100.0).unwrap();
+        assert!((level - -6.02).abs() < 0.05, "{level}");
+    }
+
+    #[test]
+    fn frames_overlap_by_half_and_are_bounded() {
+        assert_eq!(frame_starts(0..100, 100, Averaging::Mean), vec![0]);
+        assert_eq!(frame_starts(0..200, 100, Averaging::Mean), vec![0, 50, 100]);
+        assert_eq!(frame_starts(10..210, 100, Averaging::Off), vec![60]);
+        assert_eq!(
+            frame_starts(0..1_000_000, 64, Averaging::PeakHold).len(),
+            MAX_NR_FRAMES
+        );
+    }
+
+    #[test]
+    fn short_range_is_zero_padded() {
+        let buffer = sine(3000.0, 1.0, 100);
+        let spectrum = Spectrum::new(&buffer, 0..100, SpectrumConfig::default()).unwrap();
+
+        assert_eq!(spectrum.nr_frames, 1);
+        assert_eq!(spectrum.levels_db.len(), 4096 / 2 + 1);
+    }
+}
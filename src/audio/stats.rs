@@ -0,0 +1,168 @@
+//! Level statistics of a range of samples.
+
+use crate::audio::{
+    buffer::{Buffer, BufferE},
+    db,
+    sample::Sample,
+};
+use std::ops::Range;
+
+/// Level statistics in normalized full scale values, where 1.0 is 0 dBFS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub nr_samples: usize,
+    pub min: f64,
+    pub max: f64,
+    /// Mean value
+    pub dc: f64,
+    pub rms: f64,
+    /// Number of samples at or beyond full scale
+    pub nr_clipped: usize,
+}
+
+impl Stats {
+    /// Statistics of the samples in `ix_rng`, which is clamped to the buffer.
+    pub fn from_buffer(buffer: &BufferE, ix_rng: Range<usize>) -> Self {
+        match buffer {
+            BufferE::F32(buffer) => Self::from_samples(buffer, ix_rng),
+            BufferE::F64(buffer) => Self::from_samples(buffer, ix_rng),
+            BufferE::I32(buffer) => Self::from_samples(buffer, ix_rng),
+            BufferE::I16(buffer) => Self::from_samples(buffer, ix_rng),
+        }
+    }
+
+    fn from_samples<T: Sample>(buffer: &Buffer<T>, ix_rng: Range<usize>) -> Self {
+        let end = ix_rng.end.min(buffer.len());
+        let start = ix_rng.start.min(end);
+        let samples = &buffer[start..end];
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let full_scale = buffer.val_range();
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let mut nr_clipped = 0;
+        for &sample in samples {
+            if sample <= full_scale.min || sample >= full_scale.max {
+                nr_clipped += 1;
+            }
+            let value = sample.to_norm(buffer.bit_depth);
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            sum_of_squares += value * value;
+        }
+
+        let nr_samples = samples.len();
+        Self {
+            nr_samples,
+            min,
+            max,
+            dc: sum / nr_samples as f64,
+            rms: (sum_of_squares / nr_samples as f64).sqrt(),
+            nr_clipped,
+        }
+    }
+
+    /// Largest absolute value
+    pub fn peak(&self) -> f64 {
+        self.min.abs().max(self.max.abs())
+    }
+
+    pub fn peak_db(&self) -> f64 {
+        db::gain_to_db_f64(self.peak())
+    }
+
+    pub fn rms_db(&self) -> f64 {
+        db::gain_to_db_f64(self.rms)
+    }
+
+    /// Peak to RMS ratio, NaN for silence
+    pub fn crest_factor(&self) -> f64 {
+        self.peak() / self.rms
+    }
+
+    pub fn crest_factor_db(&self) -> f64 {
+        db::gain_to_db_f64(self.crest_factor())
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            nr_samples: 0,
+            min: 0.0,
+            max: 0.0,
+            dc: 0.0,
+            rms: 0.0,
+            nr_clipped: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer_i16(data: &[i16]) -> BufferE {
+        let mut buffer = Buffer::<i16>::new(48_000, 16);
+        buffer.data = data.to_vec();
+        BufferE::I16(buffer)
+    }
+
+    #[test]
+    fn full_scale_int_samples_count_as_clipped() {
+        let buffer = buffer_i16(&[i16::MAX, 0, i16::MIN, 16384]);
+
+        let stats = Stats::from_buffer(&buffer, 0..4);
+
+        assert_eq!(stats.nr_samples, 4);
+        assert_eq!(stats.nr_clipped, 2);
+        assert_eq!(stats.min, -1.0);
+        assert_eq!(stats.peak_db(), 0.0);
+    }
+
+    #[test]
+    fn square_wave_has_rms_equal_to_peak() {
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = vec![0.5, -0.5, 0.5, -0.5];
+        let buffer = BufferE::F32(buffer);
+
+        let stats = Stats::from_buffer(&buffer, 0..4);
+
+        assert_eq!(stats.dc, 0.0);
+        assert_eq!(stats.rms, 0.5);
+        assert_eq!(stats.crest_factor(), 1.0);
+        assert_eq!(stats.nr_clipped, 0);
+        assert!((stats.rms_db() - -6.0206).abs() < 1e-3);
+    }
+
+    #[test]
+    fn f64_samples_keep_double_precision() {
+        let mut buffer = Buffer::<f64>::new(48_000, 64);
+        buffer.data = vec![0.1 + 1e-12, -1e-60, 0.0];
+        let buffer = BufferE::F64(buffer);
+
+        let stats = Stats::from_buffer(&buffer, 0..3);
+
+        assert_eq!(stats.max, 0.1 + 1e-12);
+        assert_eq!(stats.min, -1e-60);
+        assert_eq!(stats.nr_clipped, 0);
+        assert!(stats.peak_db().is_finite());
+    }
+
+    #[test]
+    fn range_is_clamped_to_buffer() {
+        let buffer = buffer_i16(&[16384, 16384, -16384]);
+
+        let stats = Stats::from_buffer(&buffer, 1..10);
+        assert_eq!(stats.nr_samples, 2);
+        assert_eq!(stats.dc, 0.0);
+
+        let stats = Stats::from_buffer(&buffer, 5..10);
+        assert_eq!(stats, Stats::default());
+    }
+}
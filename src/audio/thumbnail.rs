@@ -1,5 +1,4 @@
-// use anyhow::anyhow;
-// use anyhow::{ensure, Result};
+use anyhow::{Result, anyhow};
 use std::collections::BTreeMap;
 use std::fmt;
 // use tracing::{debug, instrument};
@@ -15,17 +14,30 @@ type SampPerPix = u64;
 #[derive(Debug, Clone)]
 pub enum ThumbnailE {
     F32(Thumbnail<f32>),
+    F64(Thumbnail<f64>),
     I32(Thumbnail<i32>),
     I16(Thumbnail<i16>),
 }
 
 impl ThumbnailE {
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            ThumbnailE::F32(thumbnail) => thumbnail.size_in_bytes(),
+            ThumbnailE::F64(thumbnail) => thumbnail.size_in_bytes(),
+            ThumbnailE::I32(thumbnail) => thumbnail.size_in_bytes(),
+            ThumbnailE::I16(thumbnail) => thumbnail.size_in_bytes(),
+        }
+    }
+
     /// Get the level data for the given samples per pixel (closest smaller or equal)
     pub fn get_level_data(&self, samples_per_pixel: f32) -> Option<LevelDataERef<'_>> {
         match self {
             ThumbnailE::F32(thumbnail) => {
                 pick_level(thumbnail, samples_per_pixel as u64).map(LevelDataERef::F32)
             }
+            ThumbnailE::F64(thumbnail) => {
+                pick_level(thumbnail, samples_per_pixel as u64).map(LevelDataERef::F64)
+            }
             ThumbnailE::I32(thumbnail) => {
                 pick_level(thumbnail, samples_per_pixel as u64).map(LevelDataERef::I32)
             }
@@ -37,6 +49,7 @@ impl ThumbnailE {
     pub fn from_buffer_e(buffer: &BufferE, config: Option<ThumbnailConfig>) -> Self {
         match buffer {
             BufferE::F32(buffer) => ThumbnailE::F32(Thumbnail::from_buffer(buffer, config)),
+            BufferE::F64(buffer) => ThumbnailE::F64(Thumbnail::from_buffer(buffer, config)),
             BufferE::I32(buffer) => ThumbnailE::I32(Thumbnail::from_buffer(buffer, config)),
             BufferE::I16(buffer) => ThumbnailE::I16(Thumbnail::from_buffer(buffer, config)),
         }
@@ -45,6 +58,7 @@ impl ThumbnailE {
     pub fn get_smallest_samples_per_pixel(&self) -> Option<u64> {
         match self {
             ThumbnailE::F32(thumbnail) => thumbnail.get_smallest_samples_per_pixel(),
+            ThumbnailE::F64(thumbnail) => thumbnail.get_smallest_samples_per_pixel(),
             ThumbnailE::I32(thumbnail) => thumbnail.get_smallest_samples_per_pixel(),
             ThumbnailE::I16(thumbnail) => thumbnail.get_smallest_samples_per_pixel(),
         }
@@ -54,6 +68,7 @@ impl ThumbnailE {
 #[derive(Debug)]
 pub enum LevelDataERef<'a> {
     F32(&'a LevelData<f32>),
+    F64(&'a LevelData<f64>),
     I32(&'a LevelData<i32>),
     I16(&'a LevelData<i16>),
 }
@@ -81,7 +96,15 @@ impl<T: Sample> LevelData<T> {
             bit_depth: buffer.bit_depth,
             data: vec![],
         };
-        for chunk in &buffer.data.iter().chunks(samples_per_pixel as usize) {
+        result.push_samples(&buffer.data);
+        // tracing::trace!("Created LevelData from buffer, spp: {}, data.len(): {}", samples_per_pixel, result.data.len());
+        result
+    }
+
+    /// Append the min/max values of the next samples, all but the last call need a multiple of
+    /// samples_per_pixel samples
+    pub fn push_samples(&mut self, samples: &[T]) {
+        for chunk in samples.chunks(self.samples_per_pixel as usize) {
             let mut min_max = sample::ValRange::<T> {
                 min: T::MAX,
                 max: T::MIN,
@@ -89,10 +112,8 @@ impl<T: Sample> LevelData<T> {
             for &sample in chunk {
                 min_max.include(sample);
             }
-            result.data.push(min_max);
+            self.data.push(min_max);
         }
-        // tracing::trace!("Created LevelData from buffer, spp: {}, data.len(): {}", samples_per_pixel, result.data.len());
-        result
     }
 
     pub fn from_level_data(level_data: &LevelData<T>, samples_per_pixel: u64) -> Self {
@@ -259,6 +280,14 @@ pub struct Thumbnail<T: Sample> {
 }
 
 impl<T: Sample> Thumbnail<T> {
+    /// Memory held by the levels
+    pub fn size_in_bytes(&self) -> usize {
+        self.level_data
+            .values()
+            .map(|level| level.data.capacity() * std::mem::size_of::<sample::ValRange<T>>())
+            .sum()
+    }
+
     pub fn from_buffer(buffer: &Buffer<T>, config: Option<ThumbnailConfig>) -> Self {
         let config = config.unwrap_or_default();
         let mut level_data = BTreeMap::new();
@@ -271,21 +300,8 @@ impl<T: Sample> Thumbnail<T> {
         let do_from_buffer = false;
 
         if !do_from_buffer {
-            let mut spp = config.samples_per_pixel_delta;
-            let mut ld = LevelData::from_buffer(buffer, spp);
-            loop {
-                let ld_len = ld.data.len();
-                if ld_len <= config.min_nr_level_data_size {
-                    level_data.insert(spp, ld);
-                    break;
-                }
-                spp *= 2;
-                let new_ld = LevelData::from_level_data(&ld, spp);
-                // This moves the data into level_data, so we have to calulate
-                // the new one before adding
-                level_data.insert(ld.samples_per_pixel as u64, ld);
-                ld = new_ld;
-            }
+            let ld = LevelData::from_buffer(buffer, config.samples_per_pixel_delta);
+            return Self::from_finest_level(ld, &config);
         } else {
             let mut samp_per_pix = config.samples_per_pixel_delta;
 
@@ -315,6 +331,100 @@ impl<T: Sample> Thumbnail<T> {
     }
 }
 
+impl<T: Sample> Thumbnail<T> {
+    /// Add coarser levels, each halving the previous one, until a level is small enough
+    fn from_finest_level(mut ld: LevelData<T>, config: &ThumbnailConfig) -> Self {
+        let mut level_data = BTreeMap::new();
+        let mut spp = ld.samples_per_pixel as u64;
+        loop {
+            let ld_len = ld.data.len();
+            if ld_len <= config.min_nr_level_data_size {
+                level_data.insert(spp, ld);
+                break;
+            }
+            spp *= 2;
+            let new_ld = LevelData::from_level_data(&ld, spp);
+            // This moves the data into level_data, so we have to calulate
+            // the new one before adding
+            level_data.insert(ld.samples_per_pixel as u64, ld);
+            ld = new_ld;
+        }
+        let res = Self { level_data };
+        tracing::trace!("{res}");
+        res
+    }
+}
+
+/// Builds a thumbnail from consecutive blocks of samples, for buffers that are streamed from disk
+/// and never in memory as a whole. Blocks need a multiple of `samples_per_pixel_delta` samples,
+/// except for the last one.
+#[derive(Debug, Clone)]
+pub struct ThumbnailBuilder<T: Sample> {
+    config: ThumbnailConfig,
+    finest_level: LevelData<T>,
+}
+
+impl<T: Sample> ThumbnailBuilder<T> {
+    pub fn new(bit_depth: u16, config: ThumbnailConfig) -> Self {
+        Self {
+            finest_level: LevelData {
+                samples_per_pixel: config.samples_per_pixel_delta as f64,
+                bit_depth,
+                data: vec![],
+            },
+            config,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[T]) {
+        self.finest_level.push_samples(samples);
+    }
+
+    pub fn finish(self) -> Thumbnail<T> {
+        Thumbnail::from_finest_level(self.finest_level, &self.config)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ThumbnailBuilderE {
+    F32(ThumbnailBuilder<f32>),
+    F64(ThumbnailBuilder<f64>),
+    I32(ThumbnailBuilder<i32>),
+    I16(ThumbnailBuilder<i16>),
+}
+
+impl ThumbnailBuilderE {
+    /// Builder for buffers of the same sample type and bit depth as `buffer`
+    pub fn like_buffer_e(buffer: &BufferE, config: ThumbnailConfig) -> Self {
+        match buffer {
+            BufferE::F32(buffer) => Self::F32(ThumbnailBuilder::new(buffer.bit_depth, config)),
+            BufferE::F64(buffer) => Self::F64(ThumbnailBuilder::new(buffer.bit_depth, config)),
+            BufferE::I32(buffer) => Self::I32(ThumbnailBuilder::new(buffer.bit_depth, config)),
+            BufferE::I16(buffer) => Self::I16(ThumbnailBuilder::new(buffer.bit_depth, config)),
+        }
+    }
+
+    pub fn push(&mut self, block: &BufferE) -> Result<()> {
+        match (self, block) {
+            (Self::F32(builder), BufferE::F32(block)) => builder.push(&block.data),
+            (Self::F64(builder), BufferE::F64(block)) => builder.push(&block.data),
+            (Self::I32(builder), BufferE::I32(block)) => builder.push(&block.data),
+            (Self::I16(builder), BufferE::I16(block)) => builder.push(&block.data),
+            _ => return Err(anyhow!("Block has another sample type than the thumbnail")),
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> ThumbnailE {
+        match self {
+            Self::F32(builder) => ThumbnailE::F32(builder.finish()),
+            Self::F64(builder) => ThumbnailE::F64(builder.finish()),
+            Self::I32(builder) => ThumbnailE::I32(builder.finish()),
+            Self::I16(builder) => ThumbnailE::I16(builder.finish()),
+        }
+    }
+}
+
 impl<T: Sample> Thumbnail<T> {
     // #[instrument(skip(self), fields(self = %self))]
     // pub fn get_sample_view(&self, sample_ix_range: sample::IxRange, samples_per_pixel: f64) -> Result<sample::ViewData<T>> {
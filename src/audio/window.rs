@@ -0,0 +1,83 @@
+//! Window functions for spectral analysis.
+
+use strum_macros::EnumIter;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter, serde::Deserialize, serde::Serialize,
+)]
+pub enum WindowFunction {
+    /// Good frequency resolution, general purpose
+    #[default]
+    Hann,
+    /// Low sidelobes (-92 dB), to see small spurs next to large tones
+    BlackmanHarris,
+    /// Flat passband, for accurate amplitudes of tones
+    FlatTop,
+}
+
+impl WindowFunction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowFunction::Hann => "Hann",
+            WindowFunction::BlackmanHarris => "Blackman-Harris",
+            WindowFunction::FlatTop => "flat-top",
+        }
+    }
+
+    /// Coefficients a_k of w[n] = Σ (-1)^k a_k cos(2πkn/N)
+    fn cosine_terms(&self) -> &'static [f64] {
+        match self {
+            WindowFunction::Hann => &[0.5, 0.5],
+            WindowFunction::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            WindowFunction::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_158,
+                0.083_578_947,
+                0.006_947_368,
+            ],
+        }
+    }
+
+    /// The periodic window of `len` samples, as used for a DFT of that length
+    pub fn coefficients(&self, len: usize) -> Vec<f64> {
+        let terms = self.cosine_terms();
+        (0..len)
+            .map(|ix| {
+                let phase = std::f64::consts::TAU * ix as f64 / len as f64;
+                terms
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                        sign * a * (k as f64 * phase).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hann_is_zero_at_start_and_one_in_the_middle() {
+        let window = WindowFunction::Hann.coefficients(8);
+
+        assert!(window[0].abs() < 1e-12);
+        assert!((window[4] - 1.0).abs() < 1e-12);
+        assert!((window[2] - window[6]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn windows_peak_at_about_one() {
+        use strum::IntoEnumIterator;
+        for window_function in WindowFunction::iter() {
+            let window = window_function.coefficients(64);
+            let max = window.iter().cloned().fold(f64::MIN, f64::max);
+            assert!((max - 1.0).abs() < 1e-3, "{window_function:?}: {max}");
+        }
+    }
+}
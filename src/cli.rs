@@ -0,0 +1,7 @@
+//! Headless subcommands, these run without opening a window.
+
+pub mod diff;
+pub mod export;
+pub mod info;
+mod json;
+pub mod render;
@@ -0,0 +1,357 @@
+//! `wavalyze diff --report`: compare two files per channel pair and check them against a
+//! tolerance, e.g. to test rendered audio against golden files in CI.
+
+use crate::{
+    args::{DiffReportArgs, ReportFormat},
+    audio::{SampleType, compare::Comparison},
+    cli::json,
+    wav::{
+        ChIx, ReadConfig,
+        read::{LoadedFile, read_to_loaded_file},
+    },
+};
+use anyhow::{Context, Result};
+use std::{fmt::Write, path::PathBuf};
+
+/// Exit code when the files are within tolerance
+pub const EXIT_CODE_OK: i32 = 0;
+/// Exit code when the files differ more than the tolerance
+pub const EXIT_CODE_MISMATCH: i32 = 1;
+/// Exit code when the files could not be compared
+pub const EXIT_CODE_ERROR: i32 = 2;
+
+/// How much A and B may differ for the comparison to pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Max |A−B| in LSBs, used when both files are int
+    pub max_lsb: f64,
+    /// Max |A−B| in dBFS, used when a file is float, None allows no difference
+    pub max_db: Option<f64>,
+    /// Min SNR in dB
+    pub min_snr_db: Option<f64>,
+}
+
+impl From<&DiffReportArgs> for Tolerance {
+    fn from(args: &DiffReportArgs) -> Self {
+        Self {
+            max_lsb: args.tolerance_lsb,
+            max_db: args.tolerance_db,
+            min_snr_db: args.min_snr_db,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelReport {
+    pub ch_ix_a: ChIx,
+    pub ch_ix_b: ChIx,
+    pub comparison: Comparison,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub path_a: Option<PathBuf>,
+    pub path_b: Option<PathBuf>,
+    /// Differences are expressed in LSBs of this bit depth when both files are int, in dB
+    /// otherwise
+    pub lsb_bit_depth: Option<u16>,
+    pub nr_channels_a: usize,
+    pub nr_channels_b: usize,
+    pub channels: Vec<ChannelReport>,
+}
+
+impl Report {
+    /// Compare the channels of A and B pairwise, in channel order.
+    pub fn new(file_a: &LoadedFile, file_b: &LoadedFile, tolerance: Tolerance) -> Result<Self> {
+        anyhow::ensure!(
+            file_a.sample_rate == file_b.sample_rate,
+            "Can't compare files with different sample rates: {} Hz and {} Hz",
+            file_a.sample_rate,
+            file_b.sample_rate
+        );
+        // The finest resolution of both files, so a difference of 1 LSB is never hidden
+        let lsb_bit_depth = (file_a.sample_type == SampleType::Int
+            && file_b.sample_type == SampleType::Int)
+            .then(|| file_a.bit_depth.max(file_b.bit_depth));
+
+        let channels = file_a
+            .channels
+            .iter()
+            .zip(file_b.channels.iter())
+            .map(|((ch_ix_a, buffer_a), (ch_ix_b, buffer_b))| {
+                let comparison = Comparison::new(buffer_a, buffer_b)?;
+                Ok(ChannelReport {
+                    ch_ix_a: *ch_ix_a,
+                    ch_ix_b: *ch_ix_b,
+                    passed: passes(&comparison, lsb_bit_depth, tolerance),
+                    comparison,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            path_a: file_a.path.clone(),
+            path_b: file_b.path.clone(),
+            lsb_bit_depth,
+            nr_channels_a: file_a.channels.len(),
+            nr_channels_b: file_b.channels.len(),
+            channels,
+        })
+    }
+
+    pub fn passed(&self) -> bool {
+        self.nr_channels_a == self.nr_channels_b && self.channels.iter().all(|ch| ch.passed)
+    }
+
+    fn max_abs_diff(&self, comparison: &Comparison) -> (f64, &'static str) {
+        match self.lsb_bit_depth {
+            Some(bit_depth) => (comparison.max_abs_diff_lsb(bit_depth), "LSB"),
+            None => (comparison.max_abs_diff_db(), "dB"),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut res = String::new();
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+        };
+        let _ = writeln!(res, "A: {}", path(&self.path_a));
+        let _ = writeln!(res, "B: {}", path(&self.path_b));
+        if self.nr_channels_a != self.nr_channels_b {
+            let _ = writeln!(
+                res,
+                "channel count differs: {} and {}",
+                self.nr_channels_a, self.nr_channels_b
+            );
+        }
+        for channel in &self.channels {
+            let comparison = &channel.comparison;
+            let (max_abs_diff, unit) = self.max_abs_diff(comparison);
+            let _ = write!(
+                res,
+                "ch {} - ch {}: {}",
+                channel.ch_ix_a,
+                channel.ch_ix_b,
+                if channel.passed { "ok" } else { "FAIL" }
+            );
+            if comparison.nr_samples_a != comparison.nr_samples_b {
+                let _ = write!(
+                    res,
+                    ", length differs: {} and {}",
+                    comparison.nr_samples_a, comparison.nr_samples_b
+                );
+            }
+            let first_mismatch = comparison
+                .first_mismatch
+                .map(|ix| ix.to_string())
+                .unwrap_or_else(|| String::from("-"));
+            let _ = writeln!(
+                res,
+                ", first mismatch: {first_mismatch}, mismatches: {}, max abs diff: {max_abs_diff:.2} {unit}, SNR: {:.2} dB",
+                comparison.nr_mismatches, comparison.snr_db
+            );
+        }
+        let _ = writeln!(res, "{}", if self.passed() { "PASS" } else { "FAIL" });
+        res
+    }
+
+    pub fn to_json(&self) -> String {
+        let path = |path: &Option<PathBuf>| match path {
+            Some(path) => json::string(&path.display().to_string()),
+            None => String::from("null"),
+        };
+        let channels: Vec<String> = self
+            .channels
+            .iter()
+            .map(|channel| {
+                let comparison = &channel.comparison;
+                let (max_abs_diff, unit) = self.max_abs_diff(comparison);
+                format!(
+                    concat!(
+                        "{{\"ch_ix_a\":{},\"ch_ix_b\":{},\"passed\":{},",
+                        "\"nr_samples_a\":{},\"nr_samples_b\":{},\"first_mismatch\":{},",
+                        "\"nr_mismatches\":{},\"max_abs_diff\":{},\"max_abs_diff_unit\":{},",
+                        "\"snr_db\":{}}}"
+                    ),
+                    channel.ch_ix_a,
+                    channel.ch_ix_b,
+                    channel.passed,
+                    comparison.nr_samples_a,
+                    comparison.nr_samples_b,
+                    json::optional(comparison.first_mismatch),
+                    comparison.nr_mismatches,
+                    json::number(max_abs_diff),
+                    json::string(unit),
+                    json::number(comparison.snr_db),
+                )
+            })
+            .collect();
+        format!(
+            "{{\"file_a\":{},\"file_b\":{},\"passed\":{},\"nr_channels_a\":{},\"nr_channels_b\":{},\"channels\":[{}]}}\n",
+            path(&self.path_a),
+            path(&self.path_b),
+            self.passed(),
+            self.nr_channels_a,
+            self.nr_channels_b,
+            channels.join(",")
+        )
+    }
+}
+
+fn passes(comparison: &Comparison, lsb_bit_depth: Option<u16>, tolerance: Tolerance) -> bool {
+    let within_max_diff = match (lsb_bit_depth, tolerance.max_db) {
+        (Some(bit_depth), _) => comparison.max_abs_diff_lsb(bit_depth) <= tolerance.max_lsb,
+        (None, Some(max_db)) => comparison.max_abs_diff_db() <= max_db,
+        (None, None) => comparison.nr_mismatches == 0,
+    };
+    let within_snr = tolerance
+        .min_snr_db
+        .is_none_or(|min_snr_db| comparison.snr_db >= min_snr_db);
+    within_max_diff && within_snr && comparison.nr_samples_a == comparison.nr_samples_b
+}
+
+/// Read both files and build the report.
+pub fn report(file_a: &ReadConfig, file_b: &ReadConfig, tolerance: Tolerance) -> Result<Report> {
+    let loaded_a = read_to_loaded_file(file_a)
+        .with_context(|| format!("Failed to read {}", file_a.filepath.display()))?;
+    let loaded_b = read_to_loaded_file(file_b)
+        .with_context(|| format!("Failed to read {}", file_b.filepath.display()))?;
+    Report::new(&loaded_a, &loaded_b, tolerance)
+}
+
+/// Print the report to stdout, the exit code tells whether the files are within tolerance.
+pub fn run(file_a: &ReadConfig, file_b: &ReadConfig, args: &DiffReportArgs) -> i32 {
+    match report(file_a, file_b, args.into()) {
+        Ok(report) => {
+            match args.format {
+                ReportFormat::Text => print!("{}", report.to_text()),
+                ReportFormat::Json => print!("{}", report.to_json()),
+            }
+            if report.passed() {
+                EXIT_CODE_OK
+            } else {
+                EXIT_CODE_MISMATCH
+            }
+        }
+        Err(err) => {
+            eprintln!("Error: {err:#}");
+            EXIT_CODE_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::{Buffer, BufferE};
+    use std::collections::BTreeMap;
+
+    fn loaded_i16(channels: &[&[i16]]) -> LoadedFile {
+        let channels = channels
+            .iter()
+            .enumerate()
+            .map(|(ch_ix, data)| {
+                let mut buffer = Buffer::<i16>::new(48_000, 16);
+                buffer.data = data.to_vec();
+                (ch_ix, BufferE::I16(buffer))
+            })
+            .collect::<BTreeMap<_, _>>();
+        LoadedFile {
+            load_id: 0,
+            nr_samples: channels.values().map(|b| b.nr_samples()).max().unwrap_or(0) as u64,
+            channels,
+            sample_type: SampleType::Int,
+            bit_depth: 16,
+            sample_rate: 48_000,
+            layout: None,
+            channel_ids: BTreeMap::new(),
+            path: Some(PathBuf::from("a.wav")),
+            bext: None,
+            cue_points: Vec::new(),
+            read_config: None,
+            streamed: Default::default(),
+        }
+    }
+
+    const EXACT: Tolerance = Tolerance {
+        max_lsb: 0.0,
+        max_db: None,
+        min_snr_db: None,
+    };
+
+    #[test]
+    fn lsb_tolerance_decides_pass() {
+        let a = loaded_i16(&[&[0, 10, 20], &[5, 5, 5]]);
+        let b = loaded_i16(&[&[0, 11, 20], &[5, 5, 5]]);
+
+        let report = Report::new(&a, &b, EXACT).unwrap();
+        assert!(!report.passed());
+        assert!(!report.channels[0].passed);
+        assert!(report.channels[1].passed);
+        assert_eq!(report.channels[0].comparison.first_mismatch, Some(1));
+
+        let tolerance = Tolerance {
+            max_lsb: 1.0,
+            ..EXACT
+        };
+        assert!(Report::new(&a, &b, tolerance).unwrap().passed());
+    }
+
+    #[test]
+    fn f64_difference_below_f32_resolution_fails_db_tolerance() {
+        let buffer_f64 = |value: f64| {
+            let mut buffer = Buffer::<f64>::new(48_000, 64);
+            buffer.data = vec![0.5, value];
+            BufferE::F64(buffer)
+        };
+        let comparison = Comparison::new(&buffer_f64(0.0), &buffer_f64(1e-50)).unwrap();
+
+        let max_abs_diff_db = comparison.max_abs_diff_db();
+        assert!(max_abs_diff_db.is_finite());
+        assert!((max_abs_diff_db - -1000.0).abs() < 1e-9);
+        let tolerance = Tolerance {
+            max_db: Some(-1200.0),
+            ..EXACT
+        };
+        assert!(!passes(&comparison, None, tolerance));
+    }
+
+    #[test]
+    fn different_channel_count_fails() {
+        let a = loaded_i16(&[&[0, 1], &[0, 1]]);
+        let b = loaded_i16(&[&[0, 1]]);
+
+        let report = Report::new(&a, &b, EXACT).unwrap();
+        assert_eq!(report.channels.len(), 1);
+        assert!(report.channels[0].passed);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn json_report_has_null_for_infinite_snr() {
+        let a = loaded_i16(&[&[0, 1]]);
+
+        let json = Report::new(&a, &a, EXACT).unwrap().to_json();
+
+        assert!(json.starts_with("{\"file_a\":\"a.wav\",\"file_b\":\"a.wav\",\"passed\":true,"));
+        assert!(json.contains("\"first_mismatch\":null"));
+        assert!(json.contains("\"max_abs_diff\":0,\"max_abs_diff_unit\":\"LSB\""));
+        assert!(json.contains("\"snr_db\":null"));
+    }
+
+    #[test]
+    fn text_report_lists_channel_pairs() {
+        let a = loaded_i16(&[&[0, 10]]);
+        let b = loaded_i16(&[&[0, 13]]);
+
+        let text = Report::new(&a, &b, EXACT).unwrap().to_text();
+
+        assert!(text.contains(
+            "ch 0 - ch 0: FAIL, first mismatch: 1, mismatches: 1, max abs diff: 3.00 LSB"
+        ));
+        assert!(text.ends_with("FAIL\n"));
+    }
+}
@@ -0,0 +1,128 @@
+//! `wavalyze export`: write channels and a sample range of a file to a new wav file, e.g. to cut
+//! test vectors out of a long capture.
+
+use crate::{
+    audio::sample::convert::RequantizeConfig,
+    wav::{
+        ReadConfig,
+        read::{LoadedFile, read_to_loaded_file},
+        write::{self, ChannelSource, WavFormat, WriteSpec},
+    },
+};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Exit code when the file was written
+pub const EXIT_CODE_OK: i32 = 0;
+/// Exit code when the file could not be read or written
+pub const EXIT_CODE_ERROR: i32 = 2;
+
+/// Channels of the loaded file in the order of the channel spec, or in file order without one
+fn channel_sources<'a>(
+    loaded: &'a LoadedFile,
+    config: &ReadConfig,
+) -> Result<Vec<ChannelSource<'a>>> {
+    let ch_ixs = config
+        .ch_ixs
+        .clone()
+        .unwrap_or_else(|| loaded.channels.keys().copied().collect());
+    ch_ixs
+        .iter()
+        .map(|ch_ix| {
+            let buffer = loaded
+                .channels
+                .get(ch_ix)
+                .with_context(|| format!("No channel {ch_ix}"))?;
+            Ok(ChannelSource {
+                buffer,
+                ix_rng: 0..buffer.nr_samples(),
+                offset: 0,
+            })
+        })
+        .collect()
+}
+
+/// Read the channels and range of `config` and write them to `output`, in `format` or else in
+/// the format of the file. Returns the number of channels and samples per channel written.
+pub fn export(
+    config: &ReadConfig,
+    output: &Path,
+    format: Option<WavFormat>,
+    requantize: &RequantizeConfig,
+) -> Result<(usize, usize)> {
+    let loaded = read_to_loaded_file(config)
+        .with_context(|| format!("Failed to read {}", config.filepath.display()))?;
+    let channels = channel_sources(&loaded, config)?;
+    let first = channels.first().context("No channels to export")?;
+    let spec = WriteSpec {
+        sample_rate: loaded.sample_rate,
+        format: format.unwrap_or_else(|| WavFormat::of_buffer(first.buffer)),
+        requantize: *requantize,
+    };
+    let nr_samples = first.ix_rng.len();
+    write::write_wav(output, &spec, &channels, nr_samples)?;
+    Ok((channels.len(), nr_samples))
+}
+
+pub fn run(
+    config: &ReadConfig,
+    output: &Path,
+    format: Option<WavFormat>,
+    requantize: &RequantizeConfig,
+) -> i32 {
+    match export(config, output, format, requantize) {
+        Ok((nr_channels, nr_samples)) => {
+            println!(
+                "Wrote {nr_channels} channel(s) of {nr_samples} samples to {}",
+                output.display()
+            );
+            EXIT_CODE_OK
+        }
+        Err(err) => {
+            eprintln!("Error: {err:#}");
+            EXIT_CODE_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::BufferE;
+
+    #[test]
+    fn channels_and_range_are_exported_in_spec_order() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("wavalyze_export_in_{}.wav", std::process::id()));
+        let output = dir.join(format!("wavalyze_export_out_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 3,
+            sample_rate: 44_100,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+        for sample in 0..300 {
+            writer.write_sample(sample * 1000).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let config: ReadConfig = format!("{}:2,0:10-20", input.display()).parse().unwrap();
+        let written = export(&config, &output, None, &RequantizeConfig::default());
+        let loaded = read_to_loaded_file(&ReadConfig::new(&output));
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(written.unwrap(), (2, 10));
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.bit_depth, 24);
+        assert_eq!(loaded.sample_rate, 44_100);
+        let BufferE::I32(first) = &loaded.channels[&0] else {
+            panic!("expected i32 samples");
+        };
+        assert_eq!(
+            first.data,
+            (10..20).map(|ix| (ix * 3 + 2) * 1000).collect::<Vec<i32>>()
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! `wavalyze info`: print the format of wav files, e.g. for use in shell scripts.
+
+use crate::{
+    audio::SampleType,
+    cli::json,
+    wav::read::{FileInfo, read_file_info},
+};
+use anyhow::Context;
+use std::{fmt::Write, path::PathBuf};
+
+/// Exit code when all files could be read
+pub const EXIT_CODE_OK: i32 = 0;
+/// Exit code when a file could not be read
+pub const EXIT_CODE_ERROR: i32 = 2;
+
+fn sample_type_name(sample_type: SampleType) -> &'static str {
+    match sample_type {
+        SampleType::Float => "float",
+        SampleType::Int => "int",
+    }
+}
+
+/// Short names of the channels in file order, None when the file has no channel mask
+fn layout_names(info: &FileInfo) -> Option<Vec<&'static str>> {
+    info.layout
+        .as_ref()
+        .map(|_| info.channel_ids.iter().map(|id| id.short_name()).collect())
+}
+
+pub fn to_text(info: &FileInfo) -> String {
+    let mut res = String::new();
+    let _ = writeln!(res, "{}", info.path.display());
+    let _ = writeln!(res, "  sample rate: {} Hz", info.sample_rate);
+    let _ = writeln!(res, "  bit depth: {}", info.bit_depth);
+    let _ = writeln!(res, "  sample type: {}", sample_type_name(info.sample_type));
+    let _ = writeln!(res, "  channels: {}", info.nr_channels);
+    let _ = writeln!(
+        res,
+        "  length: {} samples ({:.3} s)",
+        info.nr_samples,
+        info.duration_s()
+    );
+    let layout = layout_names(info)
+        .map(|names| names.join(" "))
+        .unwrap_or_else(|| String::from("-"));
+    let _ = writeln!(res, "  layout: {layout}");
+    res
+}
+
+pub fn to_json(info: &FileInfo) -> String {
+    let layout = match layout_names(info) {
+        Some(names) => format!(
+            "[{}]",
+            names
+                .iter()
+                .map(|name| json::string(name))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        None => String::from("null"),
+    };
+    format!(
+        concat!(
+            "{{\"path\":{},\"sample_rate\":{},\"bit_depth\":{},\"sample_type\":{},",
+            "\"nr_channels\":{},\"nr_samples\":{},\"duration_s\":{},\"layout\":{}}}"
+        ),
+        json::string(&info.path.display().to_string()),
+        info.sample_rate,
+        info.bit_depth,
+        json::string(sample_type_name(info.sample_type)),
+        info.nr_channels,
+        info.nr_samples,
+        json::number(info.duration_s()),
+        layout,
+    )
+}
+
+/// Print the info of each file to stdout, as text or as a JSON array.
+///
+/// Files that can't be read are reported on stderr and skipped, the exit code tells whether
+/// all files could be read.
+pub fn run(files: &[PathBuf], json: bool) -> i32 {
+    let mut exit_code = EXIT_CODE_OK;
+    let infos: Vec<FileInfo> = files
+        .iter()
+        .filter_map(|path| {
+            match read_file_info(path).with_context(|| format!("Failed to read {}", path.display()))
+            {
+                Ok(info) => Some(info),
+                Err(err) => {
+                    eprintln!("Error: {err:#}");
+                    exit_code = EXIT_CODE_ERROR;
+                    None
+                }
+            }
+        })
+        .collect();
+
+    if json {
+        let infos: Vec<String> = infos.iter().map(to_json).collect();
+        println!("[{}]", infos.join(","));
+    } else {
+        let infos: Vec<String> = infos.iter().map(to_text).collect();
+        print!("{}", infos.join("\n"));
+    }
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{Id, Layout};
+
+    fn stereo_info() -> FileInfo {
+        FileInfo {
+            path: PathBuf::from("song.wav"),
+            sample_type: SampleType::Int,
+            bit_depth: 24,
+            sample_rate: 48_000,
+            nr_channels: 2,
+            nr_samples: 24_000,
+            layout: Some(Layout::from_ids(&[Id::Left, Id::Right])),
+            channel_ids: vec![Id::Left, Id::Right],
+        }
+    }
+
+    #[test]
+    fn text_lists_format_and_layout() {
+        let text = to_text(&stereo_info());
+
+        assert!(text.starts_with("song.wav\n"));
+        assert!(text.contains("  sample rate: 48000 Hz\n"));
+        assert!(text.contains("  sample type: int\n"));
+        assert!(text.contains("  length: 24000 samples (0.500 s)\n"));
+        assert!(text.ends_with("  layout: L R\n"));
+    }
+
+    #[test]
+    fn json_has_null_layout_without_channel_mask() {
+        let info = FileInfo {
+            layout: None,
+            channel_ids: Vec::new(),
+            ..stereo_info()
+        };
+
+        assert_eq!(
+            to_json(&info),
+            concat!(
+                "{\"path\":\"song.wav\",\"sample_rate\":48000,\"bit_depth\":24,",
+                "\"sample_type\":\"int\",\"nr_channels\":2,\"nr_samples\":24000,",
+                "\"duration_s\":0.5,\"layout\":null}"
+            )
+        );
+        assert!(to_json(&stereo_info()).ends_with("\"layout\":[\"L\",\"R\"]}"));
+    }
+}
@@ -0,0 +1,74 @@
+//! `wavalyze render`: draw waveforms to a PNG or SVG image, e.g. for bug reports and CI artifacts.
+
+use crate::{
+    render::{self, RenderConfig},
+    wav::{ReadConfig, read::read_to_loaded_file},
+};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Exit code when the image was written
+pub const EXIT_CODE_OK: i32 = 0;
+/// Exit code when a file could not be read or the image not written
+pub const EXIT_CODE_ERROR: i32 = 2;
+
+/// Read the channels and ranges of `configs` and draw them to `output`. Returns the number of
+/// channels drawn.
+pub fn render(configs: &[ReadConfig], output: &Path, config: &RenderConfig) -> Result<usize> {
+    let files = configs
+        .iter()
+        .map(|read_config| {
+            read_to_loaded_file(read_config)
+                .with_context(|| format!("Failed to read {}", read_config.filepath.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    render::render_to_file(&files, config, output)?;
+    Ok(files.iter().map(|file| file.channels.len()).sum())
+}
+
+pub fn run(configs: &[ReadConfig], output: &Path, config: &RenderConfig) -> i32 {
+    match render(configs, output, config) {
+        Ok(nr_channels) => {
+            println!("Drew {nr_channels} channel(s) to {}", output.display());
+            EXIT_CODE_OK
+        }
+        Err(err) => {
+            eprintln!("Error: {err:#}");
+            EXIT_CODE_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selected_channels_are_drawn_to_svg() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("wavalyze_render_in_{}.wav", std::process::id()));
+        let output = dir.join(format!("wavalyze_render_out_{}.svg", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+        for sample in 0..2000_i16 {
+            writer.write_sample(sample * 8).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let config: ReadConfig = format!("{}:1", input.display()).parse().unwrap();
+        let drawn = render(&[config], &output, &RenderConfig::default());
+        let svg = std::fs::read_to_string(&output);
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(drawn.unwrap(), 1);
+        let svg = svg.unwrap();
+        assert!(svg.contains(" ch 1</text>"));
+        assert!(!svg.contains(" ch 0</text>"));
+    }
+}
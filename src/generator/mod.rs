@@ -1,2 +1,439 @@
+//! Test signals, generated as files that are added to the tracks like an opened file.
+
+pub mod noise;
 pub mod sine;
+pub mod sweep;
 pub use sine::Sine;
+
+use crate::{
+    audio::{
+        buffer::{Buffer, BufferE},
+        sample::convert::{RequantizeConfig, Requantizer},
+    },
+    wav::{read::LoadedFile, write::WavFormat},
+};
+use anyhow::{Result, ensure};
+use std::f64::consts::{PI, TAU};
+
+/// Most channels a generated file can have
+pub const MAX_NR_CHANNELS: usize = 64;
+
+/// Most samples over all channels a generated file can have, 1 GiB as 32 bit float
+pub const MAX_NR_SAMPLES: usize = 1 << 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    /// Not band limited, so it aliases at high frequencies
+    Square,
+    /// Rising from -1 to 1, not band limited
+    Sawtooth,
+    WhiteNoise,
+    PinkNoise,
+    LinearSweep,
+    LogSweep,
+    /// A single full scale sample at the start, the rest silent
+    Impulse,
+    /// Tones spaced logarithmically from the start to the end frequency
+    Multitone,
+    Silence,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 10] = [
+        Waveform::Sine,
+        Waveform::Square,
+        Waveform::Sawtooth,
+        Waveform::WhiteNoise,
+        Waveform::PinkNoise,
+        Waveform::LinearSweep,
+        Waveform::LogSweep,
+        Waveform::Impulse,
+        Waveform::Multitone,
+        Waveform::Silence,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Square => "square",
+            Waveform::Sawtooth => "sawtooth",
+            Waveform::WhiteNoise => "white noise",
+            Waveform::PinkNoise => "pink noise",
+            Waveform::LinearSweep => "linear sweep",
+            Waveform::LogSweep => "log sweep",
+            Waveform::Impulse => "impulse",
+            Waveform::Multitone => "multitone",
+            Waveform::Silence => "silence",
+        }
+    }
+
+    pub fn uses_frequency(self) -> bool {
+        matches!(
+            self,
+            Waveform::Sine
+                | Waveform::Square
+                | Waveform::Sawtooth
+                | Waveform::LinearSweep
+                | Waveform::LogSweep
+                | Waveform::Multitone
+        )
+    }
+
+    pub fn uses_end_frequency(self) -> bool {
+        matches!(
+            self,
+            Waveform::LinearSweep | Waveform::LogSweep | Waveform::Multitone
+        )
+    }
+
+    pub fn uses_seed(self) -> bool {
+        matches!(self, Waveform::WhiteNoise | Waveform::PinkNoise)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorConfig {
+    pub waveform: Waveform,
+    /// Peak amplitude, 1.0 is full scale
+    pub amplitude: f64,
+    /// Frequency in Hz, the start of a sweep or the lowest tone of a multitone
+    pub frequency: f64,
+    /// End of a sweep or highest tone of a multitone, in Hz
+    pub end_frequency: f64,
+    pub nr_tones: usize,
+    pub duration_s: f64,
+    pub sample_rate: u32,
+    /// Int formats are rounded to the nearest value, so the samples are exactly what a file of
+    /// that format holds
+    pub format: WavFormat,
+    pub nr_channels: usize,
+    /// Seed of the noise, each channel gets different noise
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Sine,
+            amplitude: 0.5,
+            frequency: 1000.0,
+            end_frequency: 20_000.0,
+            nr_tones: 10,
+            duration_s: 1.0,
+            sample_rate: 48_000,
+            format: WavFormat::Float32,
+            nr_channels: 1,
+            seed: 0,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn nr_samples(&self) -> usize {
+        (self.duration_s * self.sample_rate as f64).round() as usize
+    }
+
+    /// Name of the generated file, e.g. `sine 1000 Hz`
+    pub fn name(&self) -> String {
+        let waveform = self.waveform.name();
+        if self.waveform.uses_end_frequency() {
+            format!("{waveform} {}-{} Hz", self.frequency, self.end_frequency)
+        } else if self.waveform.uses_frequency() {
+            format!("{waveform} {} Hz", self.frequency)
+        } else {
+            waveform.to_string()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.sample_rate > 0, "The sample rate must be positive");
+        ensure!(
+            self.duration_s.is_finite() && self.duration_s > 0.0,
+            "The duration must be positive"
+        );
+        ensure!(
+            self.amplitude.is_finite() && self.amplitude >= 0.0,
+            "The amplitude must be positive"
+        );
+        ensure!(
+            (1..=MAX_NR_CHANNELS).contains(&self.nr_channels),
+            "The number of channels must be 1 to {MAX_NR_CHANNELS}"
+        );
+        ensure!(
+            self.duration_s * self.sample_rate as f64 * self.nr_channels as f64
+                <= MAX_NR_SAMPLES as f64,
+            "At most {MAX_NR_SAMPLES} samples over all channels can be generated, shorten the \
+             duration or use fewer channels"
+        );
+        let nyquist = self.sample_rate as f64 / 2.0;
+        if self.waveform.uses_frequency() {
+            ensure!(
+                self.frequency > 0.0 && self.frequency <= nyquist,
+                "The frequency must be above 0 and at most {nyquist} Hz"
+            );
+        }
+        if self.waveform.uses_end_frequency() {
+            ensure!(
+                self.end_frequency > 0.0 && self.end_frequency <= nyquist,
+                "The end frequency must be above 0 and at most {nyquist} Hz"
+            );
+        }
+        if self.waveform == Waveform::Multitone {
+            ensure!(self.nr_tones > 0, "A multitone needs at least one tone");
+        }
+        Ok(())
+    }
+
+    /// Normalized samples of channel `ch_ix`, only noise differs per channel
+    pub fn samples(&self, ch_ix: usize) -> Vec<f64> {
+        let nr_samples = self.nr_samples();
+        let sample_rate = self.sample_rate as f64;
+        let amplitude = self.amplitude;
+        let time = |ix: usize| ix as f64 / sample_rate;
+        let periodic = |shape: fn(f64) -> f64| -> Vec<f64> {
+            (0..nr_samples)
+                .map(|ix| amplitude * shape((time(ix) * self.frequency).fract()))
+                .collect()
+        };
+        let seed = self.seed.wrapping_add(ch_ix as u64);
+        match self.waveform {
+            Waveform::Sine => periodic(|cycle| (TAU * cycle).sin()),
+            Waveform::Square => periodic(|cycle| if cycle < 0.5 { 1.0 } else { -1.0 }),
+            Waveform::Sawtooth => periodic(|cycle| 2.0 * cycle - 1.0),
+            Waveform::WhiteNoise => normalized(noise::white(nr_samples, seed), amplitude),
+            Waveform::PinkNoise => normalized(noise::pink(nr_samples, seed), amplitude),
+            Waveform::LinearSweep | Waveform::LogSweep => {
+                let phase = if self.waveform == Waveform::LinearSweep {
+                    sweep::linear_phase
+                } else {
+                    sweep::log_phase
+                };
+                (0..nr_samples)
+                    .map(|ix| {
+                        let phase = phase(
+                            time(ix),
+                            self.frequency,
+                            self.end_frequency,
+                            self.duration_s,
+                        );
+                        amplitude * phase.sin()
+                    })
+                    .collect()
+            }
+            Waveform::Impulse => {
+                let mut values = vec![0.0; nr_samples];
+                if let Some(first) = values.first_mut() {
+                    *first = amplitude;
+                }
+                values
+            }
+            Waveform::Multitone => self.multitone(),
+            Waveform::Silence => vec![0.0; nr_samples],
+        }
+    }
+
+    /// Tones of equal level with Schroeder phases, which keep the crest factor low. Each tone
+    /// has `amplitude / nr_tones`, so the peak never exceeds the amplitude.
+    fn multitone(&self) -> Vec<f64> {
+        let nr_tones = self.nr_tones.max(1);
+        let ratio = self.end_frequency / self.frequency;
+        let tones: Vec<(f64, f64)> = (0..nr_tones)
+            .map(|tone_ix| {
+                let position = match nr_tones {
+                    1 => 0.0,
+                    _ => tone_ix as f64 / (nr_tones - 1) as f64,
+                };
+                let frequency = self.frequency * ratio.powf(position);
+                let phase = -PI * (tone_ix * (tone_ix + 1)) as f64 / nr_tones as f64;
+                (frequency, phase)
+            })
+            .collect();
+        let tone_amplitude = self.amplitude / nr_tones as f64;
+        (0..self.nr_samples())
+            .map(|ix| {
+                let t = ix as f64 / self.sample_rate as f64;
+                tones
+                    .iter()
+                    .map(|(frequency, phase)| (TAU * frequency * t + phase).sin())
+                    .sum::<f64>()
+                    * tone_amplitude
+            })
+            .collect()
+    }
+}
+
+/// Scale so the peak is `amplitude`
+fn normalized(values: Vec<f64>, amplitude: f64) -> Vec<f64> {
+    let peak = values
+        .iter()
+        .fold(0.0_f64, |peak, value| peak.max(value.abs()));
+    if peak == 0.0 {
+        return values;
+    }
+    let gain = amplitude / peak;
+    values.into_iter().map(|value| value * gain).collect()
+}
+
+/// Buffer in `format`, int formats rounded to the nearest value and clipped
+fn to_buffer(values: Vec<f64>, sample_rate: u32, format: WavFormat) -> BufferE {
+    let bit_depth = format.bit_depth();
+    let mut requantizer = Requantizer::new(bit_depth as u32, &RequantizeConfig::default(), 0);
+    match format {
+        WavFormat::Int16 => BufferE::I16(Buffer {
+            sample_rate,
+            bit_depth,
+            data: values
+                .into_iter()
+                .map(|value| requantizer.requantize(value) as i16)
+                .collect(),
+        }),
+        WavFormat::Int24 | WavFormat::Int32 => BufferE::I32(Buffer {
+            sample_rate,
+            bit_depth,
+            data: values
+                .into_iter()
+                .map(|value| requantizer.requantize(value))
+                .collect(),
+        }),
+        WavFormat::Float32 => BufferE::F32(Buffer {
+            sample_rate,
+            bit_depth,
+            data: values.into_iter().map(|value| value as f32).collect(),
+        }),
+        WavFormat::Float64 => BufferE::F64(Buffer {
+            sample_rate,
+            bit_depth,
+            data: values,
+        }),
+    }
+}
+
+/// Generate the signal as a file without path, named after the signal
+pub fn generate(config: &GeneratorConfig) -> Result<LoadedFile> {
+    config.validate()?;
+    let channels = (0..config.nr_channels)
+        .map(|ch_ix| {
+            let buffer = to_buffer(config.samples(ch_ix), config.sample_rate, config.format);
+            (ch_ix, buffer)
+        })
+        .collect();
+    Ok(LoadedFile {
+        load_id: 0,
+        channels,
+        sample_type: config.format.sample_type(),
+        bit_depth: config.format.bit_depth(),
+        sample_rate: config.sample_rate,
+        layout: None,
+        channel_ids: Default::default(),
+        path: Some(format!("{}.wav", config.name()).into()),
+        nr_samples: config.nr_samples() as u64,
+        bext: None,
+        cue_points: Vec::new(),
+        read_config: None,
+        streamed: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(waveform: Waveform) -> GeneratorConfig {
+        GeneratorConfig {
+            waveform,
+            amplitude: 1.0,
+            frequency: 12_000.0,
+            duration_s: 0.001,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn periodic_waveforms_at_a_quarter_of_the_sample_rate() {
+        let samples = |waveform| {
+            let values = config(waveform).samples(0);
+            values[..4]
+                .iter()
+                .map(|value| (value * 1e9).round() / 1e9)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(samples(Waveform::Sine), [0.0, 1.0, 0.0, -1.0]);
+        assert_eq!(samples(Waveform::Square), [1.0, 1.0, -1.0, -1.0]);
+        assert_eq!(samples(Waveform::Sawtooth), [-1.0, -0.5, 0.0, 0.5]);
+        assert_eq!(samples(Waveform::Impulse), [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(samples(Waveform::Silence), [0.0; 4]);
+    }
+
+    #[test]
+    fn pcm16_sine_is_quantized_exactly() {
+        let config = GeneratorConfig {
+            format: WavFormat::Int16,
+            ..config(Waveform::Sine)
+        };
+        let loaded = generate(&config).unwrap();
+        let BufferE::I16(buffer) = &loaded.channels[&0] else {
+            panic!("expected i16 samples");
+        };
+        assert_eq!(buffer.data[..4], [0, 32767, 0, -32768]);
+        assert_eq!(loaded.nr_samples, 48);
+        assert_eq!(loaded.bit_depth, 16);
+    }
+
+    #[test]
+    fn noise_is_seeded_per_channel_and_peaks_at_the_amplitude() {
+        let config = GeneratorConfig {
+            amplitude: 0.5,
+            nr_channels: 2,
+            seed: 9,
+            ..config(Waveform::PinkNoise)
+        };
+        let left = config.samples(0);
+        assert_eq!(left, config.samples(0));
+        assert_ne!(left, config.samples(1));
+        let peak = left
+            .iter()
+            .fold(0.0_f64, |peak, value| peak.max(value.abs()));
+        assert!((peak - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn multitone_stays_below_the_amplitude() {
+        let config = GeneratorConfig {
+            frequency: 100.0,
+            end_frequency: 10_000.0,
+            duration_s: 0.1,
+            ..config(Waveform::Multitone)
+        };
+        let values = config.samples(0);
+        assert!(values.iter().all(|value| value.abs() <= 1.0));
+        assert!(values.iter().any(|value| value.abs() > 0.3));
+        assert!(
+            GeneratorConfig {
+                end_frequency: 30_000.0,
+                ..config
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn too_many_samples_are_refused() {
+        let config = GeneratorConfig {
+            duration_s: 3600.0,
+            sample_rate: 192_000,
+            nr_channels: 2,
+            ..config(Waveform::Sine)
+        };
+        assert!(config.validate().is_err());
+        assert!(
+            GeneratorConfig {
+                duration_s: 60.0,
+                ..config
+            }
+            .validate()
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::f64::consts::TAU;
+
+/// Phase in radians at `t` seconds of a sweep whose frequency goes linearly from `f0` to `f1`
+/// in `duration_s`
+pub fn linear_phase(t: f64, f0: f64, f1: f64, duration_s: f64) -> f64 {
+    let rate = (f1 - f0) / duration_s.max(f64::MIN_POSITIVE);
+    TAU * (f0 * t + 0.5 * rate * t * t)
+}
+
+/// Phase in radians at `t` seconds of an exponential sweep from `f0` to `f1` in `duration_s`,
+/// with the same time per octave. Falls back to a constant frequency when `f0` equals `f1`.
+pub fn log_phase(t: f64, f0: f64, f1: f64, duration_s: f64) -> f64 {
+    let ln_ratio = (f1 / f0).ln();
+    if ln_ratio.abs() < 1e-12 {
+        return TAU * f0 * t;
+    }
+    let duration_s = duration_s.max(f64::MIN_POSITIVE);
+    TAU * f0 * duration_s / ln_ratio * ((t / duration_s * ln_ratio).exp() - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Instantaneous frequency from the phase difference over a short time
+    fn frequency(phase: impl Fn(f64) -> f64, t: f64) -> f64 {
+        let dt = 1e-6;
+        (phase(t + dt) - phase(t)) / (TAU * dt)
+    }
+
+    #[test]
+    fn sweeps_go_from_start_to_end_frequency() {
+        let linear = |t| linear_phase(t, 100.0, 1100.0, 2.0);
+        assert!((frequency(linear, 0.0) - 100.0).abs() < 0.01);
+        assert!((frequency(linear, 1.0) - 600.0).abs() < 0.01);
+        assert!((frequency(linear, 2.0) - 1100.0).abs() < 0.01);
+
+        let log = |t| log_phase(t, 100.0, 1600.0, 4.0);
+        assert!((frequency(log, 0.0) - 100.0).abs() < 0.01);
+        // One octave per second
+        assert!((frequency(log, 1.0) - 200.0).abs() < 0.01);
+        assert!((frequency(log, 4.0) - 1600.0).abs() < 0.1);
+    }
+}
@@ -7,12 +7,14 @@
 pub mod app;
 pub mod args;
 pub mod audio;
+pub mod cli;
 pub mod generator;
 pub mod log;
 pub mod math;
 pub mod model;
 pub mod pos;
 pub mod rect;
+pub mod render;
 pub mod sample;
 pub mod util;
 pub mod view;
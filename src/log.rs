@@ -36,7 +36,11 @@ pub fn init_tracing(log_level: Option<&str>) -> Result<()> {
     filter = filter.add_directive("egui=warn".parse()?);
     filter = filter.add_directive("eframe=warn".parse()?);
 
-    let subscriber = fmt().with_env_filter(filter).finish();
+    // Log to stderr, stdout is reserved for the output of the headless subcommands
+    let subscriber = fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .finish();
 
     tracing::subscriber::set_global_default(subscriber)
         .context("failed to set global tracing subscriber")?;
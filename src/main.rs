@@ -12,6 +12,41 @@ fn main() -> anyhow::Result<()> {
 
     log::init_tracing(args2.log_level.as_deref())?;
 
+    if let Some(wavalyze::args::Commands::Diff {
+        file1,
+        file2,
+        report,
+    }) = &args2.command
+        && report.report
+    {
+        std::process::exit(wavalyze::cli::diff::run(file1, file2, report));
+    }
+    if let Some(wavalyze::args::Commands::Info { files, json }) = &args2.command {
+        std::process::exit(wavalyze::cli::info::run(files, *json));
+    }
+    if let Some(wavalyze::args::Commands::Export {
+        file,
+        output,
+        format,
+        requantize,
+    }) = &args2.command
+    {
+        std::process::exit(wavalyze::cli::export::run(
+            file,
+            output,
+            *format,
+            &requantize.into(),
+        ));
+    }
+    if let Some(wavalyze::args::Commands::Render {
+        files,
+        output,
+        render,
+    }) = &args2.command
+    {
+        std::process::exit(wavalyze::cli::render::run(files, output, &render.into()));
+    }
+
     // let args = wavalyze::AppCliConfig::parse();
     let user_config = model::Config::load_from_storage_or_default();
 
@@ -0,0 +1,171 @@
+//! Iterative radix-2 fast fourier transform.
+
+use anyhow::Result;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self { re: 0.0, im: 0.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn norm(self) -> f64 {
+        self.norm_sqr().sqrt()
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.re * rhs, self.im * rhs)
+    }
+}
+
+/// Forward transform in place, the length must be a power of two.
+pub fn fft(data: &mut [Complex]) -> Result<()> {
+    transform(data, false)
+}
+
+/// Inverse transform in place, including the 1/n scaling.
+pub fn ifft(data: &mut [Complex]) -> Result<()> {
+    transform(data, true)?;
+    let scale = 1.0 / data.len() as f64;
+    for value in data.iter_mut() {
+        *value = *value * scale;
+    }
+    Ok(())
+}
+
+fn transform(data: &mut [Complex], inverse: bool) -> Result<()> {
+    let n = data.len();
+    anyhow::ensure!(
+        n.is_power_of_two(),
+        "FFT length must be a power of two, got {n}"
+    );
+    if n == 1 {
+        return Ok(());
+    }
+
+    // bit reversal permutation
+    let nr_bits = n.trailing_zeros();
+    for ix in 0..n {
+        let rev_ix = ix.reverse_bits() >> (usize::BITS - nr_bits);
+        if ix < rev_ix {
+            data.swap(ix, rev_ix);
+        }
+    }
+
+    // twiddle factors for the largest stage, smaller stages use every `stride`th one
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let twiddles: Vec<Complex> = (0..n / 2)
+        .map(|k| {
+            let (sin, cos) = (sign * std::f64::consts::TAU * k as f64 / n as f64).sin_cos();
+            Complex::new(cos, sin)
+        })
+        .collect();
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let stride = n / len;
+        for chunk in data.chunks_exact_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(half);
+            for k in 0..half {
+                let t = hi[k] * twiddles[k * stride];
+                hi[k] = lo[k] - t;
+                lo[k] = lo[k] + t;
+            }
+        }
+        len *= 2;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_dft(data: &[Complex]) -> Vec<Complex> {
+        let n = data.len();
+        (0..n)
+            .map(|k| {
+                data.iter().enumerate().fold(Complex::ZERO, |acc, (ix, x)| {
+                    let angle = -std::f64::consts::TAU * (k * ix) as f64 / n as f64;
+                    acc + *x * Complex::new(angle.cos(), angle.sin())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fft_matches_naive_dft() {
+        let data: Vec<Complex> = (0..16)
+            .map(|ix| Complex::new((ix as f64 * 0.7).sin(), (ix as f64 * 0.3).cos()))
+            .collect();
+        let mut act = data.clone();
+
+        fft(&mut act).unwrap();
+
+        for (act, exp) in act.iter().zip(naive_dft(&data)) {
+            assert!((*act - exp).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn ifft_inverts_fft() {
+        let data: Vec<Complex> = (0..8).map(|ix| Complex::new(ix as f64, 0.0)).collect();
+        let mut act = data.clone();
+
+        fft(&mut act).unwrap();
+        ifft(&mut act).unwrap();
+
+        for (act, exp) in act.iter().zip(&data) {
+            assert!((*act - *exp).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn length_must_be_power_of_two() {
+        let mut data = vec![Complex::ZERO; 6];
+        assert!(fft(&mut data).is_err());
+    }
+}
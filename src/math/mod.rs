@@ -1,2 +1,4 @@
 pub mod compare;
+pub mod fft;
+pub mod rng;
 pub mod round;
@@ -1,5 +1,15 @@
 use crate::{
-    model::{PixelCoord, hover_info::HoverInfoE, selection_info::SelectionInfoE, track::TrackId},
+    generator,
+    model::{
+        PixelCoord,
+        align::AlignTarget,
+        export::{self, ExportConfig},
+        hover_info::HoverInfoE,
+        markers::{JumpDirection, Marker, MarkerId},
+        selection_info::SelectionInfoE,
+        session,
+        track::{DisplayMode, TrackId},
+    },
     wav,
 };
 use anyhow::{Context, Result};
@@ -11,7 +21,14 @@ pub enum Action {
 
     OpenFile(wav::ReadConfig),
     OpenFileBytes(wav::ReadConfigBytes),
+    /// Open two files and add a track comparing them per channel pair, see `track::diff`
+    OpenDiff {
+        file_a: wav::ReadConfig,
+        file_b: wav::ReadConfig,
+    },
     LoadDemo,
+    /// Add a generated test signal as a new file, see `generator::generate`
+    Generate(generator::GeneratorConfig),
 
     /// Set x-zoom so the longest track is full width
     /// Set y-zoom to fill the screen, with a minimum height per track
@@ -23,6 +40,48 @@ pub enum Action {
     /// Set x-zoom to sample-level detail, centered on the right edge of the current selection.
     ZoomToSelectionRightEdge,
 
+    /// Pan to center the next or previous marker, keeping the zoom level
+    JumpToMarker(JumpDirection),
+    /// Pan to center a marker, keeping the zoom level
+    CenterOnMarker(MarkerId),
+    AddMarker(Marker),
+    RemoveMarker(MarkerId),
+    RenameMarker {
+        marker_id: MarkerId,
+        label: String,
+    },
+    /// Move a marker to `sample_ix` on the time line
+    MoveMarker {
+        marker_id: MarkerId,
+        sample_ix: f64,
+    },
+    /// Set the colour of a marker, None for the theme's marker colour
+    SetMarkerColor {
+        marker_id: MarkerId,
+        color: Option<egui::Color32>,
+    },
+    /// Select the samples of a region marker
+    SelectMarkerRegion(MarkerId),
+    /// Add the labels of an Audacity label track as markers
+    ImportLabels(std::path::PathBuf),
+    /// Write the markers to a label track or CSV file
+    ExportMarkers {
+        path: std::path::PathBuf,
+        format: wav::labels::LabelFormat,
+    },
+    /// Write the selection, or the whole buffers when nothing is selected, to a new wav file
+    ExportSelection(ExportConfig),
+    /// Write the open files and how they are shown to a session file
+    SaveSession(std::path::PathBuf),
+    /// Close the file at this index in `Model::files2` and free its memory
+    CloseFile(usize),
+    /// Close all files and free their memory
+    CloseAllFiles,
+    /// Replace the open files by those of a session file
+    LoadSession(std::path::PathBuf),
+    /// Read the opened files of this path again, keeping how their tracks are shown
+    ReloadFile(std::path::PathBuf),
+
     /// Adjust height of tracks to fit the screen, keeping in mind the min_height for each track
     FillScreenHeight,
 
@@ -48,6 +107,24 @@ pub enum Action {
     },
     /// Reset the sample value range to full-scale for all tracks.
     RecenterYAll,
+    /// Draw the items of `track_id` on top of `onto_track_id` and remove `track_id`
+    OverlayTrack {
+        track_id: TrackId,
+        onto_track_id: TrackId,
+    },
+    /// Move the overlays of a track back to their own tracks
+    SplitOverlays(TrackId),
+    /// Shift an item so it lines up with its reference, using the lag estimated by
+    /// cross-correlation within the selection, or over the whole items without one
+    AlignToReference(AlignTarget),
+    /// Move the tracks of Broadcast Wave files to their TimeReference, or back to the start,
+    /// following `Config::place_at_time_reference`
+    PlaceFilesAtTimeReference,
+    /// Show a track as waveform or spectrogram
+    SetDisplayMode {
+        track_id: TrackId,
+        display_mode: DisplayMode,
+    },
     /// Zoom the _view_ of the given track, center_y should be absolute y-position of the
     /// mouse/center
     ZoomY {
@@ -72,41 +149,15 @@ impl Action {
             }
             Action::RemoveAllTracks => {
                 model.tracks.remove_all_tracks();
+                model.markers.clear();
             }
             Action::OpenFile(read_config) => {
-                // Native: load on a worker thread. Wasm: load synchronously (no threads).
-                let progress = crate::wav::read::new_load_progress_handle();
-                let load_id = model
-                    .load_mgr
-                    .start_load(read_config.filepath.clone(), progress.clone());
-                let tx = model.load_mgr.sender();
-                let read_config = read_config.clone();
-                #[cfg(not(target_arch = "wasm32"))]
-                std::thread::spawn(move || {
-                    let result = crate::wav::read::read_to_loaded_file_with_progress(
-                        &read_config,
-                        load_id,
-                        Some(progress.as_ref()),
-                    )
-                    .context("Action::OpenFile failed");
-                    let _ = tx.send(match result {
-                        Ok(loaded) => crate::wav::read::LoadResult::Ok(loaded),
-                        Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
-                    });
-                });
-                #[cfg(target_arch = "wasm32")]
-                {
-                    let result = crate::wav::read::read_to_loaded_file_with_progress(
-                        &read_config,
-                        load_id,
-                        Some(progress.as_ref()),
-                    )
-                    .context("Action::OpenFile failed");
-                    let _ = tx.send(match result {
-                        Ok(loaded) => crate::wav::read::LoadResult::Ok(loaded),
-                        Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
-                    });
-                }
+                start_file_load(model, read_config);
+            }
+            Action::OpenDiff { file_a, file_b } => {
+                let load_id_a = start_file_load(model, file_a);
+                let load_id_b = start_file_load(model, file_b);
+                model.load_mgr.start_diff(load_id_a, load_id_b);
             }
             Action::OpenFileBytes(read_config) => {
                 // Byte-based loads are used by wasm drag-and-drop (no filesystem access).
@@ -129,7 +180,7 @@ impl Action {
                     )
                     .context("Action::OpenFileBytes failed");
                     let _ = tx.send(match result {
-                        Ok(loaded) => crate::wav::read::LoadResult::Ok(loaded),
+                        Ok(loaded) => crate::wav::read::LoadResult::Ok(Box::new(loaded)),
                         Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
                     });
                 });
@@ -142,7 +193,7 @@ impl Action {
                     )
                     .context("Action::OpenFileBytes failed");
                     let _ = tx.send(match result {
-                        Ok(loaded) => crate::wav::read::LoadResult::Ok(loaded),
+                        Ok(loaded) => crate::wav::read::LoadResult::Ok(Box::new(loaded)),
                         Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
                     });
                 }
@@ -154,6 +205,12 @@ impl Action {
                 model.actions.push(Action::ZoomToFull);
                 model.actions.push(Action::FillScreenHeight);
             }
+            Action::Generate(config) => {
+                let loaded = generator::generate(config).context("Action::Generate failed")?;
+                model.add_loaded_file(loaded, None)?;
+                model.actions.push(Action::ZoomToFull);
+                model.actions.push(Action::FillScreenHeight);
+            }
             Action::ZoomToFull => {
                 model.tracks.zoom_to_full(&model.audio)?;
                 // model.tracks.zoom_to_full();
@@ -172,6 +229,73 @@ impl Action {
                     .tracks
                     .zoom_to_selection_edge(&model.audio, SelectionEdge::Right)?;
             }
+            Action::JumpToMarker(direction) => {
+                model.jump_to_marker(*direction)?;
+            }
+            Action::CenterOnMarker(marker_id) => {
+                if let Some(sample_ix) = model.markers.position(*marker_id, &model.tracks) {
+                    model.tracks.center_on_sample_ix(sample_ix, &model.audio)?;
+                }
+            }
+            Action::AddMarker(marker) => {
+                model.markers.insert(marker.clone());
+            }
+            Action::RemoveMarker(marker_id) => {
+                model.markers.remove(*marker_id)?;
+            }
+            Action::RenameMarker { marker_id, label } => {
+                model.markers.rename(*marker_id, label)?;
+            }
+            Action::MoveMarker {
+                marker_id,
+                sample_ix,
+            } => {
+                model
+                    .markers
+                    .move_to(*marker_id, *sample_ix, &model.tracks)?;
+            }
+            Action::SetMarkerColor { marker_id, color } => {
+                model.markers.set_color(*marker_id, *color)?;
+            }
+            Action::SelectMarkerRegion(marker_id) => {
+                model.select_marker_region(*marker_id)?;
+            }
+            Action::ImportLabels(path) => {
+                model.import_labels(path)?;
+            }
+            Action::ExportMarkers { path, format } => {
+                model.export_markers(path, *format)?;
+            }
+            Action::ExportSelection(config) => {
+                export::export_selection(model, config)?;
+            }
+            Action::SaveSession(path) => {
+                session::save_session(model, path)?;
+            }
+            Action::CloseFile(file_ix) => {
+                model.close_file(*file_ix)?;
+            }
+            Action::CloseAllFiles => {
+                model.close_all_files();
+            }
+            Action::LoadSession(path) => {
+                session::load_session(model, path)?;
+            }
+            Action::ReloadFile(path) => {
+                model.changed_files.retain(|changed| changed != path);
+                let read_configs: Vec<_> = model
+                    .files2
+                    .iter()
+                    .filter_map(|file| file.read_config.clone())
+                    .filter(|read_config| read_config.filepath == *path)
+                    .collect();
+                for read_config in read_configs {
+                    if !model.load_mgr.is_reloading(&read_config) {
+                        let load_id = start_file_load(model, &read_config);
+                        model.load_mgr.start_reload(load_id, read_config);
+                    }
+                }
+            }
             Action::FillScreenHeight => {
                 let min_height = model.user_config.track.min_height;
                 model.tracks.fill_screen_height(min_height)?;
@@ -201,6 +325,33 @@ impl Action {
             Action::RecenterYAll => {
                 model.tracks.recenter_all_value_ranges()?;
             }
+            Action::OverlayTrack {
+                track_id,
+                onto_track_id,
+            } => {
+                model.tracks.overlay_track(*track_id, *onto_track_id)?;
+            }
+            Action::SplitOverlays(track_id) => {
+                model
+                    .tracks
+                    .split_overlays(*track_id, &model.user_config.track)?;
+            }
+            Action::AlignToReference(target) => {
+                model.align_to_reference(*target)?;
+            }
+            Action::PlaceFilesAtTimeReference => {
+                model.place_files_at_time_reference()?;
+            }
+            Action::SetDisplayMode {
+                track_id,
+                display_mode,
+            } => {
+                let track = model
+                    .tracks
+                    .get_track_mut(*track_id)
+                    .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?;
+                track.display_mode = *display_mode;
+            }
             Action::ZoomY {
                 track_id,
                 nr_pixels,
@@ -225,6 +376,50 @@ impl Action {
     }
 }
 
+/// Load a file in the background, the result is picked up by `Model::drain_load_results`.
+pub(crate) fn start_file_load(
+    model: &mut crate::model::Model,
+    read_config: &wav::ReadConfig,
+) -> wav::read::LoadId {
+    // Native: load on a worker thread. Wasm: load synchronously (no threads).
+    let progress = crate::wav::read::new_load_progress_handle();
+    let load_id = model
+        .load_mgr
+        .start_load(read_config.filepath.clone(), progress.clone());
+    let tx = model.load_mgr.sender();
+    let read_config = read_config.clone();
+    let stream_threshold = model.user_config.stream_threshold();
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(move || {
+        let result = crate::wav::read::read_or_stream_loaded_file(
+            &read_config,
+            stream_threshold,
+            load_id,
+            Some(progress.as_ref()),
+        )
+        .context("Action::OpenFile failed");
+        let _ = tx.send(match result {
+            Ok(loaded) => crate::wav::read::LoadResult::Ok(Box::new(loaded)),
+            Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
+        });
+    });
+    #[cfg(target_arch = "wasm32")]
+    {
+        let result = crate::wav::read::read_or_stream_loaded_file(
+            &read_config,
+            stream_threshold,
+            load_id,
+            Some(progress.as_ref()),
+        )
+        .context("Action::OpenFile failed");
+        let _ = tx.send(match result {
+            Ok(loaded) => crate::wav::read::LoadResult::Ok(Box::new(loaded)),
+            Err(error) => crate::wav::read::LoadResult::Err { load_id, error },
+        });
+    }
+    load_id
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectionEdge {
     Left,
@@ -0,0 +1,334 @@
+use crate::{
+    audio::{
+        BufferId,
+        cross_correlation::{self, Lag},
+        thumbnail::ThumbnailE,
+    },
+    model::{
+        Model,
+        selection_stats::{buffer_ix_rng, selected_ix_rng},
+        track::{self, TrackId},
+    },
+};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// What to shift to line up with what, see `Action::AlignToReference`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignTarget {
+    /// B of a diff track to its A, the residual is recomputed
+    DiffB(TrackId),
+    /// An overlay to the item of its track
+    Overlay {
+        track_id: TrackId,
+        overlay_ix: usize,
+    },
+    /// The item of a track to the item of another track
+    Track {
+        track_id: TrackId,
+        reference_track_id: TrackId,
+    },
+}
+
+/// Outcome of the last alignment, shown in the side panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alignment {
+    pub target: AlignTarget,
+    /// Lag of the aligned item before it was shifted
+    pub lag: Lag,
+    pub sample_rate: u32,
+    /// Number of samples of the reference that were correlated
+    pub nr_samples: usize,
+}
+
+/// A buffer and where it starts in the track
+type Placed = (BufferId, f64);
+
+/// Estimate the lag of the target with respect to its reference, within the selection if any,
+/// and shift the target so both line up.
+pub fn align_to_reference(model: &mut Model, target: AlignTarget) -> Result<Alignment> {
+    let (reference, other) = placed_items(model, target)?;
+    let buffer_ref = model.audio.get_buffer(reference.0)?;
+    let buffer_other = model.audio.get_buffer(other.0)?;
+    anyhow::ensure!(
+        buffer_ref.sample_rate() == buffer_other.sample_rate(),
+        "Can't align buffers with different sample rates: {} Hz and {} Hz",
+        buffer_ref.sample_rate(),
+        buffer_other.sample_rate()
+    );
+    let ix_rng = selected_ix_rng(&model.tracks);
+    let clamp = |range: std::ops::Range<usize>| {
+        range.start
+            ..range
+                .end
+                .min(range.start + cross_correlation::MAX_NR_SAMPLES)
+    };
+    let rng_ref = clamp(buffer_ix_rng(ix_rng, reference.1, buffer_ref.nr_samples()));
+    let rng_other = clamp(buffer_ix_rng(ix_rng, other.1, buffer_other.nr_samples()));
+    anyhow::ensure!(
+        !rng_ref.is_empty() && !rng_other.is_empty(),
+        "The selection doesn't overlap both items"
+    );
+
+    let lag = cross_correlation::estimate_lag(
+        &buffer_ref.norm_values(rng_ref.clone()),
+        &buffer_other.norm_values(rng_other.clone()),
+        None,
+    )?;
+    // other[rng_other.start + n + lag] lines up with reference[rng_ref.start + n]
+    let offset = reference.1 + rng_ref.start as f64 - rng_other.start as f64 - lag.samples;
+    let alignment = Alignment {
+        target,
+        lag,
+        sample_rate: buffer_ref.sample_rate(),
+        nr_samples: rng_ref.len(),
+    };
+    apply_offset(model, target, offset)?;
+
+    tracing::info!(
+        "Aligned {target:?}: lag {:.2} samples ({:.3} ms), correlation peak {:.3}",
+        lag.samples,
+        lag.ms(alignment.sample_rate),
+        lag.peak
+    );
+    Ok(alignment)
+}
+
+/// The reference and the item to align
+fn placed_items(model: &Model, target: AlignTarget) -> Result<(Placed, Placed)> {
+    let get_track = |track_id| {
+        model
+            .tracks
+            .get_track(track_id)
+            .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))
+    };
+    let placed = |item: &track::single::Item| (item.buffer_id, item.sample_ix_offset);
+    match target {
+        AlignTarget::DiffB(track_id) => {
+            let diff = get_track(track_id)?
+                .diff
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Track {:?} is not a diff track", track_id))?;
+            Ok((
+                (diff.buffer_id_a, diff.sample_ix_offset_a),
+                (diff.buffer_id_b, diff.sample_ix_offset_b),
+            ))
+        }
+        AlignTarget::Overlay {
+            track_id,
+            overlay_ix,
+        } => {
+            let single = &get_track(track_id)?.single;
+            let overlay = single
+                .overlays
+                .get(overlay_ix)
+                .ok_or_else(|| anyhow::anyhow!("Overlay {overlay_ix} not found"))?;
+            Ok((placed(&single.item), placed(overlay)))
+        }
+        AlignTarget::Track {
+            track_id,
+            reference_track_id,
+        } => {
+            let track = get_track(track_id)?;
+            anyhow::ensure!(track.diff.is_none(), "Can't shift a diff track");
+            Ok((
+                placed(&get_track(reference_track_id)?.single.item),
+                placed(&track.single.item),
+            ))
+        }
+    }
+}
+
+fn apply_offset(model: &mut Model, target: AlignTarget, offset: f64) -> Result<()> {
+    let (AlignTarget::DiffB(track_id)
+    | AlignTarget::Overlay { track_id, .. }
+    | AlignTarget::Track { track_id, .. }) = target;
+    let track = model
+        .tracks
+        .get_track_mut(track_id)
+        .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?;
+    match target {
+        AlignTarget::DiffB(_) => {
+            let diff = track
+                .diff
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Track {:?} is not a diff track", track_id))?;
+            // A stays where it is, only B moves
+            let (offset_a, offset_b) = (diff.sample_ix_offset_a, offset);
+            let residual = track::diff::residual_buffer(
+                model.audio.get_buffer(diff.buffer_id_a)?,
+                model.audio.get_buffer(diff.buffer_id_b)?,
+                offset_a,
+                offset_b,
+            )?;
+            let thumbnail = ThumbnailE::from_buffer_e(&residual, None);
+            let residual_buffer_id = model.audio.buffers.insert(Arc::new(residual));
+            model.audio.thumbnails.insert(residual_buffer_id, thumbnail);
+            let old_residual_buffer_id =
+                track.set_diff_offsets(offset_a, offset_b, residual_buffer_id)?;
+            model.audio.remove_buffer(old_residual_buffer_id);
+        }
+        AlignTarget::Overlay { overlay_ix, .. } => {
+            track.set_sample_ix_offset(Some(overlay_ix), offset)?;
+        }
+        AlignTarget::Track { .. } => {
+            track.set_sample_ix_offset(None, offset)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::buffer::{Buffer, BufferE},
+        model::selection_info::{SelectionInfo, SelectionInfoE},
+    };
+
+    fn noise(delay: usize, nr_samples: usize) -> BufferE {
+        let mut state: u32 = 777;
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = std::iter::repeat_n(0.0, delay)
+            .chain((0..nr_samples).map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as f32 / 32768.0 - 1.0
+            }))
+            .collect();
+        BufferE::F32(buffer)
+    }
+
+    fn insert(model: &mut Model, buffer: BufferE) -> BufferId {
+        let thumbnail = ThumbnailE::from_buffer_e(&buffer, None);
+        let buffer_id = model.audio.buffers.insert(Arc::new(buffer));
+        model.audio.thumbnails.insert(buffer_id, thumbnail);
+        buffer_id
+    }
+
+    #[test]
+    fn track_is_shifted_onto_reference() {
+        let mut model = Model::default();
+        let buffer_ids = [
+            insert(&mut model, noise(0, 2000)),
+            insert(&mut model, noise(25, 2000)),
+        ];
+        let track_ids: Vec<_> = buffer_ids
+            .iter()
+            .map(|buffer_id| {
+                model
+                    .tracks
+                    .add_track_to_end(*buffer_id, &model.user_config.track)
+                    .unwrap()
+            })
+            .collect();
+        let target = AlignTarget::Track {
+            track_id: track_ids[1],
+            reference_track_id: track_ids[0],
+        };
+
+        let alignment = align_to_reference(&mut model, target).unwrap();
+
+        assert!((alignment.lag.samples - 25.0).abs() < 0.1);
+        assert!(alignment.lag.peak > 0.99);
+        let offset = model
+            .tracks
+            .get_track(track_ids[1])
+            .unwrap()
+            .single
+            .item
+            .sample_ix_offset;
+        assert!((offset + 25.0).abs() < 0.1, "{offset}");
+
+        // within a selection, starting from the shifted position the lag is gone
+        model.tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng: (500..1500).into(),
+            ..Default::default()
+        });
+        let alignment = align_to_reference(&mut model, target).unwrap();
+        assert!(alignment.lag.samples.abs() < 0.1, "{alignment:?}");
+        assert_eq!(alignment.nr_samples, 1000);
+    }
+
+    #[test]
+    fn diff_b_is_aligned_and_residual_recomputed() {
+        let mut model = Model::default();
+        let buffer_id_a = insert(&mut model, noise(10, 2000));
+        let buffer_id_b = insert(&mut model, noise(0, 2000));
+        let residual = track::diff::residual_buffer(
+            model.audio.get_buffer(buffer_id_a).unwrap(),
+            model.audio.get_buffer(buffer_id_b).unwrap(),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        let residual_buffer_id = insert(&mut model, residual);
+        let track_id = model
+            .tracks
+            .add_diff_track_to_end(
+                buffer_id_a,
+                buffer_id_b,
+                residual_buffer_id,
+                &model.audio,
+                &model.user_config.track,
+            )
+            .unwrap();
+
+        let alignment = align_to_reference(&mut model, AlignTarget::DiffB(track_id)).unwrap();
+
+        assert!((alignment.lag.samples + 10.0).abs() < 0.1, "{alignment:?}");
+        let track = model.tracks.get_track(track_id).unwrap();
+        let diff = track.diff.as_ref().unwrap();
+        assert_eq!(diff.sample_ix_offset_a, 0.0);
+        assert!((diff.sample_ix_offset_b - 10.0).abs() < 0.1);
+        assert!(!model.audio.buffers.contains_key(residual_buffer_id));
+        let residual = model.audio.get_buffer(track.single.item.buffer_id).unwrap();
+        let max_residual = (0..residual.nr_samples())
+            .filter_map(|ix| residual.norm_value(ix))
+            .fold(0.0, |max: f64, value| max.max(value.abs()));
+        assert!(max_residual < 1e-6, "{max_residual}");
+    }
+
+    #[test]
+    fn diff_b_starting_before_a_is_shifted_back() {
+        let mut model = Model::default();
+        let buffer_id_a = insert(&mut model, noise(0, 2000));
+        let buffer_id_b = insert(&mut model, noise(10, 2000));
+        let residual = track::diff::residual_buffer(
+            model.audio.get_buffer(buffer_id_a).unwrap(),
+            model.audio.get_buffer(buffer_id_b).unwrap(),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        let residual_buffer_id = insert(&mut model, residual);
+        let track_id = model
+            .tracks
+            .add_diff_track_to_end(
+                buffer_id_a,
+                buffer_id_b,
+                residual_buffer_id,
+                &model.audio,
+                &model.user_config.track,
+            )
+            .unwrap();
+
+        let alignment = align_to_reference(&mut model, AlignTarget::DiffB(track_id)).unwrap();
+
+        assert!((alignment.lag.samples - 10.0).abs() < 0.1, "{alignment:?}");
+        let track = model.tracks.get_track(track_id).unwrap();
+        let diff = track.diff.as_ref().unwrap();
+        assert_eq!(diff.sample_ix_offset_a, 0.0);
+        assert!((diff.sample_ix_offset_b + 10.0).abs() < 0.1);
+        assert_eq!(track.single.item.sample_ix_offset, -10.0);
+        // the residual is silent where both have samples
+        let values = diff.values_at(&model.audio, 100.0).unwrap();
+        assert!(values.a.is_some() && values.b.is_some());
+        assert!(values.residual.abs() < 1e-6, "{values:?}");
+        let residual = model.audio.get_buffer(track.single.item.buffer_id).unwrap();
+        assert_eq!(residual.nr_samples(), 2010);
+        let max_residual = (10..2000)
+            .filter_map(|ix| residual.norm_value(ix))
+            .fold(0.0, |max: f64, value| max.max(value.abs()));
+        assert!(max_residual < 1e-6, "{max_residual}");
+    }
+}
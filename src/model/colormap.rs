@@ -0,0 +1,93 @@
+//! Colormaps to show a level as a colour, e.g. in the spectrogram.
+
+use egui::Color32;
+use strum_macros::EnumIter;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter, serde::Deserialize, serde::Serialize,
+)]
+pub enum Colormap {
+    /// Perceptually uniform dark blue to yellow
+    #[default]
+    Viridis,
+    /// Perceptually uniform black to light yellow via purple and orange
+    Magma,
+    Grayscale,
+}
+
+impl Colormap {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Grayscale => "grayscale",
+        }
+    }
+
+    /// Colours at evenly spaced positions from 0 to 1
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Viridis => &[
+                [68, 1, 84],
+                [72, 40, 120],
+                [62, 74, 137],
+                [49, 104, 142],
+                [38, 130, 142],
+                [31, 158, 137],
+                [53, 183, 121],
+                [109, 205, 89],
+                [180, 222, 44],
+                [253, 231, 37],
+            ],
+            Colormap::Magma => &[
+                [0, 0, 4],
+                [24, 15, 61],
+                [68, 15, 118],
+                [114, 31, 129],
+                [158, 47, 127],
+                [205, 64, 113],
+                [241, 96, 93],
+                [253, 150, 104],
+                [254, 202, 141],
+                [252, 253, 191],
+            ],
+            Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Colour for `value` in 0..=1, values outside are clamped
+    pub fn color(&self, value: f32) -> Color32 {
+        let stops = self.stops();
+        let pos = value.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let ix = (pos.floor() as usize).min(stops.len() - 2);
+        let frac = pos - ix as f32;
+        let [r, g, b] = std::array::from_fn(|channel| {
+            let lo = stops[ix][channel] as f32;
+            let hi = stops[ix + 1][channel] as f32;
+            (lo + (hi - lo) * frac).round() as u8
+        });
+        Color32::from_rgb(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_interpolates_between_stops_and_clamps() {
+        let colormap = Colormap::Grayscale;
+
+        assert_eq!(colormap.color(0.0), Color32::from_rgb(0, 0, 0));
+        assert_eq!(colormap.color(0.5), Color32::from_rgb(128, 128, 128));
+        assert_eq!(colormap.color(2.0), Color32::from_rgb(255, 255, 255));
+        assert_eq!(
+            colormap.color(f32::NEG_INFINITY),
+            Color32::from_rgb(0, 0, 0)
+        );
+        assert_eq!(
+            Colormap::Viridis.color(1.0),
+            Color32::from_rgb(253, 231, 37)
+        );
+    }
+}
@@ -1,5 +1,13 @@
 // Store all app config in one place
-use crate::model::{ruler::ValueDisplayScale, shortcuts::ShortcutConfig};
+use crate::{
+    audio::{spectrogram::Analysis, window::WindowFunction},
+    model::{
+        colormap::Colormap,
+        file_watch::ReloadPolicy,
+        ruler::{TimeFormat, TimeUnit, ValueDisplayScale},
+        shortcuts::ShortcutConfig,
+    },
+};
 use egui::{Color32, Visuals};
 
 use tracing::{error, info, trace, warn};
@@ -16,12 +24,25 @@ pub struct Config {
     /// mouse position
     pub show_hover_info: bool,
 
+    /// Place Broadcast Wave files on the timeline at the TimeReference of their `bext` chunk, so
+    /// takes of several recorders line up
+    pub place_at_time_reference: bool,
+
+    /// What to do when an opened file changes on disk, e.g. the output of a re-run DSP build
+    pub reload_on_change: ReloadPolicy,
+
+    /// Files whose samples would take more MiB in memory are streamed from disk, only their
+    /// thumbnails and the samples around the visible range are kept in memory
+    pub stream_threshold_mib: u64,
+
     pub tracks_width_info: f32,
     pub value_display_scale: ValueDisplayScale,
     pub shortcuts: ShortcutConfig,
     pub selection: SelectionConfig,
     pub track: TrackConfig,
     pub colors: ColorPaletteSet,
+    pub spectrogram: SpectrogramConfig,
+    pub time: TimeConfig,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
@@ -37,11 +58,37 @@ pub struct SelectionConfig {
     pub start_edit_mode: StartEditMode,
 }
 
+/// Unit of the time ruler, the hover tick and the selection
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TimeConfig {
+    pub unit: TimeUnit,
+    /// Frames per second of the timecode, see `ruler::time_format::FRAME_RATES`
+    pub frame_rate: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct TrackConfig {
     pub min_height: f32,
 }
 
+/// Settings of tracks shown as spectrogram
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SpectrogramConfig {
+    /// Power of two, see `audio::spectrogram::FFT_SIZES`
+    pub fft_size: usize,
+    /// Samples between columns when zoomed in
+    pub hop: usize,
+    pub window: WindowFunction,
+    /// Level shown with the first colour of the colormap, lower levels are clamped
+    pub min_db: f32,
+    /// Level shown with the last colour of the colormap, higher levels are clamped
+    pub max_db: f32,
+    pub colormap: Colormap,
+    pub log_frequency: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct ColorPaletteSet {
@@ -57,6 +104,16 @@ pub struct ThemeColors {
     pub waveform_hovered_sample: Color32,
     #[serde(alias = "selection_fill")]
     pub waveform_selection_fill: Color32,
+    /// Waveform of file A on a diff track, the residual uses `waveform`
+    pub diff_a: Color32,
+    /// Waveform of file B on a diff track
+    pub diff_b: Color32,
+    /// Waveforms overlaid on a track, used in turn, the track's own item uses `waveform`
+    pub overlays: Vec<Color32>,
+    /// Lines and labels of markers
+    pub marker: Color32,
+    /// Background of marker regions
+    pub region_fill: Color32,
 }
 
 impl Default for SelectionConfig {
@@ -67,12 +124,87 @@ impl Default for SelectionConfig {
     }
 }
 
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            unit: TimeUnit::default(),
+            frame_rate: 25,
+        }
+    }
+}
+
+impl TimeConfig {
+    pub fn time_format(&self, sample_rate: u32) -> TimeFormat {
+        TimeFormat {
+            unit: self.unit,
+            frame_rate: self.frame_rate.max(1),
+            sample_rate: sample_rate.max(1),
+        }
+    }
+}
+
 impl Default for TrackConfig {
     fn default() -> Self {
         Self { min_height: 10.0 }
     }
 }
 
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        let analysis = Analysis::default();
+        Self {
+            fft_size: analysis.fft_size,
+            hop: analysis.hop,
+            window: analysis.window,
+            min_db: -120.0,
+            max_db: 0.0,
+            colormap: Colormap::default(),
+            log_frequency: false,
+        }
+    }
+}
+
+impl SpectrogramConfig {
+    /// Lowest frequency on a log frequency axis
+    pub const MIN_LOG_FREQUENCY: f64 = 20.0;
+
+    pub fn analysis(&self) -> Analysis {
+        Analysis {
+            fft_size: self.fft_size,
+            hop: self.hop,
+            window: self.window,
+        }
+    }
+
+    /// Frequency at relative height `frac`, 0.0 at the bottom (0 Hz or `MIN_LOG_FREQUENCY`) and
+    /// 1.0 at the top (Nyquist)
+    pub fn frequency_at(&self, frac: f64, sample_rate: u32) -> f64 {
+        let nyquist = sample_rate as f64 / 2.0;
+        if self.log_frequency {
+            let min = Self::MIN_LOG_FREQUENCY.min(nyquist);
+            min * (nyquist / min).powf(frac)
+        } else {
+            nyquist * frac
+        }
+    }
+
+    /// Inverse of `frequency_at`
+    pub fn frequency_frac(&self, frequency: f64, sample_rate: u32) -> f64 {
+        let nyquist = sample_rate as f64 / 2.0;
+        if self.log_frequency {
+            let min = Self::MIN_LOG_FREQUENCY.min(nyquist);
+            (frequency / min).ln() / (nyquist / min).ln()
+        } else {
+            frequency / nyquist
+        }
+    }
+
+    /// Position of `level_db` in the dB range, 0.0 at `min_db` and 1.0 at `max_db`
+    pub fn level_frac(&self, level_db: f32) -> f32 {
+        (level_db - self.min_db) / (self.max_db - self.min_db).max(f32::EPSILON)
+    }
+}
+
 impl Default for ColorPaletteSet {
     fn default() -> Self {
         Self {
@@ -89,12 +221,30 @@ impl Default for ThemeColors {
 }
 
 impl ThemeColors {
+    /// Colour of the overlay at `overlay_ix`, cycling through `overlays`
+    pub fn overlay(&self, overlay_ix: usize) -> Color32 {
+        if self.overlays.is_empty() {
+            return self.waveform;
+        }
+        self.overlays[overlay_ix % self.overlays.len()]
+    }
+
     pub fn dark_default() -> Self {
         Self {
             accent: Color32::LIGHT_BLUE,
             waveform: Color32::LIGHT_RED,
             waveform_hovered_sample: Color32::WHITE,
             waveform_selection_fill: Color32::from_rgba_unmultiplied(211, 211, 211, 13),
+            diff_a: Color32::from_rgba_unmultiplied(120, 200, 120, 160),
+            diff_b: Color32::from_rgba_unmultiplied(120, 160, 230, 160),
+            overlays: vec![
+                Color32::from_rgb(102, 204, 255),
+                Color32::from_rgb(255, 210, 90),
+                Color32::from_rgb(150, 230, 130),
+                Color32::from_rgb(220, 150, 255),
+            ],
+            marker: Color32::from_rgb(255, 170, 60),
+            region_fill: Color32::from_rgba_unmultiplied(255, 170, 60, 16),
         }
     }
 
@@ -104,6 +254,16 @@ impl ThemeColors {
             waveform: Color32::from_rgb(196, 64, 64),
             waveform_hovered_sample: Color32::from_rgb(32, 32, 32),
             waveform_selection_fill: Color32::from_rgba_unmultiplied(0, 102, 204, 28),
+            diff_a: Color32::from_rgba_unmultiplied(40, 140, 40, 160),
+            diff_b: Color32::from_rgba_unmultiplied(40, 90, 190, 160),
+            overlays: vec![
+                Color32::from_rgb(0, 110, 190),
+                Color32::from_rgb(200, 130, 0),
+                Color32::from_rgb(40, 140, 40),
+                Color32::from_rgb(140, 60, 170),
+            ],
+            marker: Color32::from_rgb(190, 100, 0),
+            region_fill: Color32::from_rgba_unmultiplied(190, 100, 0, 28),
         }
     }
 }
@@ -113,12 +273,17 @@ impl Default for Config {
         Self {
             zoom_x_scroll_factor: 4.0,
             show_hover_info: true,
+            place_at_time_reference: false,
+            reload_on_change: ReloadPolicy::default(),
+            stream_threshold_mib: 4096,
             tracks_width_info: 150.0,
             value_display_scale: ValueDisplayScale::default(),
             shortcuts: ShortcutConfig::default(),
             selection: SelectionConfig::default(),
             track: TrackConfig::default(),
             colors: ColorPaletteSet::default(),
+            spectrogram: SpectrogramConfig::default(),
+            time: TimeConfig::default(),
         }
     }
 }
@@ -132,6 +297,11 @@ impl Config {
         self.shortcuts = ShortcutConfig::default();
     }
 
+    /// `stream_threshold_mib` in bytes
+    pub fn stream_threshold(&self) -> u64 {
+        self.stream_threshold_mib.saturating_mul(1024 * 1024)
+    }
+
     pub fn active_theme_colors(&self, visuals: &Visuals) -> &ThemeColors {
         if visuals.dark_mode {
             &self.colors.dark
@@ -176,7 +346,7 @@ impl Config {
 
 #[cfg(test)]
 mod tests {
-    use super::{ColorPaletteSet, Config, ThemeColors};
+    use super::{ColorPaletteSet, Config, SpectrogramConfig, ThemeColors};
     use crate::model::{
         ruler::ValueDisplayScale,
         shortcuts::{ShortcutAction, ShortcutScope},
@@ -284,4 +454,41 @@ selection_fill = [1, 2, 3, 4]
 
         assert_ne!(config.colors.dark, config.colors.light);
     }
+
+    #[test]
+    fn partial_spectrogram_config_falls_back_missing_fields() {
+        let config: Config = toml::from_str(
+            r#"
+[spectrogram]
+fft_size = 4096
+colormap = "Magma"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.spectrogram.fft_size, 4096);
+        assert_eq!(config.spectrogram.colormap, super::Colormap::Magma);
+        assert_eq!(config.spectrogram.hop, SpectrogramConfig::default().hop);
+    }
+
+    #[test]
+    fn spectrogram_frequency_frac_inverts_frequency_at() {
+        for log_frequency in [false, true] {
+            let config = SpectrogramConfig {
+                log_frequency,
+                ..Default::default()
+            };
+
+            assert!((config.frequency_at(1.0, 48_000) - 24_000.0).abs() < 1e-6);
+            let frac = config.frequency_frac(1000.0, 48_000);
+            assert!((config.frequency_at(frac, 48_000) - 1000.0).abs() < 1e-6);
+        }
+        let log = SpectrogramConfig {
+            log_frequency: true,
+            ..Default::default()
+        };
+        assert!(
+            (log.frequency_at(0.0, 48_000) - SpectrogramConfig::MIN_LOG_FREQUENCY).abs() < 1e-9
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::f32::consts::TAU;
+use std::sync::Arc;
 
 use anyhow::Result;
 
@@ -56,10 +57,10 @@ pub fn load_demo_waveform(model: &mut crate::model::Model) -> Result<()> {
         ch4.data.push(s4);
     }
 
-    let ch1_id = model.audio.buffers.insert(BufferE::F32(ch1));
-    let ch2_id = model.audio.buffers.insert(BufferE::F32(ch2));
-    let ch3_id = model.audio.buffers.insert(BufferE::F32(ch3));
-    let ch4_id = model.audio.buffers.insert(BufferE::F32(ch4));
+    let ch1_id = model.audio.buffers.insert(Arc::new(BufferE::F32(ch1)));
+    let ch2_id = model.audio.buffers.insert(Arc::new(BufferE::F32(ch2)));
+    let ch3_id = model.audio.buffers.insert(Arc::new(BufferE::F32(ch3)));
+    let ch4_id = model.audio.buffers.insert(Arc::new(BufferE::F32(ch4)));
 
     for buffer_id in [ch1_id, ch2_id, ch3_id, ch4_id] {
         let buffer = model
@@ -113,6 +114,9 @@ pub fn load_demo_waveform(model: &mut crate::model::Model) -> Result<()> {
         layout: Some(audio::Layout::LAYOUT_4_0),
         path: None,
         nr_samples: nr_samples as u64,
+        bext: None,
+        cue_points: Vec::new(),
+        read_config: None,
     };
 
     model
@@ -0,0 +1,161 @@
+use crate::{
+    audio::{BufferId, sample, sample::convert::RequantizeConfig},
+    model::{
+        Model,
+        selection_stats::{buffer_ix_rng, selected_ix_rng},
+    },
+    wav::write::{self, ChannelSource, WavFormat, WriteSpec},
+};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// What `Action::ExportSelection` writes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportConfig {
+    pub path: PathBuf,
+    /// One channel per buffer, in this order
+    pub buffer_ids: Vec<BufferId>,
+    /// None keeps the format of the buffers, the most precise one when they differ
+    pub format: Option<WavFormat>,
+    /// How samples are brought to a lower bit depth
+    pub requantize: RequantizeConfig,
+}
+
+/// The track sample index range that is exported: the selection, or else everything of the
+/// buffers.
+pub fn export_ix_rng(model: &Model, buffer_ids: &[BufferId]) -> Option<sample::IxRange> {
+    if let Some(ix_rng) = selected_ix_rng(&model.tracks) {
+        return Some(ix_rng);
+    }
+    let extents = buffer_ids.iter().filter_map(|&buffer_id| {
+        let buffer = model.audio.get_buffer(buffer_id).ok()?;
+        let offset = model.tracks.item_offset(buffer_id).unwrap_or(0.0);
+        Some((offset, offset + buffer.nr_samples() as f64))
+    });
+    let (start, end) = extents.reduce(|(start, end), (other_start, other_end)| {
+        (start.min(other_start), end.max(other_end))
+    })?;
+    Some((start.round() as sample::Ix..end.round() as sample::Ix).into())
+}
+
+/// Write the export range of the buffers to a new wav file. Buffers that are shifted on the
+/// time line keep their position relative to each other, with silence where they don't cover
+/// the range.
+pub fn export_selection(model: &Model, config: &ExportConfig) -> Result<()> {
+    anyhow::ensure!(!config.buffer_ids.is_empty(), "No channels to export");
+    let ix_rng = export_ix_rng(model, &config.buffer_ids)
+        .ok_or_else(|| anyhow::anyhow!("Nothing to export"))?;
+    anyhow::ensure!(ix_rng.end > ix_rng.start, "The export range is empty");
+
+    let buffers = config
+        .buffer_ids
+        .iter()
+        .map(|&buffer_id| model.audio.get_buffer(buffer_id))
+        .collect::<Result<Vec<_>>>()?;
+    let sample_rate = buffers[0].sample_rate();
+    anyhow::ensure!(
+        buffers
+            .iter()
+            .all(|buffer| buffer.sample_rate() == sample_rate),
+        "Can't export channels with different sample rates to one file"
+    );
+    let format = config.format.unwrap_or_else(|| {
+        buffers
+            .iter()
+            .map(|buffer| WavFormat::of_buffer(buffer))
+            .max()
+            .unwrap_or(WavFormat::Float32)
+    });
+
+    let channels: Vec<ChannelSource<'_>> = config
+        .buffer_ids
+        .iter()
+        .zip(&buffers)
+        .map(|(&buffer_id, &buffer)| {
+            let item_offset = model.tracks.item_offset(buffer_id).unwrap_or(0.0);
+            let buffer_rng = buffer_ix_rng(Some(ix_rng), item_offset, buffer.nr_samples());
+            let start_ix = (buffer_rng.start as f64 + item_offset).round() as sample::Ix;
+            ChannelSource {
+                buffer,
+                ix_rng: buffer_rng,
+                offset: (start_ix - ix_rng.start).max(0) as usize,
+            }
+        })
+        .collect();
+    let spec = WriteSpec {
+        sample_rate,
+        format,
+        requantize: config.requantize,
+    };
+    write::write_wav(
+        &config.path,
+        &spec,
+        &channels,
+        (ix_rng.end - ix_rng.start) as usize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::buffer::{Buffer, BufferE},
+        model::selection_info::{SelectionInfo, SelectionInfoE},
+        wav::read::{ReadConfig, read_to_loaded_file},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn shifted_tracks_keep_their_position_in_the_exported_selection() {
+        let mut model = Model::new();
+        let buffer_ids: Vec<BufferId> = [1, 2]
+            .map(|value| {
+                let mut buffer = Buffer::<i16>::new(48_000, 16);
+                buffer.data = (0..8).map(|ix| ix * value).collect();
+                model.audio.buffers.insert(Arc::new(BufferE::I16(buffer)))
+            })
+            .to_vec();
+        for &buffer_id in &buffer_ids {
+            model
+                .tracks
+                .add_track_to_end(buffer_id, &model.user_config.track)
+                .unwrap();
+        }
+        let (track_id, _) = model.tracks.find_track(buffer_ids[1]).unwrap();
+        model
+            .tracks
+            .get_track_mut(track_id)
+            .unwrap()
+            .set_sample_ix_offset(None, 4.0)
+            .unwrap();
+        model.tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng: (2..10).into(),
+            ..Default::default()
+        });
+        let path = std::env::temp_dir().join(format!("wavalyze_export_{}.wav", std::process::id()));
+
+        let exported = export_selection(
+            &model,
+            &ExportConfig {
+                path: path.clone(),
+                buffer_ids: buffer_ids.clone(),
+                format: None,
+                requantize: RequantizeConfig::default(),
+            },
+        );
+        let loaded = read_to_loaded_file(&ReadConfig::new(&path));
+        std::fs::remove_file(&path).unwrap();
+
+        exported.unwrap();
+        let loaded = loaded.unwrap();
+        let samples = |ch_ix| match &loaded.channels[&ch_ix] {
+            BufferE::I16(buffer) => buffer.data.clone(),
+            _ => panic!("expected i16 samples"),
+        };
+        assert_eq!(samples(0), [2, 3, 4, 5, 6, 7, 0, 0]);
+        assert_eq!(samples(1), [0, 0, 0, 2, 4, 6, 8, 10]);
+
+        model.tracks.selection_info = SelectionInfoE::NotSelected;
+        assert_eq!(export_ix_rng(&model, &buffer_ids), Some((0..12).into()));
+    }
+}
@@ -0,0 +1,179 @@
+//! Polls the opened files for changes on disk, so output of a re-run DSP build can be reloaded.
+//!
+//! A change is only reported once the file stopped changing for one poll interval, so a file
+//! that is still being written isn't read half way.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// What to do when an opened file changes on disk
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    strum_macros::EnumIter,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+pub enum ReloadPolicy {
+    Always,
+    #[default]
+    Ask,
+    Never,
+}
+
+impl ReloadPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReloadPolicy::Always => "always reload",
+            ReloadPolicy::Ask => "ask",
+            ReloadPolicy::Never => "never",
+        }
+    }
+}
+
+/// Modification time and size, a file changed when either differs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    pub modified: SystemTime,
+    pub len: u64,
+}
+
+impl Stamp {
+    pub fn of_path(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WatchedFile {
+    /// Stamp of the version that is loaded or was last reported
+    reported: Stamp,
+    /// Stamp of a change seen at the previous poll
+    changing: Option<Stamp>,
+}
+
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, WatchedFile>,
+    #[cfg(not(target_arch = "wasm32"))]
+    last_poll: Option<std::time::Instant>,
+}
+
+impl FileWatcher {
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Paths that changed since they were loaded or last reported, checked at most once per
+    /// `POLL_INTERVAL`. Always empty on the web, which has no file system to watch.
+    pub fn poll<'a>(&mut self, paths: impl IntoIterator<Item = &'a Path>) -> Vec<PathBuf> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let now = std::time::Instant::now();
+            if self
+                .last_poll
+                .is_some_and(|last_poll| now.duration_since(last_poll) < Self::POLL_INTERVAL)
+            {
+                return Vec::new();
+            }
+            self.last_poll = Some(now);
+            let stamps = paths
+                .into_iter()
+                .map(|path| (path.to_path_buf(), Stamp::of_path(path)))
+                .collect();
+            self.update(stamps)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = paths;
+            Vec::new()
+        }
+    }
+
+    /// Compare the current stamps with the previous ones, paths that are no longer given are
+    /// forgotten. A path without stamp couldn't be read, e.g. while it is being replaced.
+    fn update(&mut self, stamps: Vec<(PathBuf, Option<Stamp>)>) -> Vec<PathBuf> {
+        self.files
+            .retain(|path, _| stamps.iter().any(|(other, _)| other == path));
+        let mut changed = Vec::new();
+        for (path, stamp) in stamps {
+            let Some(stamp) = stamp else {
+                continue;
+            };
+            let Some(file) = self.files.get_mut(&path) else {
+                self.files.insert(
+                    path,
+                    WatchedFile {
+                        reported: stamp,
+                        changing: None,
+                    },
+                );
+                continue;
+            };
+            if stamp == file.reported {
+                file.changing = None;
+            } else if file.changing == Some(stamp) {
+                file.reported = stamp;
+                file.changing = None;
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            } else {
+                file.changing = Some(stamp);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(secs: u64, len: u64) -> Option<Stamp> {
+        Some(Stamp {
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            len,
+        })
+    }
+
+    #[test]
+    fn change_is_reported_once_the_file_stops_changing() {
+        let mut watcher = FileWatcher::default();
+        let path = PathBuf::from("out.wav");
+        let mut poll = |stamp| watcher.update(vec![(path.clone(), stamp)]);
+
+        assert!(poll(stamp(1, 100)).is_empty());
+        assert!(poll(stamp(1, 100)).is_empty());
+        // being written
+        assert!(poll(stamp(2, 50)).is_empty());
+        assert!(poll(None).is_empty());
+        assert!(poll(stamp(3, 200)).is_empty());
+        assert_eq!(poll(stamp(3, 200)), vec![path.clone()]);
+        assert!(poll(stamp(3, 200)).is_empty());
+    }
+
+    #[test]
+    fn closed_files_are_forgotten() {
+        let mut watcher = FileWatcher::default();
+        let path = PathBuf::from("out.wav");
+        watcher.update(vec![(path.clone(), stamp(1, 100))]);
+        watcher.update(vec![]);
+        // opened again: the new stamp is the loaded version
+        assert!(
+            watcher
+                .update(vec![(path.clone(), stamp(2, 100))])
+                .is_empty()
+        );
+        assert!(watcher.update(vec![(path, stamp(2, 100))]).is_empty());
+    }
+}
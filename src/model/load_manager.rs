@@ -1,5 +1,6 @@
+use crate::model::session::Session;
 use crate::wav;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 
@@ -9,6 +10,27 @@ pub struct LoadProgressEntry {
     pub handle: wav::read::LoadProgressHandle,
 }
 
+/// Two loads whose files should be compared once both are integrated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingDiff {
+    load_id_a: wav::read::LoadId,
+    load_id_b: wav::read::LoadId,
+    file_ix_a: Option<usize>,
+    file_ix_b: Option<usize>,
+}
+
+/// Loads of the files of a session, whose tracks are laid out once all of them are done.
+#[derive(Debug)]
+struct PendingSession {
+    session: Session,
+    /// Load id per file of the session
+    load_ids: Vec<wav::read::LoadId>,
+    /// File index per loaded file of the session, by index in `Session::files`
+    file_ixs: BTreeMap<usize, usize>,
+    /// Number of loads that didn't finish yet
+    pending: usize,
+}
+
 #[derive(Debug)]
 /// Tracks in-flight loads and their progress handles.
 ///
@@ -24,6 +46,14 @@ pub struct LoadManager {
     next_id: wav::read::LoadId,
 
     progress: HashMap<wav::read::LoadId, LoadProgressEntry>,
+
+    pending_diffs: Vec<PendingDiff>,
+
+    /// Loads that replace an opened file, with the read config of that file
+    reloads: HashMap<wav::read::LoadId, wav::ReadConfig>,
+
+    /// Session whose files are loading, a new session replaces it
+    pending_session: Option<PendingSession>,
 }
 
 impl LoadManager {
@@ -36,6 +66,9 @@ impl LoadManager {
             pending: 0,
             next_id: 1,
             progress: HashMap::new(),
+            pending_diffs: Vec::new(),
+            reloads: HashMap::new(),
+            pending_session: None,
         }
     }
 
@@ -106,6 +139,139 @@ impl LoadManager {
         }
         results
     }
+
+    /// Register two loads whose files should be compared, see `resolve_diff_load`.
+    pub fn start_diff(&mut self, load_id_a: wav::read::LoadId, load_id_b: wav::read::LoadId) {
+        self.pending_diffs.push(PendingDiff {
+            load_id_a,
+            load_id_b,
+            file_ix_a: None,
+            file_ix_b: None,
+        });
+    }
+
+    /// Whether the given load is one side of a diff still waiting for its files.
+    pub fn is_diff_load(&self, load_id: wav::read::LoadId) -> bool {
+        self.pending_diffs
+            .iter()
+            .any(|diff| diff.load_id_a == load_id || diff.load_id_b == load_id)
+    }
+
+    /// Record the file index a diff load ended up at.
+    ///
+    /// Returns the file indices of A and B once both sides are loaded, the diff is then no longer
+    /// pending.
+    pub fn resolve_diff_load(
+        &mut self,
+        load_id: wav::read::LoadId,
+        file_ix: usize,
+    ) -> Option<(usize, usize)> {
+        let diff_ix = self
+            .pending_diffs
+            .iter()
+            .position(|diff| diff.load_id_a == load_id || diff.load_id_b == load_id)?;
+        let diff = &mut self.pending_diffs[diff_ix];
+        if diff.load_id_a == load_id {
+            diff.file_ix_a = Some(file_ix);
+        } else {
+            diff.file_ix_b = Some(file_ix);
+        }
+        let (Some(file_ix_a), Some(file_ix_b)) = (diff.file_ix_a, diff.file_ix_b) else {
+            return None;
+        };
+        self.pending_diffs.remove(diff_ix);
+        Some((file_ix_a, file_ix_b))
+    }
+
+    /// Forget a diff when one of its loads failed.
+    pub fn cancel_diff_load(&mut self, load_id: wav::read::LoadId) {
+        self.pending_diffs
+            .retain(|diff| diff.load_id_a != load_id && diff.load_id_b != load_id);
+    }
+
+    /// Register the loads of the files of `session`, one per file in `Session::files`.
+    pub fn start_session(&mut self, session: Session, load_ids: Vec<wav::read::LoadId>) {
+        self.pending_session = Some(PendingSession {
+            session,
+            pending: load_ids.len(),
+            load_ids,
+            file_ixs: BTreeMap::new(),
+        });
+    }
+
+    /// Whether the given load is a file of the session that is loading.
+    pub fn is_session_load(&self, load_id: wav::read::LoadId) -> bool {
+        self.pending_session
+            .as_ref()
+            .is_some_and(|session| session.load_ids.contains(&load_id))
+    }
+
+    /// Record the file index a session load ended up at, None when it failed.
+    ///
+    /// Returns the session with the file index per loaded session file once all its loads are
+    /// done, the session is then no longer pending.
+    pub fn resolve_session_load(
+        &mut self,
+        load_id: wav::read::LoadId,
+        file_ix: Option<usize>,
+    ) -> Option<(Session, BTreeMap<usize, usize>)> {
+        let pending = self.pending_session.as_mut()?;
+        let session_file_ix = pending.load_ids.iter().position(|id| *id == load_id)?;
+        if let Some(file_ix) = file_ix {
+            pending.file_ixs.insert(session_file_ix, file_ix);
+        }
+        pending.pending = pending.pending.saturating_sub(1);
+        if pending.pending > 0 {
+            return None;
+        }
+        let pending = self.pending_session.take()?;
+        Some((pending.session, pending.file_ixs))
+    }
+
+    /// Keep the file indices of pending diffs and of the pending session valid when the file at
+    /// `file_ix` is closed, a diff waiting for its other side is forgotten when its loaded side is
+    /// closed.
+    pub fn file_closed(&mut self, file_ix: usize) {
+        if let Some(pending) = &mut self.pending_session {
+            pending
+                .file_ixs
+                .retain(|_, session_file_ix| *session_file_ix != file_ix);
+            for session_file_ix in pending.file_ixs.values_mut() {
+                if *session_file_ix > file_ix {
+                    *session_file_ix -= 1;
+                }
+            }
+        }
+        self.pending_diffs.retain_mut(|diff| {
+            for diff_file_ix in [&mut diff.file_ix_a, &mut diff.file_ix_b]
+                .into_iter()
+                .flatten()
+            {
+                if *diff_file_ix == file_ix {
+                    return false;
+                }
+                if *diff_file_ix > file_ix {
+                    *diff_file_ix -= 1;
+                }
+            }
+            true
+        });
+    }
+
+    /// Register a load that replaces the opened file read with `read_config`.
+    pub fn start_reload(&mut self, load_id: wav::read::LoadId, read_config: wav::ReadConfig) {
+        self.reloads.insert(load_id, read_config);
+    }
+
+    /// Whether a reload of the file read with `read_config` is still in progress.
+    pub fn is_reloading(&self, read_config: &wav::ReadConfig) -> bool {
+        self.reloads.values().any(|reload| reload == read_config)
+    }
+
+    /// The read config of the file the given load replaces, None when it isn't a reload.
+    pub fn take_reload(&mut self, load_id: wav::read::LoadId) -> Option<wav::ReadConfig> {
+        self.reloads.remove(&load_id)
+    }
 }
 
 impl Default for LoadManager {
@@ -113,3 +279,76 @@ impl Default for LoadManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_resolves_once_both_loads_are_integrated() {
+        let mut load_mgr = LoadManager::new();
+        load_mgr.start_diff(1, 2);
+        assert!(load_mgr.is_diff_load(1));
+        assert!(load_mgr.is_diff_load(2));
+        assert!(!load_mgr.is_diff_load(3));
+
+        assert_eq!(load_mgr.resolve_diff_load(2, 5), None);
+        assert_eq!(load_mgr.resolve_diff_load(1, 6), Some((6, 5)));
+        assert!(!load_mgr.is_diff_load(1));
+    }
+
+    #[test]
+    fn closing_files_keeps_pending_diffs_valid() {
+        let mut load_mgr = LoadManager::new();
+        load_mgr.start_diff(1, 2);
+        load_mgr.start_diff(3, 4);
+        assert_eq!(load_mgr.resolve_diff_load(1, 3), None);
+        assert_eq!(load_mgr.resolve_diff_load(3, 5), None);
+
+        load_mgr.file_closed(0);
+        load_mgr.file_closed(2);
+        assert!(!load_mgr.is_diff_load(2));
+        assert_eq!(load_mgr.resolve_diff_load(4, 6), Some((3, 6)));
+    }
+
+    #[test]
+    fn reload_is_taken_once() {
+        let mut load_mgr = LoadManager::new();
+        let read_config = wav::ReadConfig::new("out.wav");
+        load_mgr.start_reload(3, read_config.clone());
+        assert!(load_mgr.is_reloading(&read_config));
+        assert!(!load_mgr.is_reloading(&read_config.clone().with_ch_ixs([0])));
+
+        assert_eq!(load_mgr.take_reload(2), None);
+        assert_eq!(load_mgr.take_reload(3), Some(read_config.clone()));
+        assert!(!load_mgr.is_reloading(&read_config));
+    }
+
+    #[test]
+    fn session_resolves_once_all_its_loads_are_done() {
+        let mut load_mgr = LoadManager::new();
+        let session = Session::from_toml("version = 1\n[value_display_scale]\n").unwrap();
+        load_mgr.start_session(session.clone(), vec![4, 5, 6]);
+        assert!(load_mgr.is_session_load(5));
+        assert!(!load_mgr.is_session_load(3));
+
+        assert_eq!(load_mgr.resolve_session_load(6, Some(1)), None);
+        assert_eq!(load_mgr.resolve_session_load(4, Some(2)), None);
+        load_mgr.file_closed(0);
+        assert_eq!(
+            load_mgr.resolve_session_load(5, None),
+            Some((session, BTreeMap::from([(0, 1), (2, 0)])))
+        );
+        assert!(!load_mgr.is_session_load(4));
+    }
+
+    #[test]
+    fn cancelled_diff_is_forgotten() {
+        let mut load_mgr = LoadManager::new();
+        load_mgr.start_diff(1, 2);
+        load_mgr.cancel_diff_load(2);
+
+        assert!(!load_mgr.is_diff_load(1));
+        assert_eq!(load_mgr.resolve_diff_load(1, 0), None);
+    }
+}
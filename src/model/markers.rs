@@ -0,0 +1,286 @@
+use crate::{
+    audio::{manager::BufferId, sample},
+    model::tracks2::Tracks,
+    wav,
+};
+use anyhow::{Result, anyhow};
+use slotmap::{SlotMap, new_key_type};
+
+new_key_type! {
+    pub struct MarkerId;
+}
+
+/// A point or a labelled region on the time line, e.g. a cue point of a wav file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    /// Sample index relative to the start of `buffer_ids`, or on the time line without any
+    pub sample_ix: f64,
+    /// Number of samples of a region, None for a point
+    pub length: Option<f64>,
+    pub label: String,
+    /// Buffers the marker belongs to, e.g. the channels of its file. It moves along with the
+    /// first of them that is in a track and is removed with the last of them.
+    pub buffer_ids: Vec<BufferId>,
+    /// None uses the `marker` colour of the theme
+    pub color: Option<egui::Color32>,
+}
+
+impl Marker {
+    /// Marker at `sample_ix` on the time line
+    pub fn point(sample_ix: f64, label: impl Into<String>) -> Self {
+        Self {
+            sample_ix,
+            length: None,
+            label: label.into(),
+            buffer_ids: Vec::new(),
+            color: None,
+        }
+    }
+
+    /// Region of `ix_range` on the time line
+    pub fn region(ix_range: sample::IxRange, label: impl Into<String>) -> Self {
+        Self {
+            length: Some(ix_range.len() as f64),
+            ..Self::point(ix_range.start as f64, label)
+        }
+    }
+
+    pub fn is_region(&self) -> bool {
+        self.length.is_some()
+    }
+
+    pub fn from_cue_point(cue_point: &wav::cue::CuePoint, buffer_ids: &[BufferId]) -> Self {
+        Self {
+            sample_ix: cue_point.sample_ix as f64,
+            length: cue_point.length.map(|length| length as f64),
+            label: cue_point.text().to_string(),
+            buffer_ids: buffer_ids.to_vec(),
+            color: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpDirection {
+    Previous,
+    Next,
+}
+
+#[derive(Debug, Default)]
+pub struct Markers {
+    markers: SlotMap<MarkerId, Marker>,
+}
+
+impl Markers {
+    pub fn insert(&mut self, marker: Marker) -> MarkerId {
+        self.markers.insert(marker)
+    }
+
+    pub fn get(&self, marker_id: MarkerId) -> Option<&Marker> {
+        self.markers.get(marker_id)
+    }
+
+    fn get_mut(&mut self, marker_id: MarkerId) -> Result<&mut Marker> {
+        self.markers
+            .get_mut(marker_id)
+            .ok_or_else(|| anyhow!("Marker {:?} not found", marker_id))
+    }
+
+    pub fn remove(&mut self, marker_id: MarkerId) -> Result<Marker> {
+        self.markers
+            .remove(marker_id)
+            .ok_or_else(|| anyhow!("Marker {:?} not found", marker_id))
+    }
+
+    /// Remove the markers of the buffers, e.g. when their file is closed. Markers that also
+    /// belong to other buffers are kept with those.
+    pub fn remove_buffer_markers(&mut self, buffer_ids: &[BufferId]) {
+        self.markers.retain(|_, marker| {
+            if marker.buffer_ids.is_empty() {
+                return true;
+            }
+            marker
+                .buffer_ids
+                .retain(|buffer_id| !buffer_ids.contains(buffer_id));
+            !marker.buffer_ids.is_empty()
+        });
+    }
+
+    /// Keep the markers of a buffer with the buffer that replaces it, e.g. when its file is
+    /// reloaded
+    pub fn replace_buffer(&mut self, old_buffer_id: BufferId, new_buffer_id: BufferId) {
+        for marker in self.markers.values_mut() {
+            for buffer_id in &mut marker.buffer_ids {
+                if *buffer_id == old_buffer_id {
+                    *buffer_id = new_buffer_id;
+                }
+            }
+        }
+    }
+
+    pub fn rename(&mut self, marker_id: MarkerId, label: &str) -> Result<()> {
+        self.get_mut(marker_id)?.label = label.to_string();
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, marker_id: MarkerId, color: Option<egui::Color32>) -> Result<()> {
+        self.get_mut(marker_id)?.color = color;
+        Ok(())
+    }
+
+    /// Sample index of the marker on the time line, None when its buffer isn't in a track
+    pub fn position(&self, marker_id: MarkerId, tracks: &Tracks) -> Option<f64> {
+        let marker = self.markers.get(marker_id)?;
+        Some(marker.sample_ix + anchor_offset(marker, tracks)?)
+    }
+
+    /// Move the marker to `sample_ix` on the time line, it stays with its buffer
+    pub fn move_to(&mut self, marker_id: MarkerId, sample_ix: f64, tracks: &Tracks) -> Result<()> {
+        let marker = self.get_mut(marker_id)?;
+        let offset = anchor_offset(marker, tracks)
+            .ok_or_else(|| anyhow!("Marker {:?} is not in a track", marker_id))?;
+        marker.sample_ix = sample_ix - offset;
+        Ok(())
+    }
+
+    /// Sample indices of a region on the time line, rounded to whole samples
+    pub fn region_ix_range(&self, marker_id: MarkerId, tracks: &Tracks) -> Option<sample::IxRange> {
+        let start = self.position(marker_id, tracks)?;
+        let length = self.markers.get(marker_id)?.length?;
+        Some(sample::IxRange {
+            start: start.round() as i64,
+            end: (start + length).round() as i64,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.markers.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Markers with their sample index on the time line, sorted by that index. Markers of
+    /// buffers that are no longer in a track are left out.
+    pub fn positioned<'a>(&'a self, tracks: &Tracks) -> Vec<(MarkerId, &'a Marker, f64)> {
+        let mut positioned: Vec<_> = self
+            .markers
+            .iter()
+            .filter_map(|(marker_id, marker)| {
+                Some((
+                    marker_id,
+                    marker,
+                    marker.sample_ix + anchor_offset(marker, tracks)?,
+                ))
+            })
+            .collect();
+        positioned.sort_by(|a, b| a.2.total_cmp(&b.2));
+        positioned
+    }
+
+    /// Sample index of the first marker after (or last before) `sample_ix` on the time line.
+    /// Markers within half a sample of `sample_ix` are skipped, so jumping from a marker goes
+    /// to the one next to it.
+    pub fn jump_target(
+        &self,
+        tracks: &Tracks,
+        sample_ix: f64,
+        direction: JumpDirection,
+    ) -> Option<f64> {
+        let mut positions = self
+            .positioned(tracks)
+            .into_iter()
+            .map(|(_, _, position)| position);
+        match direction {
+            JumpDirection::Next => positions.find(|ix| *ix > sample_ix + 0.5),
+            JumpDirection::Previous => positions.rev().find(|ix| *ix < sample_ix - 0.5),
+        }
+    }
+}
+
+/// Position of the first buffer of the marker that is in a track, 0 for markers without buffers,
+/// None when none of its buffers is in a track
+fn anchor_offset(marker: &Marker, tracks: &Tracks) -> Option<f64> {
+    if marker.buffer_ids.is_empty() {
+        return Some(0.0);
+    }
+    marker
+        .buffer_ids
+        .iter()
+        .find_map(|buffer_id| tracks.item_offset(*buffer_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(sample_ix: f64) -> Marker {
+        Marker::point(sample_ix, format!("at {sample_ix}"))
+    }
+
+    #[test]
+    fn jump_target_skips_the_marker_at_the_current_position() {
+        let mut markers = Markers::default();
+        for sample_ix in [300.0, 100.0, 200.0] {
+            markers.insert(marker(sample_ix));
+        }
+        let tracks = Tracks::default();
+
+        let target = |sample_ix, direction| markers.jump_target(&tracks, sample_ix, direction);
+
+        assert_eq!(target(0.0, JumpDirection::Next), Some(100.0));
+        assert_eq!(target(100.2, JumpDirection::Next), Some(200.0));
+        assert_eq!(target(200.0, JumpDirection::Previous), Some(100.0));
+        assert_eq!(target(300.0, JumpDirection::Next), None);
+        assert_eq!(target(100.0, JumpDirection::Previous), None);
+    }
+
+    #[test]
+    fn markers_of_buffers_without_track_are_not_positioned() {
+        let mut markers = Markers::default();
+        let mut buffer_ids = SlotMap::<BufferId, ()>::with_key();
+        markers.insert(Marker {
+            buffer_ids: vec![buffer_ids.insert(())],
+            ..marker(10.0)
+        });
+        markers.insert(marker(20.0));
+
+        let positioned = markers.positioned(&Tracks::default());
+
+        assert_eq!(positioned.len(), 1);
+        assert_eq!(positioned[0].2, 20.0);
+        assert_eq!(markers.len(), 2);
+    }
+
+    #[test]
+    fn markers_are_renamed_coloured_moved_and_removed() {
+        let mut markers = Markers::default();
+        let tracks = Tracks::default();
+        let marker_id = markers.insert(Marker::region((100..150).into(), "intro"));
+
+        markers.rename(marker_id, "verse").unwrap();
+        markers
+            .set_color(marker_id, Some(egui::Color32::RED))
+            .unwrap();
+        markers.move_to(marker_id, 200.4, &tracks).unwrap();
+
+        let marker = markers.get(marker_id).unwrap();
+        assert_eq!(marker.label, "verse");
+        assert_eq!(marker.color, Some(egui::Color32::RED));
+        assert_eq!(markers.position(marker_id, &tracks), Some(200.4));
+        assert_eq!(
+            markers.region_ix_range(marker_id, &tracks),
+            Some((200..250).into())
+        );
+
+        markers.remove(marker_id).unwrap();
+        assert!(markers.is_empty());
+        assert!(markers.rename(marker_id, "gone").is_err());
+        assert!(markers.remove(marker_id).is_err());
+    }
+}
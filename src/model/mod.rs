@@ -1,12 +1,20 @@
 pub mod action;
+pub mod align;
+pub mod colormap;
 pub mod config;
 pub mod demo;
+pub mod export;
+pub mod file_watch;
 pub mod hover_info;
 pub mod load_manager;
+pub mod markers;
 pub mod ruler;
 pub mod sample_ix_zoom;
 pub mod selection_info;
+pub mod selection_stats;
+pub mod session;
 pub mod shortcuts;
+pub mod spectrograms;
 pub mod track;
 pub mod tracks2;
 pub mod types;
@@ -28,6 +36,7 @@ use tracing::{info, trace};
 
 use crate::wav;
 use anyhow::Result;
+use std::sync::Arc;
 // use std::collections::VecDeque;
 
 #[derive(Debug, Default)]
@@ -38,6 +47,25 @@ pub struct Model {
     pub tracks: tracks2::Tracks,
     pub actions: Vec<Action>,
     pub load_mgr: LoadManager,
+    pub selection_stats: selection_stats::SelectionStats,
+    /// Spectrum of the selection, shown in the spectrum panel
+    pub fft: track::fft::Fft,
+    /// Tiles of the tracks shown as spectrogram
+    pub spectrograms: spectrograms::Spectrograms,
+    /// Outcome of the last `Action::AlignToReference`
+    pub alignment: Option<align::Alignment>,
+    /// Cue points and regions shown on the time line
+    pub markers: markers::Markers,
+    pub file_watcher: file_watch::FileWatcher,
+    /// Opened files that changed on disk, waiting for the user to reload or ignore them
+    pub changed_files: Vec<std::path::PathBuf>,
+    /// Why the last failed action failed, shown until dismissed
+    pub action_error: Option<String>,
+    /// TimeReference in seconds that files placed by `place_at_time_reference` are counted from
+    pub time_reference_start_s: Option<f64>,
+    /// Offsets that `place_at_time_reference` gave to buffers, so turning it off only resets
+    /// those that weren't moved since
+    pub time_reference_offsets: std::collections::HashMap<audio::BufferId, f64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -76,6 +104,28 @@ impl Model {
         Ok(())
     }
 
+    /// Keep the selection stats in sync with the selection and the visible tracks
+    pub fn update_selection_stats(&mut self) {
+        self.selection_stats.update(&self.tracks, &self.audio);
+    }
+
+    /// Keep the spectra in sync with the selection, the visible tracks and the FFT settings
+    pub fn update_fft(&mut self) {
+        self.fft.update(&self.tracks, &self.audio);
+    }
+
+    /// Shift the target onto its reference and keep the outcome for the side panel
+    pub fn align_to_reference(&mut self, target: align::AlignTarget) -> Result<()> {
+        self.alignment = Some(align::align_to_reference(self, target)?);
+        Ok(())
+    }
+
+    /// Pick up computed spectrogram tiles and drop outdated ones
+    pub fn update_spectrograms(&mut self) {
+        self.spectrograms
+            .update(&self.audio, self.user_config.spectrogram.analysis());
+    }
+
     pub fn load_demo_waveform(&mut self) -> Result<()> {
         demo::load_demo_waveform(self)
     }
@@ -85,13 +135,16 @@ impl Model {
         track_id: TrackId,
     ) -> Option<(&wav::file2::File, &wav::file2::Channel)> {
         let track = self.tracks.get_track(track_id)?;
-        let buffer_id = track.single.item.buffer_id;
-        for file in self.files2.iter() {
-            if let Some(channel) = file.get_channel(buffer_id) {
-                return Some((file, channel));
-            }
-        }
-        None
+        self.find_file_channel(track.single.item.buffer_id)
+    }
+
+    pub fn find_file_channel(
+        &self,
+        buffer_id: audio::BufferId,
+    ) -> Option<(&wav::file2::File, &wav::file2::Channel)> {
+        self.files2
+            .iter()
+            .find_map(|file| file.get_channel(buffer_id).map(|channel| (file, channel)))
     }
 
     pub fn find_track_id_for_buffer(&self, buffer_id: audio::BufferId) -> Option<TrackId> {
@@ -161,6 +214,12 @@ impl Model {
         let Some(track_id) = self.find_track_id_for_buffer(buffer_id) else {
             return false;
         };
+        // an overlaid channel only leaves the track it is drawn on
+        if let Some(track) = self.tracks.get_track_mut(track_id)
+            && track.remove_overlay(buffer_id)
+        {
+            return true;
+        }
         self.tracks.remove_track(track_id);
         true
     }
@@ -201,11 +260,237 @@ impl Model {
         self.tracks.zoom_to_full(&self.audio)
     }
 
+    /// Close a file: remove its channels from the tracks, drop the diff tracks comparing it and
+    /// the markers on its channels, and free its buffers and thumbnails. Channels of other files
+    /// overlaid on its tracks get their own track.
+    pub fn close_file(&mut self, file_ix: usize) -> Result<()> {
+        anyhow::ensure!(file_ix < self.files2.len(), "File {file_ix} not found");
+        let file = self.files2.remove(file_ix);
+        self.load_mgr.file_closed(file_ix);
+        let buffer_ids: Vec<_> = file
+            .channels
+            .values()
+            .map(|channel| channel.buffer_id)
+            .collect();
+        let track_ids: Vec<_> = self.tracks.tracks.keys().collect();
+        for track_id in track_ids {
+            let Some(track) = self.tracks.get_track(track_id) else {
+                continue;
+            };
+            if let Some(diff) = &track.diff {
+                if buffer_ids.contains(&diff.buffer_id_a) || buffer_ids.contains(&diff.buffer_id_b)
+                {
+                    let residual_buffer_id = track.single.item.buffer_id;
+                    self.tracks.remove_track(track_id);
+                    self.audio.remove_buffer(residual_buffer_id);
+                }
+            } else if buffer_ids.contains(&track.single.item.buffer_id)
+                && !track.single.overlays.is_empty()
+            {
+                self.tracks
+                    .split_overlays(track_id, &self.user_config.track)?;
+            }
+        }
+        for buffer_id in &buffer_ids {
+            self.remove_channel_track(*buffer_id);
+        }
+        self.markers.remove_buffer_markers(&buffer_ids);
+        for buffer_id in &buffer_ids {
+            self.time_reference_offsets.remove(buffer_id);
+        }
+        if self.files2.is_empty() {
+            self.time_reference_start_s = None;
+        }
+        if let Some(path) = &file.path {
+            // The same file may be open more than once
+            if !self
+                .files2
+                .iter()
+                .any(|other| other.path.as_ref() == Some(path))
+            {
+                self.changed_files.retain(|changed| changed != path);
+            }
+        }
+        self.audio.remove_buffers_from_file(&file);
+        Ok(())
+    }
+
+    /// Close all files and free all buffers and thumbnails, diff residuals included
+    pub fn close_all_files(&mut self) {
+        self.tracks.remove_all_tracks();
+        self.tracks.selection_info = selection_info::SelectionInfoE::NotSelected;
+        self.markers.clear();
+        self.alignment = None;
+        self.changed_files.clear();
+        self.time_reference_start_s = None;
+        self.time_reference_offsets.clear();
+        self.files2.clear();
+        self.audio.clear();
+    }
+
     pub fn add_loaded_file(
         &mut self,
         loaded: wav::read::LoadedFile,
         progress: Option<wav::read::LoadProgressHandle>,
     ) -> Result<()> {
+        let file = self.store_loaded_file(loaded, progress)?;
+        self.tracks
+            .add_tracks_from_file(&file, &self.user_config.track)?;
+        self.add_file_markers(&file);
+        self.files2.push(file);
+        if self.user_config.place_at_time_reference {
+            let file = &self.files2[self.files2.len() - 1];
+            if let Some(bext) = &file.bext {
+                let start_s = *self
+                    .time_reference_start_s
+                    .get_or_insert(bext.time_reference_s(file.sample_rate));
+                self.place_file_at_time_reference(self.files2.len() - 1, start_s)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shift the tracks of files with a `bext` chunk so each file starts at its TimeReference,
+    /// counted from the earliest of them. When `place_at_time_reference` is off, move the tracks
+    /// it placed back to 0, unless they were moved since.
+    pub fn place_files_at_time_reference(&mut self) -> Result<()> {
+        if !self.user_config.place_at_time_reference {
+            for (buffer_id, offset) in std::mem::take(&mut self.time_reference_offsets) {
+                if self.tracks.item_offset(buffer_id) == Some(offset) {
+                    self.tracks.set_item_offset(buffer_id, 0.0)?;
+                }
+            }
+            self.time_reference_start_s = None;
+            return Ok(());
+        }
+        let start_s = self
+            .files2
+            .iter()
+            .filter_map(|file| {
+                let bext = file.bext.as_ref()?;
+                Some(bext.time_reference_s(file.sample_rate))
+            })
+            .reduce(f64::min);
+        let Some(start_s) = start_s else {
+            return Ok(());
+        };
+        self.time_reference_start_s = Some(start_s);
+        for file_ix in 0..self.files2.len() {
+            self.place_file_at_time_reference(file_ix, start_s)?;
+        }
+        Ok(())
+    }
+
+    /// Shift the tracks of a file with a `bext` chunk to its TimeReference, counted from
+    /// `start_s`
+    fn place_file_at_time_reference(&mut self, file_ix: usize, start_s: f64) -> Result<()> {
+        let file = &self.files2[file_ix];
+        let Some(bext) = &file.bext else {
+            return Ok(());
+        };
+        let offset =
+            ((bext.time_reference_s(file.sample_rate) - start_s) * file.sample_rate as f64).round();
+        for channel in file.channels.values() {
+            if self.tracks.set_item_offset(channel.buffer_id, offset)? {
+                self.time_reference_offsets
+                    .insert(channel.buffer_id, offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a marker per cue point of the file, it stays as long as any channel of the file has a
+    /// track
+    fn add_file_markers(&mut self, file: &wav::file2::File) {
+        let buffer_ids = file
+            .channels
+            .values()
+            .map(|channel| channel.buffer_id)
+            .collect::<Vec<_>>();
+        for cue_point in &file.cue_points {
+            self.markers
+                .insert(markers::Marker::from_cue_point(cue_point, &buffer_ids));
+        }
+    }
+
+    /// Pan the view to center the next or previous marker, relative to the current center
+    pub fn jump_to_marker(&mut self, direction: markers::JumpDirection) -> Result<()> {
+        let Some(ix_range) = self.tracks.ruler.ix_range() else {
+            return Ok(());
+        };
+        let center_ix = (ix_range.start + ix_range.end) / 2.0;
+        if let Some(target_ix) = self.markers.jump_target(&self.tracks, center_ix, direction) {
+            self.tracks.center_on_sample_ix(target_ix, &self.audio)?;
+        }
+        Ok(())
+    }
+
+    /// Add the labels of an Audacity label track as markers, at the sample rate of the tracks
+    pub fn import_labels(&mut self, path: &std::path::Path) -> Result<()> {
+        let sample_rate = self
+            .tracks
+            .sample_rate(&self.audio)
+            .ok_or_else(|| anyhow::anyhow!("Open a file before importing labels"))?;
+        for cue_point in wav::labels::read_audacity_labels(path, sample_rate)? {
+            self.markers
+                .insert(markers::Marker::from_cue_point(&cue_point, &[]));
+        }
+        Ok(())
+    }
+
+    /// Write the markers as Audacity label track or CSV, at the sample rate of the tracks
+    pub fn export_markers(
+        &self,
+        path: &std::path::Path,
+        format: wav::labels::LabelFormat,
+    ) -> Result<()> {
+        let sample_rate = self
+            .tracks
+            .sample_rate(&self.audio)
+            .ok_or_else(|| anyhow::anyhow!("No sample rate to convert markers to seconds"))?;
+        let cue_points: Vec<_> = self
+            .markers
+            .positioned(&self.tracks)
+            .into_iter()
+            .enumerate()
+            .map(|(ix, (_, marker, sample_ix))| wav::cue::CuePoint {
+                id: ix as u32 + 1,
+                sample_ix: sample_ix.round().max(0.0) as u64,
+                length: marker.length.map(|length| length.round().max(0.0) as u64),
+                label: marker.label.clone(),
+                note: String::new(),
+            })
+            .collect();
+        wav::labels::write_labels(path, format, &cue_points, sample_rate)
+    }
+
+    /// Select the samples of a region marker
+    pub fn select_marker_region(&mut self, marker_id: markers::MarkerId) -> Result<()> {
+        let ix_rng = self
+            .markers
+            .region_ix_range(marker_id, &self.tracks)
+            .ok_or_else(|| anyhow::anyhow!("Marker {:?} is not a visible region", marker_id))?;
+        let screen_x = |sample_ix: i64| {
+            self.tracks
+                .sample_ix_to_screen_x(sample_ix as f64)
+                .unwrap_or_default()
+        };
+        self.tracks.selection_info =
+            selection_info::SelectionInfoE::IsSelected(selection_info::SelectionInfo {
+                ix_rng,
+                screen_x_start: screen_x(ix_rng.start),
+                screen_x_end: screen_x(ix_rng.end),
+            });
+        Ok(())
+    }
+
+    /// Store the buffers of a loaded file and create their thumbnails, without adding tracks
+    fn store_loaded_file(
+        &mut self,
+        loaded: wav::read::LoadedFile,
+        progress: Option<wav::read::LoadProgressHandle>,
+    ) -> Result<wav::file2::File> {
         let mut channels = std::collections::BTreeMap::new();
         if let Some(progress) = progress.as_ref() {
             progress.set_stage(
@@ -216,14 +501,14 @@ impl Model {
         let mut thumbnail_count: u64 = 0;
         for (ch_ix, buffer) in loaded.channels {
             let thumbnail = ThumbnailE::from_buffer_e(&buffer, None);
-            let buffer_id = self.audio.buffers.insert(buffer);
+            let buffer_id = self.audio.buffers.insert(Arc::new(buffer));
             self.audio.thumbnails.insert(buffer_id, thumbnail);
             channels.insert(
                 ch_ix,
                 wav::file2::Channel {
                     ch_ix,
                     buffer_id,
-                    channel_id: None,
+                    channel_id: loaded.channel_ids.get(&ch_ix).copied(),
                 },
             );
             thumbnail_count += 1;
@@ -231,6 +516,18 @@ impl Model {
                 progress.set_current(thumbnail_count);
             }
         }
+        // Their thumbnails were built while streaming through the file
+        for (ch_ix, streamed) in loaded.streamed {
+            let buffer_id = self.audio.insert_streamed(streamed);
+            channels.insert(
+                ch_ix,
+                wav::file2::Channel {
+                    ch_ix,
+                    buffer_id,
+                    channel_id: loaded.channel_ids.get(&ch_ix).copied(),
+                },
+            );
+        }
 
         let file = wav::file2::File {
             channels,
@@ -240,12 +537,179 @@ impl Model {
             layout: loaded.layout,
             path: loaded.path,
             nr_samples: loaded.nr_samples,
+            bext: loaded.bext,
+            cue_points: loaded.cue_points,
+            read_config: loaded.read_config,
         };
 
-        self.tracks
-            .add_tracks_from_file(&file, &self.user_config.track)?;
+        Ok(file)
+    }
+
+    /// Replace the opened file read with `read_config` by its reloaded version. Its tracks keep
+    /// their order, visibility, height, value range and position on the time line, markers stay
+    /// with their channel. Channels the file no longer has lose their track, new ones get one.
+    fn replace_loaded_file(
+        &mut self,
+        read_config: &wav::ReadConfig,
+        loaded: wav::read::LoadedFile,
+        progress: Option<wav::read::LoadProgressHandle>,
+    ) -> Result<()> {
+        let Some(file_ix) = self
+            .files2
+            .iter()
+            .position(|file| file.read_config.as_ref() == Some(read_config))
+        else {
+            info!(
+                "{} was closed before it was reloaded",
+                read_config.filepath.display()
+            );
+            return Ok(());
+        };
+        let file = self.store_loaded_file(loaded, progress)?;
+        let old_file = std::mem::replace(&mut self.files2[file_ix], file.clone());
+        let mut buffer_ids = Vec::new();
+        for (ch_ix, old_channel) in &old_file.channels {
+            match file.channels.get(ch_ix) {
+                Some(channel) => {
+                    self.tracks
+                        .replace_buffer(old_channel.buffer_id, channel.buffer_id);
+                    self.markers
+                        .replace_buffer(old_channel.buffer_id, channel.buffer_id);
+                    if let Some(offset) = self.time_reference_offsets.remove(&old_channel.buffer_id)
+                    {
+                        self.time_reference_offsets
+                            .insert(channel.buffer_id, offset);
+                    }
+                    buffer_ids.push(channel.buffer_id);
+                }
+                None => {
+                    self.remove_channel_track(old_channel.buffer_id);
+                }
+            }
+        }
+        for channel in file.channels.values() {
+            if !old_file.channels.contains_key(&channel.ch_ix) {
+                self.restore_channel_track(channel.buffer_id)?;
+            }
+        }
+        self.update_diff_residuals(&buffer_ids)?;
+        self.audio.remove_buffers_from_file(&old_file);
+        Ok(())
+    }
+
+    /// Compute the A−B residual again for the diff tracks comparing any of `buffer_ids`
+    fn update_diff_residuals(&mut self, buffer_ids: &[audio::BufferId]) -> Result<()> {
+        for track in self.tracks.tracks.values_mut() {
+            let Some(diff) = track.diff.as_ref() else {
+                continue;
+            };
+            if !buffer_ids.contains(&diff.buffer_id_a) && !buffer_ids.contains(&diff.buffer_id_b) {
+                continue;
+            }
+            let (offset_a, offset_b) = (diff.sample_ix_offset_a, diff.sample_ix_offset_b);
+            let residual = track::diff::residual_buffer(
+                self.audio.get_buffer(diff.buffer_id_a)?,
+                self.audio.get_buffer(diff.buffer_id_b)?,
+                offset_a,
+                offset_b,
+            )?;
+            let thumbnail = ThumbnailE::from_buffer_e(&residual, None);
+            let residual_buffer_id = self.audio.buffers.insert(Arc::new(residual));
+            self.audio.thumbnails.insert(residual_buffer_id, thumbnail);
+            let old_residual_buffer_id =
+                track.set_diff_offsets(offset_a, offset_b, residual_buffer_id)?;
+            self.audio.remove_buffer(old_residual_buffer_id);
+        }
+        Ok(())
+    }
+
+    /// Reload or offer to reload the opened files that changed on disk, see
+    /// `Config::reload_on_change`
+    pub fn watch_files(&mut self) {
+        let paths: Vec<_> = self
+            .files2
+            .iter()
+            .filter_map(|file| file.read_config.as_ref())
+            .map(|read_config| read_config.filepath.clone())
+            .collect();
+        for path in self
+            .file_watcher
+            .poll(paths.iter().map(std::path::PathBuf::as_path))
+        {
+            match self.user_config.reload_on_change {
+                file_watch::ReloadPolicy::Always => self.actions.push(Action::ReloadFile(path)),
+                file_watch::ReloadPolicy::Ask => {
+                    if !self.changed_files.contains(&path) {
+                        self.changed_files.push(path);
+                    }
+                }
+                file_watch::ReloadPolicy::Never => {}
+            }
+        }
+    }
+
+    /// Add a loaded file that is one side of a diff, the diff tracks are added once the other
+    /// side is loaded too.
+    fn add_loaded_diff_file(
+        &mut self,
+        loaded: wav::read::LoadedFile,
+        progress: Option<wav::read::LoadProgressHandle>,
+    ) -> Result<()> {
+        let load_id = loaded.load_id;
+        let file = self.store_loaded_file(loaded, progress)?;
         self.files2.push(file);
+        if let Some((file_ix_a, file_ix_b)) = self
+            .load_mgr
+            .resolve_diff_load(load_id, self.files2.len() - 1)
+        {
+            self.add_diff_tracks(file_ix_a, file_ix_b)?;
+        }
+        Ok(())
+    }
+
+    /// Add a diff track per channel pair of file A and B, in channel order.
+    pub fn add_diff_tracks(&mut self, file_ix_a: usize, file_ix_b: usize) -> Result<()> {
+        let file_a = self
+            .files2
+            .get(file_ix_a)
+            .ok_or_else(|| anyhow::anyhow!("File {file_ix_a} not found"))?;
+        let file_b = self
+            .files2
+            .get(file_ix_b)
+            .ok_or_else(|| anyhow::anyhow!("File {file_ix_b} not found"))?;
+        if file_a.channels.len() != file_b.channels.len() {
+            tracing::warn!(
+                "Diff files have a different number of channels ({} and {}), comparing the first {}",
+                file_a.channels.len(),
+                file_b.channels.len(),
+                file_a.channels.len().min(file_b.channels.len())
+            );
+        }
+        let buffer_pairs: Vec<_> = file_a
+            .channels
+            .values()
+            .zip(file_b.channels.values())
+            .map(|(channel_a, channel_b)| (channel_a.buffer_id, channel_b.buffer_id))
+            .collect();
 
+        for (buffer_id_a, buffer_id_b) in buffer_pairs {
+            let residual = track::diff::residual_buffer(
+                self.audio.get_buffer(buffer_id_a)?,
+                self.audio.get_buffer(buffer_id_b)?,
+                0.0,
+                0.0,
+            )?;
+            let thumbnail = ThumbnailE::from_buffer_e(&residual, None);
+            let residual_buffer_id = self.audio.buffers.insert(Arc::new(residual));
+            self.audio.thumbnails.insert(residual_buffer_id, thumbnail);
+            self.tracks.add_diff_track_to_end(
+                buffer_id_a,
+                buffer_id_b,
+                residual_buffer_id,
+                &self.audio,
+                &self.user_config.track,
+            )?;
+        }
         Ok(())
     }
 
@@ -256,24 +720,55 @@ impl Model {
             had_results = true;
             match result {
                 wav::read::LoadResult::Ok(loaded) => {
-                    if let Err(err) = self.add_loaded_file(loaded, progress.clone()) {
+                    let load_id = loaded.load_id;
+                    let reload = self.load_mgr.take_reload(load_id);
+                    let is_reload = reload.is_some();
+                    let is_session = self.load_mgr.is_session_load(load_id);
+                    let result = if let Some(read_config) = reload {
+                        self.replace_loaded_file(&read_config, *loaded, progress.clone())
+                    } else if self.load_mgr.is_diff_load(load_id) {
+                        self.add_loaded_diff_file(*loaded, progress.clone())
+                    } else {
+                        self.add_loaded_file(*loaded, progress.clone())
+                    };
+                    if let Err(err) = result {
+                        self.load_mgr.cancel_diff_load(load_id);
+                        self.resolve_session_load(load_id, None);
                         tracing::error!("Failed to integrate loaded file: {err}");
                     } else {
                         if let Some(progress) = progress.as_ref() {
                             progress.set_stage(wav::read::LoadStage::Done, 1);
                             progress.set_current(1);
                         }
-                        self.actions.push(Action::ZoomToFull);
-                        self.actions.push(Action::FillScreenHeight);
+                        // a reloaded file keeps the zoom and track heights, a session restores them
+                        if is_session {
+                            self.resolve_session_load(load_id, Some(self.files2.len() - 1));
+                        } else if !is_reload {
+                            self.actions.push(Action::ZoomToFull);
+                            self.actions.push(Action::FillScreenHeight);
+                        }
                     }
                 }
-                wav::read::LoadResult::Err { error, .. } => {
+                wav::read::LoadResult::Err { load_id, error } => {
+                    self.load_mgr.cancel_diff_load(load_id);
+                    self.load_mgr.take_reload(load_id);
+                    self.resolve_session_load(load_id, None);
                     tracing::error!("Failed to load wav file: {error}");
                 }
             }
         }
         had_results
     }
+
+    /// Lay out the tracks of a session once the last of its files is loaded, a file that failed
+    /// to load has `file_ix` None
+    fn resolve_session_load(&mut self, load_id: wav::read::LoadId, file_ix: Option<usize>) {
+        if let Some((session, file_ixs)) = self.load_mgr.resolve_session_load(load_id, file_ix)
+            && let Err(err) = session::restore_layout(self, &session, &file_ixs)
+        {
+            tracing::error!("Failed to restore the session layout: {err:#}");
+        }
+    }
 }
 
 impl Model {
@@ -290,18 +785,22 @@ impl Model {
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
+    use std::sync::Arc;
 
     use super::{FileVisibilityState, Model};
     use crate::{
         audio,
-        model::track,
+        model::{Action, markers, selection_info, track},
         wav::{self, file2},
     };
 
     fn add_buffer(model: &mut Model) -> audio::BufferId {
-        model.audio.buffers.insert(audio::buffer::BufferE::F32(
-            audio::buffer::Buffer::with_size(48_000, 32, 16),
-        ))
+        model
+            .audio
+            .buffers
+            .insert(Arc::new(audio::buffer::BufferE::F32(
+                audio::buffer::Buffer::with_size(48_000, 32, 16),
+            )))
     }
 
     fn make_file(buffers: &[audio::BufferId]) -> file2::File {
@@ -328,9 +827,169 @@ mod tests {
             layout: None,
             path: None,
             nr_samples: 16,
+            bext: None,
+            cue_points: Vec::new(),
+            read_config: None,
         }
     }
 
+    #[test]
+    fn files_are_placed_at_their_time_reference() {
+        let loaded = |seconds: u64| {
+            let mut buffer = audio::buffer::Buffer::<f32>::new(48_000, 32);
+            buffer.data = vec![0.0; 16];
+            wav::read::LoadedFile {
+                load_id: 0,
+                channels: BTreeMap::from([(0, audio::buffer::BufferE::F32(buffer))]),
+                sample_type: audio::SampleType::Float,
+                bit_depth: 32,
+                sample_rate: 48_000,
+                layout: None,
+                channel_ids: BTreeMap::new(),
+                path: None,
+                nr_samples: 16,
+                bext: Some(Box::new(wav::bext::Bext {
+                    time_reference: seconds * 48_000,
+                    ..Default::default()
+                })),
+                cue_points: Vec::new(),
+                read_config: None,
+                streamed: BTreeMap::new(),
+            }
+        };
+        let buffer_id =
+            |model: &Model, file_ix: usize| model.files2[file_ix].channels[&0].buffer_id;
+        let offset =
+            |model: &Model, file_ix| model.tracks.item_offset(buffer_id(model, file_ix)).unwrap();
+        let mut model = Model::new();
+        model.add_loaded_file(loaded(3600), None).unwrap();
+        model.add_loaded_file(loaded(3602), None).unwrap();
+        assert_eq!(offset(&model, 1), 0.0);
+
+        model.user_config.place_at_time_reference = true;
+        model.place_files_at_time_reference().unwrap();
+        assert_eq!(offset(&model, 0), 0.0);
+        assert_eq!(offset(&model, 1), 96_000.0);
+
+        // a newly added file is placed without moving the others
+        model
+            .tracks
+            .set_item_offset(buffer_id(&model, 0), 10.0)
+            .unwrap();
+        model.add_loaded_file(loaded(3601), None).unwrap();
+        assert_eq!(offset(&model, 0), 10.0);
+        assert_eq!(offset(&model, 2), 48_000.0);
+
+        // turning it off keeps the file that was moved since
+        model.user_config.place_at_time_reference = false;
+        model.place_files_at_time_reference().unwrap();
+        assert_eq!(offset(&model, 0), 10.0);
+        assert_eq!(offset(&model, 1), 0.0);
+        assert_eq!(offset(&model, 2), 0.0);
+    }
+
+    #[test]
+    fn cue_points_become_markers_that_move_with_their_file() {
+        let mut model = Model::new();
+        let buffer_id = add_buffer(&mut model);
+        let file = file2::File {
+            cue_points: vec![wav::cue::CuePoint {
+                sample_ix: 4,
+                label: "onset".into(),
+                ..Default::default()
+            }],
+            ..make_file(&[buffer_id])
+        };
+        model
+            .tracks
+            .add_tracks_from_file(&file, &model.user_config.track)
+            .unwrap();
+        model.add_file_markers(&file);
+
+        let (track_id, _) = model.tracks.find_track(buffer_id).unwrap();
+        model
+            .tracks
+            .get_track_mut(track_id)
+            .unwrap()
+            .set_sample_ix_offset(None, 10.0)
+            .unwrap();
+
+        let positioned = model.markers.positioned(&model.tracks);
+        assert_eq!(positioned.len(), 1);
+        assert_eq!(positioned[0].1.label, "onset");
+        assert_eq!(positioned[0].2, 14.0);
+
+        model.tracks.remove_track(track_id);
+        assert!(model.markers.positioned(&model.tracks).is_empty());
+    }
+
+    #[test]
+    fn region_marker_becomes_the_selection() {
+        let mut model = Model::new();
+        let region = markers::Marker::region((480..960).into(), "chorus");
+        Action::AddMarker(region).process(&mut model).unwrap();
+        let (marker_id, _, _) = model.markers.positioned(&model.tracks)[0];
+
+        Action::SelectMarkerRegion(marker_id)
+            .process(&mut model)
+            .unwrap();
+
+        let selection_info::SelectionInfoE::IsSelected(selection) = model.tracks.selection_info
+        else {
+            panic!("region not selected");
+        };
+        assert_eq!(selection.ix_rng, (480..960).into());
+
+        let point = markers::Marker::point(10.0, "click");
+        Action::AddMarker(point).process(&mut model).unwrap();
+        let (point_id, _, _) = model.markers.positioned(&model.tracks)[0];
+        assert!(model.select_marker_region(point_id).is_err());
+    }
+
+    #[test]
+    fn exported_markers_are_read_back_when_the_file_is_opened_again() {
+        let path =
+            std::env::temp_dir().join(format!("wavalyze_markers_{}.wav", std::process::id()));
+        let label_path = wav::labels::label_file_path(&path);
+        let note_path = path.with_extension("txt");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in 0..100 {
+            writer.write_sample(sample as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        std::fs::write(&note_path, "not a label track\n").unwrap();
+        let open = |model: &mut Model| {
+            let loaded = wav::read::read_to_loaded_file(&wav::ReadConfig::new(&path)).unwrap();
+            model.add_loaded_file(loaded, None).unwrap();
+        };
+        let mut model = Model::new();
+        open(&mut model);
+        Action::AddMarker(markers::Marker::region((20..30).into(), "glitch"))
+            .process(&mut model)
+            .unwrap();
+        model
+            .export_markers(&label_path, wav::labels::LabelFormat::Audacity)
+            .unwrap();
+
+        model.close_all_files();
+        open(&mut model);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&label_path).unwrap();
+        std::fs::remove_file(&note_path).unwrap();
+
+        let cue_points = &model.files2[0].cue_points;
+        assert_eq!(cue_points.len(), 1);
+        assert_eq!(cue_points[0].label, "glitch");
+        assert_eq!(cue_points[0].sample_ix, 20);
+        assert_eq!(cue_points[0].length, Some(10));
+    }
+
     #[test]
     fn file_visibility_state_tracks_partial_visibility() {
         let mut model = Model::new();
@@ -386,6 +1045,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cue_markers_stay_while_any_channel_of_the_file_has_a_track() {
+        let mut model = Model::new();
+        let buffers = [add_buffer(&mut model), add_buffer(&mut model)];
+        let mut file = make_file(&buffers);
+        file.cue_points = vec![wav::cue::CuePoint {
+            sample_ix: 5,
+            ..Default::default()
+        }];
+        model
+            .tracks
+            .add_tracks_from_file(&file, &model.user_config.track)
+            .unwrap();
+        model.add_file_markers(&file);
+        model.files2.push(file);
+
+        assert!(model.remove_channel_track(buffers[0]));
+        assert_eq!(model.markers.positioned(&model.tracks).len(), 1);
+
+        Action::CloseFile(0).process(&mut model).unwrap();
+        assert_eq!(model.markers.len(), 0);
+    }
+
     #[test]
     fn restore_channel_track_recreates_missing_track() {
         let mut model = Model::new();
@@ -495,4 +1177,220 @@ mod tests {
         assert!(!model.restore_channel_track(buffers[0]).unwrap());
         assert_eq!(model.tracks.tracks_order.len(), 2);
     }
+
+    #[test]
+    fn add_diff_tracks_pairs_channels_and_stores_residual() {
+        let mut model = Model::new();
+        let mut buffer_a = audio::buffer::Buffer::<f32>::new(48_000, 32);
+        buffer_a.data = vec![0.5, 0.25];
+        let mut buffer_b = audio::buffer::Buffer::<f32>::new(48_000, 32);
+        buffer_b.data = vec![0.25, 0.25];
+        let buffer_a = model
+            .audio
+            .buffers
+            .insert(Arc::new(audio::buffer::BufferE::F32(buffer_a)));
+        let buffer_b = model
+            .audio
+            .buffers
+            .insert(Arc::new(audio::buffer::BufferE::F32(buffer_b)));
+        model.files2.push(make_file(&[buffer_a]));
+        model.files2.push(make_file(&[buffer_b]));
+
+        model.add_diff_tracks(0, 1).unwrap();
+
+        assert_eq!(model.tracks.tracks_order.len(), 1);
+        let track = model
+            .tracks
+            .get_track(model.tracks.tracks_order[0])
+            .unwrap();
+        let diff = track.diff.as_ref().unwrap();
+        assert_eq!(diff.buffer_id_a, buffer_a);
+        assert_eq!(diff.buffer_id_b, buffer_b);
+        let residual = model.audio.get_buffer(track.single.item.buffer_id).unwrap();
+        assert_eq!(residual.norm_value(0), Some(0.25));
+        assert_eq!(residual.norm_value(1), Some(0.0));
+        assert!(
+            model
+                .audio
+                .thumbnails
+                .contains_key(track.single.item.buffer_id)
+        );
+    }
+
+    #[test]
+    fn reloaded_file_keeps_how_its_tracks_are_shown() {
+        let path = std::env::temp_dir().join(format!("wavalyze_reload_{}.wav", std::process::id()));
+        let write = |first_sample: i16| {
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: 48_000,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+            for sample in first_sample..first_sample + 20 {
+                writer.write_sample(sample).unwrap();
+            }
+            writer.finalize().unwrap();
+        };
+        let read_config = wav::ReadConfig::new(&path);
+        write(0);
+        let mut model = Model::new();
+        let loaded = wav::read::read_to_loaded_file(&read_config).unwrap();
+        model.add_loaded_file(loaded, None).unwrap();
+        model.tracks.tracks_order.reverse();
+        let tracks_order = model.tracks.tracks_order.clone();
+        let old_buffer_id = model.files2[0].channels[&0].buffer_id;
+        let (track_id, _) = model.tracks.find_track(old_buffer_id).unwrap();
+        model.tracks.set_track_visibility(track_id, false);
+        let track = model.tracks.get_track_mut(track_id).unwrap();
+        track.set_sample_ix_offset(None, 7.0).unwrap();
+        model.markers.insert(markers::Marker::from_cue_point(
+            &Default::default(),
+            &[old_buffer_id],
+        ));
+
+        write(1000);
+        let reloaded = wav::read::read_to_loaded_file(&read_config);
+        std::fs::remove_file(&path).unwrap();
+        model
+            .replace_loaded_file(&read_config, reloaded.unwrap(), None)
+            .unwrap();
+
+        assert_eq!(model.files2.len(), 1);
+        assert_eq!(model.tracks.tracks_order, tracks_order);
+        let new_buffer_id = model.files2[0].channels[&0].buffer_id;
+        assert_ne!(new_buffer_id, old_buffer_id);
+        assert!(model.audio.get_buffer(old_buffer_id).is_err());
+        let track = model.tracks.get_track(track_id).unwrap();
+        assert_eq!(track.single.item.buffer_id, new_buffer_id);
+        assert!(!track.visible);
+        assert_eq!(track.single.item.sample_ix_offset, 7.0);
+        let buffer = model.audio.get_buffer(new_buffer_id).unwrap();
+        assert!(buffer.norm_value(0).unwrap() > 0.0);
+        let (_, marker, sample_ix) = model.markers.positioned(&model.tracks)[0];
+        assert_eq!(marker.buffer_ids, vec![new_buffer_id]);
+        assert_eq!(sample_ix, 7.0);
+    }
+
+    #[test]
+    fn closing_a_file_frees_its_buffers_and_keeps_overlaid_channels() {
+        let mut model = Model::new();
+        for name in ["a.wav", "b.wav"] {
+            let file = file2::File {
+                path: Some(name.into()),
+                ..make_file(&[add_buffer(&mut model)])
+            };
+            model
+                .tracks
+                .add_tracks_from_file(&file, &model.user_config.track)
+                .unwrap();
+            model.files2.push(file);
+        }
+        let buffer_a = model.files2[0].channels[&0].buffer_id;
+        let buffer_b = model.files2[1].channels[&0].buffer_id;
+        model.time_reference_start_s = Some(3600.0);
+        model.time_reference_offsets.insert(buffer_a, 0.0);
+        model.time_reference_offsets.insert(buffer_b, 48_000.0);
+        model.changed_files = vec!["a.wav".into(), "b.wav".into()];
+        let (track_a, _) = model.tracks.find_track(buffer_a).unwrap();
+        let (track_b, _) = model.tracks.find_track(buffer_b).unwrap();
+        model.tracks.overlay_track(track_b, track_a).unwrap();
+        model.markers.insert(markers::Marker::from_cue_point(
+            &Default::default(),
+            &[buffer_a],
+        ));
+        let usage = model.audio.memory_usage();
+
+        Action::CloseFile(0).process(&mut model).unwrap();
+
+        assert_eq!(model.files2.len(), 1);
+        assert!(model.audio.get_buffer(buffer_a).is_err());
+        assert_eq!(
+            model.audio.memory_usage().buffers,
+            usage.buffers - 16 * std::mem::size_of::<f32>()
+        );
+        assert!(model.tracks.find_track(buffer_a).is_none());
+        let (track_id, track) = model.tracks.find_track(buffer_b).unwrap();
+        assert_eq!(track.single.item.buffer_id, buffer_b);
+        assert_eq!(model.tracks.tracks_order, vec![track_id]);
+        assert!(model.markers.is_empty());
+        assert_eq!(
+            model.time_reference_offsets,
+            [(buffer_b, 48_000.0)].into_iter().collect()
+        );
+        assert_eq!(model.time_reference_start_s, Some(3600.0));
+        assert_eq!(model.changed_files, [std::path::PathBuf::from("b.wav")]);
+
+        Action::CloseAllFiles.process(&mut model).unwrap();
+        assert!(model.files2.is_empty());
+        assert!(model.tracks.tracks_order.is_empty());
+        assert!(model.time_reference_offsets.is_empty());
+        assert_eq!(model.time_reference_start_s, None);
+        assert!(model.changed_files.is_empty());
+        assert_eq!(model.audio.memory_usage(), Default::default());
+    }
+
+    #[test]
+    fn file_above_stream_threshold_is_shown_from_disk() {
+        let path =
+            std::env::temp_dir().join(format!("wavalyze_streamed_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in 0..5000 {
+            writer.write_sample(sample as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        let mut model = Model::new();
+        model.user_config.stream_threshold_mib = 0;
+        let loaded = wav::read::read_or_stream_loaded_file(
+            &wav::ReadConfig::new(&path),
+            model.user_config.stream_threshold(),
+            0,
+            None,
+        )
+        .unwrap();
+        assert!(loaded.channels.is_empty());
+        model.add_loaded_file(loaded, None).unwrap();
+        let buffer_id = model.files2[0].channels[&0].buffer_id;
+
+        assert!(model.audio.get_buffer(buffer_id).is_err());
+        let mut sample_rect = model.audio.get_sample_rect(buffer_id).unwrap();
+        assert_eq!(sample_rect.width(), 5000.0);
+        sample_rect.set_ix_rng(audio::sample::FracIxRange {
+            start: 100.0,
+            end: 200.0,
+        });
+        let view = |model: &Model| {
+            model.audio.get_sample_view(
+                buffer_id,
+                sample_rect,
+                crate::rect::Rect::new(0.0, 0.0, 100.0, 100.0),
+                Default::default(),
+            )
+        };
+        // the thumbnail is shown while the samples are read
+        assert!(view(&model).is_ok());
+        while model.audio.is_reading_streams() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(model.audio.nr_stream_reads(), 1);
+        assert!(view(&model).is_ok());
+        assert_eq!(model.audio.nr_stream_reads(), 1);
+        assert!(model.audio.memory_usage().streamed > 0);
+        assert_eq!(
+            model.audio.norm_value(buffer_id, 150).unwrap(),
+            Some(150.0 / 32768.0)
+        );
+
+        Action::CloseFile(0).process(&mut model).unwrap();
+        assert!(model.audio.streams.is_empty());
+        assert_eq!(model.audio.memory_usage(), Default::default());
+    }
 }
@@ -1,7 +1,9 @@
 use crate::{
     audio::sample,
-    math::round::round_up_to_power_of_10,
-    model::ruler::{ceil_to_multiple, floor_to_multiple, sample_ix_to_screen_x},
+    model::ruler::{
+        sample_ix_to_screen_x,
+        time_format::{TickSpacing, TimeFormat},
+    },
     rect,
 };
 
@@ -18,8 +20,8 @@ pub enum TickType {
 /// A tick (lattice/grid line) on the ruler
 #[derive(Debug, Clone)]
 pub struct Tick {
-    /// The sample ix of this tick, can be negative
-    pub sample_ix: i64,
+    /// The sample ix of this tick, can be negative and fractional when the unit isn't samples
+    pub sample_ix: f64,
 
     /// Pixel position of this tick
     pub screen_x: f32,
@@ -31,6 +33,8 @@ pub struct Tick {
 #[derive(Debug, Clone, Default)]
 pub struct IxLattice {
     pub ticks: Vec<Tick>,
+    /// Spacing of the current ticks, the labels need it for their precision
+    pub spacing: Option<TickSpacing>,
 }
 
 impl IxLattice {
@@ -39,6 +43,7 @@ impl IxLattice {
         sample_ix_range: sample::FracIxRange,
         screen_rect: rect::Rect,
         nr_pixels_per_tick: f32,
+        time_format: &TimeFormat,
     ) -> anyhow::Result<()> {
         // tracing::trace!("compute_ticks {:?} {:?}", sample_ix_range, screen_rect.x_range_inc());
         let max_nr_ticks: f32 = screen_rect.width() / nr_pixels_per_tick;
@@ -50,28 +55,27 @@ impl IxLattice {
         self.ticks.clear();
 
         let min_nr_samples_per_label_tick: f64 = sample_width / max_nr_ticks as f64;
-        let nr_samples_per_label_tick: u64 =
-            round_up_to_power_of_10(min_nr_samples_per_label_tick) as u64;
-        // When very zoomed in, this could be smaller than 1 (i.e. 0), but we want to have at least
-        // 1 tick per sample
-        let nr_samples_per_label_tick: u64 = nr_samples_per_label_tick.max(1);
-        let nr_samples_per_mid_tick: u64 = (nr_samples_per_label_tick / 2).max(1);
-        let nr_samples_per_small_tick: u64 = (nr_samples_per_label_tick / 10).max(1);
-
-        let multiple = nr_samples_per_small_tick as i64;
-        let start_sample_ix = ceil_to_multiple(sample_ix_range.start.ceil() as i64, multiple);
-        let end_sample_ix = floor_to_multiple(sample_ix_range.end.floor() as i64, multiple);
-        for cur_sample_ix in
-            (start_sample_ix..=end_sample_ix).step_by(nr_samples_per_small_tick as usize)
-        {
-            let screen_x =
-                sample_ix_to_screen_x(cur_sample_ix as f64, sample_ix_range, screen_rect);
+        // When very zoomed in, the spacing is at least 1 sample (or frame)
+        let spacing = time_format.tick_spacing(min_nr_samples_per_label_tick);
+        self.spacing = Some(spacing);
+        let nr_small_ticks_per_big_tick = spacing.nr_small_ticks_per_big_tick as i64;
+        let nr_small_ticks_per_mid_tick =
+            (nr_small_ticks_per_big_tick % 2 == 0).then_some(nr_small_ticks_per_big_tick / 2);
+
+        // Ticks are counted in small ticks from sample ix 0, so they don't drift for fractional
+        // spacings, e.g. frames of 1837.5 samples
+        let nr_samples_per_small_tick = spacing.nr_samples_per_small_tick();
+        let start_tick_ix = (sample_ix_range.start / nr_samples_per_small_tick).ceil() as i64;
+        let end_tick_ix = (sample_ix_range.end / nr_samples_per_small_tick).floor() as i64;
+        for tick_ix in start_tick_ix..=end_tick_ix {
+            let cur_sample_ix = tick_ix as f64 * nr_samples_per_small_tick;
+            let screen_x = sample_ix_to_screen_x(cur_sample_ix, sample_ix_range, screen_rect);
             if !screen_rect.contains_x(screen_x) {
                 continue;
             };
-            let tick_type = if cur_sample_ix % nr_samples_per_label_tick as i64 == 0 {
+            let tick_type = if tick_ix % nr_small_ticks_per_big_tick == 0 {
                 TickType::Big
-            } else if cur_sample_ix % nr_samples_per_mid_tick as i64 == 0 {
+            } else if nr_small_ticks_per_mid_tick.is_some_and(|n| tick_ix % n == 0) {
                 TickType::Mid
             } else {
                 TickType::Small
@@ -90,6 +94,7 @@ impl IxLattice {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::ruler::time_format::TimeUnit;
 
     #[test]
     fn test_ticks() {
@@ -100,8 +105,65 @@ mod tests {
         };
         let screen_rect = rect::Rect::new(0.0, 0.0, 1000.0, 0.0);
         lattice
-            .compute_ticks(sample_ix_range, screen_rect, 100.0)
+            .compute_ticks(sample_ix_range, screen_rect, 100.0, &TimeFormat::default())
             .unwrap();
         dbg!(lattice);
     }
+
+    #[test]
+    fn timecode_ticks_fall_on_frames() {
+        let mut lattice = IxLattice::default();
+        let time_format = TimeFormat {
+            unit: TimeUnit::Timecode,
+            frame_rate: 24,
+            sample_rate: 44100,
+        };
+        // 10 frames of 1837.5 samples on 1000 pixels, a tick per frame
+        let sample_ix_range = sample::FracIxRange {
+            start: -100.0,
+            end: 18275.0,
+        };
+        let screen_rect = rect::Rect::new(0.0, 0.0, 1000.0, 0.0);
+
+        lattice
+            .compute_ticks(sample_ix_range, screen_rect, 50.0, &time_format)
+            .unwrap();
+
+        assert_eq!(lattice.ticks.len(), 10);
+        assert!(
+            lattice
+                .ticks
+                .iter()
+                .all(|tick| tick.tick_type == TickType::Big)
+        );
+        assert_eq!(lattice.ticks[3].sample_ix, 3.0 * 1837.5);
+    }
+
+    #[test]
+    fn second_ticks_use_the_sample_rate() {
+        let mut lattice = IxLattice::default();
+        let time_format = TimeFormat {
+            unit: TimeUnit::Seconds,
+            frame_rate: 25,
+            sample_rate: 8000,
+        };
+        // 3.75 seconds on 200 pixels, at most 4 labels: a big tick every second
+        let sample_ix_range = sample::FracIxRange {
+            start: 0.0,
+            end: 30000.0,
+        };
+        let screen_rect = rect::Rect::new(0.0, 0.0, 200.0, 0.0);
+
+        lattice
+            .compute_ticks(sample_ix_range, screen_rect, 50.0, &time_format)
+            .unwrap();
+
+        let big: Vec<f64> = lattice
+            .ticks
+            .iter()
+            .filter(|tick| tick.tick_type == TickType::Big)
+            .map(|tick| tick.sample_ix)
+            .collect();
+        assert_eq!(big, [0.0, 8000.0, 16000.0, 24000.0]);
+    }
 }
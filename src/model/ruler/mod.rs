@@ -1,6 +1,7 @@
 pub mod display_range;
 pub mod ix_lattice;
 pub mod time;
+pub mod time_format;
 pub mod util;
 pub mod value;
 pub mod value_lattice;
@@ -8,6 +9,7 @@ pub mod value_scale;
 
 pub use ix_lattice::{IxLattice, Tick, TickType};
 pub use time::Time;
+pub use time_format::{TimeFormat, TimeUnit};
 pub use util::*;
 pub use value_lattice::{ValueLattice, ValueTick};
 pub use value_scale::ValueDisplayScale;
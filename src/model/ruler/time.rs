@@ -2,7 +2,7 @@ use crate::{
     audio::sample,
     model::{
         PixelCoord, SampleIxZoom,
-        ruler::{ix_lattice::IxLattice, sample_ix_to_screen_x, screen_x_to_sample_ix},
+        ruler::{TimeFormat, ix_lattice::IxLattice, sample_ix_to_screen_x, screen_x_to_sample_ix},
     },
     rect,
 };
@@ -31,6 +31,9 @@ pub struct Time {
 
     /// The sample index ticks/lattice to draw for current screen rect/time_line
     pub ix_lattice: IxLattice,
+
+    /// Unit of the tick labels, hover and selection, with the sample rate of the shown files
+    pub time_format: TimeFormat,
     // TODO: don't recalculate ix_lattice every time, only when needed
     // make API a bit cleaner
 }
@@ -60,6 +63,7 @@ impl Time {
                 ix_range,
                 self.screen_rect,
                 crate::view::ruler::NR_PIXELS_PER_TICK,
+                &self.time_format,
             )
             .ok()?;
         Some(&self.ix_lattice)
@@ -0,0 +1,387 @@
+//! Sample indices shown as samples, seconds, milliseconds or SMPTE timecode.
+
+use strum_macros::EnumIter;
+use thousands::Separable;
+
+/// Frame rates to choose from for `TimeUnit::Timecode`, non-drop-frame only
+pub const FRAME_RATES: [u32; 6] = [24, 25, 30, 48, 50, 60];
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+pub enum TimeUnit {
+    #[default]
+    Samples,
+    /// h:mm:ss.ms
+    Seconds,
+    Milliseconds,
+    /// hh:mm:ss:ff at a frame rate
+    Timecode,
+}
+
+impl TimeUnit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeUnit::Samples => "samples",
+            TimeUnit::Seconds => "h:mm:ss.ms",
+            TimeUnit::Milliseconds => "milliseconds",
+            TimeUnit::Timecode => "timecode",
+        }
+    }
+}
+
+/// Distance between ticks of the time ruler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TickSpacing {
+    /// Number of samples between labelled ticks
+    pub nr_samples_per_big_tick: f64,
+    /// Number of small ticks a big tick is divided in, a mid tick is halfway when even
+    pub nr_small_ticks_per_big_tick: u32,
+}
+
+impl TickSpacing {
+    pub fn nr_samples_per_small_tick(&self) -> f64 {
+        self.nr_samples_per_big_tick / self.nr_small_ticks_per_big_tick as f64
+    }
+
+    /// Number of samples between labels, mid ticks are labelled too
+    fn nr_samples_per_label(&self) -> f64 {
+        if self.nr_small_ticks_per_big_tick.is_multiple_of(2) {
+            self.nr_samples_per_big_tick / 2.0
+        } else {
+            self.nr_samples_per_big_tick
+        }
+    }
+}
+
+/// Nice spacings of at least a second as (seconds, small ticks), continued with 1-2-5 hours
+const SECOND_SPACINGS: [(f64, u32); 15] = [
+    (1.0, 10),
+    (2.0, 4),
+    (5.0, 5),
+    (10.0, 10),
+    (15.0, 3),
+    (30.0, 6),
+    (60.0, 6),
+    (120.0, 4),
+    (300.0, 5),
+    (600.0, 10),
+    (900.0, 3),
+    (1800.0, 6),
+    (3600.0, 6),
+    (7200.0, 4),
+    (18000.0, 5),
+];
+
+/// How to show a sample index of a file with `sample_rate`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeFormat {
+    pub unit: TimeUnit,
+    /// Frames per second for `TimeUnit::Timecode`
+    pub frame_rate: u32,
+    pub sample_rate: u32,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self {
+            unit: TimeUnit::Samples,
+            frame_rate: 25,
+            sample_rate: 48000,
+        }
+    }
+}
+
+impl TimeFormat {
+    fn seconds(&self, sample_ix: f64) -> f64 {
+        sample_ix / self.sample_rate as f64
+    }
+
+    fn nr_samples_per_frame(&self) -> f64 {
+        self.sample_rate as f64 / self.frame_rate as f64
+    }
+
+    /// The smallest nice spacing of at least `min_nr_samples` samples between labelled ticks,
+    /// never less than a sample or a frame.
+    pub fn tick_spacing(&self, min_nr_samples: f64) -> TickSpacing {
+        match self.unit {
+            TimeUnit::Samples => {
+                let big = round_up_to_1_2_5(min_nr_samples).max(1.0);
+                TickSpacing {
+                    nr_samples_per_big_tick: big,
+                    nr_small_ticks_per_big_tick: small_ticks_1_2_5(big).min(big as u32),
+                }
+            }
+            TimeUnit::Seconds | TimeUnit::Milliseconds => {
+                let min_seconds = self.seconds(min_nr_samples);
+                let (seconds, nr_small) = if min_seconds <= 1.0 {
+                    let seconds = round_up_to_1_2_5(min_seconds);
+                    (seconds, small_ticks_1_2_5(seconds))
+                } else {
+                    second_spacing(min_seconds)
+                };
+                TickSpacing {
+                    nr_samples_per_big_tick: seconds * self.sample_rate as f64,
+                    nr_small_ticks_per_big_tick: nr_small,
+                }
+            }
+            TimeUnit::Timecode => {
+                let samples_per_frame = self.nr_samples_per_frame();
+                let min_frames = min_nr_samples / samples_per_frame;
+                let frames = [1, 2, 5, 10]
+                    .into_iter()
+                    .filter(|&frames| frames < self.frame_rate)
+                    .find(|&frames| frames as f64 >= min_frames);
+                match frames {
+                    Some(frames) => TickSpacing {
+                        nr_samples_per_big_tick: frames as f64 * samples_per_frame,
+                        nr_small_ticks_per_big_tick: frames,
+                    },
+                    None if min_frames <= self.frame_rate as f64 => TickSpacing {
+                        nr_samples_per_big_tick: self.sample_rate as f64,
+                        // small ticks on whole frames
+                        nr_small_ticks_per_big_tick: (1..=10)
+                            .rev()
+                            .find(|&n| self.frame_rate.is_multiple_of(n))
+                            .unwrap_or(1),
+                    },
+                    None => {
+                        let (seconds, nr_small) = second_spacing(self.seconds(min_nr_samples));
+                        TickSpacing {
+                            nr_samples_per_big_tick: seconds * self.sample_rate as f64,
+                            nr_small_ticks_per_big_tick: nr_small,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Label of a tick of the ruler, with as many decimals as the spacing needs
+    pub fn tick_label(&self, sample_ix: f64, spacing: &TickSpacing) -> String {
+        let label_seconds = self.seconds(spacing.nr_samples_per_label());
+        match self.unit {
+            TimeUnit::Samples => (sample_ix.round() as i64).separate_with_commas(),
+            TimeUnit::Seconds => format_seconds(self.seconds(sample_ix), decimals(label_seconds)),
+            TimeUnit::Milliseconds => {
+                let ms = label_seconds * 1000.0;
+                format_ms(self.seconds(sample_ix) * 1000.0, decimals(ms))
+            }
+            TimeUnit::Timecode => self.format_timecode(sample_ix),
+        }
+    }
+
+    /// Text for a single sample index, e.g. the hover position or a selection edge
+    pub fn format(&self, sample_ix: f64) -> String {
+        match self.unit {
+            TimeUnit::Samples => (sample_ix.round() as i64).separate_with_commas(),
+            TimeUnit::Seconds => format_seconds(self.seconds(sample_ix), 3),
+            TimeUnit::Milliseconds => format_ms(self.seconds(sample_ix) * 1000.0, 3),
+            TimeUnit::Timecode => self.format_timecode(sample_ix),
+        }
+    }
+
+    /// hh:mm:ss:ff of the frame the sample is in
+    fn format_timecode(&self, sample_ix: f64) -> String {
+        // a small tolerance so ticks on a frame boundary don't round down to the previous frame
+        let frames = (sample_ix.abs() / self.nr_samples_per_frame() + 1e-6).floor() as u64;
+        let fps = self.frame_rate as u64;
+        let seconds = frames / fps;
+        format!(
+            "{}{:02}:{:02}:{:02}:{:02}",
+            sign(sample_ix),
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            frames % fps
+        )
+    }
+}
+
+fn sign(value: f64) -> &'static str {
+    if value < 0.0 { "-" } else { "" }
+}
+
+/// h:mm:ss with `decimals` decimals for the seconds
+fn format_seconds(seconds: f64, decimals: usize) -> String {
+    let scale = 10_f64.powi(decimals as i32);
+    let units = (seconds.abs() * scale).round() as u64;
+    let scale = scale as u64;
+    let whole = units / scale;
+    let mut text = format!(
+        "{}{}:{:02}:{:02}",
+        sign(seconds),
+        whole / 3600,
+        whole / 60 % 60,
+        whole % 60
+    );
+    if decimals > 0 {
+        text += &format!(".{:0decimals$}", units % scale);
+    }
+    text
+}
+
+fn format_ms(ms: f64, decimals: usize) -> String {
+    let text = format!("{ms:.decimals$}");
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let whole: i64 = whole.parse().unwrap_or_default();
+    let sign = if whole == 0 { sign(ms) } else { "" };
+    if fraction.is_empty() {
+        format!("{sign}{} ms", whole.separate_with_commas())
+    } else {
+        format!("{sign}{}.{fraction} ms", whole.separate_with_commas())
+    }
+}
+
+/// Number of decimals to show multiples of `step` exactly
+fn decimals(step: f64) -> usize {
+    (0..9)
+        .find(|&decimals| {
+            let scaled = step * 10_f64.powi(decimals as i32);
+            (scaled - scaled.round()).abs() < 1e-6 * scaled.max(1.0)
+        })
+        .unwrap_or(9)
+}
+
+/// The smallest 1, 2 or 5 times a power of 10 that is at least `x`
+fn round_up_to_1_2_5(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let decade = 10_f64.powf(x.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * decade)
+        .find(|&step| step >= x * (1.0 - 1e-9))
+        .unwrap_or(10.0 * decade)
+}
+
+/// Small ticks of a 1-2-5 step: tenths of a 1, halves of a 2 split once more, fifths of a 5
+fn small_ticks_1_2_5(step: f64) -> u32 {
+    let mantissa = step / 10_f64.powf(step.log10().floor());
+    if (mantissa - 2.0).abs() < 1e-6 {
+        4
+    } else if (mantissa - 5.0).abs() < 1e-6 {
+        5
+    } else {
+        10
+    }
+}
+
+/// Spacing of at least a second in whole seconds, minutes or hours
+fn second_spacing(min_seconds: f64) -> (f64, u32) {
+    SECOND_SPACINGS
+        .into_iter()
+        .find(|&(seconds, _)| seconds >= min_seconds)
+        .unwrap_or_else(|| {
+            let hours = round_up_to_1_2_5(min_seconds / 3600.0);
+            (hours * 3600.0, small_ticks_1_2_5(hours))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{a} != {b}");
+    }
+
+    fn time_format(unit: TimeUnit) -> TimeFormat {
+        TimeFormat {
+            unit,
+            frame_rate: 25,
+            sample_rate: 48000,
+        }
+    }
+
+    #[test]
+    fn format_each_unit() {
+        let ix = 48000.0 * 3723.5;
+
+        assert_eq!(time_format(TimeUnit::Samples).format(ix), "178,728,000");
+        assert_eq!(time_format(TimeUnit::Seconds).format(ix), "1:02:03.500");
+        assert_eq!(
+            time_format(TimeUnit::Milliseconds).format(ix),
+            "3,723,500.000 ms"
+        );
+        assert_eq!(time_format(TimeUnit::Timecode).format(ix), "01:02:03:12");
+        assert_eq!(time_format(TimeUnit::Seconds).format(-48.0), "-0:00:00.001");
+        assert_eq!(
+            time_format(TimeUnit::Milliseconds).format(-24.0),
+            "-0.500 ms"
+        );
+    }
+
+    #[test]
+    fn second_spacings_are_nice() {
+        let format = time_format(TimeUnit::Seconds);
+
+        let spacing = format.tick_spacing(48000.0 * 0.003);
+        assert_close(spacing.nr_samples_per_big_tick, 48000.0 * 0.005);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 5);
+
+        let spacing = format.tick_spacing(48000.0 * 40.0);
+        assert_close(spacing.nr_samples_per_big_tick, 48000.0 * 60.0);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 6);
+
+        let spacing = format.tick_spacing(48000.0 * 4.0 * 3600.0);
+        assert_close(spacing.nr_samples_per_big_tick, 48000.0 * 5.0 * 3600.0);
+    }
+
+    #[test]
+    fn tick_labels_have_the_decimals_the_spacing_needs() {
+        let format = time_format(TimeUnit::Seconds);
+        let spacing = format.tick_spacing(48000.0 * 0.08);
+        assert_close(spacing.nr_samples_per_big_tick, 48000.0 * 0.1);
+        // the mid tick is at 0.05 s
+        assert_eq!(format.tick_label(48000.0 * 1.05, &spacing), "0:00:01.05");
+
+        let spacing = format.tick_spacing(48000.0 * 10.0);
+        assert_eq!(format.tick_label(48000.0 * 75.0, &spacing), "0:01:15");
+
+        let format = time_format(TimeUnit::Milliseconds);
+        let spacing = format.tick_spacing(48.0 * 2.0);
+        assert_eq!(format.tick_label(48.0 * 1002.0, &spacing), "1,002 ms");
+    }
+
+    #[test]
+    fn timecode_spacings_are_whole_frames() {
+        let format = time_format(TimeUnit::Timecode);
+
+        let spacing = format.tick_spacing(1000.0);
+        assert_close(spacing.nr_samples_per_big_tick, 1920.0);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 1);
+
+        let spacing = format.tick_spacing(48000.0 * 0.5);
+        assert_close(spacing.nr_samples_per_big_tick, 48000.0);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 5);
+
+        assert_eq!(
+            format.tick_label(48000.0 + 1920.0 * 5.0, &spacing),
+            "00:00:01:05"
+        );
+    }
+
+    #[test]
+    fn sample_spacings_are_at_least_one_sample() {
+        let format = time_format(TimeUnit::Samples);
+
+        let spacing = format.tick_spacing(0.2);
+        assert_close(spacing.nr_samples_per_big_tick, 1.0);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 1);
+
+        let spacing = format.tick_spacing(150.0);
+        assert_close(spacing.nr_samples_per_big_tick, 200.0);
+        assert_eq!(spacing.nr_small_ticks_per_big_tick, 4);
+    }
+}
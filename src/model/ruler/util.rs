@@ -189,7 +189,10 @@ mod tests {
             min: -1.0f64,
             max: 1.0,
         };
-        let display_scale = ValueDisplayScale { skew_factor: 1.0 };
+        let display_scale = ValueDisplayScale {
+            skew_factor: 1.0,
+            ..Default::default()
+        };
 
         let original_y = 25.0;
         let sample = screen_y_to_sample_value(original_y, range, rect, display_scale).unwrap();
@@ -1,6 +1,9 @@
 use crate::{
     audio::sample,
-    model::ruler::{TickType, ValueDisplayScale, floor_to_multiple, sample_value_to_screen_y},
+    model::ruler::{
+        TickType, ValueDisplayScale, display_range, floor_to_multiple, sample_value_to_screen_y,
+        value_scale::ValueScaleMode,
+    },
     rect,
 };
 
@@ -9,6 +12,8 @@ pub struct ValueTick {
     pub sample_value: f64,
     pub screen_y: f32,
     pub tick_type: TickType,
+    /// Level of the tick in a dB lattice, minus infinity for the zero line
+    pub db: Option<f64>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -22,6 +27,7 @@ pub struct ValueLattice {
     pub minor_step: f64,
     /// Values that should receive text labels on the ruler.
     pub label_step: f64,
+    // NOTE: the steps are in dB for `ValueScaleMode::Dbfs`
 }
 
 impl ValueLattice {
@@ -40,6 +46,11 @@ impl ValueLattice {
 
         self.ticks.clear();
 
+        if display_scale.mode == ValueScaleMode::Dbfs {
+            self.compute_db_ticks(val_range, screen_rect, max_nr_ticks, display_scale);
+            return Ok(());
+        }
+
         let visible_range = visible_tick_range(val_range, display_scale);
         let range_len = visible_range.len();
         if range_len == 0.0 {
@@ -90,19 +101,100 @@ impl ValueLattice {
                 sample_value: value,
                 screen_y,
                 tick_type: classify_tick_type(value, cadence),
+                db: None,
             });
             step_ix += 1;
         }
 
         Ok(())
     }
+
+    /// Ticks at levels in dB on both sides of zero, from full scale down to the floor which is
+    /// drawn at zero
+    fn compute_db_ticks(
+        &mut self,
+        val_range: sample::ValRange<f64>,
+        screen_rect: rect::Rect,
+        max_nr_ticks: f32,
+        display_scale: ValueDisplayScale,
+    ) {
+        let display_range = display_range::sample_to_display_range(val_range, display_scale);
+        // Like for the skewed scale, only full scale and below gets ticks
+        let (min, max) = (display_range.min.max(-1.0), display_range.max.min(1.0));
+        if min >= max {
+            return;
+        }
+        let db_range = display_scale.db_range();
+        let label_step = nice_db_step((max - min) * db_range / max_nr_ticks as f64);
+        self.major_step = label_step;
+        self.mid_step = None;
+        self.minor_step = label_step / 2.0;
+        self.label_step = label_step;
+
+        let mut push = |sample_value: f64, db: f64, tick_type: TickType| {
+            if let Some(screen_y) =
+                sample_value_to_screen_y(sample_value, val_range, screen_rect, display_scale)
+                && screen_rect.contains_y(screen_y)
+            {
+                self.ticks.push(ValueTick {
+                    sample_value,
+                    screen_y,
+                    tick_type,
+                    db: Some(db),
+                });
+            }
+        };
+        if (min..=max).contains(&0.0) {
+            push(0.0, f64::NEG_INFINITY, TickType::Big);
+        }
+        for sign in [-1.0, 1.0] {
+            // The part of the display range on this side, as positive display values
+            let (lo, hi) = if sign > 0.0 {
+                (min.max(0.0), max)
+            } else {
+                ((-max).max(0.0), -min)
+            };
+            if lo >= hi {
+                continue;
+            }
+            let minor_step = self.minor_step;
+            let start = (display_scale.display_to_db(lo) / minor_step).ceil() as i64;
+            let end = (display_scale.display_to_db(hi) / minor_step).floor() as i64;
+            for step_ix in start..=end {
+                let db = normalize_zero(step_ix as f64 * minor_step);
+                // the floor is the zero line
+                if db <= -db_range {
+                    continue;
+                }
+                let tick_type = if is_multiple_of(db, label_step) {
+                    TickType::Big
+                } else {
+                    TickType::Small
+                };
+                push(sign * 10_f64.powf(db / 20.0), db, tick_type);
+            }
+        }
+        self.ticks
+            .sort_by(|a, b| a.sample_value.total_cmp(&b.sample_value));
+    }
+}
+
+/// The smallest of the usual dB spacings that is at least `min_step`
+fn nice_db_step(min_step: f64) -> f64 {
+    const STEPS: [f64; 12] = [
+        0.1, 0.2, 0.5, 1.0, 2.0, 3.0, 6.0, 10.0, 20.0, 30.0, 60.0, 120.0,
+    ];
+    STEPS
+        .into_iter()
+        .find(|&step| step >= min_step)
+        .unwrap_or_else(|| 120.0 * (min_step / 120.0).ceil())
 }
 
 fn visible_tick_range(
     val_range: sample::ValRange<f64>,
     display_scale: ValueDisplayScale,
 ) -> sample::ValRange<f64> {
-    if display_scale.skew_factor == 0.0 {
+    if display_scale.is_linear() {
         return val_range;
     }
 
@@ -233,6 +325,96 @@ fn floor_to_multiple_f64(x: f64, step: f64) -> f64 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn dbfs_ticks_are_levels_mirrored_around_the_floor() {
+        let mut lattice = ValueLattice::default();
+        let screen_rect = rect::Rect::new(0.0, 0.0, 60.0, 400.0);
+        let display_scale = ValueDisplayScale {
+            mode: ValueScaleMode::Dbfs,
+            floor_db: -120.0,
+            ..Default::default()
+        };
+        lattice
+            .compute_ticks(
+                sample::ValRange {
+                    min: -1.0,
+                    max: 1.0,
+                },
+                screen_rect,
+                50.0,
+                display_scale,
+            )
+            .unwrap();
+
+        // 240 dB over 8 labels
+        assert_eq!(lattice.label_step, 30.0);
+        let big: Vec<_> = lattice
+            .ticks
+            .iter()
+            .filter(|tick| tick.tick_type == TickType::Big)
+            .map(|tick| tick.db.unwrap())
+            .collect();
+        assert_eq!(
+            big,
+            [
+                -0.0,
+                -30.0,
+                -60.0,
+                -90.0,
+                f64::NEG_INFINITY,
+                -90.0,
+                -60.0,
+                -30.0,
+                0.0
+            ]
+        );
+        let minus_60 = lattice
+            .ticks
+            .iter()
+            .find(|tick| tick.db == Some(-60.0) && tick.sample_value > 0.0)
+            .unwrap();
+        assert!((minus_60.sample_value - 1e-3).abs() < 1e-12);
+        // the floor itself is the zero line
+        assert!(
+            lattice
+                .ticks
+                .iter()
+                .all(|tick| tick.db.unwrap() > -120.0 || tick.sample_value == 0.0)
+        );
+    }
+
+    #[test]
+    fn dbfs_ticks_follow_a_zoomed_range() {
+        let mut lattice = ValueLattice::default();
+        let screen_rect = rect::Rect::new(0.0, 0.0, 60.0, 250.0);
+        let display_scale = ValueDisplayScale {
+            mode: ValueScaleMode::Dbfs,
+            floor_db: -120.0,
+            ..Default::default()
+        };
+        // -12.5 dB to full scale, positive side only
+        lattice
+            .compute_ticks(
+                sample::ValRange {
+                    min: display_scale.display_to_sample(display_scale.db_to_display(-12.5)),
+                    max: 1.0,
+                },
+                screen_rect,
+                50.0,
+                display_scale,
+            )
+            .unwrap();
+
+        assert_eq!(lattice.label_step, 3.0);
+        let labelled: Vec<_> = lattice
+            .ticks
+            .iter()
+            .filter(|tick| tick.tick_type == TickType::Big)
+            .map(|tick| tick.db.unwrap())
+            .collect();
+        assert_eq!(labelled, [-12.0, -9.0, -6.0, -3.0, 0.0]);
+    }
+
     #[test]
     fn full_scale_range_includes_zero_and_major_ticks() {
         let mut lattice = ValueLattice::default();
@@ -479,7 +661,10 @@ mod tests {
                 },
                 screen_rect,
                 50.0,
-                ValueDisplayScale { skew_factor: 1.0 },
+                ValueDisplayScale {
+                    skew_factor: 1.0,
+                    ..Default::default()
+                },
             )
             .unwrap();
 
@@ -517,7 +702,10 @@ mod tests {
                 sample::ValRange { min: 0.5, max: 1.5 },
                 screen_rect,
                 50.0,
-                ValueDisplayScale { skew_factor: 1.0 },
+                ValueDisplayScale {
+                    skew_factor: 1.0,
+                    ..Default::default()
+                },
             )
             .unwrap();
 
@@ -534,7 +722,10 @@ mod tests {
                 sample::ValRange { min: 1.1, max: 1.9 },
                 screen_rect,
                 50.0,
-                ValueDisplayScale { skew_factor: 1.0 },
+                ValueDisplayScale {
+                    skew_factor: 1.0,
+                    ..Default::default()
+                },
             )
             .unwrap();
 
@@ -1,19 +1,47 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum ValueScaleMode {
+    /// Power law with `skew_factor`, linear when it is zero
+    #[default]
+    Skew,
+    /// Level in dBFS mirrored around zero, `floor_db` and below are drawn at zero
+    Dbfs,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct ValueDisplayScale {
+    pub mode: ValueScaleMode,
     /// `0.0` is linear. `1.0` maps `0.5 -> 0.75`.
     pub skew_factor: f32,
+    /// Lowest level shown in `ValueScaleMode::Dbfs`, negative
+    pub floor_db: f32,
 }
 
 impl Default for ValueDisplayScale {
     fn default() -> Self {
-        Self { skew_factor: 0.0 }
+        Self {
+            mode: ValueScaleMode::Skew,
+            skew_factor: 0.0,
+            floor_db: -120.0,
+        }
     }
 }
 
 impl ValueDisplayScale {
     const SKEW_UNIT_EXPONENT: f64 = 0.415_037_499_278_843_8;
     pub const MAX_SKEW_FACTOR: f32 = 5.0;
+    pub const MIN_FLOOR_DB: f32 = -200.0;
+    pub const MAX_FLOOR_DB: f32 = -6.0;
+
+    /// Plain linear display, without skew or dB
+    pub fn is_linear(self) -> bool {
+        self.mode == ValueScaleMode::Skew && self.skew_factor == 0.0
+    }
+
+    /// `floor_db` clamped to a usable range, as positive number of dB
+    pub fn db_range(self) -> f64 {
+        -(self.floor_db.clamp(Self::MIN_FLOOR_DB, Self::MAX_FLOOR_DB) as f64)
+    }
 
     pub fn exponent(self) -> f64 {
         let skew = self.skew_factor.clamp(0.0, Self::MAX_SKEW_FACTOR) as f64;
@@ -25,7 +53,15 @@ impl ValueDisplayScale {
             return 0.0;
         }
 
-        sample_value.signum() * sample_value.abs().powf(self.exponent())
+        let magnitude = match self.mode {
+            ValueScaleMode::Skew => sample_value.abs().powf(self.exponent()),
+            ValueScaleMode::Dbfs => {
+                let db_range = self.db_range();
+                let db = 20.0 * sample_value.abs().log10();
+                ((db + db_range) / db_range).max(0.0)
+            }
+        };
+        sample_value.signum() * magnitude
     }
 
     pub fn display_to_sample(self, display_value: f64) -> f64 {
@@ -33,17 +69,74 @@ impl ValueDisplayScale {
             return 0.0;
         }
 
-        display_value.signum() * display_value.abs().powf(1.0 / self.exponent())
+        let magnitude = match self.mode {
+            ValueScaleMode::Skew => display_value.abs().powf(1.0 / self.exponent()),
+            ValueScaleMode::Dbfs => 10_f64.powf(self.display_to_db(display_value) / 20.0),
+        };
+        display_value.signum() * magnitude
+    }
+
+    /// Level in dB of a display value in `ValueScaleMode::Dbfs`, the sign is ignored
+    pub fn display_to_db(self, display_value: f64) -> f64 {
+        let db_range = self.db_range();
+        display_value.abs() * db_range - db_range
+    }
+
+    /// Display value of a level in dB in `ValueScaleMode::Dbfs`, on the positive side
+    pub fn db_to_display(self, db: f64) -> f64 {
+        let db_range = self.db_range();
+        ((db + db_range) / db_range).max(0.0)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ValueDisplayScale;
+    use super::{ValueDisplayScale, ValueScaleMode};
+
+    fn dbfs() -> ValueDisplayScale {
+        ValueDisplayScale {
+            mode: ValueScaleMode::Dbfs,
+            floor_db: -120.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dbfs_maps_full_scale_to_one_and_floor_to_zero() {
+        let scale = dbfs();
+
+        assert_eq!(scale.sample_to_display(1.0), 1.0);
+        assert_eq!(scale.sample_to_display(-1.0), -1.0);
+        assert!((scale.sample_to_display(1e-3) - 0.5).abs() < 1e-9);
+        assert!((scale.sample_to_display(-1e-3) + 0.5).abs() < 1e-9);
+        assert_eq!(scale.sample_to_display(1e-7), 0.0);
+        assert_eq!(scale.sample_to_display(0.0), 0.0);
+        // above full scale stays monotonic
+        assert!(scale.sample_to_display(2.0) > 1.0);
+    }
+
+    #[test]
+    fn dbfs_round_trip_above_the_floor() {
+        let scale = dbfs();
+
+        for sample_value in [-1.0, -0.5, -1e-4, 1e-5, 0.25, 1.0, 1.5] {
+            let round_trip = scale.display_to_sample(scale.sample_to_display(sample_value));
+
+            assert!(
+                (sample_value - round_trip).abs() < 1e-9 * sample_value.abs().max(1.0),
+                "{sample_value} {round_trip}"
+            );
+        }
+        assert!((scale.display_to_db(-0.75) + 30.0).abs() < 1e-9);
+        assert!((scale.db_to_display(-30.0) - 0.75).abs() < 1e-9);
+    }
 
     #[test]
     fn endpoints_and_zero_stay_fixed() {
-        let scale = ValueDisplayScale { skew_factor: 1.0 };
+        let scale = ValueDisplayScale {
+            skew_factor: 1.0,
+            ..Default::default()
+        };
 
         assert_eq!(scale.sample_to_display(-1.0), -1.0);
         assert_eq!(scale.sample_to_display(0.0), 0.0);
@@ -52,7 +145,10 @@ mod tests {
 
     #[test]
     fn positive_and_negative_sides_are_symmetric() {
-        let scale = ValueDisplayScale { skew_factor: 0.8 };
+        let scale = ValueDisplayScale {
+            skew_factor: 0.8,
+            ..Default::default()
+        };
         let positive = scale.sample_to_display(0.3);
         let negative = scale.sample_to_display(-0.3);
 
@@ -62,7 +158,10 @@ mod tests {
     #[test]
     fn skew_expands_low_amplitudes() {
         let linear = ValueDisplayScale::default();
-        let skewed = ValueDisplayScale { skew_factor: 1.0 };
+        let skewed = ValueDisplayScale {
+            skew_factor: 1.0,
+            ..Default::default()
+        };
 
         assert_eq!(linear.sample_to_display(0.5), 0.5);
         assert!((skewed.sample_to_display(0.5) - 0.75).abs() < 1e-6);
@@ -70,15 +169,24 @@ mod tests {
 
     #[test]
     fn larger_skew_values_expand_more() {
-        let skewed_a = ValueDisplayScale { skew_factor: 1.0 };
-        let skewed_b = ValueDisplayScale { skew_factor: 5.0 };
+        let skewed_a = ValueDisplayScale {
+            skew_factor: 1.0,
+            ..Default::default()
+        };
+        let skewed_b = ValueDisplayScale {
+            skew_factor: 5.0,
+            ..Default::default()
+        };
 
         assert!(skewed_b.sample_to_display(0.5) > skewed_a.sample_to_display(0.5));
     }
 
     #[test]
     fn inverse_round_trip_is_stable() {
-        let scale = ValueDisplayScale { skew_factor: 0.65 };
+        let scale = ValueDisplayScale {
+            skew_factor: 0.65,
+            ..Default::default()
+        };
 
         for sample_value in [-2.0, -1.5, -1.0, -0.5, -0.1, 0.0, 0.1, 0.5, 1.0, 1.5, 2.0] {
             let display_value = scale.sample_to_display(sample_value);
@@ -100,7 +208,10 @@ mod tests {
 
     #[test]
     fn skewed_scale_is_monotonic_outside_full_scale() {
-        let scale = ValueDisplayScale { skew_factor: 1.0 };
+        let scale = ValueDisplayScale {
+            skew_factor: 1.0,
+            ..Default::default()
+        };
         let inputs = [-2.0, -1.5, -1.0, -0.5, 0.5, 1.0, 1.5, 2.0];
         let outputs: Vec<_> = inputs
             .into_iter()
@@ -0,0 +1,247 @@
+use crate::{
+    audio::{
+        buffer::BufferE,
+        manager::{AudioManager, BufferId},
+        sample,
+        stats::Stats,
+    },
+    model::{selection_info::SelectionInfoE, track::TrackId, tracks2::Tracks},
+};
+use std::{
+    ops::Range,
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+};
+
+/// Above this number of samples, summed over the tracks, the stats are computed on a worker
+/// thread so the UI stays responsive.
+const MAX_NR_SAMPLES_ON_UI_THREAD: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackStats {
+    pub track_id: TrackId,
+    pub stats: Stats,
+}
+
+/// What the stats are computed for, they are recomputed when this changes.
+#[derive(Debug, Clone, PartialEq)]
+struct StatsKey {
+    /// None for the whole buffer
+    ix_rng: Option<sample::IxRange>,
+    items: Vec<(TrackId, BufferId, f64)>,
+}
+
+/// Level statistics per visible track, for the selected range or the whole track when nothing
+/// is selected.
+///
+/// At most one computation runs on the worker thread, changes in the meantime (e.g. while
+/// dragging a selection) are picked up when it finishes.
+#[derive(Debug)]
+pub struct SelectionStats {
+    key: Option<StatsKey>,
+    /// Stats for the current key, empty while computing
+    pub tracks: Vec<TrackStats>,
+
+    tx: Sender<Vec<TrackStats>>,
+    rx: Receiver<Vec<TrackStats>>,
+    pending: bool,
+}
+
+impl Default for SelectionStats {
+    fn default() -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        Self {
+            key: None,
+            tracks: Vec::new(),
+            tx,
+            rx,
+            pending: false,
+        }
+    }
+}
+
+impl SelectionStats {
+    /// True while the stats are computed on a worker thread
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Recompute the stats when the selection or the tracks changed, and pick up results of
+    /// the worker thread.
+    pub fn update(&mut self, tracks: &Tracks, audio: &AudioManager) {
+        if let Ok(result) = self.rx.try_recv() {
+            self.tracks = result;
+            self.pending = false;
+        }
+        if self.pending {
+            return;
+        }
+        let key = stats_key(tracks);
+        if self.key.as_ref() != Some(&key) {
+            self.start(key, audio);
+        }
+    }
+
+    fn start(&mut self, key: StatsKey, audio: &AudioManager) {
+        self.tracks.clear();
+
+        let jobs: Vec<(TrackId, Arc<BufferE>, Range<usize>)> = key
+            .items
+            .iter()
+            .filter_map(|&(track_id, buffer_id, sample_ix_offset)| {
+                let buffer = audio.get_shared_buffer(buffer_id).ok()?;
+                let ix_rng = buffer_ix_rng(key.ix_rng, sample_ix_offset, buffer.nr_samples());
+                Some((track_id, buffer.clone(), ix_rng))
+            })
+            .collect();
+        self.key = Some(key);
+
+        let nr_samples: usize = jobs.iter().map(|(_, _, ix_rng)| ix_rng.len()).sum();
+        if cfg!(target_arch = "wasm32") || nr_samples <= MAX_NR_SAMPLES_ON_UI_THREAD {
+            self.tracks = compute(&jobs);
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.pending = true;
+            let tx = self.tx.clone();
+            std::thread::spawn(move || {
+                let _ = tx.send(compute(&jobs));
+            });
+        }
+    }
+
+    pub fn get(&self, track_id: TrackId) -> Option<&Stats> {
+        self.tracks
+            .iter()
+            .find(|track| track.track_id == track_id)
+            .map(|track| &track.stats)
+    }
+}
+
+fn stats_key(tracks: &Tracks) -> StatsKey {
+    StatsKey {
+        ix_rng: selected_ix_rng(tracks),
+        items: visible_items(tracks),
+    }
+}
+
+/// The selected track sample index range, None when nothing is selected
+pub fn selected_ix_rng(tracks: &Tracks) -> Option<sample::IxRange> {
+    match tracks.selection_info {
+        SelectionInfoE::IsSelected(selection_info) => Some(selection_info.ix_rng),
+        SelectionInfoE::NotSelected => None,
+    }
+}
+
+/// Track id, buffer id and sample index offset of the visible tracks, in track order
+pub fn visible_items(tracks: &Tracks) -> Vec<(TrackId, BufferId, f64)> {
+    tracks
+        .tracks_order
+        .iter()
+        .filter_map(|&track_id| {
+            let track = tracks.get_track(track_id).filter(|track| track.visible)?;
+            let item = &track.single.item;
+            Some((track_id, item.buffer_id, item.sample_ix_offset))
+        })
+        .collect()
+}
+
+/// Buffer sample indices of the track sample index range, clamped to the buffer
+pub fn buffer_ix_rng(
+    ix_rng: Option<sample::IxRange>,
+    sample_ix_offset: f64,
+    nr_samples: usize,
+) -> Range<usize> {
+    let Some(ix_rng) = ix_rng else {
+        return 0..nr_samples;
+    };
+    let to_buffer_ix = |ix: sample::Ix| {
+        (ix as f64 - sample_ix_offset)
+            .round()
+            .clamp(0.0, nr_samples as f64) as usize
+    };
+    let start = to_buffer_ix(ix_rng.start);
+    let end = to_buffer_ix(ix_rng.end).max(start);
+    start..end
+}
+
+fn compute(jobs: &[(TrackId, Arc<BufferE>, Range<usize>)]) -> Vec<TrackStats> {
+    jobs.iter()
+        .map(|(track_id, buffer, ix_rng)| TrackStats {
+            track_id: *track_id,
+            stats: Stats::from_buffer(buffer, ix_rng.clone()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::buffer::Buffer,
+        model::{config::TrackConfig, selection_info::SelectionInfo},
+    };
+
+    fn tracks_with_buffer(audio: &mut AudioManager, data: &[f32]) -> (Tracks, TrackId) {
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = data.to_vec();
+        let buffer_id = audio.buffers.insert(Arc::new(BufferE::F32(buffer)));
+        let mut tracks = Tracks::default();
+        let track_id = tracks
+            .add_track_to_end(buffer_id, &TrackConfig::default())
+            .unwrap();
+        (tracks, track_id)
+    }
+
+    #[test]
+    fn stats_follow_the_selection() {
+        let mut audio = AudioManager::default();
+        let (mut tracks, track_id) = tracks_with_buffer(&mut audio, &[1.0, 0.5, 0.25, 0.0]);
+        let mut selection_stats = SelectionStats::default();
+
+        selection_stats.update(&tracks, &audio);
+        let stats = selection_stats.get(track_id).unwrap();
+        assert_eq!(stats.nr_samples, 4);
+        assert_eq!(stats.nr_clipped, 1);
+
+        tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng: (1..3).into(),
+            ..Default::default()
+        });
+        selection_stats.update(&tracks, &audio);
+        let stats = selection_stats.get(track_id).unwrap();
+        assert_eq!(stats.nr_samples, 2);
+        assert_eq!(stats.max, 0.5);
+        assert_eq!(stats.nr_clipped, 0);
+    }
+
+    #[test]
+    fn selection_is_shifted_by_item_offset() {
+        assert_eq!(buffer_ix_rng(Some((10..20).into()), 5.0, 100), 5..15);
+        assert_eq!(buffer_ix_rng(Some((0..20).into()), 15.0, 100), 0..5);
+        assert_eq!(buffer_ix_rng(Some((0..20).into()), 50.0, 100), 0..0);
+        assert_eq!(buffer_ix_rng(None, 50.0, 100), 0..100);
+    }
+
+    #[test]
+    fn large_selection_is_computed_off_thread() {
+        let mut audio = AudioManager::default();
+        let data = vec![0.5; MAX_NR_SAMPLES_ON_UI_THREAD + 1];
+        let (tracks, track_id) = tracks_with_buffer(&mut audio, &data);
+        let mut selection_stats = SelectionStats::default();
+
+        selection_stats.update(&tracks, &audio);
+        while selection_stats.pending() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            selection_stats.update(&tracks, &audio);
+        }
+
+        let stats = selection_stats.get(track_id).unwrap();
+        assert_eq!(stats.nr_samples, MAX_NR_SAMPLES_ON_UI_THREAD + 1);
+        assert_eq!(stats.dc, 0.5);
+    }
+}
@@ -0,0 +1,514 @@
+//! Session files: the opened files and how they are shown, stored as TOML so a debugging setup
+//! can be restored or shared.
+
+use crate::{
+    audio::{BufferId, sample},
+    model::{
+        Model, SampleIxZoom,
+        action::start_file_load,
+        ruler::ValueDisplayScale,
+        selection_info::{SelectionInfo, SelectionInfoE},
+    },
+    wav::{
+        self,
+        read::{ChIx, ReadConfig},
+    },
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Format version of the session file
+    pub version: u32,
+    pub value_display_scale: ValueDisplayScale,
+    /// Zoom level and position of the time line, None zooms to full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<SessionZoom>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<SessionRange>,
+    /// Files read from disk, files from memory like generated signals are not stored
+    #[serde(default)]
+    pub files: Vec<SessionFile>,
+    /// Tracks from top to bottom, diff tracks are not stored
+    #[serde(default)]
+    pub tracks: Vec<SessionTrack>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionZoom {
+    pub samples_per_pixel: f64,
+    pub ix_start: sample::FracIx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionRange {
+    pub start: sample::Ix,
+    pub end: sample::Ix,
+}
+
+/// The `ReadConfig` of an opened file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionFile {
+    /// Relative paths are relative to the directory of the session file
+    pub path: PathBuf,
+    /// Indices of the channels read from the file, default: all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<ChIx>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<sample::Ix>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<sample::Ix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTrack {
+    /// Index in `Session::files`
+    pub file: usize,
+    pub channel: ChIx,
+    pub visible: bool,
+    pub height: f32,
+    /// Value range of the track, None for the full range of the sample type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<f64>,
+    /// Position of the channel on the time line
+    #[serde(default)]
+    pub offset: f64,
+    /// Channels drawn on top of this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<SessionOverlay>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionOverlay {
+    /// Index in `Session::files`
+    pub file: usize,
+    pub channel: ChIx,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+impl Session {
+    pub const VERSION: u32 = 1;
+
+    /// Capture the files and tracks of the model, for a session file in `base_dir`
+    pub fn from_model(model: &Model, base_dir: &Path) -> Self {
+        let files: Vec<&wav::file2::File> = model
+            .files2
+            .iter()
+            .filter(|file| file.read_config.is_some())
+            .collect();
+        let file_channel = |buffer_id: BufferId| {
+            files.iter().enumerate().find_map(|(file_ix, file)| {
+                file.get_channel(buffer_id)
+                    .map(|channel| (file_ix, channel.ch_ix))
+            })
+        };
+
+        let tracks = model
+            .tracks
+            .tracks_order
+            .iter()
+            .filter_map(|track_id| model.tracks.get_track(*track_id))
+            .filter(|track| track.diff.is_none())
+            .filter_map(|track| {
+                let (file, channel) = file_channel(track.single.item.buffer_id)?;
+                let val_rng = track.sample_rect.and_then(|rect| rect.val_rng());
+                let overlays = track
+                    .single
+                    .overlays
+                    .iter()
+                    .filter_map(|item| {
+                        let (file, channel) = file_channel(item.buffer_id)?;
+                        Some(SessionOverlay {
+                            file,
+                            channel,
+                            offset: item.sample_ix_offset,
+                        })
+                    })
+                    .collect();
+                Some(SessionTrack {
+                    file,
+                    channel,
+                    visible: track.visible,
+                    height: track.height,
+                    min_value: val_rng.map(|val_rng| val_rng.min),
+                    max_value: val_rng.map(|val_rng| val_rng.max),
+                    offset: track.single.item.sample_ix_offset,
+                    overlays,
+                })
+            })
+            .collect();
+
+        let selection = match model.tracks.selection_info {
+            SelectionInfoE::IsSelected(selection_info) => Some(SessionRange {
+                start: selection_info.ix_rng.start,
+                end: selection_info.ix_rng.end,
+            }),
+            SelectionInfoE::NotSelected => None,
+        };
+
+        Self {
+            version: Self::VERSION,
+            value_display_scale: model.user_config.value_display_scale,
+            zoom: model
+                .tracks
+                .ruler
+                .time_line
+                .as_ref()
+                .map(|time_line| SessionZoom {
+                    samples_per_pixel: time_line.samples_per_pixel(),
+                    ix_start: time_line.ix_start,
+                }),
+            selection,
+            files: files
+                .iter()
+                .filter_map(|file| file.read_config.as_ref())
+                .map(|config| SessionFile::new(config, base_dir))
+                .collect(),
+            tracks,
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let session: Self = toml::from_str(text)?;
+        anyhow::ensure!(
+            session.version <= Self::VERSION,
+            "Session version {} is newer than the supported version {}",
+            session.version,
+            Self::VERSION
+        );
+        Ok(session)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+impl SessionFile {
+    /// File read with `config`, with its path relative to `base_dir` when it is inside it and
+    /// absolute otherwise
+    pub fn new(config: &ReadConfig, base_dir: &Path) -> Self {
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.into());
+        let filepath = absolute(&config.filepath);
+        let path = match filepath.strip_prefix(absolute(base_dir)) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => filepath,
+        };
+        Self {
+            path,
+            channels: config.ch_ixs.clone(),
+            start: config.sample_range.start,
+            end: config.sample_range.end,
+        }
+    }
+
+    /// Read config with the path resolved against `base_dir`
+    pub fn read_config(&self, base_dir: &Path) -> ReadConfig {
+        ReadConfig {
+            filepath: base_dir.join(&self.path),
+            ch_ixs: self.channels.clone(),
+            sample_range: sample::OptIxRange {
+                start: self.start,
+                end: self.end,
+            },
+        }
+    }
+}
+
+/// Write the session of the model to `path`
+pub fn save_session(model: &Model, path: &Path) -> Result<()> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let text = Session::from_model(model, base_dir).to_toml()?;
+    std::fs::write(path, text)
+        .with_context(|| format!("Failed to write session {}", path.display()))
+}
+
+/// Replace the open files by those of the session at `path`. The files load in the background,
+/// once all are done their tracks are shown as they were saved, see `restore_layout`. Files that
+/// can't be read are skipped.
+pub fn load_session(model: &mut Model, path: &Path) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read session {}", path.display()))?;
+    let session = Session::from_toml(&text)
+        .with_context(|| format!("Failed to parse session {}", path.display()))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    model.close_all_files();
+    let load_ids: Vec<_> = session
+        .files
+        .iter()
+        .map(|file| start_file_load(model, &file.read_config(base_dir)))
+        .collect();
+    if load_ids.is_empty() {
+        return restore_layout(model, &session, &BTreeMap::new());
+    }
+    model.load_mgr.start_session(session, load_ids);
+    Ok(())
+}
+
+/// Show the loaded files of the session as they were saved. `file_ixs` holds the index in
+/// `Model::files2` per loaded file, by index in `Session::files`.
+pub fn restore_layout(
+    model: &mut Model,
+    session: &Session,
+    file_ixs: &BTreeMap<usize, usize>,
+) -> Result<()> {
+    let mut buffer_ids = BTreeMap::new();
+    for (&session_file_ix, &file_ix) in file_ixs {
+        let Some(file) = model.files2.get(file_ix) else {
+            continue;
+        };
+        for channel in file.channels.values() {
+            buffer_ids.insert((session_file_ix, channel.ch_ix), channel.buffer_id);
+        }
+    }
+
+    restore_tracks(model, &session.tracks, &buffer_ids)?;
+    model.user_config.value_display_scale = session.value_display_scale;
+    match session.zoom {
+        Some(zoom) => {
+            let mut time_line = SampleIxZoom::default();
+            time_line.ix_start = zoom.ix_start;
+            time_line.set_samples_per_pixel(zoom.samples_per_pixel);
+            model.tracks.ruler.time_line = Some(time_line);
+        }
+        None => model.actions.push(super::Action::ZoomToFull),
+    }
+    if let Some(selection) = session.selection {
+        let ix_rng = sample::IxRange {
+            start: selection.start,
+            end: selection.end,
+        };
+        let screen_x = |sample_ix: sample::Ix| {
+            model
+                .tracks
+                .sample_ix_to_screen_x(sample_ix as f64)
+                .unwrap_or_default()
+        };
+        model.tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng,
+            screen_x_start: screen_x(ix_rng.start),
+            screen_x_end: screen_x(ix_rng.end),
+        });
+    }
+    Ok(())
+}
+
+/// Order, overlay and position the tracks of the loaded files as in the session, tracks of
+/// channels the session doesn't list are removed. Tracks of files opened meanwhile stay below.
+fn restore_tracks(
+    model: &mut Model,
+    session_tracks: &[SessionTrack],
+    buffer_ids: &BTreeMap<(usize, ChIx), BufferId>,
+) -> Result<()> {
+    let tracks = &mut model.tracks;
+    let mut tracks_order = Vec::new();
+    for session_track in session_tracks {
+        let Some(&buffer_id) = buffer_ids.get(&(session_track.file, session_track.channel)) else {
+            continue;
+        };
+        let Some((track_id, _)) = tracks.find_track(buffer_id) else {
+            continue;
+        };
+        for overlay in &session_track.overlays {
+            let Some(&overlay_buffer_id) = buffer_ids.get(&(overlay.file, overlay.channel)) else {
+                continue;
+            };
+            if let Some((overlay_track_id, _)) = tracks.find_track(overlay_buffer_id)
+                && overlay_track_id != track_id
+            {
+                tracks.overlay_track(overlay_track_id, track_id)?;
+            }
+        }
+
+        tracks.set_track_visibility(track_id, session_track.visible);
+        tracks.set_track_height(track_id, session_track.height);
+        let track = tracks
+            .get_track_mut(track_id)
+            .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?;
+        track.set_sample_ix_offset(None, session_track.offset)?;
+        for overlay in &session_track.overlays {
+            let Some(overlay_buffer_id) = buffer_ids.get(&(overlay.file, overlay.channel)) else {
+                continue;
+            };
+            if let Some(overlay_ix) = track
+                .single
+                .overlays
+                .iter()
+                .position(|item| item.buffer_id == *overlay_buffer_id)
+            {
+                track.set_sample_ix_offset(Some(overlay_ix), overlay.offset)?;
+            }
+        }
+        if let (Some(min), Some(max)) = (session_track.min_value, session_track.max_value) {
+            let mut sample_rect = match track.sample_rect {
+                Some(sample_rect) => sample_rect,
+                None => model.audio.get_sample_rect(buffer_id)?,
+            };
+            sample_rect.set_val_rng(sample::ValRange { min, max });
+            track.set_sample_rect(sample_rect);
+        }
+        tracks_order.push(track_id);
+    }
+
+    let others: Vec<_> = tracks
+        .tracks_order
+        .iter()
+        .filter(|track_id| !tracks_order.contains(track_id))
+        .copied()
+        .collect();
+    for track_id in others {
+        let is_session_track = tracks.get_track(track_id).is_some_and(|track| {
+            buffer_ids
+                .values()
+                .any(|buffer_id| track.single.contains_buffer(*buffer_id))
+        });
+        if is_session_track {
+            tracks.remove_track(track_id);
+        } else {
+            tracks_order.push(track_id);
+        }
+    }
+    tracks.tracks_order = tracks_order;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Action;
+
+    fn write_wav(path: &Path, nr_channels: u16) {
+        let spec = hound::WavSpec {
+            channels: nr_channels,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for sample in 0..100 * nr_channels as i16 {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn session_restores_files_tracks_zoom_and_selection() {
+        let dir = std::env::temp_dir().join(format!("wavalyze_session_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (wav_a, wav_b) = (dir.join("a.wav"), dir.join("b.wav"));
+        write_wav(&wav_a, 2);
+        write_wav(&wav_b, 1);
+
+        let mut model = Model::new();
+        for config in [
+            ReadConfig::new(&wav_a).with_sample_range(sample::OptIxRange {
+                start: Some(10),
+                end: None,
+            }),
+            ReadConfig::new(&wav_b),
+        ] {
+            let loaded = wav::read::read_to_loaded_file(&config).unwrap();
+            model.add_loaded_file(loaded, None).unwrap();
+        }
+        let track_ids = model.tracks.tracks_order.clone();
+        model
+            .tracks
+            .overlay_track(track_ids[2], track_ids[0])
+            .unwrap();
+        let track = model.tracks.get_track_mut(track_ids[0]).unwrap();
+        track.set_sample_ix_offset(Some(0), 5.0).unwrap();
+        let mut sample_rect = model
+            .audio
+            .get_sample_rect(track.single.item.buffer_id)
+            .unwrap();
+        sample_rect.set_val_rng(sample::ValRange {
+            min: -0.25,
+            max: 0.5,
+        });
+        track.set_sample_rect(sample_rect);
+        model.tracks.set_track_visibility(track_ids[1], false);
+        model.tracks.set_track_height(track_ids[1], 123.0);
+        model.tracks.tracks_order.reverse();
+        model.tracks.ruler.set_samples_per_pixel(0.5);
+        model.tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng: sample::IxRange { start: 3, end: 7 },
+            ..Default::default()
+        });
+        model.user_config.value_display_scale.skew_factor = 1.0;
+
+        let session_path = dir.join("debug.session.toml");
+        save_session(&model, &session_path).unwrap();
+        let mut restored = Model::new();
+        let loaded = load_session(&mut restored, &session_path);
+        while restored.load_mgr.pending() > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            restored.drain_load_results();
+        }
+        let text = std::fs::read_to_string(&session_path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        loaded.unwrap();
+
+        assert_eq!(
+            Session::from_model(&restored, &dir),
+            Session::from_model(&model, &dir)
+        );
+        assert_eq!(
+            restored.files2[0]
+                .read_config
+                .as_ref()
+                .unwrap()
+                .sample_range,
+            sample::OptIxRange {
+                start: Some(10),
+                end: None
+            }
+        );
+        assert_eq!(restored.tracks.tracks_order.len(), 2);
+        assert!(restored.actions.is_empty());
+        assert!(text.contains("[[tracks.overlays]]"));
+        assert!(text.contains("path = \"a.wav\""));
+    }
+
+    #[test]
+    fn missing_zoom_zooms_to_full_and_relative_paths_use_the_session_dir() {
+        let session = Session::from_toml(
+            r#"
+            version = 1
+            [value_display_scale]
+            [[files]]
+            path = "captures/a.wav"
+            channels = [1]
+            "#,
+        )
+        .unwrap();
+        let config = session.files[0].read_config(Path::new("/tmp/sessions"));
+        assert_eq!(config.filepath, Path::new("/tmp/sessions/captures/a.wav"));
+        assert_eq!(config.ch_ixs, Some(vec![1]));
+        let base_dir = Path::new("/tmp/sessions");
+        assert_eq!(SessionFile::new(&config, base_dir), session.files[0]);
+        let outside = ReadConfig::new("/tmp/captures/b.wav");
+        assert_eq!(
+            SessionFile::new(&outside, base_dir).path,
+            Path::new("/tmp/captures/b.wav")
+        );
+
+        let dir = std::env::temp_dir().join(format!("wavalyze_no_zoom_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let session_path = dir.join("empty.toml");
+        std::fs::write(&session_path, "version = 1\n[value_display_scale]\n").unwrap();
+        let mut model = Model::new();
+        let loaded = load_session(&mut model, &session_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        loaded.unwrap();
+        assert_eq!(model.actions, vec![Action::ZoomToFull]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::model::{Action, tracks2::Tracks};
+use crate::model::{Action, markers::JumpDirection, tracks2::Tracks};
 use egui::{Key, KeyboardShortcut, Modifiers};
 use egui_custom_widgets::focused_widget_is_digitwise_editor;
 use tracing::warn;
@@ -12,16 +12,20 @@ pub enum ShortcutAction {
     ZoomToFull,
     FillScreenHeight,
     RecenterYAll,
+    JumpToPreviousMarker,
+    JumpToNextMarker,
 }
 
 impl ShortcutAction {
-    pub const ALL: [Self; 6] = [
+    pub const ALL: [Self; 8] = [
         Self::ZoomToSelection,
         Self::ZoomToSelectionLeftEdge,
         Self::ZoomToSelectionRightEdge,
         Self::ZoomToFull,
         Self::FillScreenHeight,
         Self::RecenterYAll,
+        Self::JumpToPreviousMarker,
+        Self::JumpToNextMarker,
     ];
 
     pub fn label(self) -> &'static str {
@@ -32,6 +36,8 @@ impl ShortcutAction {
             Self::ZoomToFull => "Reset X Zoom",
             Self::FillScreenHeight => "Fill Screen Height",
             Self::RecenterYAll => "Recenter Y",
+            Self::JumpToPreviousMarker => "Jump To Previous Marker",
+            Self::JumpToNextMarker => "Jump To Next Marker",
         }
     }
 
@@ -43,6 +49,8 @@ impl ShortcutAction {
             Self::ZoomToFull => Action::ZoomToFull,
             Self::FillScreenHeight => Action::FillScreenHeight,
             Self::RecenterYAll => Action::RecenterYAll,
+            Self::JumpToPreviousMarker => Action::JumpToMarker(JumpDirection::Previous),
+            Self::JumpToNextMarker => Action::JumpToMarker(JumpDirection::Next),
         }
     }
 }
@@ -233,6 +241,22 @@ impl ShortcutConfig {
             (ShortcutAction::RecenterYAll, ShortcutScope::OneHand) => {
                 ShortcutBinding::new(action, "Y").with_scope(scope)
             }
+            (ShortcutAction::JumpToPreviousMarker, ShortcutScope::Global) => {
+                ShortcutBinding::new(action, ",")
+                    .with_scope(scope)
+                    .with_command()
+            }
+            (ShortcutAction::JumpToPreviousMarker, ShortcutScope::OneHand) => {
+                ShortcutBinding::new(action, ",").with_scope(scope)
+            }
+            (ShortcutAction::JumpToNextMarker, ShortcutScope::Global) => {
+                ShortcutBinding::new(action, ".")
+                    .with_scope(scope)
+                    .with_command()
+            }
+            (ShortcutAction::JumpToNextMarker, ShortcutScope::OneHand) => {
+                ShortcutBinding::new(action, ".").with_scope(scope)
+            }
         }
     }
 
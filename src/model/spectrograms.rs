@@ -0,0 +1,218 @@
+use crate::audio::{
+    buffer::BufferE,
+    manager::{AudioManager, BufferId},
+    spectrogram::{Analysis, Spectrogram, Tile, TileKey},
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+};
+
+/// Tiles requested beyond this are skipped, they are requested again on a next frame if still
+/// visible. Keeps fast panning over long files from queueing work for tiles long out of view.
+const MAX_NR_PENDING_TILES: usize = 32;
+
+#[derive(Debug)]
+struct TileResult {
+    buffer_id: BufferId,
+    analysis: Analysis,
+    key: TileKey,
+    tile: Result<Tile>,
+}
+
+/// Spectrogram tiles of the buffers shown as spectrogram, computed on the rayon thread pool.
+#[derive(Debug)]
+pub struct Spectrograms {
+    spectrograms: HashMap<BufferId, Spectrogram>,
+    pending: HashSet<(BufferId, TileKey)>,
+    tx: Sender<TileResult>,
+    rx: Receiver<TileResult>,
+    /// Incremented when tiles arrive or are dropped, so views know their image is outdated
+    generation: u64,
+}
+
+impl Default for Spectrograms {
+    fn default() -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        Self {
+            spectrograms: HashMap::new(),
+            pending: HashSet::new(),
+            tx,
+            rx,
+            generation: 0,
+        }
+    }
+}
+
+impl Spectrograms {
+    /// True while tiles are computed on worker threads
+    pub fn pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Pick up computed tiles, and drop the tiles of removed buffers or of another analysis.
+    pub fn update(&mut self, audio: &AudioManager, analysis: Analysis) {
+        let nr_spectrograms = self.spectrograms.len();
+        self.spectrograms.retain(|buffer_id, spectrogram| {
+            audio.buffers.contains_key(*buffer_id) && spectrogram.analysis == analysis
+        });
+        if self.spectrograms.len() != nr_spectrograms {
+            self.generation += 1;
+        }
+
+        while let Ok(result) = self.rx.try_recv() {
+            self.pending.remove(&(result.buffer_id, result.key));
+            let tile = match result.tile {
+                Ok(tile) => tile,
+                Err(e) => {
+                    tracing::warn!("Spectrogram tile {:?} failed: {e}", result.key);
+                    continue;
+                }
+            };
+            if result.analysis != analysis || !audio.buffers.contains_key(result.buffer_id) {
+                continue;
+            }
+            self.spectrograms
+                .entry(result.buffer_id)
+                .or_insert_with(|| Spectrogram::new(analysis))
+                .insert(tile);
+            self.generation += 1;
+        }
+    }
+
+    /// Get a tile, or start computing it when it is not cached yet
+    pub fn get_or_request(
+        &mut self,
+        buffer_id: BufferId,
+        key: TileKey,
+        audio: &AudioManager,
+        analysis: Analysis,
+    ) -> Option<&Tile> {
+        let cached = self
+            .spectrograms
+            .get(&buffer_id)
+            .is_some_and(|spectrogram| spectrogram.contains(key));
+        if !cached {
+            self.request(buffer_id, key, audio, analysis);
+            return None;
+        }
+        self.spectrograms.get_mut(&buffer_id)?.get(key)
+    }
+
+    fn request(
+        &mut self,
+        buffer_id: BufferId,
+        key: TileKey,
+        audio: &AudioManager,
+        analysis: Analysis,
+    ) {
+        if self.pending.len() >= MAX_NR_PENDING_TILES || self.pending.contains(&(buffer_id, key)) {
+            return;
+        }
+        let Ok(buffer) = audio.get_shared_buffer(buffer_id) else {
+            return;
+        };
+        let buffer: Arc<BufferE> = buffer.clone();
+        self.pending.insert((buffer_id, key));
+        let tx = self.tx.clone();
+        let compute = move || {
+            let tile = Tile::new(&buffer, analysis, key);
+            let _ = tx.send(TileResult {
+                buffer_id,
+                analysis,
+                key,
+                tile,
+            });
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        rayon::spawn(compute);
+        #[cfg(target_arch = "wasm32")]
+        compute();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::Buffer;
+
+    fn wait_for_tiles(spectrograms: &mut Spectrograms, audio: &AudioManager, analysis: Analysis) {
+        while spectrograms.pending() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            spectrograms.update(audio, analysis);
+        }
+    }
+
+    #[test]
+    fn tiles_are_computed_once_and_dropped_on_analysis_change() {
+        let mut audio = AudioManager::default();
+        let buffer = Buffer::<f32>::with_size(48_000, 32, 10_000);
+        let buffer_id = audio.buffers.insert(Arc::new(BufferE::F32(buffer)));
+        let analysis = Analysis {
+            fft_size: 256,
+            hop: 64,
+            ..Default::default()
+        };
+        let key = TileKey {
+            samples_per_column: 64,
+            tile_ix: 0,
+        };
+        let mut spectrograms = Spectrograms::default();
+
+        assert!(
+            spectrograms
+                .get_or_request(buffer_id, key, &audio, analysis)
+                .is_none()
+        );
+        assert!(spectrograms.pending());
+        wait_for_tiles(&mut spectrograms, &audio, analysis);
+        let generation = spectrograms.generation();
+        assert!(
+            spectrograms
+                .get_or_request(buffer_id, key, &audio, analysis)
+                .is_some()
+        );
+        assert!(!spectrograms.pending());
+
+        let other = Analysis {
+            hop: 128,
+            ..analysis
+        };
+        spectrograms.update(&audio, other);
+        assert!(spectrograms.generation() > generation);
+        assert!(
+            spectrograms
+                .get_or_request(buffer_id, key, &audio, other)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn tiles_of_removed_buffers_are_dropped() {
+        let mut audio = AudioManager::default();
+        let buffer = Buffer::<f32>::with_size(48_000, 32, 1000);
+        let buffer_id = audio.buffers.insert(Arc::new(BufferE::F32(buffer)));
+        let analysis = Analysis::default();
+        let key = TileKey {
+            samples_per_column: 512,
+            tile_ix: 0,
+        };
+        let mut spectrograms = Spectrograms::default();
+        spectrograms.get_or_request(buffer_id, key, &audio, analysis);
+        wait_for_tiles(&mut spectrograms, &audio, analysis);
+
+        audio.remove_buffer(buffer_id);
+        spectrograms.update(&audio, analysis);
+
+        assert!(spectrograms.spectrograms.is_empty());
+    }
+}
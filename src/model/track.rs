@@ -6,6 +6,10 @@ use crate::{
 use anyhow::{Result, anyhow};
 use slotmap::new_key_type;
 
+#[path = "track/diff.rs"]
+pub mod diff;
+#[path = "track/fft.rs"]
+pub mod fft;
 #[path = "track/single.rs"]
 pub mod single;
 
@@ -14,6 +18,7 @@ use crate::{
     model::{self},
     rect::Rect,
 };
+use diff::Diff;
 use single::Single;
 
 new_key_type! { pub struct TrackId; }
@@ -29,6 +34,16 @@ pub enum TrackMetaData {
     None,
 }
 
+/// How the samples of a track are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+    /// Sample values over time
+    #[default]
+    Waveform,
+    /// Level per frequency over time, see `audio::spectrogram`
+    Spectrogram,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Track {
     // pub id: Option<TrackId>,
@@ -53,14 +68,20 @@ pub struct Track {
     // track_item: TrackItem,
     pub single: Single,
 
+    /// Set for A/B comparison tracks, `single` then holds the A−B residual
+    pub diff: Option<Diff>,
+
     /// Dirty flag for the inputs of the view buffer
     update_view_buffer_: bool,
     sample_view_scale: ValueDisplayScale,
+    /// `AudioManager::nr_stream_reads` when the sample views were made
+    sample_view_stream_reads: u64,
 
     track_md: TrackMetaData,
 
     pub height: f32,
     pub visible: bool,
+    pub display_mode: DisplayMode,
 }
 
 impl Track {
@@ -76,15 +97,32 @@ impl Track {
             // samples_per_pixel: None,
             view_buffer: None,
             single,
+            diff: None,
             update_view_buffer_: false,
             sample_view_scale: ValueDisplayScale::default(),
+            sample_view_stream_reads: 0,
             track_md: TrackMetaData::None,
             height: min_total_height(track_config),
             visible: true,
+            display_mode: DisplayMode::default(),
         })
 
         // todo!()
     }
+
+    /// Track comparing buffer A with buffer B, `residual_buffer_id` holds A−B
+    pub fn new_diff(
+        buffer_id_a: BufferId,
+        buffer_id_b: BufferId,
+        residual_buffer_id: BufferId,
+        audio: &AudioManager,
+        track_config: &TrackConfig,
+    ) -> Result<Self> {
+        let sample_rect = audio::SampleRect::from_buffere(audio.get_buffer(residual_buffer_id)?);
+        let mut track = Self::new2(residual_buffer_id, track_config)?;
+        track.diff = Some(Diff::new(buffer_id_a, buffer_id_b, sample_rect));
+        Ok(track)
+    }
 }
 
 impl Track {
@@ -99,10 +137,65 @@ impl Track {
         if self.sample_rect != Some(sample_rect) {
             self.update_view_buffer_ = true;
             self.sample_rect = Some(sample_rect);
-            self.single.item.set_sample_rect(sample_rect);
+            self.set_item_sample_rects(sample_rect);
+            if let Some(diff) = self.diff.as_mut() {
+                diff.set_sample_rect(sample_rect);
+            }
         }
     }
 
+    /// Each item views the track's sample rect from where it is positioned in the track
+    fn set_item_sample_rects(&mut self, sample_rect: audio::SampleRect) {
+        for item in std::iter::once(&mut self.single.item).chain(self.single.overlays.iter_mut()) {
+            item.set_sample_rect(diff::shifted_sample_rect(
+                sample_rect,
+                item.sample_ix_offset,
+            ));
+        }
+    }
+
+    /// Position the item (None) or an overlay in the track
+    pub fn set_sample_ix_offset(&mut self, overlay_ix: Option<usize>, offset: f64) -> Result<()> {
+        let item = match overlay_ix {
+            None => &mut self.single.item,
+            Some(overlay_ix) => self
+                .single
+                .overlays
+                .get_mut(overlay_ix)
+                .ok_or_else(|| anyhow!("Overlay {overlay_ix} not found"))?,
+        };
+        item.sample_ix_offset = offset;
+        if let Some(sample_rect) = self.sample_rect {
+            self.set_item_sample_rects(sample_rect);
+        }
+        self.update_view_buffer_ = true;
+        Ok(())
+    }
+
+    /// Position A and B of a diff track, `residual_buffer_id` holds their new A−B. Returns the
+    /// buffer id of the previous residual.
+    pub fn set_diff_offsets(
+        &mut self,
+        offset_a: f64,
+        offset_b: f64,
+        residual_buffer_id: BufferId,
+    ) -> Result<BufferId> {
+        let diff = self
+            .diff
+            .as_mut()
+            .ok_or_else(|| anyhow!("Not a diff track"))?;
+        diff.sample_ix_offset_a = offset_a;
+        diff.sample_ix_offset_b = offset_b;
+        let old_residual_buffer_id = self.single.item.buffer_id;
+        self.single.item.buffer_id = residual_buffer_id;
+        self.single.item.sample_ix_offset = diff::residual_offset(offset_a, offset_b);
+        if let Some(sample_rect) = self.sample_rect {
+            self.set_item_sample_rects(sample_rect);
+        }
+        self.update_view_buffer_ = true;
+        Ok(old_residual_buffer_id)
+    }
+
     /// Create or update the sample rect to the given range
     /// TODO: we could do this by only knowing the sample_type/bit_depth, iso depending on AudioManager?
     pub fn set_ix_range(
@@ -115,8 +208,7 @@ impl Track {
             new_sample_rect.set_ix_rng(ix_range);
             self.set_sample_rect(new_sample_rect);
         } else {
-            let buffer = audio.get_buffer(self.single.item.buffer_id)?;
-            let mut sample_rect = audio::SampleRect::from_buffere(buffer);
+            let mut sample_rect = audio.get_sample_rect(self.single.item.buffer_id)?;
             sample_rect.set_ix_rng(ix_range);
             self.set_sample_rect(sample_rect);
         }
@@ -128,7 +220,10 @@ impl Track {
         audio: &mut AudioManager,
         display_scale: ValueDisplayScale,
     ) -> Result<()> {
-        if self.sample_view_scale != display_scale {
+        let nr_stream_reads = audio.nr_stream_reads();
+        if self.sample_view_scale != display_scale
+            || self.sample_view_stream_reads != nr_stream_reads
+        {
             self.update_view_buffer_ = true;
         }
         if !self.update_view_buffer_ {
@@ -148,13 +243,78 @@ impl Track {
 
         self.single.item.sample_view =
             Some(audio.get_sample_view(buffer_id, sample_rect, screen_rect, display_scale)?);
+        for overlay in self.single.overlays.iter_mut() {
+            let sample_rect = overlay
+                .sample_rect()
+                .ok_or_else(|| anyhow::anyhow!("sample_rect is missing"))?;
+            overlay.sample_view = Some(audio.get_sample_view(
+                overlay.buffer_id,
+                sample_rect,
+                screen_rect,
+                display_scale,
+            )?);
+        }
+        if let Some(diff) = self.diff.as_mut() {
+            diff.update_sample_views(audio, screen_rect, display_scale)?;
+        }
         self.sample_view_scale = display_scale;
+        self.sample_view_stream_reads = nr_stream_reads;
 
         // trace!("self.single.item.sample_view: {:?}", self.single.item.sample_view);
 
         Ok(())
     }
 
+    /// Show `new_buffer_id` where `old_buffer_id` was shown, as item, overlay or side of a
+    /// diff, keeping its position. Returns whether the track showed `old_buffer_id`.
+    pub fn replace_buffer(&mut self, old_buffer_id: BufferId, new_buffer_id: BufferId) -> bool {
+        let mut replaced = false;
+        for item in std::iter::once(&mut self.single.item).chain(self.single.overlays.iter_mut()) {
+            if item.buffer_id == old_buffer_id {
+                item.buffer_id = new_buffer_id;
+                item.sample_view = None;
+                replaced = true;
+            }
+        }
+        if let Some(diff) = self.diff.as_mut() {
+            for buffer_id in [&mut diff.buffer_id_a, &mut diff.buffer_id_b] {
+                if *buffer_id == old_buffer_id {
+                    *buffer_id = new_buffer_id;
+                    replaced = true;
+                }
+            }
+        }
+        self.update_view_buffer_ |= replaced;
+        replaced
+    }
+
+    /// Draw the items of `other` on top of this track's item
+    pub fn add_overlays_from(&mut self, other: Track) -> Result<()> {
+        anyhow::ensure!(
+            self.diff.is_none() && other.diff.is_none(),
+            "Diff tracks can't be overlaid"
+        );
+        let Single { item, overlays, .. } = other.single;
+        for mut overlay in std::iter::once(item).chain(overlays) {
+            overlay.sample_view = None;
+            self.single.overlays.push(overlay);
+        }
+        if let Some(sample_rect) = self.sample_rect {
+            self.set_item_sample_rects(sample_rect);
+        }
+        self.update_view_buffer_ = true;
+        Ok(())
+    }
+
+    /// Remove the overlay showing `buffer_id`, returns whether there was one
+    pub fn remove_overlay(&mut self, buffer_id: BufferId) -> bool {
+        let nr_overlays = self.single.overlays.len();
+        self.single
+            .overlays
+            .retain(|overlay| overlay.buffer_id != buffer_id);
+        nr_overlays != self.single.overlays.len()
+    }
+
     pub fn get_sample_view(&self) -> Result<&sample::View> {
         self.single
             .item
@@ -183,7 +343,7 @@ mod tests {
             .map(|i| i as f32 / nr_samples as f32)
             .collect();
         let buffere = BufferE::F32(buffer);
-        let buffer_id = audio.buffers.insert(buffere.clone());
+        let buffer_id = audio.buffers.insert(std::sync::Arc::new(buffere.clone()));
         audio.thumbnails.insert(
             buffer_id,
             audio::thumbnail::ThumbnailE::from_buffer_e(&buffere, None),
@@ -216,4 +376,34 @@ mod tests {
         let updated_view = track.get_sample_view().unwrap();
         assert_eq!(updated_view.data, ViewData::MinMax(vec![]));
     }
+
+    #[test]
+    fn overlays_follow_the_track_view() {
+        let mut audio = AudioManager::default();
+        let buffer_id = insert_buffer(&mut audio, 32);
+        let overlay_buffer_id = insert_buffer(&mut audio, 32);
+        let mut track = Track::new2(buffer_id, &TrackConfig::default()).unwrap();
+        track.set_screen_rect(Rect::new(0.0, 0.0, 16.0, 40.0));
+        track.set_ix_range((0.0..16.0).into(), &audio).unwrap();
+
+        let mut other = Track::new2(overlay_buffer_id, &TrackConfig::default()).unwrap();
+        other.single.item.sample_ix_offset = 4.0;
+        track.add_overlays_from(other).unwrap();
+        track
+            .update_sample_view(&mut audio, ValueDisplayScale::default())
+            .unwrap();
+
+        let overlay = &track.single.overlays[0];
+        assert_eq!(overlay.sample_rect().unwrap().ix_rng(), (-4.0..12.0).into());
+        assert!(overlay.sample_view.is_some());
+        assert_eq!(
+            overlay.value_at(&audio, 4.0).unwrap(),
+            Some(0.0),
+            "sample 0 of the overlay is at track sample 4"
+        );
+        assert!(track.single.contains_buffer(overlay_buffer_id));
+
+        assert!(track.remove_overlay(overlay_buffer_id));
+        assert!(!track.single.contains_buffer(overlay_buffer_id));
+    }
 }
@@ -1,6 +1,19 @@
-use crate::audio;
+use crate::{
+    audio::{
+        self,
+        buffer::{Buffer, BufferE},
+        manager::AudioManager,
+        sample,
+    },
+    model::ruler::ValueDisplayScale,
+    rect::Rect,
+};
+use anyhow::Result;
 
 /// Repesents a time domain view of 2 audio buffers and their difference
+///
+/// The A−B residual itself is a regular buffer, shown by the track's `Single` item, so zooming,
+/// hovering and selecting on a diff track works on the residual like on any other track.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Diff {
     pub buffer_id_a: audio::BufferId,
@@ -13,4 +26,260 @@ pub struct Diff {
     /// allow for separate offset for each buffer
     pub sample_ix_offset_a: f64,
     pub sample_ix_offset_b: f64,
+
+    /// The data to display for A and B, still in 'sample' coordinates
+    pub sample_view_a: Option<sample::View>,
+    pub sample_view_b: Option<sample::View>,
+}
+
+/// Sample values of a diff track at one sample index of the track, normalized to full scale
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DiffValues {
+    /// None when A has no sample at this index
+    pub a: Option<f64>,
+    /// None when B has no sample at this index
+    pub b: Option<f64>,
+    /// A−B, where a missing sample counts as 0.0
+    pub residual: f64,
+}
+
+impl Diff {
+    pub fn new(
+        buffer_id_a: audio::BufferId,
+        buffer_id_b: audio::BufferId,
+        sample_rect: audio::SampleRect,
+    ) -> Self {
+        Self {
+            buffer_id_a,
+            buffer_id_b,
+            sample_rect,
+            sample_ix_offset_a: 0.0,
+            sample_ix_offset_b: 0.0,
+            sample_view_a: None,
+            sample_view_b: None,
+        }
+    }
+
+    pub fn set_sample_rect(&mut self, sample_rect: audio::SampleRect) {
+        self.sample_rect = sample_rect;
+    }
+
+    pub fn update_sample_views(
+        &mut self,
+        audio: &AudioManager,
+        screen_rect: Rect,
+        display_scale: ValueDisplayScale,
+    ) -> Result<()> {
+        let sample_rect_a = shifted_sample_rect(self.sample_rect, self.sample_ix_offset_a);
+        let sample_rect_b = shifted_sample_rect(self.sample_rect, self.sample_ix_offset_b);
+        self.sample_view_a = Some(audio.get_sample_view(
+            self.buffer_id_a,
+            sample_rect_a,
+            screen_rect,
+            display_scale,
+        )?);
+        self.sample_view_b = Some(audio.get_sample_view(
+            self.buffer_id_b,
+            sample_rect_b,
+            screen_rect,
+            display_scale,
+        )?);
+        Ok(())
+    }
+
+    /// Values of A, B and A−B at the given track sample index, B at the same whole-sample lag as
+    /// in the residual
+    pub fn values_at(&self, audio: &AudioManager, sample_ix: f64) -> Result<DiffValues> {
+        let buffer_a = audio.get_buffer(self.buffer_id_a)?;
+        let buffer_b = audio.get_buffer(self.buffer_id_b)?;
+        let ix_a = (sample_ix - self.sample_ix_offset_a).round();
+        let lag = whole_sample_lag(self.sample_ix_offset_a, self.sample_ix_offset_b);
+        let a = buffer_value(buffer_a, ix_a);
+        let b = buffer_value(buffer_b, ix_a - lag as f64);
+        Ok(DiffValues {
+            a,
+            b,
+            residual: a.unwrap_or(0.0) - b.unwrap_or(0.0),
+        })
+    }
+}
+
+/// Sample rect of a buffer positioned at `sample_ix_offset` in the track
+pub fn shifted_sample_rect(
+    sample_rect: audio::SampleRect,
+    sample_ix_offset: f64,
+) -> audio::SampleRect {
+    let mut ix_rng = sample_rect.ix_rng();
+    ix_rng.shift(-sample_ix_offset);
+    let mut res = sample_rect;
+    res.set_ix_rng(ix_rng);
+    res
+}
+
+fn buffer_value(buffer: &BufferE, sample_ix: f64) -> Option<f64> {
+    let sample_ix = sample_ix.round();
+    if sample_ix < 0.0 {
+        return None;
+    }
+    buffer.norm_value(sample_ix as usize)
+}
+
+/// Offset of B with respect to A, in whole samples. A sub-sample lag, e.g. found by aligning,
+/// shifts how B is drawn, but A−B subtracts the samples nearest to each other.
+pub fn whole_sample_lag(sample_ix_offset_a: f64, sample_ix_offset_b: f64) -> i64 {
+    (sample_ix_offset_b - sample_ix_offset_a).round() as i64
+}
+
+/// Where the residual of `residual_buffer` starts in the track: at A, or earlier when B starts
+/// before A
+pub fn residual_offset(sample_ix_offset_a: f64, sample_ix_offset_b: f64) -> f64 {
+    sample_ix_offset_a + whole_sample_lag(sample_ix_offset_a, sample_ix_offset_b).min(0) as f64
+}
+
+/// Compute the A−B residual of two buffers as a normalized float buffer. It is f64 when either
+/// buffer is, so differences below the f32 resolution don't vanish, and f32 otherwise.
+///
+/// The residual spans both buffers and starts at `residual_offset`, only the offset of B with
+/// respect to A counts, rounded to whole samples by `whole_sample_lag`. Where only one of them
+/// has samples the other one counts as silence.
+pub fn residual_buffer(
+    buffer_a: &BufferE,
+    buffer_b: &BufferE,
+    sample_ix_offset_a: f64,
+    sample_ix_offset_b: f64,
+) -> Result<BufferE> {
+    anyhow::ensure!(
+        buffer_a.sample_rate() == buffer_b.sample_rate(),
+        "Can't diff buffers with different sample rates: {} Hz and {} Hz",
+        buffer_a.sample_rate(),
+        buffer_b.sample_rate()
+    );
+    let lag = whole_sample_lag(sample_ix_offset_a, sample_ix_offset_b);
+    let offset_a = (-lag).max(0) as usize;
+    let offset_b = lag.max(0) as usize;
+    let nr_samples = (buffer_a.nr_samples() + offset_a).max(buffer_b.nr_samples() + offset_b);
+
+    let value = |buffer: &BufferE, offset: usize, ix: usize| {
+        ix.checked_sub(offset)
+            .and_then(|ix| buffer.norm_value(ix))
+            .unwrap_or(0.0)
+    };
+    let residual_values =
+        (0..nr_samples).map(|ix| value(buffer_a, offset_a, ix) - value(buffer_b, offset_b, ix));
+    if matches!(buffer_a, BufferE::F64(_)) || matches!(buffer_b, BufferE::F64(_)) {
+        let mut residual = Buffer::<f64>::with_capacity(buffer_a.sample_rate(), 64, nr_samples);
+        residual.data.extend(residual_values);
+        Ok(BufferE::F64(residual))
+    } else {
+        let mut residual = Buffer::<f32>::with_capacity(buffer_a.sample_rate(), 32, nr_samples);
+        residual
+            .data
+            .extend(residual_values.map(|value| value as f32));
+        Ok(BufferE::F32(residual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn buffer_i16(data: &[i16]) -> BufferE {
+        let mut buffer = Buffer::<i16>::new(48_000, 16);
+        buffer.data = data.to_vec();
+        BufferE::I16(buffer)
+    }
+
+    fn buffer_f32(data: &[f32]) -> BufferE {
+        let mut buffer = Buffer::<f32>::new(48_000, 32);
+        buffer.data = data.to_vec();
+        BufferE::F32(buffer)
+    }
+
+    fn residual_data(buffer: &BufferE) -> Vec<f32> {
+        match buffer {
+            BufferE::F32(buffer) => buffer.data.clone(),
+            _ => panic!("residual should be a float buffer"),
+        }
+    }
+
+    #[test]
+    fn residual_of_mixed_sample_types_is_normalized() {
+        let a = buffer_i16(&[16384, -16384, 0]);
+        let b = buffer_f32(&[0.5, -0.25, 0.125]);
+
+        let residual = residual_buffer(&a, &b, 0.0, 0.0).unwrap();
+
+        assert_eq!(residual_data(&residual), vec![0.0, -0.25, -0.125]);
+    }
+
+    #[test]
+    fn residual_of_f64_keeps_differences_below_f32_resolution() {
+        let mut a = Buffer::<f64>::new(48_000, 64);
+        a.data = vec![0.5, 0.25];
+        let mut b = a.clone();
+        b.data[0] += 1e-12;
+
+        let residual = residual_buffer(&BufferE::F64(a), &BufferE::F64(b), 0.0, 0.0).unwrap();
+
+        let BufferE::F64(residual) = residual else {
+            panic!("residual of f64 buffers should be f64");
+        };
+        assert!(
+            (residual.data[0] + 1e-12).abs() < 1e-15,
+            "{}",
+            residual.data[0]
+        );
+        assert_eq!(residual.data[1], 0.0);
+    }
+
+    #[test]
+    fn residual_spans_both_buffers_with_offset() {
+        let a = buffer_f32(&[0.5, 0.5]);
+        let b = buffer_f32(&[0.25, 0.25]);
+
+        let residual = residual_buffer(&a, &b, 0.0, 1.0).unwrap();
+
+        assert_eq!(residual_data(&residual), vec![0.5, 0.25, -0.25]);
+    }
+
+    #[test]
+    fn residual_starts_at_b_when_b_starts_before_a() {
+        let a = buffer_f32(&[0.5, 0.5]);
+        let b = buffer_f32(&[0.25, 0.25]);
+
+        let residual = residual_buffer(&a, &b, 3.0, 1.6).unwrap();
+
+        assert_eq!(whole_sample_lag(3.0, 1.6), -1);
+        assert_eq!(residual_offset(3.0, 1.6), 2.0);
+        assert_eq!(residual_data(&residual), vec![-0.25, 0.25, 0.5]);
+        assert_eq!(residual_offset(3.0, 4.4), 3.0);
+    }
+
+    #[test]
+    fn residual_requires_same_sample_rate() {
+        let a = buffer_f32(&[0.5]);
+        let mut b = Buffer::<f32>::new(44_100, 32);
+        b.data = vec![0.5];
+
+        assert!(residual_buffer(&a, &BufferE::F32(b), 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn values_at_reports_missing_samples() {
+        let mut audio = AudioManager::default();
+        let buffer_id_a = audio.buffers.insert(Arc::new(buffer_f32(&[0.5, 0.5, 0.5])));
+        let buffer_id_b = audio.buffers.insert(Arc::new(buffer_f32(&[0.25])));
+        let sample_rect = audio::SampleRect::from_buffere(audio.get_buffer(buffer_id_a).unwrap());
+        let diff = Diff::new(buffer_id_a, buffer_id_b, sample_rect);
+
+        let values = diff.values_at(&audio, 0.0).unwrap();
+        assert_eq!(values.a, Some(0.5));
+        assert_eq!(values.b, Some(0.25));
+        assert_eq!(values.residual, 0.25);
+
+        let values = diff.values_at(&audio, 2.0).unwrap();
+        assert_eq!(values.b, None);
+        assert_eq!(values.residual, 0.5);
+    }
 }
@@ -1 +1,228 @@
+use crate::{
+    audio::{
+        buffer::BufferE,
+        manager::{AudioManager, BufferId},
+        sample,
+        spectrum::{Spectrum, SpectrumConfig},
+    },
+    model::{
+        selection_stats::{buffer_ix_rng, selected_ix_rng, visible_items},
+        track::TrackId,
+        tracks2::Tracks,
+    },
+};
+use std::{
+    collections::HashSet,
+    ops::Range,
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+};
 
+/// Below this number of samples per track the spectrum is computed on the UI thread, so it
+/// follows a small selection without a frame of delay.
+const MAX_NR_SAMPLES_ON_UI_THREAD: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSpectrum {
+    pub track_id: TrackId,
+    pub spectrum: Spectrum,
+}
+
+/// What the spectra are computed for, they are recomputed when this changes.
+#[derive(Debug, Clone, PartialEq)]
+struct FftKey {
+    /// None for the whole buffer
+    ix_rng: Option<sample::IxRange>,
+    items: Vec<(TrackId, BufferId, f64)>,
+    config: SpectrumConfig,
+}
+
+/// Magnitude spectrum of the selection for the visible tracks, or the whole track when nothing
+/// is selected.
+///
+/// Like the selection stats, at most one computation runs on the worker thread and the
+/// spectra follow the selection as fast as they can be computed.
+#[derive(Debug)]
+pub struct Fft {
+    /// The spectra are only computed while the spectrum panel is open
+    pub open: bool,
+    pub config: SpectrumConfig,
+    /// Log or linear frequency axis
+    pub log_frequency: bool,
+    /// Bottom of the level axis in dBFS
+    pub min_db: f64,
+    /// Visible tracks left out of the spectrum
+    pub excluded_track_ids: HashSet<TrackId>,
+
+    /// Spectra for the current key, in track order
+    pub spectra: Vec<TrackSpectrum>,
+    key: Option<FftKey>,
+    tx: Sender<Vec<TrackSpectrum>>,
+    rx: Receiver<Vec<TrackSpectrum>>,
+    pending: bool,
+}
+
+impl Default for Fft {
+    fn default() -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        Self {
+            open: false,
+            config: SpectrumConfig::default(),
+            log_frequency: true,
+            min_db: -140.0,
+            excluded_track_ids: HashSet::new(),
+            spectra: Vec::new(),
+            key: None,
+            tx,
+            rx,
+            pending: false,
+        }
+    }
+}
+
+impl Fft {
+    /// True while the spectra are computed on a worker thread
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Recompute the spectra when the selection, the tracks or the config changed, and pick up
+    /// results of the worker thread.
+    pub fn update(&mut self, tracks: &Tracks, audio: &AudioManager) {
+        if let Ok(result) = self.rx.try_recv() {
+            self.spectra = result;
+            self.pending = false;
+        }
+        if self.pending || !self.open {
+            return;
+        }
+        let mut items = visible_items(tracks);
+        items.retain(|(track_id, _, _)| !self.excluded_track_ids.contains(track_id));
+        let key = FftKey {
+            ix_rng: selected_ix_rng(tracks),
+            items,
+            config: self.config,
+        };
+        if self.key.as_ref() != Some(&key) {
+            self.start(key, audio);
+        }
+    }
+
+    fn start(&mut self, key: FftKey, audio: &AudioManager) {
+        let jobs: Vec<(TrackId, Arc<BufferE>, Range<usize>)> = key
+            .items
+            .iter()
+            .filter_map(|&(track_id, buffer_id, sample_ix_offset)| {
+                let buffer = audio.get_shared_buffer(buffer_id).ok()?;
+                let ix_rng = buffer_ix_rng(key.ix_rng, sample_ix_offset, buffer.nr_samples());
+                Some((track_id, buffer.clone(), ix_rng))
+            })
+            .collect();
+        let config = key.config;
+        self.key = Some(key);
+
+        // Keep showing the previous spectra until the new ones are ready, to avoid flicker
+        // while scrubbing
+        let max_nr_samples = jobs.iter().map(|(_, _, ix_rng)| ix_rng.len()).max();
+        if cfg!(target_arch = "wasm32")
+            || max_nr_samples.unwrap_or(0) <= MAX_NR_SAMPLES_ON_UI_THREAD
+        {
+            self.spectra = compute(&jobs, config);
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.pending = true;
+            let tx = self.tx.clone();
+            std::thread::spawn(move || {
+                let _ = tx.send(compute(&jobs, config));
+            });
+        }
+    }
+
+    pub fn get(&self, track_id: TrackId) -> Option<&Spectrum> {
+        self.spectra
+            .iter()
+            .find(|track| track.track_id == track_id)
+            .map(|track| &track.spectrum)
+    }
+}
+
+fn compute(
+    jobs: &[(TrackId, Arc<BufferE>, Range<usize>)],
+    config: SpectrumConfig,
+) -> Vec<TrackSpectrum> {
+    jobs.iter()
+        .filter_map(|(track_id, buffer, ix_rng)| {
+            // an empty range, e.g. a selection outside of this track, has no spectrum
+            let spectrum = Spectrum::new(buffer, ix_rng.clone(), config).ok()?;
+            Some(TrackSpectrum {
+                track_id: *track_id,
+                spectrum,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::buffer::Buffer,
+        model::{
+            config::TrackConfig,
+            selection_info::{SelectionInfo, SelectionInfoE},
+        },
+    };
+
+    fn tracks_with_buffers(audio: &mut AudioManager, nr_tracks: usize) -> (Tracks, Vec<TrackId>) {
+        let mut tracks = Tracks::default();
+        let track_ids = (0..nr_tracks)
+            .map(|_| {
+                let buffer = Buffer::<f32>::with_size(48_000, 32, 1000);
+                let buffer_id = audio.buffers.insert(Arc::new(BufferE::F32(buffer)));
+                tracks
+                    .add_track_to_end(buffer_id, &TrackConfig::default())
+                    .unwrap()
+            })
+            .collect();
+        (tracks, track_ids)
+    }
+
+    #[test]
+    fn spectra_are_computed_only_while_open() {
+        let mut audio = AudioManager::default();
+        let (tracks, track_ids) = tracks_with_buffers(&mut audio, 2);
+        let mut fft = Fft::default();
+
+        fft.update(&tracks, &audio);
+        assert!(fft.spectra.is_empty());
+
+        fft.open = true;
+        fft.excluded_track_ids.insert(track_ids[1]);
+        fft.update(&tracks, &audio);
+        assert_eq!(fft.spectra.len(), 1);
+        assert!(fft.get(track_ids[0]).is_some());
+    }
+
+    #[test]
+    fn selection_outside_of_track_has_no_spectrum() {
+        let mut audio = AudioManager::default();
+        let (mut tracks, track_ids) = tracks_with_buffers(&mut audio, 1);
+        let mut fft = Fft {
+            open: true,
+            ..Default::default()
+        };
+
+        tracks.selection_info = SelectionInfoE::IsSelected(SelectionInfo {
+            ix_rng: (2000..3000).into(),
+            ..Default::default()
+        });
+        fft.update(&tracks, &audio);
+
+        assert!(fft.get(track_ids[0]).is_none());
+    }
+}
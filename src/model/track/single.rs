@@ -15,6 +15,10 @@ pub struct Single {
 
     // NOTE: maybe more than one Item at some point (in sequence on the 'single' track)
     pub item: Item,
+
+    /// Items drawn on top of `item` in the same lane, each in its own colour, sharing the value
+    /// range of the track
+    pub overlays: Vec<Item>,
 }
 
 impl Single {
@@ -22,9 +26,19 @@ impl Single {
         Ok(Self {
             screen_rect: None,
             item: Item::new(buffer_id),
+            overlays: Vec::new(),
         })
     }
 
+    /// `item` followed by the overlays, in drawing order
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        std::iter::once(&self.item).chain(self.overlays.iter())
+    }
+
+    pub fn contains_buffer(&self, buffer_id: BufferId) -> bool {
+        self.items().any(|item| item.buffer_id == buffer_id)
+    }
+
     pub fn update_sample_view(
         &mut self,
         samples_per_pixel: f32,
@@ -98,4 +112,13 @@ impl Item {
     pub fn set_sample_rect(&mut self, sample_rect: SampleRect) {
         self.sample_rect = Some(sample_rect);
     }
+
+    /// Normalized sample value at the given track sample index, None outside of the buffer
+    pub fn value_at(&self, audio: &AudioManager, sample_ix: f64) -> Result<Option<f64>> {
+        let buffer_ix = (sample_ix - self.sample_ix_offset).round();
+        if buffer_ix < 0.0 {
+            return Ok(None);
+        }
+        audio.norm_value(self.buffer_id, buffer_ix as usize)
+    }
 }
@@ -60,6 +60,32 @@ impl Tracks {
         Ok(track_id)
     }
 
+    /// Add a track comparing buffer A with buffer B, showing the A−B residual buffer
+    pub fn add_diff_track_to_end(
+        &mut self,
+        buffer_id_a: BufferId,
+        buffer_id_b: BufferId,
+        residual_buffer_id: BufferId,
+        audio: &audio::manager::AudioManager,
+        track_config: &TrackConfig,
+    ) -> Result<TrackId> {
+        anyhow::ensure!(
+            self.find_track(residual_buffer_id).is_none(),
+            "Track for buffer {:?} already exists",
+            residual_buffer_id
+        );
+        let track = Track::new_diff(
+            buffer_id_a,
+            buffer_id_b,
+            residual_buffer_id,
+            audio,
+            track_config,
+        )?;
+        let track_id = self.tracks.insert(track);
+        self.tracks_order.push(track_id);
+        Ok(track_id)
+    }
+
     pub fn remove_track(&mut self, track_id: TrackId) {
         self.tracks.remove(track_id);
         self.tracks_order.retain(|id| *id != track_id);
@@ -77,10 +103,98 @@ impl Tracks {
         self.tracks_order.clear();
     }
 
+    /// Sample rate of the first visible track, the time ruler uses it to show seconds
+    pub fn sample_rate(&self, audio: &audio::manager::AudioManager) -> Option<u32> {
+        self.tracks_order
+            .iter()
+            .filter_map(|track_id| self.tracks.get(*track_id))
+            .filter(|track| track.visible)
+            .find_map(|track| audio.get_buffer_format(track.single.item.buffer_id).ok())
+            .map(|buffer| buffer.sample_rate())
+    }
+
+    /// The track showing `buffer_id`, as its item or as one of its overlays
     pub fn find_track(&self, buffer_id: BufferId) -> Option<(TrackId, &Track)> {
         self.tracks
             .iter()
-            .find(|(_, track)| track.single.item.buffer_id == buffer_id)
+            .find(|(_, track)| track.single.contains_buffer(buffer_id))
+    }
+
+    /// Where `buffer_id` starts on the time line, None when it isn't in a track
+    pub fn item_offset(&self, buffer_id: BufferId) -> Option<f64> {
+        let (_, track) = self.find_track(buffer_id)?;
+        track
+            .single
+            .items()
+            .find(|item| item.buffer_id == buffer_id)
+            .map(|item| item.sample_ix_offset)
+    }
+
+    /// Set the offset of the buffer in its track, false when it isn't in a track
+    pub fn set_item_offset(&mut self, buffer_id: BufferId, offset: f64) -> Result<bool> {
+        let Some((track_id, track)) = self.find_track(buffer_id) else {
+            return Ok(false);
+        };
+        let overlay_ix = track
+            .single
+            .overlays
+            .iter()
+            .position(|item| item.buffer_id == buffer_id);
+        if let Some(track) = self.get_track_mut(track_id) {
+            track.set_sample_ix_offset(overlay_ix, offset)?;
+        }
+        Ok(true)
+    }
+
+    /// Show `new_buffer_id` in the track that shows `old_buffer_id`, see `Track::replace_buffer`
+    pub fn replace_buffer(&mut self, old_buffer_id: BufferId, new_buffer_id: BufferId) {
+        for track in self.tracks.values_mut() {
+            track.replace_buffer(old_buffer_id, new_buffer_id);
+        }
+    }
+
+    /// Move the items of `track_id` onto `onto_track_id` and remove `track_id`
+    pub fn overlay_track(&mut self, track_id: TrackId, onto_track_id: TrackId) -> Result<()> {
+        anyhow::ensure!(
+            track_id != onto_track_id,
+            "Can't overlay a track onto itself"
+        );
+        anyhow::ensure!(
+            self.tracks.contains_key(onto_track_id),
+            "Track {:?} not found",
+            onto_track_id
+        );
+        let track = self
+            .tracks
+            .get(track_id)
+            .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?
+            .clone();
+        self.tracks[onto_track_id].add_overlays_from(track)?;
+        self.remove_track(track_id);
+        Ok(())
+    }
+
+    /// Move the overlays of `track_id` back to their own tracks, right below it
+    pub fn split_overlays(&mut self, track_id: TrackId, track_config: &TrackConfig) -> Result<()> {
+        let track = self
+            .tracks
+            .get_mut(track_id)
+            .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?;
+        let overlays = std::mem::take(&mut track.single.overlays);
+        let height = track.height;
+        let mut insert_ix = self
+            .tracks_order
+            .iter()
+            .position(|id| *id == track_id)
+            .map_or(self.tracks_order.len(), |ix| ix + 1);
+        for overlay in overlays {
+            let new_track_id = self.insert_track(overlay.buffer_id, insert_ix, track_config)?;
+            let new_track = &mut self.tracks[new_track_id];
+            new_track.single.item.sample_ix_offset = overlay.sample_ix_offset;
+            new_track.height = height;
+            insert_ix += 1;
+        }
+        Ok(())
     }
 
     pub fn get_track(&self, track_id: TrackId) -> Option<&Track> {
@@ -262,8 +376,7 @@ impl Tracks {
                 continue;
             }
             let buffer_id = track.single.item.buffer_id;
-            let buffer = audio.get_buffer(buffer_id).ok()?;
-            let sample_rect = audio::SampleRect::from_buffere(buffer);
+            let sample_rect = audio.get_sample_rect(buffer_id).ok()?;
             if max_sample_rect
                 .as_ref()
                 .is_none_or(|max_rect| max_rect.width() < sample_rect.width())
@@ -333,6 +446,24 @@ impl Tracks {
         Ok(())
     }
 
+    /// Pan so `sample_ix` is in the middle of the view, keeping the zoom level
+    pub fn center_on_sample_ix(
+        &mut self,
+        sample_ix: f64,
+        audio: &audio::manager::AudioManager,
+    ) -> Result<()> {
+        let Some(ix_range) = self.ruler.ix_range() else {
+            return Ok(());
+        };
+        let half_visible_len = ix_range.len() / 2.0;
+        self.ruler.zoom_to_ix_range(audio::sample::FracIxRange {
+            start: sample_ix - half_visible_len,
+            end: sample_ix + half_visible_len,
+        });
+        self.update_tracks_sample_ix_ranges_to_ruler(audio)?;
+        Ok(())
+    }
+
     /// Update track heights to equally distribute the available height, taking min_height into account.
     pub fn fill_screen_height(&mut self, min_height: f32) -> Result<()> {
         let visible_tracks = self.visible_tracks_len();
@@ -397,7 +528,7 @@ mod tests {
     ) -> audio::BufferId {
         let buffer =
             audio::buffer::BufferE::F32(audio::buffer::Buffer::with_size(48_000, 32, nr_samples));
-        audio.buffers.insert(buffer)
+        audio.buffers.insert(std::sync::Arc::new(buffer))
     }
 
     fn track_with_value_range(
@@ -679,7 +810,14 @@ mod tests {
         );
 
         tracks
-            .pan_track_value_range(track_id, -100.0, ValueDisplayScale { skew_factor: 1.0 })
+            .pan_track_value_range(
+                track_id,
+                -100.0,
+                ValueDisplayScale {
+                    skew_factor: 1.0,
+                    ..Default::default()
+                },
+            )
             .unwrap();
 
         let val_rng = tracks
@@ -693,6 +831,45 @@ mod tests {
         assert!(val_rng.max <= -1.0);
     }
 
+    #[test]
+    fn zoom_track_value_range_in_dbfs_reaches_quiet_levels() {
+        let mut tracks = Tracks::default();
+        let mut audio = audio::manager::AudioManager::default();
+        let full_scale = audio::sample::ValRange {
+            min: -1.0,
+            max: 1.0,
+        };
+        let linear_track_id = track_with_value_range(&mut tracks, &mut audio, full_scale);
+        let dbfs_track_id = track_with_value_range(&mut tracks, &mut audio, full_scale);
+        let dbfs = ValueDisplayScale {
+            mode: crate::model::ruler::value_scale::ValueScaleMode::Dbfs,
+            floor_db: -120.0,
+            ..Default::default()
+        };
+
+        tracks
+            .zoom_track_value_range(linear_track_id, -50.0, 50.0, ValueDisplayScale::default())
+            .unwrap();
+        tracks
+            .zoom_track_value_range(dbfs_track_id, -50.0, 50.0, dbfs)
+            .unwrap();
+
+        let val_rng = |track_id| {
+            tracks
+                .get_track(track_id)
+                .unwrap()
+                .sample_rect
+                .unwrap()
+                .val_rng
+                .unwrap()
+        };
+        let (linear, zoomed) = (val_rng(linear_track_id), val_rng(dbfs_track_id));
+        assert!((zoomed.min + zoomed.max).abs() < 1e-12, "{zoomed:?}");
+        // the same zoom shows levels far below what the linear scale shows
+        assert!(zoomed.max < linear.max / 100.0, "{zoomed:?} {linear:?}");
+        assert!(zoomed.max > 0.0);
+    }
+
     #[test]
     fn recenter_track_value_range_restores_full_scale_after_out_of_range_pan() {
         let mut tracks = Tracks::default();
@@ -726,4 +903,35 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn overlay_track_and_split_overlays_round_trip() {
+        let mut tracks = Tracks::default();
+        let mut audio = audio::manager::AudioManager::default();
+        let buffer_ids: Vec<_> = (0..3).map(|_| insert_buffer(&mut audio, 64)).collect();
+        let track_ids: Vec<_> = buffer_ids
+            .iter()
+            .map(|buffer_id| {
+                tracks
+                    .add_track_to_end(*buffer_id, &TrackConfig::default())
+                    .unwrap()
+            })
+            .collect();
+
+        tracks.overlay_track(track_ids[2], track_ids[0]).unwrap();
+
+        assert_eq!(tracks.tracks_order, vec![track_ids[0], track_ids[1]]);
+        assert_eq!(tracks.find_track(buffer_ids[2]).unwrap().0, track_ids[0]);
+        assert!(tracks.overlay_track(track_ids[0], track_ids[0]).is_err());
+
+        tracks
+            .split_overlays(track_ids[0], &TrackConfig::default())
+            .unwrap();
+
+        assert_eq!(tracks.tracks_order.len(), 3);
+        assert_eq!(tracks.tracks_order[0], track_ids[0]);
+        let (split_track_id, _) = tracks.find_track(buffer_ids[2]).unwrap();
+        assert_eq!(tracks.tracks_order[1], split_track_id);
+        assert!(tracks.tracks[track_ids[0]].single.overlays.is_empty());
+    }
 }
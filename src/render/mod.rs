@@ -0,0 +1,603 @@
+//! Headless rendering of waveforms with their time and value rulers to a PNG or SVG image, e.g.
+//! for bug reports and CI artifacts. Uses the same sample views and tick lattices as the
+//! tracks, but draws on a CPU canvas instead of through egui.
+
+mod raster;
+mod svg;
+
+use crate::{
+    Pos,
+    audio::{
+        SampleRect,
+        buffer::BufferE,
+        sample::{
+            self, ValRange,
+            view::{SINGLE_SAMPLE_DRAW_MAX_SPP, View, ViewData},
+        },
+    },
+    model::{
+        config::ThemeColors,
+        ruler::{
+            IxLattice, TickType, TimeFormat, TimeUnit, ValueDisplayScale, ValueLattice,
+            sample_value_to_screen_y,
+        },
+    },
+    rect::Rect,
+    view::{
+        ruler::{NR_PIXELS_PER_TICK, format_compact},
+        value_ruler2::{NR_PIXELS_PER_VALUE_TICK, format_tick_label, is_multiple_of},
+    },
+    wav::read::LoadedFile,
+};
+use anyhow::{Context, Result, bail, ensure};
+use egui::{Color32, FontDefinitions, FontId, Galley, epaint::text::Fonts};
+use std::{path::Path, sync::Arc};
+
+const FONT_SIZE: f32 = 12.0;
+const RULER_HEIGHT: f32 = 24.0;
+const VALUE_RULER_WIDTH: f32 = 48.0;
+const MIN_TRACK_HEIGHT: f32 = 16.0;
+/// Narrowest plot right of the value ruler, room for a couple of time ruler labels
+const MIN_PLOT_WIDTH: f32 = 64.0;
+const MAX_SIZE: u32 = 16_384;
+const TICK_HEIGHT_LONG: f32 = 13.0;
+const TICK_HEIGHT_MID: f32 = 8.0;
+const TICK_HEIGHT_SHORT: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    /// Format from the extension of `path`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("png") => Ok(ImageFormat::Png),
+            Some("svg") => Ok(ImageFormat::Svg),
+            _ => bail!(
+                "Unknown image format of {}, use .png or .svg",
+                path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    /// Size of the image in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Start of the drawn time range in seconds, None for the start of the files
+    pub start_s: Option<f64>,
+    /// End of the drawn time range in seconds, None for the end of the longest channel
+    pub end_s: Option<f64>,
+    /// Drawn range of normalized sample values
+    pub val_rng: ValRange<f64>,
+    pub time_unit: TimeUnit,
+    /// Colours of the light theme instead of the dark one
+    pub light: bool,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 600,
+            start_s: None,
+            end_s: None,
+            val_rng: ValRange {
+                min: -1.0,
+                max: 1.0,
+            },
+            time_unit: TimeUnit::Samples,
+            light: false,
+        }
+    }
+}
+
+impl RenderConfig {
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            (1..=MAX_SIZE).contains(&self.width) && (1..=MAX_SIZE).contains(&self.height),
+            "The image size must be 1 to {MAX_SIZE} pixels"
+        );
+        ensure!(
+            self.width as f32 >= VALUE_RULER_WIDTH + MIN_PLOT_WIDTH,
+            "The image must be at least {} pixels wide",
+            VALUE_RULER_WIDTH + MIN_PLOT_WIDTH
+        );
+        ensure!(
+            self.val_rng.min.is_finite()
+                && self.val_rng.max.is_finite()
+                && self.val_rng.min < self.val_rng.max,
+            "The minimum value must be below the maximum value"
+        );
+        Ok(())
+    }
+
+    /// Sample index range of the time range at `sample_rate`, defaulting to `nr_samples`
+    fn ix_rng(&self, sample_rate: u32, nr_samples: usize) -> Result<sample::FracIxRange> {
+        let to_ix = |seconds: f64| seconds * sample_rate as f64;
+        let ix_rng = sample::FracIxRange {
+            start: self.start_s.map_or(0.0, to_ix),
+            end: self.end_s.map_or(nr_samples as f64, to_ix),
+        };
+        ensure!(
+            ix_rng.start.is_finite() && ix_rng.end.is_finite() && ix_rng.start < ix_rng.end,
+            "The time range is empty"
+        );
+        Ok(ix_rng)
+    }
+}
+
+/// Drawing primitives in pixel coordinates, implemented for PNG and SVG
+trait Canvas {
+    /// Fill with partial coverage of the pixels at the edges
+    fn fill_rect(&mut self, rect: Rect, color: Color32);
+    fn polyline(&mut self, points: &[Pos], width: f32, color: Color32);
+    fn circle(&mut self, center: Pos, radius: f32, color: Color32);
+    /// Text laid out in `galley`, with its top left corner at `pos`
+    fn text(&mut self, pos: Pos, galley: Arc<Galley>, color: Color32);
+}
+
+struct Colors {
+    background: Color32,
+    track_background: Color32,
+    text: Color32,
+    grid: Color32,
+    zero: Color32,
+    border: Color32,
+    waveform: Color32,
+}
+
+impl Colors {
+    fn new(light: bool) -> Self {
+        let (visuals, theme_colors) = if light {
+            (egui::Visuals::light(), ThemeColors::light_default())
+        } else {
+            (egui::Visuals::dark(), ThemeColors::dark_default())
+        };
+        let border = visuals.widgets.noninteractive.bg_stroke.color;
+        Self {
+            background: visuals.panel_fill,
+            track_background: visuals.extreme_bg_color,
+            text: visuals.text_color(),
+            grid: border.linear_multiply(0.7),
+            zero: visuals.text_color().linear_multiply(0.55),
+            border,
+            waveform: theme_colors.waveform,
+        }
+    }
+}
+
+/// A channel drawn as a track
+struct Track<'a> {
+    label: String,
+    buffer: &'a BufferE,
+}
+
+fn tracks(files: &[LoadedFile]) -> Vec<Track<'_>> {
+    files
+        .iter()
+        .flat_map(|file| {
+            let name = file
+                .path
+                .as_deref()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            file.channels.iter().map(move |(ch_ix, buffer)| {
+                let channel = match file.channel_ids.get(ch_ix) {
+                    Some(id) => id.short_name().to_string(),
+                    None => format!("ch {ch_ix}"),
+                };
+                Track {
+                    label: format!("{name} {channel}").trim().to_string(),
+                    buffer,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Draw all channels of `files` below each other as an image in `format`. The time axis uses the
+/// sample rate of the first file.
+pub fn render(files: &[LoadedFile], config: &RenderConfig, format: ImageFormat) -> Result<Vec<u8>> {
+    config.validate()?;
+    let tracks = tracks(files);
+    ensure!(!tracks.is_empty(), "No channels to render");
+    let sample_rate = files[0].sample_rate;
+    let nr_samples = tracks
+        .iter()
+        .map(|track| track.buffer.nr_samples())
+        .max()
+        .unwrap_or_default();
+    let ix_rng = config.ix_rng(sample_rate, nr_samples)?;
+    let colors = Colors::new(config.light);
+    let (width, height) = (config.width, config.height);
+    let mut renderer = Renderer {
+        fonts: Fonts::new(1.0, 2048, FontDefinitions::default()),
+        colors: &colors,
+        time_format: TimeFormat {
+            unit: config.time_unit,
+            sample_rate,
+            ..Default::default()
+        },
+        ix_rng,
+        val_rng: config.val_rng,
+        size: Pos::new(width as f32, height as f32),
+    };
+    match format {
+        ImageFormat::Png => {
+            let mut canvas = raster::Pixmap::new(width, height, colors.background);
+            renderer.draw(&mut canvas, &tracks)?;
+            canvas.encode_png(&renderer.fonts)
+        }
+        ImageFormat::Svg => {
+            let mut canvas = svg::Svg::new(width, height, colors.background);
+            renderer.draw(&mut canvas, &tracks)?;
+            Ok(canvas.finish().into_bytes())
+        }
+    }
+}
+
+/// Render to `path`, the extension picks the format
+pub fn render_to_file(files: &[LoadedFile], config: &RenderConfig, path: &Path) -> Result<()> {
+    let format = ImageFormat::from_path(path)?;
+    let bytes = render(files, config, format)?;
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+struct Renderer<'a> {
+    fonts: Fonts,
+    colors: &'a Colors,
+    time_format: TimeFormat,
+    ix_rng: sample::FracIxRange,
+    val_rng: ValRange<f64>,
+    /// Size of the image
+    size: Pos,
+}
+
+impl Renderer<'_> {
+    fn draw(&mut self, canvas: &mut impl Canvas, tracks: &[Track<'_>]) -> Result<()> {
+        let tracks_height = self.size.y - RULER_HEIGHT;
+        let track_height = (tracks_height / tracks.len() as f32).floor();
+        ensure!(
+            track_height >= MIN_TRACK_HEIGHT,
+            "The image is too low for {} channels",
+            tracks.len()
+        );
+        let ruler_rect = Rect::new(VALUE_RULER_WIDTH, 0.0, self.size.x, RULER_HEIGHT);
+        let mut ix_lattice = IxLattice::default();
+        ix_lattice.compute_ticks(
+            self.ix_rng,
+            ruler_rect,
+            NR_PIXELS_PER_TICK,
+            &self.time_format,
+        )?;
+        self.draw_time_ruler(canvas, ruler_rect, &ix_lattice);
+
+        for (track_ix, track) in tracks.iter().enumerate() {
+            let top = RULER_HEIGHT + track_ix as f32 * track_height;
+            let track_rect = Rect::new(VALUE_RULER_WIDTH, top, self.size.x, top + track_height);
+            canvas.fill_rect(track_rect, self.colors.track_background);
+            for tick in ix_lattice
+                .ticks
+                .iter()
+                .filter(|tick| tick.tick_type == TickType::Big)
+            {
+                let x = tick.screen_x.floor();
+                let line = Rect::new(x, track_rect.top(), x + 1.0, track_rect.bottom());
+                canvas.fill_rect(line, self.colors.grid);
+            }
+            let value_ruler_rect = Rect::new(0.0, top, VALUE_RULER_WIDTH, top + track_height);
+            self.draw_values(canvas, track_rect, value_ruler_rect);
+            self.draw_waveform(canvas, track_rect, track.buffer)?;
+            let galley = self.layout(&track.label);
+            canvas.text(
+                Pos::new(track_rect.left() + 4.0, track_rect.top() + 2.0),
+                galley,
+                self.colors.text,
+            );
+            let separator = Rect::new(
+                0.0,
+                top + track_height - 1.0,
+                self.size.x,
+                top + track_height,
+            );
+            canvas.fill_rect(separator, self.colors.border);
+        }
+        let border = Rect::new(VALUE_RULER_WIDTH - 1.0, 0.0, VALUE_RULER_WIDTH, self.size.y);
+        canvas.fill_rect(border, self.colors.border);
+        Ok(())
+    }
+
+    fn layout(&self, text: &str) -> Arc<Galley> {
+        self.fonts.layout_no_wrap(
+            text.to_string(),
+            FontId::proportional(FONT_SIZE),
+            self.colors.text,
+        )
+    }
+
+    /// Place the label at `pos` unless it overlaps a label that was placed before
+    fn place_label(
+        &self,
+        canvas: &mut impl Canvas,
+        galley: Arc<Galley>,
+        pos: Pos,
+        occupied: &mut Vec<Rect>,
+    ) {
+        let size = galley.size();
+        let label_rect = Rect::new(pos.x - 2.0, pos.y, pos.x + size.x + 2.0, pos.y + size.y);
+        let overlaps = |rect: &Rect| {
+            rect.left() < label_rect.right()
+                && label_rect.left() < rect.right()
+                && rect.top() < label_rect.bottom()
+                && label_rect.top() < rect.bottom()
+        };
+        if occupied.iter().any(overlaps) {
+            return;
+        }
+        canvas.text(pos, galley, self.colors.text);
+        occupied.push(label_rect);
+    }
+
+    /// Ticks like the time ruler of the tracks, with labels at the big ticks and then at the mid
+    /// ticks where they fit
+    fn draw_time_ruler(&self, canvas: &mut impl Canvas, rect: Rect, ix_lattice: &IxLattice) {
+        canvas.fill_rect(
+            Rect::new(0.0, rect.bottom() - 1.0, rect.right(), rect.bottom()),
+            self.colors.border,
+        );
+        for tick in &ix_lattice.ticks {
+            let tick_height = match tick.tick_type {
+                TickType::Big => TICK_HEIGHT_LONG,
+                TickType::Mid => TICK_HEIGHT_MID,
+                TickType::Small => TICK_HEIGHT_SHORT,
+            };
+            let x = tick.screen_x.floor();
+            let line = Rect::new(x, rect.bottom() - tick_height, x + 1.0, rect.bottom());
+            canvas.fill_rect(line, self.colors.text);
+        }
+        let label =
+            |tick: &crate::model::ruler::Tick| match (self.time_format.unit, ix_lattice.spacing) {
+                (TimeUnit::Samples, _) | (_, None) => format_compact(tick.sample_ix.round() as i64),
+                (_, Some(spacing)) => self.time_format.tick_label(tick.sample_ix, &spacing),
+            };
+        let mut occupied = Vec::new();
+        for tick_type in [TickType::Big, TickType::Mid] {
+            for tick in ix_lattice
+                .ticks
+                .iter()
+                .filter(|tick| tick.tick_type == tick_type)
+            {
+                let galley = self.layout(&label(tick));
+                let width = galley.size().x;
+                let x = (tick.screen_x - width / 2.0)
+                    .min(rect.right() - width - 2.0)
+                    .max(rect.left() + 2.0);
+                self.place_label(canvas, galley, Pos::new(x, rect.top() + 2.0), &mut occupied);
+            }
+        }
+    }
+
+    /// Horizontal grid lines in the track, ticks and labels in the value ruler next to it
+    fn draw_values(&self, canvas: &mut impl Canvas, track_rect: Rect, ruler_rect: Rect) {
+        const TICK_LEN_LONG: f32 = 10.0;
+        const TICK_LEN_MID: f32 = 8.0;
+        const TICK_LEN_SHORT: f32 = 6.0;
+
+        let mut lattice = ValueLattice::default();
+        if lattice
+            .compute_ticks(
+                self.val_rng,
+                track_rect,
+                NR_PIXELS_PER_VALUE_TICK,
+                ValueDisplayScale::default(),
+            )
+            .is_err()
+        {
+            return;
+        }
+        for tick in &lattice.ticks {
+            let y = tick.screen_y.floor();
+            if tick.sample_value == 0.0 || tick.tick_type == TickType::Big {
+                let color = if tick.sample_value == 0.0 {
+                    self.colors.zero
+                } else {
+                    self.colors.grid
+                };
+                let line = Rect::new(track_rect.left(), y, track_rect.right(), y + 1.0);
+                canvas.fill_rect(line, color);
+            }
+            let tick_len = match tick.tick_type {
+                TickType::Big => TICK_LEN_LONG,
+                TickType::Mid => TICK_LEN_MID,
+                TickType::Small => TICK_LEN_SHORT,
+            };
+            let line = Rect::new(
+                ruler_rect.right() - tick_len,
+                y,
+                ruler_rect.right(),
+                y + 1.0,
+            );
+            canvas.fill_rect(line, self.colors.text);
+        }
+        let mut occupied = Vec::new();
+        for tick in lattice
+            .ticks
+            .iter()
+            .filter(|tick| is_multiple_of(tick.sample_value, lattice.label_step))
+        {
+            let galley = self.layout(&format_tick_label(tick.sample_value, lattice.label_step));
+            let size = galley.size();
+            let x = ruler_rect.right() - TICK_LEN_LONG - 2.0 - size.x;
+            let y = (tick.screen_y - size.y / 2.0)
+                .min(ruler_rect.bottom() - size.y)
+                .max(ruler_rect.top());
+            self.place_label(canvas, galley, Pos::new(x, y), &mut occupied);
+        }
+    }
+
+    /// Min/max per pixel column, a line through the samples, or stems with a dot per sample when
+    /// zoomed in far enough, like the tracks
+    fn draw_waveform(&self, canvas: &mut impl Canvas, rect: Rect, buffer: &BufferE) -> Result<()> {
+        let sample_rect = SampleRect {
+            ix_rng: self.ix_rng,
+            val_rng: Some(self.val_rng),
+        };
+        let display_scale = ValueDisplayScale::default();
+        let view = View::from_buffere(buffer, sample_rect, rect, display_scale)?;
+        let color = self.colors.waveform;
+        let line_color = color.linear_multiply(0.7);
+        match view.data {
+            ViewData::MinMax(min_max_positions) => {
+                for pos in min_max_positions {
+                    if pos.is_outside(rect.top(), rect.bottom()) || !rect.contains_x(pos.min.x) {
+                        continue;
+                    }
+                    let column = Rect::new(pos.min.x, pos.min.y, pos.min.x + 1.0, pos.max.y);
+                    canvas.fill_rect(column, color);
+                }
+            }
+            ViewData::Single(single_view)
+                if view.samples_per_pixel < SINGLE_SAMPLE_DRAW_MAX_SPP =>
+            {
+                let y_mid = sample_value_to_screen_y(0.0, self.val_rng, rect, display_scale)
+                    .unwrap_or(rect.center().y);
+                let radius = if view.samples_per_pixel < 1.0 / 16.0 {
+                    3.0
+                } else {
+                    2.0
+                };
+                for pos in single_view.samples {
+                    let pos = Pos::new(pos.x + 0.5, pos.y);
+                    if pos.y < rect.top() && y_mid < rect.top()
+                        || pos.y > rect.bottom() && y_mid > rect.bottom()
+                    {
+                        continue;
+                    }
+                    let mid = rect.clip_pos(Pos::new(pos.x, y_mid));
+                    canvas.polyline(
+                        &[mid, rect.clip_pos(pos)],
+                        1.0,
+                        line_color.linear_multiply(0.5),
+                    );
+                    if rect.contains(pos) {
+                        canvas.circle(pos, radius, color);
+                    }
+                }
+            }
+            ViewData::Single(single_view) => {
+                for segment in single_view.line_segments {
+                    let points: Vec<Pos> = segment
+                        .iter()
+                        .map(|pos| Pos::new(pos.x + 0.5, pos.y))
+                        .collect();
+                    canvas.polyline(&points, 1.0, line_color);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{audio::buffer::Buffer, wav::read::LoadedFile};
+    use std::collections::BTreeMap;
+
+    fn sine_file(nr_samples: usize) -> LoadedFile {
+        let data = (0..nr_samples)
+            .map(|ix| (ix as f32 * 0.05).sin() * 0.8)
+            .collect();
+        let buffer = BufferE::F32(Buffer {
+            sample_rate: 48_000,
+            bit_depth: 32,
+            data,
+        });
+        LoadedFile {
+            load_id: 0,
+            channels: BTreeMap::from([(0, buffer.clone()), (1, buffer)]),
+            sample_type: crate::audio::SampleType::Float,
+            bit_depth: 32,
+            sample_rate: 48_000,
+            layout: None,
+            channel_ids: BTreeMap::new(),
+            path: Some("sine & noise.wav".into()),
+            nr_samples: nr_samples as u64,
+            bext: None,
+            cue_points: Vec::new(),
+            read_config: None,
+            streamed: Default::default(),
+        }
+    }
+
+    fn config(width: u32, height: u32) -> RenderConfig {
+        RenderConfig {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn png_has_the_size_and_shows_the_waveform() {
+        let bytes = render(&[sine_file(48_000)], &config(320, 200), ImageFormat::Png).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (320, 200));
+        let waveform = ThemeColors::dark_default().waveform.to_srgba_unmultiplied();
+        let nr_waveform_pixels = pixels
+            .chunks_exact(4)
+            .filter(|pixel| pixel[..3] == waveform[..3])
+            .count();
+        assert!(nr_waveform_pixels > 1000);
+    }
+
+    #[test]
+    fn svg_has_rulers_and_escaped_labels_at_sample_level() {
+        let config = RenderConfig {
+            end_s: Some(20.0 / 48_000.0),
+            ..config(400, 300)
+        };
+        let svg = String::from_utf8(render(&[sine_file(100)], &config, ImageFormat::Svg).unwrap())
+            .unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("sine &amp; noise.wav ch 1"));
+        assert!(svg.contains(">0</text>"));
+        assert!(svg.contains("<circle"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let files = [sine_file(100)];
+        assert!(render(&files, &config(40, 200), ImageFormat::Svg).is_err());
+        // No room for the plot next to the value ruler
+        assert!(render(&files, &config(100, 200), ImageFormat::Svg).is_err());
+        assert!(render(&files, &config(112, 200), ImageFormat::Svg).is_ok());
+        // Two channels don't fit in 40 pixels below the ruler
+        assert!(render(&files, &config(400, 50), ImageFormat::Svg).is_err());
+        let reversed = RenderConfig {
+            start_s: Some(1.0),
+            end_s: Some(0.5),
+            ..Default::default()
+        };
+        assert!(render(&files, &reversed, ImageFormat::Svg).is_err());
+        assert!(ImageFormat::from_path(Path::new("out.jpg")).is_err());
+        assert_eq!(
+            ImageFormat::from_path(Path::new("out.PNG")).unwrap(),
+            ImageFormat::Png
+        );
+    }
+}
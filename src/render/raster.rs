@@ -0,0 +1,183 @@
+//! Software rasterizer for the PNG images: anti-aliased rects, lines and circles, and text from
+//! the font atlas of epaint.
+
+use super::Canvas;
+use crate::{Pos, rect::Rect};
+use anyhow::Result;
+use egui::{Color32, Galley, epaint::text::Fonts};
+use std::sync::Arc;
+
+/// Same coverage gamma as epaint uses for the font texture
+const TEXT_GAMMA: f32 = 0.55;
+
+/// Opaque RGBA pixels
+pub struct Pixmap {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+    /// Text is drawn when encoding, when the font atlas holds all glyphs
+    texts: Vec<(Pos, Arc<Galley>, Color32)>,
+}
+
+impl Pixmap {
+    pub fn new(width: u32, height: u32, background: Color32) -> Self {
+        let [r, g, b, _] = background.to_srgba_unmultiplied();
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            data: [r, g, b, 255].repeat(width * height),
+            texts: Vec::new(),
+        }
+    }
+
+    /// Blend `color` over the pixel, `coverage` scales the alpha of the colour
+    fn blend(&mut self, x: usize, y: usize, color: Color32, coverage: f32) {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let alpha = (a as f32 / 255.0 * coverage).clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+        let ix = (y * self.width + x) * 4;
+        for (dst, src) in self.data[ix..ix + 3].iter_mut().zip([r, g, b]) {
+            *dst = (*dst as f32 * (1.0 - alpha) + src as f32 * alpha).round() as u8;
+        }
+    }
+
+    /// Pixels with their centers within `margin` of the bounding box of `points`
+    fn pixels_around(
+        &self,
+        points: &[Pos],
+        margin: f32,
+    ) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let (min_x, max_x, min_y, max_y) = points.iter().fold(
+            (
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+            ),
+            |(min_x, max_x, min_y, max_y), pos| {
+                (
+                    min_x.min(pos.x),
+                    max_x.max(pos.x),
+                    min_y.min(pos.y),
+                    max_y.max(pos.y),
+                )
+            },
+        );
+        let x_rng = pixel_range(min_x - margin, max_x + margin, self.width);
+        let y_rng = pixel_range(min_y - margin, max_y + margin, self.height);
+        y_rng.flat_map(move |y| x_rng.clone().map(move |x| (x, y)))
+    }
+
+    pub fn encode_png(mut self, fonts: &Fonts) -> Result<Vec<u8>> {
+        let font_image = fonts.image();
+        for (pos, galley, color) in std::mem::take(&mut self.texts) {
+            for glyph in galley.rows.iter().flat_map(|row| &row.glyphs) {
+                let uv_rect = glyph.uv_rect;
+                let left = (pos.x + glyph.pos.x + uv_rect.offset.x).round() as isize;
+                let top = (pos.y + glyph.pos.y + uv_rect.offset.y).round() as isize;
+                for v in uv_rect.min[1]..uv_rect.max[1] {
+                    for u in uv_rect.min[0]..uv_rect.max[0] {
+                        let x = left + (u - uv_rect.min[0]) as isize;
+                        let y = top + (v - uv_rect.min[1]) as isize;
+                        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                            continue;
+                        }
+                        let coverage =
+                            font_image.pixels[v as usize * font_image.size[0] + u as usize];
+                        self.blend(x as usize, y as usize, color, coverage.powf(TEXT_GAMMA));
+                    }
+                }
+            }
+        }
+
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+/// Indices of the pixels whose centers lie in `start..=end`, clipped to `len`
+fn pixel_range(start: f32, end: f32, len: usize) -> std::ops::Range<usize> {
+    let start = (start - 0.5).ceil().max(0.0) as usize;
+    let end = ((end - 0.5).floor() + 1.0).clamp(0.0, len as f32) as usize;
+    start.min(end)..end
+}
+
+fn distance_to_segment(pos: Pos, a: Pos, b: Pos) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq > 0.0 {
+        (((pos.x - a.x) * dx + (pos.y - a.y) * dy) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (pos.x - (a.x + t * dx)).hypot(pos.y - (a.y + t * dy))
+}
+
+impl Canvas for Pixmap {
+    fn fill_rect(&mut self, rect: Rect, color: Color32) {
+        let left = rect.left().max(0.0);
+        let right = rect.right().min(self.width as f32);
+        let top = rect.top().max(0.0);
+        let bottom = rect.bottom().min(self.height as f32);
+        if left >= right || top >= bottom {
+            return;
+        }
+        for y in top.floor() as usize..bottom.ceil() as usize {
+            let coverage_y = bottom.min(y as f32 + 1.0) - top.max(y as f32);
+            for x in left.floor() as usize..right.ceil() as usize {
+                let coverage_x = right.min(x as f32 + 1.0) - left.max(x as f32);
+                self.blend(x, y, color, coverage_x * coverage_y);
+            }
+        }
+    }
+
+    fn polyline(&mut self, points: &[Pos], width: f32, color: Color32) {
+        let half_width = width / 2.0;
+        for segment in points.windows(2) {
+            let pixels: Vec<_> = self.pixels_around(segment, half_width + 0.5).collect();
+            for (x, y) in pixels {
+                let center = Pos::new(x as f32 + 0.5, y as f32 + 0.5);
+                let distance = distance_to_segment(center, segment[0], segment[1]);
+                self.blend(x, y, color, half_width + 0.5 - distance);
+            }
+        }
+    }
+
+    fn circle(&mut self, center: Pos, radius: f32, color: Color32) {
+        let pixels: Vec<_> = self.pixels_around(&[center], radius + 0.5).collect();
+        for (x, y) in pixels {
+            let distance = (x as f32 + 0.5 - center.x).hypot(y as f32 + 0.5 - center.y);
+            self.blend(x, y, color, radius + 0.5 - distance);
+        }
+    }
+
+    fn text(&mut self, pos: Pos, galley: Arc<Galley>, color: Color32) {
+        self.texts.push((pos, galley, color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red(pixmap: &Pixmap, x: usize, y: usize) -> u8 {
+        pixmap.data[(y * pixmap.width + x) * 4]
+    }
+
+    #[test]
+    fn rect_edges_are_partially_covered() {
+        let mut pixmap = Pixmap::new(4, 2, Color32::BLACK);
+        pixmap.fill_rect(Rect::new(0.5, 0.0, 2.0, 1.0), Color32::WHITE);
+        assert_eq!([0, 1, 2].map(|x| red(&pixmap, x, 0)), [128, 255, 0]);
+        assert_eq!(red(&pixmap, 1, 1), 0);
+    }
+}
@@ -0,0 +1,101 @@
+//! SVG output, text is left to the viewer's sans-serif font.
+
+use super::{Canvas, FONT_SIZE};
+use crate::{Pos, rect::Rect};
+use egui::{Color32, Galley};
+use std::{fmt::Write, sync::Arc};
+
+pub struct Svg {
+    out: String,
+}
+
+/// `name` attribute with the colour, and its opacity when not opaque
+fn paint(name: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut res = format!("{name}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a < 255 {
+        let _ = write!(res, " {name}-opacity=\"{:.3}\"", a as f32 / 255.0);
+    }
+    res
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Svg {
+    pub fn new(width: u32, height: u32, background: Color32) -> Self {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\">\n"
+        );
+        let _ = writeln!(
+            out,
+            "<rect width=\"100%\" height=\"100%\" {}/>",
+            paint("fill", background)
+        );
+        Self { out }
+    }
+
+    pub fn finish(mut self) -> String {
+        self.out.push_str("</svg>\n");
+        self.out
+    }
+}
+
+impl Canvas for Svg {
+    fn fill_rect(&mut self, rect: Rect, color: Color32) {
+        let _ = writeln!(
+            self.out,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" {} shape-rendering=\"crispEdges\"/>",
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+            paint("fill", color)
+        );
+    }
+
+    fn polyline(&mut self, points: &[Pos], width: f32, color: Color32) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|pos| format!("{:.1},{:.1}", pos.x, pos.y))
+            .collect();
+        let _ = writeln!(
+            self.out,
+            "<polyline points=\"{}\" fill=\"none\" stroke-width=\"{width}\" {}/>",
+            points.join(" "),
+            paint("stroke", color)
+        );
+    }
+
+    fn circle(&mut self, center: Pos, radius: f32, color: Color32) {
+        let _ = writeln!(
+            self.out,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{radius}\" {}/>",
+            center.x,
+            center.y,
+            paint("fill", color)
+        );
+    }
+
+    fn text(&mut self, pos: Pos, galley: Arc<Galley>, color: Color32) {
+        // SVG places text at its baseline
+        let baseline = galley
+            .rows
+            .first()
+            .and_then(|row| row.glyphs.first())
+            .map_or(FONT_SIZE * 0.8, |glyph| glyph.pos.y);
+        let _ = writeln!(
+            self.out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"{FONT_SIZE}\" {}>{}</text>",
+            pos.x,
+            pos.y + baseline,
+            paint("fill", color),
+            escape(galley.text())
+        );
+    }
+}
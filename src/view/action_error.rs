@@ -0,0 +1,22 @@
+//! Shows why the last action failed, e.g. a diff of a file that is streamed from disk.
+
+use crate::model::Model;
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let Some(error) = &model.action_error else {
+        return;
+    };
+    let mut open = true;
+    let mut dismissed = false;
+    egui::Window::new("Action failed")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            dismissed = ui.button("OK").clicked();
+        });
+    if !open || dismissed {
+        model.action_error = None;
+    }
+}
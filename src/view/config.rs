@@ -1,5 +1,17 @@
-use crate::model::{self, shortcuts::ShortcutScope};
+use crate::{
+    audio::{spectrogram::FFT_SIZES, window::WindowFunction},
+    model::{
+        self,
+        colormap::Colormap,
+        file_watch::ReloadPolicy,
+        ruler::{
+            TimeUnit, ValueDisplayScale, time_format::FRAME_RATES, value_scale::ValueScaleMode,
+        },
+        shortcuts::ShortcutScope,
+    },
+};
 use egui;
+use strum::IntoEnumIterator;
 
 pub fn show_config(ui: &mut egui::Ui, config: &mut model::Config) {
     ui.group(|ui| {
@@ -14,18 +26,37 @@ pub fn show_config(ui: &mut egui::Ui, config: &mut model::Config) {
                     .prefix(""),
             );
         });
-        ui.horizontal(|ui| {
-            ui.label("Value Skew: ");
-            ui.add(
-                egui::Slider::new(
-                    &mut config.value_display_scale.skew_factor,
-                    0.0..=model::ruler::ValueDisplayScale::MAX_SKEW_FACTOR,
+        ui_value_display_scale(ui, &mut config.value_display_scale);
+        ui.checkbox(&mut config.show_hover_info, "Show floating hover info");
+        ui.checkbox(
+            &mut config.place_at_time_reference,
+            "Place BWF files at their time reference",
+        );
+        if !cfg!(target_arch = "wasm32") {
+            ui.horizontal(|ui| {
+                ui.label("Changed files: ");
+                for policy in ReloadPolicy::iter() {
+                    ui.radio_value(&mut config.reload_on_change, policy, policy.name());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Stream files larger than: ");
+                ui.add(
+                    egui::DragValue::new(&mut config.stream_threshold_mib)
+                        .speed(64.0)
+                        .suffix(" MiB"),
                 )
-                .step_by(0.01)
-                .show_value(true),
-            );
+                .on_hover_text(
+                    "Files whose samples take more memory are read from disk while shown, \
+                     diff, align, export, spectrogram and statistics need them in memory",
+                );
+            });
+        }
+        ui.group(|ui| {
+            ui.label("Time");
+            ui.separator();
+            ui_time_config(ui, &mut config.time);
         });
-        ui.checkbox(&mut config.show_hover_info, "Show floating hover info");
         ui.group(|ui| {
             ui.label("Shortcuts");
             ui.separator();
@@ -67,6 +98,11 @@ pub fn show_config(ui: &mut egui::Ui, config: &mut model::Config) {
                 });
             });
         }
+        ui.group(|ui| {
+            ui.label("Spectrogram");
+            ui.separator();
+            ui_spectrogram_config(ui, &mut config.spectrogram);
+        });
         ui.separator();
         if ui.button("Reset all settings").clicked() {
             config.reset_to_default();
@@ -75,3 +111,103 @@ pub fn show_config(ui: &mut egui::Ui, config: &mut model::Config) {
         }
     });
 }
+
+fn ui_value_display_scale(ui: &mut egui::Ui, scale: &mut ValueDisplayScale) {
+    ui.horizontal(|ui| {
+        ui.label("Value Scale: ");
+        ui.radio_value(&mut scale.mode, ValueScaleMode::Skew, "skew");
+        ui.radio_value(&mut scale.mode, ValueScaleMode::Dbfs, "dBFS");
+    });
+    match scale.mode {
+        ValueScaleMode::Skew => {
+            ui.horizontal(|ui| {
+                ui.label("Value Skew: ");
+                ui.add(
+                    egui::Slider::new(
+                        &mut scale.skew_factor,
+                        0.0..=ValueDisplayScale::MAX_SKEW_FACTOR,
+                    )
+                    .step_by(0.01)
+                    .show_value(true),
+                );
+            });
+        }
+        ValueScaleMode::Dbfs => {
+            ui.horizontal(|ui| {
+                ui.label("Floor: ");
+                ui.add(
+                    egui::DragValue::new(&mut scale.floor_db)
+                        .range(ValueDisplayScale::MIN_FLOOR_DB..=ValueDisplayScale::MAX_FLOOR_DB)
+                        .suffix(" dB"),
+                );
+            });
+        }
+    }
+}
+
+/// Unit of the time ruler, also in the context menu of the ruler
+pub(crate) fn ui_time_config(ui: &mut egui::Ui, config: &mut model::config::TimeConfig) {
+    for unit in TimeUnit::iter() {
+        ui.radio_value(&mut config.unit, unit, unit.name());
+    }
+    ui.add_enabled_ui(config.unit == TimeUnit::Timecode, |ui| {
+        egui::ComboBox::from_label("frame rate")
+            .selected_text(format!("{} fps", config.frame_rate))
+            .show_ui(ui, |ui| {
+                for frame_rate in FRAME_RATES {
+                    ui.selectable_value(
+                        &mut config.frame_rate,
+                        frame_rate,
+                        format!("{frame_rate} fps"),
+                    );
+                }
+            });
+    });
+}
+
+fn ui_spectrogram_config(ui: &mut egui::Ui, config: &mut model::config::SpectrogramConfig) {
+    egui::ComboBox::from_label("FFT size")
+        .selected_text(config.fft_size.to_string())
+        .show_ui(ui, |ui| {
+            for fft_size in FFT_SIZES {
+                ui.selectable_value(&mut config.fft_size, fft_size, fft_size.to_string());
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.label("Hop: ");
+        ui.add(
+            egui::DragValue::new(&mut config.hop)
+                .range(16..=config.fft_size)
+                .suffix(" samples"),
+        );
+    });
+    egui::ComboBox::from_label("window")
+        .selected_text(config.window.name())
+        .show_ui(ui, |ui| {
+            for window in WindowFunction::iter() {
+                ui.selectable_value(&mut config.window, window, window.name());
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.label("Range: ");
+        ui.add(
+            egui::DragValue::new(&mut config.min_db)
+                .range(-300.0..=config.max_db - 1.0)
+                .suffix(" dB"),
+        );
+        ui.label("to");
+        ui.add(
+            egui::DragValue::new(&mut config.max_db)
+                .range(config.min_db + 1.0..=20.0)
+                .suffix(" dB"),
+        );
+    });
+    egui::ComboBox::from_label("colormap")
+        .selected_text(config.colormap.name())
+        .show_ui(ui, |ui| {
+            for colormap in Colormap::iter() {
+                ui.selectable_value(&mut config.colormap, colormap, colormap.name());
+            }
+        });
+    ui.checkbox(&mut config.log_frequency, "log frequency");
+}
@@ -0,0 +1,210 @@
+//! Export window: write the selection of chosen channels to a new wav file.
+
+use crate::{
+    audio::{
+        BufferId, SampleType,
+        manager::STREAMED_BUFFER_HINT,
+        sample::convert::{NoiseShaping, RequantizeConfig, Rounding},
+    },
+    model::{
+        Action, Model,
+        export::{ExportConfig, export_ix_rng},
+    },
+    view::file::{channel_label, file_title},
+    wav::write::WavFormat,
+};
+
+/// State of the export window, kept in egui's temp data between frames
+#[derive(Debug, Clone, Default)]
+struct ExportDialog {
+    open: bool,
+    path: String,
+    buffer_ids: Vec<BufferId>,
+    /// None keeps the format of the channels
+    format: Option<WavFormat>,
+    requantize: RequantizeConfig,
+}
+
+fn dialog_id() -> egui::Id {
+    egui::Id::new("export_dialog")
+}
+
+/// Open the export window with the channels of the visible tracks checked
+pub fn open(ctx: &egui::Context, model: &Model) {
+    let buffer_ids = model
+        .tracks
+        .tracks_order
+        .iter()
+        .filter_map(|&track_id| model.tracks.get_track(track_id))
+        .filter(|track| track.visible)
+        .map(|track| track.single.item.buffer_id)
+        .filter(|&buffer_id| !model.audio.is_streamed(buffer_id))
+        .collect();
+    let path = model
+        .files2
+        .iter()
+        .find_map(|file| file.path.as_deref())
+        .map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}_export.wav"))
+        })
+        .unwrap_or_else(|| "export.wav".into())
+        .display()
+        .to_string();
+    let dialog = ExportDialog {
+        open: true,
+        path,
+        buffer_ids,
+        format: None,
+        requantize: RequantizeConfig::default(),
+    };
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let Some(mut dialog) = ctx.data(|data| data.get_temp::<ExportDialog>(dialog_id())) else {
+        return;
+    };
+    if !dialog.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Export selection")
+        .open(&mut open)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui_range(ui, model, &dialog.buffer_ids);
+            ui.separator();
+            ui_channels(ui, model, &mut dialog.buffer_ids);
+            ui.separator();
+            egui::ComboBox::from_label("format")
+                .selected_text(dialog.format.map_or("original", WavFormat::name))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut dialog.format, None, "original");
+                    for format in WavFormat::ALL {
+                        ui.selectable_value(&mut dialog.format, Some(format), format.name());
+                    }
+                });
+            if dialog
+                .format
+                .is_none_or(|format| format.sample_type() == SampleType::Int)
+            {
+                ui_requantize(ui, &mut dialog.requantize);
+            }
+            ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(f32::INFINITY));
+            let can_export = !dialog.buffer_ids.is_empty() && !dialog.path.is_empty();
+            if ui
+                .add_enabled(can_export, egui::Button::new("Export"))
+                .clicked()
+            {
+                model.actions.push(Action::ExportSelection(ExportConfig {
+                    path: dialog.path.clone().into(),
+                    buffer_ids: dialog.buffer_ids.clone(),
+                    format: dialog.format,
+                    requantize: dialog.requantize,
+                }));
+                dialog.open = false;
+            }
+        });
+    dialog.open &= open;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
+
+/// Only used when the format has a lower resolution than a channel
+fn ui_requantize(ui: &mut egui::Ui, requantize: &mut RequantizeConfig) {
+    ui.horizontal_wrapped(|ui| {
+        egui::ComboBox::from_label("rounding")
+            .selected_text(requantize.rounding.name())
+            .show_ui(ui, |ui| {
+                for rounding in Rounding::ALL {
+                    ui.selectable_value(&mut requantize.rounding, rounding, rounding.name());
+                }
+            });
+        egui::ComboBox::from_label("noise shaping")
+            .selected_text(requantize.noise_shaping.name())
+            .show_ui(ui, |ui| {
+                for noise_shaping in NoiseShaping::ALL {
+                    ui.selectable_value(
+                        &mut requantize.noise_shaping,
+                        noise_shaping,
+                        noise_shaping.name(),
+                    );
+                }
+            });
+        if requantize.rounding == Rounding::Tpdf {
+            ui.add(egui::DragValue::new(&mut requantize.seed).prefix("seed "));
+        }
+    })
+    .response
+    .on_hover_text("Applied when the format has a lower bit depth than a channel");
+}
+
+fn ui_range(ui: &mut egui::Ui, model: &Model, buffer_ids: &[BufferId]) {
+    let what = if model.tracks.selection_info.is_selected() {
+        "selection"
+    } else {
+        "whole channels"
+    };
+    match export_ix_rng(model, buffer_ids) {
+        Some(ix_rng) => {
+            let time_format = &model.tracks.ruler.time_format;
+            ui.label(format!(
+                "{what}: {} - {} ({} samples)",
+                time_format.format(ix_rng.start as f64),
+                time_format.format(ix_rng.end as f64),
+                ix_rng.end - ix_rng.start
+            ));
+        }
+        None => {
+            ui.label("Nothing to export");
+        }
+    }
+}
+
+/// A checkbox per file that checks all its channels, and one per channel. The channels are
+/// written in the order they were checked.
+fn ui_channels(ui: &mut egui::Ui, model: &Model, buffer_ids: &mut Vec<BufferId>) {
+    for (file_ix, file) in model.files2.iter().enumerate() {
+        let file_buffer_ids: Vec<BufferId> = file
+            .channels
+            .values()
+            .map(|channel| channel.buffer_id)
+            .filter(|&buffer_id| model.audio.get_buffer(buffer_id).is_ok())
+            .collect();
+        let nr_checked = file_buffer_ids
+            .iter()
+            .filter(|buffer_id| buffer_ids.contains(buffer_id))
+            .count();
+        let mut all_checked = nr_checked == file_buffer_ids.len();
+        let checkbox = egui::Checkbox::new(&mut all_checked, file_title(file))
+            .indeterminate(nr_checked > 0 && nr_checked < file_buffer_ids.len());
+        if ui.push_id(file_ix, |ui| ui.add(checkbox)).inner.changed() {
+            buffer_ids.retain(|buffer_id| !file_buffer_ids.contains(buffer_id));
+            if all_checked {
+                buffer_ids.extend(&file_buffer_ids);
+            }
+        }
+        ui.indent((file_ix, "channels"), |ui| {
+            for channel in file.channels.values() {
+                if model.audio.is_streamed(channel.buffer_id) {
+                    ui.add_enabled(
+                        false,
+                        egui::Checkbox::new(&mut false, channel_label(channel)),
+                    )
+                    .on_disabled_hover_text(STREAMED_BUFFER_HINT);
+                    continue;
+                }
+                if !file_buffer_ids.contains(&channel.buffer_id) {
+                    continue;
+                }
+                let mut checked = buffer_ids.contains(&channel.buffer_id);
+                if ui.checkbox(&mut checked, channel_label(channel)).changed() {
+                    buffer_ids.retain(|&buffer_id| buffer_id != channel.buffer_id);
+                    if checked {
+                        buffer_ids.push(channel.buffer_id);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    model::{FileVisibilityState, Model},
+    model::{Action, FileVisibilityState, Model},
     wav,
 };
 
@@ -38,7 +38,7 @@ pub fn ui(ui: &mut egui::Ui, model: &mut Model) {
             .map(|(file_ix, file)| FileRow {
                 file_ix,
                 title: file_title(file),
-                hover_text: format!("{file}"),
+                hover_text: file_hover_text(file),
                 visibility: model
                     .file_visibility_state_at(file_ix)
                     .unwrap_or(FileVisibilityState::NoneVisible),
@@ -78,7 +78,14 @@ pub fn ui(ui: &mut egui::Ui, model: &mut Model) {
                         let make_visible = row.visibility != FileVisibilityState::AllVisible;
                         model.set_file_visible_at(row.file_ix, make_visible);
                     }
-                    add_row_label(ui, &row.title).on_hover_text(row.hover_text);
+                    add_row_label(ui, &row.title)
+                        .on_hover_text(row.hover_text)
+                        .context_menu(|ui| {
+                            if ui.button("Close file").clicked() {
+                                model.actions.push(Action::CloseFile(row.file_ix));
+                                ui.close_menu();
+                            }
+                        });
                 })
                 .body(|ui| {
                     for channel in row.channels {
@@ -126,7 +133,7 @@ pub fn ui(ui: &mut egui::Ui, model: &mut Model) {
     });
 }
 
-fn file_title(file: &wav::file2::File) -> String {
+pub(crate) fn file_title(file: &wav::file2::File) -> String {
     file.path
         .as_ref()
         .and_then(|path| path.file_name())
@@ -135,7 +142,14 @@ fn file_title(file: &wav::file2::File) -> String {
         .unwrap_or_else(|| "Demo".to_string())
 }
 
-fn channel_label(channel: &wav::file2::Channel) -> String {
+fn file_hover_text(file: &wav::file2::File) -> String {
+    match &file.bext {
+        Some(bext) => format!("{file}\n\n{bext}"),
+        None => format!("{file}"),
+    }
+}
+
+pub(crate) fn channel_label(channel: &wav::file2::Channel) -> String {
     match channel.channel_id {
         Some(channel_id) => format!("ch {} - {}", channel.ch_ix, channel_id.long_name()),
         None => format!("ch {}", channel.ch_ix),
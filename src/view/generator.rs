@@ -0,0 +1,152 @@
+//! Generator window: add a test signal as a new file.
+
+use crate::{
+    generator::{GeneratorConfig, MAX_NR_CHANNELS, Waveform},
+    model::{Action, Model},
+    wav::write::WavFormat,
+};
+
+/// State of the generator window, kept in egui's temp data between frames so the last settings
+/// are there when it is opened again
+#[derive(Debug, Clone, Default)]
+struct GeneratorDialog {
+    open: bool,
+    config: GeneratorConfig,
+}
+
+fn dialog_id() -> egui::Id {
+    egui::Id::new("generator_dialog")
+}
+
+pub fn open(ctx: &egui::Context) {
+    ctx.data_mut(|data| {
+        data.get_temp_mut_or_default::<GeneratorDialog>(dialog_id())
+            .open = true;
+    });
+}
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let Some(mut dialog) = ctx.data(|data| data.get_temp::<GeneratorDialog>(dialog_id())) else {
+        return;
+    };
+    if !dialog.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Generate")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let config = &mut dialog.config;
+            egui::Grid::new("generator_grid")
+                .num_columns(2)
+                .show(ui, |ui| ui_config(ui, config));
+            let validation = config.validate();
+            if let Err(err) = &validation {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+            }
+            if ui
+                .add_enabled(validation.is_ok(), egui::Button::new("Generate"))
+                .clicked()
+            {
+                model.actions.push(Action::Generate(config.clone()));
+            }
+        });
+    dialog.open &= open;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
+
+/// Only the settings the waveform uses are shown
+fn ui_config(ui: &mut egui::Ui, config: &mut GeneratorConfig) {
+    ui.label("waveform");
+    egui::ComboBox::from_id_salt("generator_waveform")
+        .selected_text(config.waveform.name())
+        .show_ui(ui, |ui| {
+            for waveform in Waveform::ALL {
+                ui.selectable_value(&mut config.waveform, waveform, waveform.name());
+            }
+        });
+    ui.end_row();
+
+    let nyquist = config.sample_rate as f64 / 2.0;
+    if config.waveform != Waveform::Silence {
+        ui.label("amplitude");
+        ui.add(
+            egui::DragValue::new(&mut config.amplitude)
+                .range(0.0..=1.0)
+                .speed(0.01),
+        );
+        ui.end_row();
+    }
+    if config.waveform.uses_frequency() {
+        ui.label(if config.waveform.uses_end_frequency() {
+            "start frequency"
+        } else {
+            "frequency"
+        });
+        ui.add(
+            egui::DragValue::new(&mut config.frequency)
+                .range(1.0..=nyquist)
+                .suffix(" Hz"),
+        );
+        ui.end_row();
+    }
+    if config.waveform.uses_end_frequency() {
+        ui.label("end frequency");
+        ui.add(
+            egui::DragValue::new(&mut config.end_frequency)
+                .range(1.0..=nyquist)
+                .suffix(" Hz"),
+        );
+        ui.end_row();
+    }
+    if config.waveform == Waveform::Multitone {
+        ui.label("tones");
+        ui.add(egui::DragValue::new(&mut config.nr_tones).range(1..=1000));
+        ui.end_row();
+    }
+    if config.waveform.uses_seed() {
+        ui.label("seed");
+        ui.add(egui::DragValue::new(&mut config.seed));
+        ui.end_row();
+    }
+
+    ui.label("duration");
+    ui.add(
+        egui::DragValue::new(&mut config.duration_s)
+            .range(0.001..=3600.0)
+            .speed(0.1)
+            .suffix(" s"),
+    );
+    ui.end_row();
+
+    ui.label("sample rate");
+    egui::ComboBox::from_id_salt("generator_sample_rate")
+        .selected_text(format!("{} Hz", config.sample_rate))
+        .show_ui(ui, |ui| {
+            for sample_rate in [
+                8_000, 16_000, 22_050, 32_000, 44_100, 48_000, 88_200, 96_000, 192_000,
+            ] {
+                ui.selectable_value(
+                    &mut config.sample_rate,
+                    sample_rate,
+                    format!("{sample_rate} Hz"),
+                );
+            }
+        });
+    ui.end_row();
+
+    ui.label("format");
+    egui::ComboBox::from_id_salt("generator_format")
+        .selected_text(config.format.name())
+        .show_ui(ui, |ui| {
+            for format in WavFormat::ALL {
+                ui.selectable_value(&mut config.format, format, format.name());
+            }
+        });
+    ui.end_row();
+
+    ui.label("channels");
+    ui.add(egui::DragValue::new(&mut config.nr_channels).range(1..=MAX_NR_CHANNELS));
+    ui.end_row();
+}
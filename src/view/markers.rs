@@ -0,0 +1,170 @@
+use crate::{
+    model::{Action, Model},
+    view::ruler::markers::marker_colors,
+    wav::labels::{self, LabelFormat},
+};
+
+/// List of the markers on the time line, to rename, recolour, select or remove them
+pub fn ui_markers_side_panel(ui: &mut egui::Ui, model: &mut Model) {
+    if model.markers.is_empty() {
+        return;
+    }
+    let theme_colors = model.user_config.active_theme_colors(ui.visuals()).clone();
+    let time_format = model.tracks.ruler.time_format;
+    let rows: Vec<_> = model
+        .markers
+        .positioned(&model.tracks)
+        .into_iter()
+        .map(|(marker_id, marker, sample_ix)| {
+            let (color, _) = marker_colors(marker, &theme_colors);
+            (marker_id, marker.clone(), sample_ix, color)
+        })
+        .collect();
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.heading("Markers");
+            ui.separator();
+            egui::Grid::new(ui.id().with("markers"))
+                .num_columns(4)
+                .spacing([4.0, 2.0])
+                .show(ui, |ui| {
+                    for (marker_id, marker, sample_ix, mut color) in rows {
+                        if ui.color_edit_button_srgba(&mut color).changed() {
+                            model.actions.push(Action::SetMarkerColor {
+                                marker_id,
+                                color: Some(color),
+                            });
+                        }
+                        let mut label = marker.label.clone();
+                        let response =
+                            ui.add(egui::TextEdit::singleline(&mut label).desired_width(80.0));
+                        if response.changed() {
+                            model
+                                .actions
+                                .push(Action::RenameMarker { marker_id, label });
+                        }
+                        let position = time_format.format(sample_ix);
+                        if ui
+                            .small_button(position)
+                            .on_hover_text("Jump to the marker, or select the region")
+                            .clicked()
+                        {
+                            if marker.is_region() {
+                                model.actions.push(Action::SelectMarkerRegion(marker_id));
+                                model.actions.push(Action::ZoomToSelection);
+                            } else {
+                                model.actions.push(Action::CenterOnMarker(marker_id));
+                            }
+                        }
+                        if ui.small_button("x").on_hover_text("Remove").clicked() {
+                            model.actions.push(Action::RemoveMarker(marker_id));
+                        }
+                        ui.end_row();
+                    }
+                });
+            if !cfg!(target_arch = "wasm32") {
+                ui.separator();
+                if ui.button("Export markers…").clicked() {
+                    open(ui.ctx(), model);
+                }
+            }
+        });
+    });
+}
+
+/// State of the marker export window, kept in egui's temp data between frames
+#[derive(Debug, Clone)]
+struct ExportDialog {
+    open: bool,
+    path: String,
+    format: LabelFormat,
+    /// `path` exists, the user is asked whether to overwrite it
+    confirm_overwrite: bool,
+}
+
+fn dialog_id() -> egui::Id {
+    egui::Id::new("markers_export_dialog")
+}
+
+/// Open the export window, with the path used last or else the label track next to the first file,
+/// which is read along when that file is opened again.
+pub fn open(ctx: &egui::Context, model: &Model) {
+    let mut dialog = ctx
+        .data(|data| data.get_temp::<ExportDialog>(dialog_id()))
+        .unwrap_or_else(|| ExportDialog {
+            open: false,
+            path: model
+                .files2
+                .iter()
+                .find_map(|file| file.path.as_deref())
+                .map(labels::label_file_path)
+                .unwrap_or_else(|| "markers.txt".into())
+                .display()
+                .to_string(),
+            format: LabelFormat::Audacity,
+            confirm_overwrite: false,
+        });
+    dialog.open = true;
+    dialog.confirm_overwrite = false;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let Some(mut dialog) = ctx.data(|data| data.get_temp::<ExportDialog>(dialog_id())) else {
+        return;
+    };
+    if !dialog.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Export markers")
+        .open(&mut open)
+        .resizable(true)
+        .show(ctx, |ui| {
+            let response =
+                ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(f32::INFINITY));
+            if response.changed() {
+                dialog.confirm_overwrite = false;
+            }
+            ui.horizontal(|ui| {
+                for (format, text) in [
+                    (LabelFormat::Audacity, "Audacity labels"),
+                    (LabelFormat::Csv, "csv"),
+                ] {
+                    if ui.radio_value(&mut dialog.format, format, text).changed() {
+                        dialog.path = std::path::Path::new(&dialog.path)
+                            .with_extension(format.extension())
+                            .display()
+                            .to_string();
+                        dialog.confirm_overwrite = false;
+                    }
+                }
+            });
+            let path = std::path::PathBuf::from(&dialog.path);
+            let mut export = false;
+            if dialog.confirm_overwrite {
+                ui.label(format!("{} exists, overwrite it?", path.display()));
+                ui.horizontal(|ui| {
+                    export = ui.button("Overwrite").clicked();
+                    if ui.button("Cancel").clicked() {
+                        dialog.confirm_overwrite = false;
+                    }
+                });
+            } else if ui
+                .add_enabled(!dialog.path.is_empty(), egui::Button::new("Export"))
+                .clicked()
+            {
+                dialog.confirm_overwrite = path.exists();
+                export = !dialog.confirm_overwrite;
+            }
+            if export {
+                model.actions.push(Action::ExportMarkers {
+                    path,
+                    format: dialog.format,
+                });
+                dialog.open = false;
+            }
+        });
+    dialog.open &= open;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
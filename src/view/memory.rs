@@ -0,0 +1,50 @@
+//! Status readout of the memory held by sample buffers and their thumbnails.
+
+use crate::audio::manager::AudioManager;
+
+pub fn ui(ui: &mut egui::Ui, audio: &AudioManager) {
+    let usage = audio.memory_usage();
+    let streamed = if audio.streams.is_empty() {
+        String::new()
+    } else {
+        format!(", streamed {}", format_bytes(usage.streamed))
+    };
+    ui.label(format!(
+        "buffers {}, thumbnails {}{streamed}",
+        format_bytes(usage.buffers),
+        format_bytes(usage.thumbnails)
+    ))
+    .on_hover_text(format!(
+        "Memory held by the samples of {} buffers and their thumbnails",
+        audio.buffers.len()
+    ));
+}
+
+/// Size with a binary unit and one decimal, e.g. "1.5 GiB"
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit_ix = 0;
+    while size >= 1024.0 && unit_ix < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_ix += 1;
+    }
+    if unit_ix == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit_ix])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_are_formatted_with_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
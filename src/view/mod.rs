@@ -1,14 +1,22 @@
+pub mod action_error;
 pub mod config;
+pub mod export;
 pub mod file;
 pub mod fps;
+pub mod generator;
 pub mod grid;
+pub mod markers;
+pub mod memory;
+pub mod reload;
 pub mod ruler;
 pub mod selection_info;
+pub mod session;
+pub mod spectrum;
 pub mod track;
 pub mod util;
 pub mod value_ruler2;
 
-use crate::model::{Action, hover_info::HoverInfoE, shortcuts};
+use crate::model::{Action, hover_info::HoverInfoE, markers::JumpDirection, shortcuts};
 use crate::{model, wav};
 use anyhow::Result;
 use egui;
@@ -46,6 +54,23 @@ impl View {
         if self.model.drain_load_results() {
             ctx.request_repaint();
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.model.user_config.reload_on_change != model::file_watch::ReloadPolicy::Never
+            && !self.model.files2.is_empty()
+        {
+            self.model.watch_files();
+            ctx.request_repaint_after(model::file_watch::FileWatcher::POLL_INTERVAL);
+        }
+        self.model.update_selection_stats();
+        self.model.update_fft();
+        self.model.update_spectrograms();
+        if self.model.selection_stats.pending()
+            || self.model.fft.pending()
+            || self.model.spectrograms.pending()
+            || self.model.audio.is_reading_streams()
+        {
+            ctx.request_repaint();
+        }
 
         // Clear hover by default; hover interactions in this frame can override it.
         // TODO: move to ruler + tracks
@@ -72,12 +97,20 @@ impl View {
             tracing::error!("{}", e.backtrace());
         }
 
+        spectrum::ui(ctx, &mut self.model);
+        export::ui(ctx, &mut self.model);
+        generator::ui(ctx, &mut self.model);
+        session::ui(ctx, &mut self.model);
+        markers::ui(ctx, &mut self.model);
+        reload::ui(ctx, &mut self.model);
+        action_error::ui(ctx, &mut self.model);
         self.ui_loading_modal(ctx);
 
         let had_dropped_files = self.handle_drag_and_drop_into_app(ctx);
 
         // We don't stop the program when something fails, like opening a wav file.
         if let Err(e) = self.model.process_actions() {
+            self.model.action_error = Some(format!("{e:#}"));
             tracing::error!("Error processing actions");
             tracing::error!("{:#?}", e);
             tracing::error!("{}", e.backtrace());
@@ -89,12 +122,18 @@ impl View {
             .resizable(false)
             .min_height(0.0)
             .show(ctx, |ui| {
-                selection_info::ui_selection_info_toolbar(
-                    ui,
-                    &mut self.model.user_config,
-                    self.model.tracks.selection_info,
-                    &mut self.model.actions,
-                );
+                ui.horizontal(|ui| {
+                    selection_info::ui_selection_info_toolbar(
+                        ui,
+                        &mut self.model.user_config,
+                        self.model.tracks.selection_info,
+                        &self.model.tracks.ruler.time_format,
+                        &mut self.model.actions,
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        memory::ui(ui, &self.model.audio);
+                    });
+                });
                 // ui.vertical_centered(|ui| {
                 //     ui.heading("Bottom Panel");
                 // });
@@ -108,7 +147,11 @@ impl View {
             .width_range(80.0..=ctx.available_rect().width() / 1.5)
             .show(ctx, |ui| {
                 ui.add_space(5.0);
+                let place_at_time_reference = self.model.user_config.place_at_time_reference;
                 config::show_config(ui, &mut self.model.user_config);
+                if self.model.user_config.place_at_time_reference != place_at_time_reference {
+                    self.model.actions.push(Action::PlaceFilesAtTimeReference);
+                }
                 ui.add_space(5.0);
                 self.fps.ui(ui);
                 ui.add_space(5.0);
@@ -120,6 +163,12 @@ impl View {
                     ui,
                     &mut self.model.tracks.selection_info,
                 );
+                ui.add_space(5.0);
+                selection_info::ui_selection_stats_side_panel(ui, &self.model);
+                ui.add_space(5.0);
+                selection_info::ui_alignment_side_panel(ui, &self.model);
+                ui.add_space(5.0);
+                markers::ui_markers_side_panel(ui, &mut self.model);
             });
     }
 
@@ -168,6 +217,12 @@ impl View {
                     self.model
                         .actions
                         .push(Action::OpenFile(wav::ReadConfig::new(path)));
+                } else if let Some(path) = &file.path
+                    && path.extension() == Some(std::ffi::OsStr::new("txt"))
+                {
+                    self.model
+                        .actions
+                        .push(Action::ImportLabels(path.to_path_buf()));
                 }
             }
         });
@@ -183,6 +238,20 @@ impl View {
                 let is_web = cfg!(target_arch = "wasm32");
                 if !is_web {
                     ui.menu_button("File", |ui| {
+                        if ui.button("Open session…").clicked() {
+                            session::open(ctx, &self.model, session::SessionMode::Open);
+                            ui.close_menu();
+                        }
+                        if ui.button("Save session…").clicked() {
+                            session::open(ctx, &self.model, session::SessionMode::Save);
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button("Export selection…").clicked() {
+                            export::open(ctx, &self.model);
+                            ui.close_menu();
+                        }
+                        ui.separator();
                         if ui.button("Quit").clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
@@ -206,17 +275,52 @@ impl View {
             if ui.button("recenter y").clicked() {
                 self.model.actions.push(Action::RecenterYAll);
             }
+            if !self.model.markers.is_empty() {
+                if ui.button("prev marker").clicked() {
+                    self.model
+                        .actions
+                        .push(Action::JumpToMarker(JumpDirection::Previous));
+                }
+                if ui.button("next marker").clicked() {
+                    self.model
+                        .actions
+                        .push(Action::JumpToMarker(JumpDirection::Next));
+                }
+            }
+            ui.toggle_value(&mut self.model.fft.open, "spectrum")
+                .on_hover_text("Spectrum of the selection");
+            if ui
+                .button("generate")
+                .on_hover_text("Add a test signal as a new file")
+                .clicked()
+            {
+                generator::open(ctx);
+            }
             if cfg!(target_arch = "wasm32") && ui.button("load demo").clicked() {
                 self.model.actions.push(Action::LoadDemo);
             }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 ui.style_mut().spacing.window_margin = egui::Margin::same(4.0);
-                if ui.button("close all x").clicked() {
+                if ui
+                    .button("close all x")
+                    .on_hover_text("Close all files and free their memory")
+                    .clicked()
+                {
                     // × ✖ ❌ 🗑️
                     // if ui.button("✖").clicked() {
-                    self.model.actions.push(Action::RemoveAllTracks);
+                    self.model.actions.push(Action::CloseAllFiles);
                     // model.actions.push(Action::RemoveTrack(track_id));
                 }
+                ui.add_enabled_ui(!self.model.files2.is_empty(), |ui| {
+                    ui.menu_button("close file", |ui| {
+                        for (file_ix, file) in self.model.files2.iter().enumerate() {
+                            if ui.button(file::file_title(file)).clicked() {
+                                self.model.actions.push(Action::CloseFile(file_ix));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
             });
         });
     }
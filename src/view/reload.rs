@@ -0,0 +1,40 @@
+//! Asks to reload opened files that changed on disk, when `Config::reload_on_change` is `Ask`.
+
+use crate::model::{Action, Model};
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    if model.changed_files.is_empty() {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Files changed on disk")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let mut reload = Vec::new();
+            for path in &model.changed_files {
+                ui.horizontal(|ui| {
+                    if ui.button("reload").clicked() {
+                        reload.push(path.clone());
+                    }
+                    ui.label(path.display().to_string());
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Reload all").clicked() {
+                    reload = model.changed_files.clone();
+                }
+                if ui.button("Ignore").clicked() {
+                    model.changed_files.clear();
+                }
+            });
+            model
+                .actions
+                .extend(reload.into_iter().map(Action::ReloadFile));
+        });
+    if !open {
+        model.changed_files.clear();
+    }
+}
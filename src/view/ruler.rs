@@ -7,10 +7,12 @@ use anyhow::Result;
 use thousands::Separable;
 
 mod hover;
+pub(crate) mod markers;
 mod selection;
 mod ticks;
 
 pub use ticks::NR_PIXELS_PER_TICK;
+pub(crate) use ticks::format_compact;
 
 pub fn ui(ui: &mut egui::Ui, model: &mut model::Model) -> Result<()> {
     let container_rect = ui.min_rect();
@@ -44,9 +46,17 @@ pub fn ui(ui: &mut egui::Ui, model: &mut model::Model) -> Result<()> {
 
     // Update the screen rect of the ruler
     model.tracks.ruler.set_screen_rect(ruler_rect.into());
+    // and the time unit, keeping the last sample rate when no track is shown
+    let sample_rate = model
+        .tracks
+        .sample_rate(&model.audio)
+        .unwrap_or(model.tracks.ruler.time_format.sample_rate);
+    model.tracks.ruler.time_format = model.user_config.time.time_format(sample_rate);
 
-    // Do interactions
-    handle_drag_interaction(&mut ui_ruler, &response, &mut model.actions);
+    // Do interactions, dragging a marker moves it instead of panning
+    if !markers::ui_marker_interaction(&mut ui_ruler, model, &response) {
+        handle_drag_interaction(&mut ui_ruler, &response, &mut model.actions);
+    }
     handle_scroll_interaction(
         &mut ui_ruler,
         &mut model.actions,
@@ -55,6 +65,9 @@ pub fn ui(ui: &mut egui::Ui, model: &mut model::Model) -> Result<()> {
 
     // Draw stuff
     //
+    let theme_colors = model.user_config.active_theme_colors(ui.visuals()).clone();
+    markers::ui_markers(&mut ui_ruler, model, &theme_colors);
+    //
     // We get the hover text rect so we can avoid it when drawing ix lattice labels
     let hover_tick_label_rect = hover::ui_hover_interaction_and_tick(&mut ui_ruler, model)?;
     let mut existing_tick_label_rects = hover_tick_label_rect.into_iter().collect::<Vec<_>>();
@@ -1,9 +1,9 @@
 use crate::model::{
     self, Action,
     hover_info::{HoverInfo, HoverInfoE},
+    ruler,
 };
 use anyhow::Result;
-use thousands::Separable;
 
 use super::ticks::{self, TickLabel, TriangleType};
 
@@ -38,18 +38,22 @@ pub(crate) fn ui_hover_interaction_and_tick(
     let mut hover_text_rect = None;
     if let HoverInfoE::IsHovered(hover_info) = &model.tracks.hover_info {
         let theme_colors = model.user_config.active_theme_colors(ui.visuals());
-        hover_text_rect = ui_hover_tick_label(ui, hover_info);
+        hover_text_rect = ui_hover_tick_label(ui, hover_info, &model.tracks.ruler.time_format);
         ui_hover_tick_line_triangle(ui, hover_info, theme_colors.accent);
     }
     Ok(hover_text_rect)
 }
 
-fn ui_hover_tick_label(ui: &mut egui::Ui, hover_info: &HoverInfo) -> Option<egui::Rect> {
+fn ui_hover_tick_label(
+    ui: &mut egui::Ui,
+    hover_info: &HoverInfo,
+    time_format: &ruler::TimeFormat,
+) -> Option<egui::Rect> {
     let sample_ix = hover_info.sample_ix.round() as i64;
     ticks::ui_tick_label(
         ui,
         hover_info.screen_pos.x,
-        TickLabel::Text(sample_ix.separate_with_commas()),
+        TickLabel::for_sample_ix(time_format, sample_ix, false),
         None,
         true,
     )
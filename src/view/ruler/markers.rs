@@ -0,0 +1,242 @@
+use crate::model::{
+    self, Action,
+    config::ThemeColors,
+    markers::{Marker, MarkerId},
+    selection_info::SelectionInfoE,
+};
+use crate::view::util::rpc;
+
+/// Below the tick labels, above the ticks
+const LABEL_OFFSET_Y: f32 = 21.0;
+const REGION_BAND_HEIGHT: f32 = 16.0;
+/// Distance in pixels from a marker line within which the pointer grabs the marker
+const MARKER_HIT_SLACK_PX: f32 = 4.0;
+
+/// Marker the pointer is on when a drag starts or the context menu opens
+#[derive(Clone, Copy)]
+struct MarkerInteraction {
+    marker_id: Option<MarkerId>,
+    sample_ix: f64,
+}
+
+/// Screen x of the start and end of a marker positioned at `sample_ix`, the end only for a
+/// region. None when it is completely outside `rect`.
+pub(crate) fn marker_screen_x_range(
+    model: &model::Model,
+    marker: &Marker,
+    sample_ix: f64,
+    rect: egui::Rect,
+) -> Option<(f32, Option<f32>)> {
+    let start_x = model.tracks.sample_ix_to_screen_x(sample_ix)?;
+    let end_x = marker
+        .length
+        .and_then(|length| model.tracks.sample_ix_to_screen_x(sample_ix + length));
+    let visible = start_x <= rect.right() && end_x.unwrap_or(start_x) >= rect.left();
+    visible.then_some((start_x, end_x))
+}
+
+/// Line and region colour of a marker, its own colour or the one of the theme
+pub(crate) fn marker_colors(
+    marker: &Marker,
+    theme_colors: &ThemeColors,
+) -> (egui::Color32, egui::Color32) {
+    match marker.color {
+        Some(color) => {
+            let [r, g, b, _] = color.to_array();
+            let alpha = theme_colors.region_fill.a();
+            (color, egui::Color32::from_rgba_unmultiplied(r, g, b, alpha))
+        }
+        None => (theme_colors.marker, theme_colors.region_fill),
+    }
+}
+
+/// Draw a line per marker with its label next to it, and a band between the edges of regions.
+/// Labels that would overlap the label of an earlier marker are left out.
+pub fn ui_markers(ui: &mut egui::Ui, model: &model::Model, theme_colors: &ThemeColors) {
+    let rect = ui.min_rect();
+    let font_id = egui::FontId::proportional(12.0);
+    let mut label_rects: Vec<egui::Rect> = Vec::new();
+    for (_, marker, sample_ix) in model.markers.positioned(&model.tracks) {
+        let Some((start_x, end_x)) = marker_screen_x_range(model, marker, sample_ix, rect) else {
+            continue;
+        };
+        let (color, region_fill) = marker_colors(marker, theme_colors);
+        if let Some(end_x) = end_x {
+            ui.painter().rect(
+                region_band(rect, start_x, end_x),
+                0.0,
+                region_fill,
+                egui::Stroke::NONE,
+            );
+        }
+        for x in std::iter::once(start_x).chain(end_x) {
+            if rect.x_range().contains(x) {
+                let top = rpc(ui, [x, rect.top()].into());
+                let bottom = rpc(ui, [x, rect.bottom()].into());
+                ui.painter().line_segment([top, bottom], (1.0, color));
+            }
+        }
+
+        if marker.label.is_empty() {
+            continue;
+        }
+        let galley =
+            ui.fonts(|fonts| fonts.layout_no_wrap(marker.label.clone(), font_id.clone(), color));
+        let text_pos = egui::pos2(start_x.max(rect.left()) + 3.0, rect.top() + LABEL_OFFSET_Y);
+        let text_rect = egui::Rect::from_min_size(text_pos, galley.size()).expand(1.0);
+        if text_rect.right() > rect.right()
+            || label_rects.iter().any(|other| other.intersects(text_rect))
+        {
+            continue;
+        }
+        ui.painter().galley(text_pos, galley, color);
+        label_rects.push(text_rect);
+    }
+}
+
+fn region_band(rect: egui::Rect, start_x: f32, end_x: f32) -> egui::Rect {
+    let band_top = rect.top() + LABEL_OFFSET_Y - 1.0;
+    egui::Rect::from_x_y_ranges(
+        start_x..=end_x.max(start_x + 1.0),
+        band_top..=band_top + REGION_BAND_HEIGHT,
+    )
+    .intersect(rect)
+}
+
+/// The marker whose line is closest to `pos`, or else the region whose band contains `pos`
+fn hovered_marker(model: &model::Model, rect: egui::Rect, pos: egui::Pos2) -> Option<MarkerId> {
+    let mut closest: Option<(MarkerId, f32)> = None;
+    let mut region = None;
+    for (marker_id, marker, sample_ix) in model.markers.positioned(&model.tracks) {
+        let Some((start_x, end_x)) = marker_screen_x_range(model, marker, sample_ix, rect) else {
+            continue;
+        };
+        for x in std::iter::once(start_x).chain(end_x) {
+            let dist = (x - pos.x).abs();
+            if dist <= MARKER_HIT_SLACK_PX && closest.is_none_or(|(_, closest)| dist < closest) {
+                closest = Some((marker_id, dist));
+            }
+        }
+        if let Some(end_x) = end_x
+            && region_band(rect, start_x, end_x).contains(pos)
+        {
+            region = Some(marker_id);
+        }
+    }
+    closest.map(|(marker_id, _)| marker_id).or(region)
+}
+
+/// Drag markers, select regions with a double click and edit markers in the context menu.
+/// Returns true when the pointer interacts with a marker, so the ruler doesn't pan.
+pub fn ui_marker_interaction(
+    ui: &mut egui::Ui,
+    model: &mut model::Model,
+    response: &egui::Response,
+) -> bool {
+    let rect = ui.min_rect();
+    let drag_id = response.id.with("marker_drag");
+    let menu_id = response.id.with("marker_menu");
+    let hover_pos = response.hover_pos();
+    let pointer_interaction = |model: &model::Model, pos: egui::Pos2| MarkerInteraction {
+        marker_id: hovered_marker(model, rect, pos),
+        sample_ix: model.tracks.screen_x_to_sample_ix(pos.x).unwrap_or(0.0),
+    };
+
+    if response.drag_started()
+        && let Some(pos) = response.interact_pointer_pos()
+        && let Some(marker_id) = hovered_marker(model, rect, pos)
+    {
+        ui.data_mut(|data| data.insert_temp(drag_id, marker_id));
+    }
+    let dragged_marker = ui.data(|data| data.get_temp::<MarkerId>(drag_id));
+    if response.drag_stopped() {
+        ui.data_mut(|data| data.remove_temp::<MarkerId>(drag_id));
+    }
+    if let Some(marker_id) = dragged_marker
+        && let Some(pos) = response.interact_pointer_pos()
+        && let Some(sample_ix) = model.tracks.screen_x_to_sample_ix(pos.x)
+    {
+        model.actions.push(Action::MoveMarker {
+            marker_id,
+            sample_ix: sample_ix.round(),
+        });
+    }
+
+    let hovered = hover_pos.and_then(|pos| hovered_marker(model, rect, pos));
+    if hovered.is_some() || dragged_marker.is_some() {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+    }
+    if response.double_clicked()
+        && let Some(marker_id) = hovered
+        && model.markers.get(marker_id).is_some_and(Marker::is_region)
+    {
+        model.actions.push(Action::SelectMarkerRegion(marker_id));
+    }
+
+    if response.secondary_clicked()
+        && let Some(pos) = response.interact_pointer_pos()
+    {
+        let interaction = pointer_interaction(model, pos);
+        ui.data_mut(|data| data.insert_temp(menu_id, interaction));
+    }
+    response.context_menu(|ui| {
+        let interaction = ui.data(|data| data.get_temp::<MarkerInteraction>(menu_id));
+        let interaction = interaction.unwrap_or(MarkerInteraction {
+            marker_id: None,
+            sample_ix: 0.0,
+        });
+        match interaction.marker_id {
+            Some(marker_id) if model.markers.get(marker_id).is_some() => {
+                ui_marker_menu(ui, model, marker_id);
+            }
+            _ => {
+                ui_add_marker_menu(ui, model, interaction.sample_ix);
+                ui.separator();
+                crate::view::config::ui_time_config(ui, &mut model.user_config.time);
+            }
+        }
+    });
+
+    dragged_marker.is_some()
+}
+
+fn ui_marker_menu(ui: &mut egui::Ui, model: &mut model::Model, marker_id: MarkerId) {
+    let Some(marker) = model.markers.get(marker_id) else {
+        return;
+    };
+    let mut label = marker.label.clone();
+    let is_region = marker.is_region();
+    if ui.text_edit_singleline(&mut label).changed() {
+        model
+            .actions
+            .push(Action::RenameMarker { marker_id, label });
+    }
+    if is_region && ui.button("Select region").clicked() {
+        model.actions.push(Action::SelectMarkerRegion(marker_id));
+        ui.close_menu();
+    }
+    if ui.button("Remove marker").clicked() {
+        model.actions.push(Action::RemoveMarker(marker_id));
+        ui.close_menu();
+    }
+}
+
+fn ui_add_marker_menu(ui: &mut egui::Ui, model: &mut model::Model, sample_ix: f64) {
+    let label = format!("marker {}", model.markers.len() + 1);
+    if ui.button("Add marker here").clicked() {
+        model.actions.push(Action::AddMarker(Marker::point(
+            sample_ix.round(),
+            label.clone(),
+        )));
+        ui.close_menu();
+    }
+    if let SelectionInfoE::IsSelected(selection_info) = model.tracks.selection_info
+        && ui.button("Add region from selection").clicked()
+    {
+        model.actions.push(Action::AddMarker(Marker::region(
+            selection_info.ix_rng,
+            label,
+        )));
+        ui.close_menu();
+    }
+}
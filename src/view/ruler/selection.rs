@@ -1,6 +1,5 @@
 use crate::model::{self, selection_info::SelectionInfoE};
 use anyhow::Result;
-use thousands::Separable;
 
 use super::ticks::{self, TickLabel, TriangleType};
 
@@ -26,6 +25,7 @@ pub fn ui_selection_interaction_and_tics(
 
     let mut result = Vec::new();
     let accent = model.user_config.active_theme_colors(ui.visuals()).accent;
+    let time_format = model.tracks.ruler.time_format;
 
     // For each visible selection edge, draw its tick/triangle immediately and keep the
     // corresponding label payload around for the placement pass below.
@@ -38,7 +38,10 @@ pub fn ui_selection_interaction_and_tics(
         .map(|left_x| {
             ticks::ui_tick_line(ui, left_x, ticks::TICK_HEIGHT_LONG, None);
             ticks::ui_triangle(ui, left_x, TriangleType::Left, accent);
-            (left_x, TickLabel::Text(left_ix.separate_with_commas()))
+            (
+                left_x,
+                TickLabel::for_sample_ix(&time_format, left_ix, false),
+            )
         });
 
     let right_ix = selection_ix_range.end - 1;
@@ -49,7 +52,10 @@ pub fn ui_selection_interaction_and_tics(
         .map(|right_x| {
             ticks::ui_tick_line(ui, right_x, ticks::TICK_HEIGHT_LONG, None);
             ticks::ui_triangle(ui, right_x - 1.0, TriangleType::Right, accent);
-            (right_x, TickLabel::Text(right_ix.separate_with_commas()))
+            (
+                right_x,
+                TickLabel::for_sample_ix(&time_format, right_ix, false),
+            )
         });
 
     // If both labels are visible, try the paired placement logic first so they can resolve
@@ -60,7 +66,7 @@ pub fn ui_selection_interaction_and_tics(
                 && let Some(rect) = ticks::ui_tick_label(
                     ui,
                     left_x,
-                    TickLabel::SampleIx(left_ix),
+                    TickLabel::for_sample_ix(&time_format, left_ix, true),
                     Some(existing_rects.as_slice()),
                     true,
                 )
@@ -76,7 +82,7 @@ pub fn ui_selection_interaction_and_tics(
             } else if let Some(rect) = ticks::ui_tick_label(
                 ui,
                 left_x,
-                TickLabel::SampleIx(left_ix),
+                TickLabel::for_sample_ix(&time_format, left_ix, true),
                 Some(existing_rects.as_slice()),
                 true,
             ) {
@@ -87,7 +93,7 @@ pub fn ui_selection_interaction_and_tics(
             if let Some(rect) = ticks::ui_tick_label(
                 ui,
                 left_x,
-                TickLabel::SampleIx(left_ix),
+                TickLabel::for_sample_ix(&time_format, left_ix, true),
                 Some(existing_rects.as_slice()),
                 true,
             ) {
@@ -98,7 +104,7 @@ pub fn ui_selection_interaction_and_tics(
             if let Some(rect) = ticks::ui_tick_label(
                 ui,
                 right_x,
-                TickLabel::SampleIx(right_ix),
+                TickLabel::for_sample_ix(&time_format, right_ix, true),
                 Some(existing_rects.as_slice()),
                 true,
             ) {
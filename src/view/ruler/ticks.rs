@@ -1,6 +1,9 @@
 use crate::{
     audio::sample,
-    model::{self, ruler},
+    model::{
+        self,
+        ruler::{self, TimeFormat, TimeUnit},
+    },
     view::util::{rp, rpc},
 };
 use thousands::Separable;
@@ -29,6 +32,17 @@ impl From<String> for TickLabel {
     }
 }
 
+impl TickLabel {
+    /// Label of a single sample ix, e.g. the hover position or a selection edge. Sample indices
+    /// are shown in full, or `compact` as e.g. 1.5k.
+    pub(crate) fn for_sample_ix(time_format: &TimeFormat, sample_ix: i64, compact: bool) -> Self {
+        match time_format.unit {
+            TimeUnit::Samples if compact => TickLabel::SampleIx(sample_ix),
+            _ => TickLabel::Text(time_format.format(sample_ix as f64)),
+        }
+    }
+}
+
 #[derive(Clone)]
 struct TickLabelLayout {
     galley: std::sync::Arc<egui::Galley>,
@@ -250,9 +264,14 @@ pub(crate) fn ui_ix_lattice(
     ruler: &mut model::ruler::Time,
     existing_rects: &mut Vec<egui::Rect>,
 ) {
+    let time_format = ruler.time_format;
     let Some(ix_lattice) = ruler.ix_lattice() else {
         return;
     };
+    let label = |tick: &ruler::Tick| match (time_format.unit, ix_lattice.spacing) {
+        (TimeUnit::Samples, _) | (_, None) => TickLabel::SampleIx(tick.sample_ix.round() as i64),
+        (_, Some(spacing)) => TickLabel::Text(time_format.tick_label(tick.sample_ix, &spacing)),
+    };
 
     for tick in &ix_lattice.ticks {
         let tick_height = match tick.tick_type {
@@ -266,7 +285,7 @@ pub(crate) fn ui_ix_lattice(
             let rect = ui_tick_label(
                 ui,
                 tick.screen_x,
-                tick.sample_ix.into(),
+                label(tick),
                 Some(existing_rects.as_slice()),
                 false,
             );
@@ -281,7 +300,7 @@ pub(crate) fn ui_ix_lattice(
             let rect = ui_tick_label(
                 ui,
                 tick.screen_x,
-                tick.sample_ix.into(),
+                label(tick),
                 Some(existing_rects.as_slice()),
                 false,
             );
@@ -346,7 +365,7 @@ fn paint_tick_label(ui: &mut egui::Ui, layout: &TickLabelLayout, draw_rect: bool
     }
 }
 
-fn format_compact(n: i64) -> String {
+pub(crate) fn format_compact(n: i64) -> String {
     format_compact_exact(n, 2)
 }
 
@@ -1,7 +1,12 @@
+use crate::audio::buffer::BufferE;
+use crate::audio::manager::STREAMED_BUFFER_HINT;
 use crate::model::{
-    self, Action,
+    self, Action, Model,
+    align::AlignTarget,
     config::StartEditMode,
+    ruler::{TimeFormat, TimeUnit},
     selection_info::{SelectionInfo, SelectionInfoE},
+    track::TrackId,
 };
 use egui_custom_widgets::DigitwiseNumberEditor;
 
@@ -41,10 +46,151 @@ pub fn ui_selection_info_side_panel(ui: &mut egui::Ui, selection_info: &mut Sele
     });
 }
 
+/// Level statistics per visible track for the selection, or the whole track without selection
+pub fn ui_selection_stats_side_panel(ui: &mut egui::Ui, model: &Model) {
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.heading("Selection Stats");
+            ui.separator();
+            if model.selection_stats.pending() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("computing");
+                });
+                return;
+            }
+            if !model.tracks.selection_info.is_selected() {
+                ui.label("whole tracks");
+            }
+            for track_stats in &model.selection_stats.tracks {
+                let stats = &track_stats.stats;
+                // f64 values get all their digits, less would hide what f64 data is about
+                let is_f64 = model
+                    .tracks
+                    .get_track(track_stats.track_id)
+                    .and_then(|track| model.audio.get_buffer(track.single.item.buffer_id).ok())
+                    .is_some_and(|buffer| matches!(buffer, BufferE::F64(_)));
+                let format_value = |value: f64| {
+                    if is_f64 {
+                        value.to_string()
+                    } else {
+                        format!("{value:.6}")
+                    }
+                };
+                ui.label(egui::RichText::new(track_label(model, track_stats.track_id)).strong());
+                egui::Grid::new(ui.id().with(("selection_stats", track_stats.track_id)))
+                    .num_columns(2)
+                    .spacing([8.0, 2.0])
+                    .show(ui, |ui| {
+                        let mut row = |name: &str, value: String| {
+                            ui.label(name);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("min", format_value(stats.min));
+                        row("max", format_value(stats.max));
+                        row("peak", format!("{:.2} dBFS", stats.peak_db()));
+                        row("RMS", format!("{:.2} dBFS", stats.rms_db()));
+                        row("DC", format_value(stats.dc));
+                        let crest_factor = stats.crest_factor();
+                        row(
+                            "crest",
+                            if crest_factor.is_finite() {
+                                format!("{crest_factor:.3} ({:.2} dB)", stats.crest_factor_db())
+                            } else {
+                                String::from("-")
+                            },
+                        );
+                        row("clipped", stats.nr_clipped.to_string());
+                    });
+            }
+            let streamed_track_ids = model.tracks.tracks_order.iter().filter(|&&track_id| {
+                model.tracks.get_track(track_id).is_some_and(|track| {
+                    track.visible && model.audio.is_streamed(track.single.item.buffer_id)
+                })
+            });
+            for &track_id in streamed_track_ids {
+                ui.label(egui::RichText::new(track_label(model, track_id)).strong());
+                ui.label(egui::RichText::new(STREAMED_BUFFER_HINT).weak());
+            }
+        });
+    });
+}
+
+/// Lag and correlation peak found by the last alignment
+pub fn ui_alignment_side_panel(ui: &mut egui::Ui, model: &Model) {
+    let Some(alignment) = model.alignment.as_ref() else {
+        return;
+    };
+    let (track_id, what) = match alignment.target {
+        AlignTarget::DiffB(track_id) => (track_id, "B to A"),
+        AlignTarget::Overlay { track_id, .. } => (track_id, "overlay"),
+        AlignTarget::Track { track_id, .. } => (track_id, "track"),
+    };
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.heading("Alignment");
+            ui.separator();
+            ui.label(
+                egui::RichText::new(format!("{} ({what})", track_label(model, track_id))).strong(),
+            );
+            egui::Grid::new(ui.id().with("alignment"))
+                .num_columns(2)
+                .spacing([8.0, 2.0])
+                .show(ui, |ui| {
+                    let lag = alignment.lag;
+                    let mut row = |name: &str, value: String| {
+                        ui.label(name);
+                        ui.label(value);
+                        ui.end_row();
+                    };
+                    row("lag", format!("{:.2} samples", lag.samples));
+                    row("", format!("{:.3} ms", lag.ms(alignment.sample_rate)));
+                    row("peak", format!("{:.3}", lag.peak));
+                    row("over", format!("{} samples", alignment.nr_samples));
+                });
+        });
+    });
+}
+
+/// Short label of a track: file name and channel index
+pub fn track_label(model: &Model, track_id: TrackId) -> String {
+    if let Some((file, channel)) = model.get_file_channel_for_track(track_id) {
+        return file_channel_label(file, channel);
+    }
+    let is_diff = model
+        .tracks
+        .get_track(track_id)
+        .is_some_and(|track| track.diff.is_some());
+    String::from(if is_diff { "A-B" } else { "track" })
+}
+
+/// Short label of a buffer of a file: "file.wav - ch N"
+pub fn buffer_label(model: &Model, buffer_id: crate::audio::BufferId) -> String {
+    match model.find_file_channel(buffer_id) {
+        Some((file, channel)) => file_channel_label(file, channel),
+        None => String::from("unknown"),
+    }
+}
+
+fn file_channel_label(
+    file: &crate::wav::file2::File,
+    channel: &crate::wav::file2::Channel,
+) -> String {
+    let name = file
+        .path
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .and_then(|p| p.to_str())
+        .unwrap_or("unknown");
+    format!("{name} - ch {}", channel.ch_ix)
+}
+
 pub fn ui_selection_info_toolbar(
     ui: &mut egui::Ui,
     config: &mut model::Config,
     selection_info: SelectionInfoE,
+    time_format: &TimeFormat,
     actions: &mut Vec<Action>,
 ) {
     let has_selection = selection_info.is_selected();
@@ -71,6 +217,12 @@ pub fn ui_selection_info_toolbar(
                 )
             }
         };
+    // the editors are in samples, other units are shown next to them
+    let ui_time = |ui: &mut egui::Ui, nr_samples: u64| {
+        if time_format.unit != TimeUnit::Samples && had_selection {
+            ui.monospace(time_format.format(nr_samples as f64));
+        }
+    };
 
     ui.group(|ui| {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
@@ -99,7 +251,7 @@ pub fn ui_selection_info_toolbar(
 
             egui::Grid::new(ui.id().with("selection_toolbar_grid"))
                 .striped(true)
-                .num_columns(2)
+                .num_columns(3)
                 .spacing([8.0, 4.0])
                 .show(ui, |ui| {
                     ui.label("start");
@@ -109,6 +261,7 @@ pub fn ui_selection_info_toolbar(
                         .dim_leading_zeroes(true)
                         .max(SELECTION_EDITOR_MAX)
                         .show(ui);
+                    ui_time(ui, start_val);
                     ui.end_row();
 
                     ui.label("length");
@@ -119,6 +272,7 @@ pub fn ui_selection_info_toolbar(
                             .dim_leading_zeroes(true)
                             .max(SELECTION_EDITOR_MAX)
                             .show(ui);
+                    ui_time(ui, length_val);
                     ui.end_row();
 
                     ui.label("end");
@@ -128,6 +282,7 @@ pub fn ui_selection_info_toolbar(
                         .dim_leading_zeroes(true)
                         .max(SELECTION_EDITOR_MAX)
                         .show(ui);
+                    ui_time(ui, end_val);
                     ui.end_row();
 
                     let any_changed = out_start.changed || out_length.changed || out_end.changed;
@@ -0,0 +1,75 @@
+//! Session window: save the open files and how they are shown, or restore them.
+
+use crate::model::{Action, Model};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionMode {
+    #[default]
+    Open,
+    Save,
+}
+
+/// State of the session window, kept in egui's temp data between frames
+#[derive(Debug, Clone, Default)]
+struct SessionDialog {
+    open: bool,
+    mode: SessionMode,
+    path: String,
+}
+
+fn dialog_id() -> egui::Id {
+    egui::Id::new("session_dialog")
+}
+
+/// Open the session window, with the path used last or else a session next to the first file
+pub fn open(ctx: &egui::Context, model: &Model, mode: SessionMode) {
+    let mut dialog = ctx
+        .data(|data| data.get_temp::<SessionDialog>(dialog_id()))
+        .unwrap_or_default();
+    if dialog.path.is_empty() {
+        dialog.path = model
+            .files2
+            .iter()
+            .find_map(|file| file.path.as_deref())
+            .map(|path| path.with_file_name("session.toml"))
+            .unwrap_or_else(|| "session.toml".into())
+            .display()
+            .to_string();
+    }
+    dialog.open = true;
+    dialog.mode = mode;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let Some(mut dialog) = ctx.data(|data| data.get_temp::<SessionDialog>(dialog_id())) else {
+        return;
+    };
+    if !dialog.open {
+        return;
+    }
+    let (title, button) = match dialog.mode {
+        SessionMode::Open => ("Open session", "Open"),
+        SessionMode::Save => ("Save session", "Save"),
+    };
+    let mut open = true;
+    egui::Window::new(title)
+        .open(&mut open)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(f32::INFINITY));
+            if ui
+                .add_enabled(!dialog.path.is_empty(), egui::Button::new(button))
+                .clicked()
+            {
+                let path = dialog.path.clone().into();
+                model.actions.push(match dialog.mode {
+                    SessionMode::Open => Action::LoadSession(path),
+                    SessionMode::Save => Action::SaveSession(path),
+                });
+                dialog.open = false;
+            }
+        });
+    dialog.open &= open;
+    ctx.data_mut(|data| data.insert_temp(dialog_id(), dialog));
+}
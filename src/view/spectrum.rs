@@ -0,0 +1,376 @@
+//! Spectrum panel: magnitude spectrum of the selection for the visible tracks.
+
+use crate::{
+    audio::{
+        manager::STREAMED_BUFFER_HINT,
+        spectrum::{Averaging, FFT_SIZES, Spectrum},
+        window::WindowFunction,
+    },
+    model::{Model, track::TrackId},
+    view::selection_info::track_label,
+};
+use strum::IntoEnumIterator;
+
+/// Colors of the spectrum curves, by track order
+const TRACK_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(0x4f, 0xa3, 0xff),
+    egui::Color32::from_rgb(0xff, 0x9f, 0x43),
+    egui::Color32::from_rgb(0x5f, 0xd0, 0x68),
+    egui::Color32::from_rgb(0xe8, 0x5a, 0x5a),
+    egui::Color32::from_rgb(0xb3, 0x88, 0xff),
+    egui::Color32::from_rgb(0xe0, 0xd0, 0x50),
+];
+
+/// Lowest frequency of the log frequency axis
+const LOG_MIN_FREQUENCY: f64 = 10.0;
+
+pub fn track_color(track_ix: usize) -> egui::Color32 {
+    TRACK_COLORS[track_ix % TRACK_COLORS.len()]
+}
+
+pub fn ui(ctx: &egui::Context, model: &mut Model) {
+    let mut open = model.fft.open;
+    egui::Window::new("Spectrum")
+        .open(&mut open)
+        .default_size([640.0, 320.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui_settings(ui, model);
+            ui_tracks(ui, model);
+            ui_plot(ui, model);
+        });
+    model.fft.open = open;
+}
+
+fn ui_settings(ui: &mut egui::Ui, model: &mut Model) {
+    let fft = &mut model.fft;
+    ui.horizontal_wrapped(|ui| {
+        egui::ComboBox::from_label("window")
+            .selected_text(fft.config.window.name())
+            .show_ui(ui, |ui| {
+                for window in WindowFunction::iter() {
+                    ui.selectable_value(&mut fft.config.window, window, window.name());
+                }
+            });
+        egui::ComboBox::from_label("FFT size")
+            .selected_text(fft.config.fft_size.to_string())
+            .show_ui(ui, |ui| {
+                for fft_size in FFT_SIZES {
+                    ui.selectable_value(&mut fft.config.fft_size, fft_size, fft_size.to_string());
+                }
+            });
+        egui::ComboBox::from_label("averaging")
+            .selected_text(fft.config.averaging.name())
+            .show_ui(ui, |ui| {
+                for averaging in Averaging::iter() {
+                    ui.selectable_value(&mut fft.config.averaging, averaging, averaging.name());
+                }
+            });
+        ui.checkbox(&mut fft.log_frequency, "log frequency");
+        ui.add(
+            egui::DragValue::new(&mut fft.min_db)
+                .range(-300.0..=-20.0)
+                .suffix(" dB")
+                .speed(1.0),
+        )
+        .on_hover_text("Bottom of the level axis");
+        if fft.pending() {
+            ui.spinner();
+        }
+    });
+}
+
+/// Checkbox per visible track to include it in the spectrum, in the color of its curve
+fn ui_tracks(ui: &mut egui::Ui, model: &mut Model) {
+    let track_ids: Vec<TrackId> = model
+        .tracks
+        .tracks_order
+        .iter()
+        .copied()
+        .filter(|&track_id| {
+            model
+                .tracks
+                .get_track(track_id)
+                .is_some_and(|track| track.visible)
+        })
+        .collect();
+    ui.horizontal_wrapped(|ui| {
+        for (track_ix, track_id) in track_ids.into_iter().enumerate() {
+            let mut included = !model.fft.excluded_track_ids.contains(&track_id);
+            let label =
+                egui::RichText::new(track_label(model, track_id)).color(track_color(track_ix));
+            let is_streamed = model
+                .tracks
+                .get_track(track_id)
+                .is_some_and(|track| model.audio.is_streamed(track.single.item.buffer_id));
+            if is_streamed {
+                ui.add_enabled(false, egui::Checkbox::new(&mut false, label))
+                    .on_disabled_hover_text(STREAMED_BUFFER_HINT);
+                continue;
+            }
+            if ui.checkbox(&mut included, label).changed() {
+                if included {
+                    model.fft.excluded_track_ids.remove(&track_id);
+                } else {
+                    model.fft.excluded_track_ids.insert(track_id);
+                }
+            }
+        }
+    });
+}
+
+/// Maps frequencies and levels to screen positions in the plot rect
+struct PlotTransform {
+    rect: egui::Rect,
+    min_frequency: f64,
+    max_frequency: f64,
+    log_frequency: bool,
+    min_db: f64,
+}
+
+impl PlotTransform {
+    fn frequency_to_x(&self, frequency: f64) -> f32 {
+        let t = if self.log_frequency {
+            (frequency.max(self.min_frequency) / self.min_frequency).ln()
+                / (self.max_frequency / self.min_frequency).ln()
+        } else {
+            (frequency - self.min_frequency) / (self.max_frequency - self.min_frequency)
+        };
+        self.rect.left() + t as f32 * self.rect.width()
+    }
+
+    fn x_to_frequency(&self, x: f32) -> f64 {
+        let t = ((x - self.rect.left()) / self.rect.width()) as f64;
+        if self.log_frequency {
+            self.min_frequency * (self.max_frequency / self.min_frequency).powf(t)
+        } else {
+            self.min_frequency + t * (self.max_frequency - self.min_frequency)
+        }
+    }
+
+    fn db_to_y(&self, db: f64) -> f32 {
+        let t = (db / self.min_db).clamp(0.0, 1.0);
+        self.rect.top() + t as f32 * self.rect.height()
+    }
+}
+
+fn ui_plot(ui: &mut egui::Ui, model: &Model) {
+    let size = ui.available_size().max(egui::vec2(100.0, 80.0));
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let visuals = ui.visuals();
+    painter.rect_filled(response.rect, 0.0, visuals.extreme_bg_color);
+
+    let fft = &model.fft;
+    let Some(max_frequency) = fft
+        .spectra
+        .iter()
+        .map(|track| track.spectrum.nyquist())
+        .reduce(f64::max)
+    else {
+        painter.text(
+            response.rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "no spectrum",
+            egui::FontId::proportional(14.0),
+            visuals.weak_text_color(),
+        );
+        return;
+    };
+
+    let font_id = egui::FontId::monospace(10.0);
+    let label_width = 48.0;
+    let transform = PlotTransform {
+        rect: egui::Rect::from_min_max(
+            response.rect.min + egui::vec2(label_width, 4.0),
+            response.rect.max - egui::vec2(4.0, 14.0),
+        ),
+        min_frequency: if fft.log_frequency {
+            LOG_MIN_FREQUENCY
+        } else {
+            0.0
+        },
+        max_frequency,
+        log_frequency: fft.log_frequency,
+        min_db: fft.min_db,
+    };
+    let rect = transform.rect;
+    let grid_stroke = egui::Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+    let text_color = visuals.weak_text_color();
+
+    // level grid
+    let db_step = if fft.min_db < -60.0 { 20.0 } else { 10.0 };
+    let mut db = 0.0;
+    while db >= fft.min_db {
+        let y = transform.db_to_y(db);
+        painter.hline(rect.x_range(), y, grid_stroke);
+        painter.text(
+            egui::pos2(rect.left() - 4.0, y),
+            egui::Align2::RIGHT_CENTER,
+            format!("{db:.0} dB"),
+            font_id.clone(),
+            text_color,
+        );
+        db -= db_step;
+    }
+
+    // frequency grid
+    for frequency in frequency_ticks(&transform) {
+        let x = transform.frequency_to_x(frequency);
+        painter.vline(x, rect.y_range(), grid_stroke);
+        painter.text(
+            egui::pos2(x, rect.bottom() + 1.0),
+            egui::Align2::CENTER_TOP,
+            format_frequency(frequency),
+            font_id.clone(),
+            text_color,
+        );
+    }
+
+    // one curve per track, with the max level of the bins under each pixel column
+    let track_ixs = visible_track_ixs(model);
+    let painter = painter.with_clip_rect(rect);
+    for track in &fft.spectra {
+        let color = track_color(track_ixs(track.track_id));
+        let points = curve_points(&track.spectrum, &transform);
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+    }
+
+    // hover readout
+    if let Some(pos) = response.hover_pos()
+        && rect.contains(pos)
+    {
+        let frequency = transform.x_to_frequency(pos.x);
+        painter.vline(pos.x, rect.y_range(), egui::Stroke::new(1.0, text_color));
+        let mut lines = vec![format_frequency_precise(frequency)];
+        for track in &fft.spectra {
+            if let Some(level) = track.spectrum.level_at(frequency) {
+                lines.push(format!(
+                    "{}: {level:.1} dB",
+                    track_label(model, track.track_id)
+                ));
+            }
+        }
+        painter.text(
+            rect.right_top() + egui::vec2(-4.0, 4.0),
+            egui::Align2::RIGHT_TOP,
+            lines.join("\n"),
+            egui::FontId::monospace(12.0),
+            visuals.text_color(),
+        );
+    }
+}
+
+/// Index of each visible track, so a track keeps the color of its checkbox
+fn visible_track_ixs(model: &Model) -> impl Fn(TrackId) -> usize + '_ {
+    move |track_id| {
+        model
+            .tracks
+            .tracks_order
+            .iter()
+            .filter(|&&id| {
+                model
+                    .tracks
+                    .get_track(id)
+                    .is_some_and(|track| track.visible)
+            })
+            .position(|&id| id == track_id)
+            .unwrap_or(0)
+    }
+}
+
+fn curve_points(spectrum: &Spectrum, transform: &PlotTransform) -> Vec<egui::Pos2> {
+    let rect = transform.rect;
+    let nr_columns = rect.width().max(1.0) as usize;
+    (0..nr_columns)
+        .filter_map(|column| {
+            let x = rect.left() + column as f32;
+            let frequencies = transform.x_to_frequency(x)..transform.x_to_frequency(x + 1.0);
+            let level = spectrum.max_level_in(frequencies)?;
+            Some(egui::pos2(x + 0.5, transform.db_to_y(level)))
+        })
+        .collect()
+}
+
+/// Frequencies of the vertical grid lines: 1, 2, 5 per decade on a log axis, a 1/2/5 step on a
+/// linear one
+fn frequency_ticks(transform: &PlotTransform) -> Vec<f64> {
+    let min_frequency = transform.min_frequency;
+    let max_frequency = transform.max_frequency;
+    if transform.log_frequency {
+        let mut res = Vec::new();
+        let mut decade = 10.0_f64.powf(min_frequency.log10().floor());
+        while decade <= max_frequency {
+            for factor in [1.0, 2.0, 5.0] {
+                let frequency = factor * decade;
+                if frequency >= min_frequency && frequency <= max_frequency {
+                    res.push(frequency);
+                }
+            }
+            decade *= 10.0;
+        }
+        return res;
+    }
+    let max_nr_ticks = (transform.rect.width() / 60.0).max(1.0) as f64;
+    let min_step = (max_frequency - min_frequency) / max_nr_ticks;
+    let base = 10.0_f64.powf(min_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * base)
+        .find(|step| *step >= min_step)
+        .unwrap_or(10.0 * base);
+    (0..)
+        .map(|ix| ix as f64 * step)
+        .take_while(|frequency| *frequency <= max_frequency)
+        .collect()
+}
+
+fn format_frequency(frequency: f64) -> String {
+    if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    } else {
+        format!("{frequency}")
+    }
+}
+
+fn format_frequency_precise(frequency: f64) -> String {
+    if frequency >= 1000.0 {
+        format!("{:.3} kHz", frequency / 1000.0)
+    } else {
+        format!("{frequency:.1} Hz")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(log_frequency: bool) -> PlotTransform {
+        PlotTransform {
+            rect: egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(600.0, 100.0)),
+            min_frequency: if log_frequency { 10.0 } else { 0.0 },
+            max_frequency: 24_000.0,
+            log_frequency,
+            min_db: -100.0,
+        }
+    }
+
+    #[test]
+    fn frequency_and_screen_x_round_trip() {
+        for log_frequency in [true, false] {
+            let transform = transform(log_frequency);
+            let x = transform.frequency_to_x(1000.0);
+            assert!((transform.x_to_frequency(x) - 1000.0).abs() < 1e-2);
+        }
+        assert_eq!(transform(true).db_to_y(-50.0), 50.0);
+        assert_eq!(transform(true).db_to_y(-200.0), 100.0);
+    }
+
+    #[test]
+    fn log_ticks_are_one_two_five_per_decade() {
+        let ticks = frequency_ticks(&transform(true));
+
+        assert_eq!(&ticks[..4], &[10.0, 20.0, 50.0, 100.0]);
+        assert_eq!(ticks.last(), Some(&20_000.0));
+        assert_eq!(format_frequency(20_000.0), "20k");
+    }
+}
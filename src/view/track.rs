@@ -1,16 +1,21 @@
 use crate::{
     model::{
         Action, Model,
+        align::AlignTarget,
         track::{self, TrackId},
     },
-    view::value_ruler2,
+    view::{selection_info, value_ruler2},
 };
 use anyhow::Result;
 
 #[path = "track/hover.rs"]
 mod hover;
+#[path = "track/markers.rs"]
+mod markers;
 #[path = "track/selection.rs"]
 mod selection;
+#[path = "track/spectrogram.rs"]
+mod spectrogram;
 #[path = "track/waveform.rs"]
 mod waveform;
 
@@ -57,7 +62,11 @@ pub fn ui(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) -> Result<()>
             let stroke = ui.style().visuals.widgets.noninteractive.bg_stroke;
             ui.painter()
                 .rect(ui.min_rect(), 0.0, egui::Color32::TRANSPARENT, stroke);
-            if let Some(track) = model.tracks.get_track(track_id) {
+            if let Some(track) = model.tracks.get_track(track_id)
+                && track.display_mode == track::DisplayMode::Spectrogram
+            {
+                spectrogram::ui_frequency_ruler(ui, model, track_id, ruler_rect, &theme_colors);
+            } else if let Some(track) = model.tracks.get_track(track_id) {
                 let hover_info = model.tracks.hover_info;
                 let mut value_ruler_ctx = value_ruler2::ValueRulerContext {
                     actions: &mut model.actions,
@@ -163,9 +172,59 @@ pub fn ui_header(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) -> Res
                     .and_then(|p| p.to_str())
                     .unwrap_or("unknown");
                 path_text = Some(path.to_string());
-                channel_text = Some(format!(" - ch {}", channel.ch_ix));
-                text = format!("{} - ch {}", path, channel.ch_ix);
+                let channel_suffix = match channel.channel_id {
+                    Some(channel_id) => {
+                        format!(" - ch {} ({})", channel.ch_ix, channel_id.short_name())
+                    }
+                    None => format!(" - ch {}", channel.ch_ix),
+                };
+                text = format!("{path}{channel_suffix}");
+                channel_text = Some(channel_suffix);
                 hover_text = Some(format!("{file}"));
+            } else if let Some(diff) = model
+                .tracks
+                .get_track(track_id)
+                .and_then(|track| track.diff.as_ref())
+            {
+                let side_label = |buffer_id| match model.find_file_channel(buffer_id) {
+                    Some((file, channel)) => {
+                        let name = file
+                            .path
+                            .as_ref()
+                            .and_then(|p| p.file_name())
+                            .and_then(|p| p.to_str())
+                            .unwrap_or("unknown");
+                        (format!("{name} - ch {}", channel.ch_ix), format!("{file}"))
+                    }
+                    None => (String::from("unknown"), String::new()),
+                };
+                let (label_a, file_a) = side_label(diff.buffer_id_a);
+                let (label_b, file_b) = side_label(diff.buffer_id_b);
+                text = format!("A: {label_a}  B: {label_b}  (A-B)");
+                hover_text = Some(format!("A: {file_a}\nB: {file_b}"));
+            }
+
+            let overlay_labels: Vec<String> = model
+                .tracks
+                .get_track(track_id)
+                .map(|track| {
+                    track
+                        .single
+                        .overlays
+                        .iter()
+                        .map(|overlay| selection_info::buffer_label(model, overlay.buffer_id))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !overlay_labels.is_empty() {
+                let overlay_text: String = overlay_labels
+                    .iter()
+                    .map(|label| format!("  + {label}"))
+                    .collect();
+                text.push_str(&overlay_text);
+                if let Some(channel_text) = channel_text.as_mut() {
+                    channel_text.push_str(&overlay_text);
+                }
             }
 
             let rect = ui.max_rect();
@@ -216,6 +275,36 @@ pub fn ui_header(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) -> Res
             );
             right = button_center_rect.left() - item_spacing;
 
+            let button_spectrogram_size = button_size("spectrogram");
+            let button_spectrogram_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    right - button_spectrogram_size.x,
+                    rect.center().y - button_spectrogram_size.y / 2.0,
+                ),
+                button_spectrogram_size,
+            );
+            right = button_spectrogram_rect.left() - item_spacing;
+
+            let button_overlay_size = button_size("overlay");
+            let button_overlay_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    right - button_overlay_size.x,
+                    rect.center().y - button_overlay_size.y / 2.0,
+                ),
+                button_overlay_size,
+            );
+            right = button_overlay_rect.left() - item_spacing;
+
+            let button_align_size = button_size("align");
+            let button_align_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    right - button_align_size.x,
+                    rect.center().y - button_align_size.y / 2.0,
+                ),
+                button_align_size,
+            );
+            right = button_align_rect.left() - item_spacing;
+
             if ui.put(button_x_rect, egui::Button::new("x")).clicked() {
                 model.actions.push(Action::RemoveTrack(track_id));
             }
@@ -225,6 +314,40 @@ pub fn ui_header(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) -> Res
             {
                 model.actions.push(Action::RecenterY { track_id });
             }
+            ui.scope_builder(egui::UiBuilder::new().max_rect(button_overlay_rect), |ui| {
+                ui.menu_button("overlay", |ui| ui_overlay_menu(ui, model, track_id))
+                    .response
+                    .on_hover_text("Draw this track on top of another track");
+            });
+            ui.scope_builder(egui::UiBuilder::new().max_rect(button_align_rect), |ui| {
+                ui.menu_button("align", |ui| ui_align_menu(ui, model, track_id))
+                    .response
+                    .on_hover_text(
+                        "Shift by the lag found with cross-correlation, within the selection if any",
+                    );
+            });
+            let is_spectrogram = model
+                .tracks
+                .get_track(track_id)
+                .is_some_and(|track| track.display_mode == track::DisplayMode::Spectrogram);
+            if ui
+                .put(
+                    button_spectrogram_rect,
+                    egui::Button::new("spectrogram").selected(is_spectrogram),
+                )
+                .on_hover_text("Show the track as spectrogram, see the settings")
+                .clicked()
+            {
+                let display_mode = if is_spectrogram {
+                    track::DisplayMode::Waveform
+                } else {
+                    track::DisplayMode::Spectrogram
+                };
+                model.actions.push(Action::SetDisplayMode {
+                    track_id,
+                    display_mode,
+                });
+            }
 
             let label_rect =
                 egui::Rect::from_min_max(rect.left_top(), egui::pos2(right, rect.bottom()));
@@ -252,6 +375,93 @@ pub fn ui_header(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) -> Res
     Ok(())
 }
 
+/// Targets to overlay this track onto, and splitting the overlays of this track
+fn ui_overlay_menu(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) {
+    let is_overlayable = |track: &track::Track| track.diff.is_none();
+    let Some(this_track) = model.tracks.get_track(track_id) else {
+        return;
+    };
+    let has_overlays = !this_track.single.overlays.is_empty();
+    let this_overlayable = is_overlayable(this_track);
+    let targets: Vec<(TrackId, String)> = model
+        .tracks
+        .tracks_order
+        .iter()
+        .copied()
+        .filter(|id| *id != track_id)
+        .filter(|id| model.tracks.get_track(*id).is_some_and(is_overlayable))
+        .map(|id| (id, selection_info::track_label(model, id)))
+        .collect();
+
+    ui.add_enabled_ui(this_overlayable, |ui| {
+        if targets.is_empty() {
+            ui.label("No other tracks");
+        }
+        for (onto_track_id, label) in targets {
+            if ui.button(format!("onto {label}")).clicked() {
+                model.actions.push(Action::OverlayTrack {
+                    track_id,
+                    onto_track_id,
+                });
+                ui.close_menu();
+            }
+        }
+    });
+    if has_overlays {
+        ui.separator();
+        if ui.button("split overlays").clicked() {
+            model.actions.push(Action::SplitOverlays(track_id));
+            ui.close_menu();
+        }
+    }
+}
+
+/// What this track can be aligned to: B to A on a diff track, overlays to the track's item,
+/// or the track to another track
+fn ui_align_menu(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId) {
+    let Some(this_track) = model.tracks.get_track(track_id) else {
+        return;
+    };
+    let mut targets: Vec<(AlignTarget, String)> = Vec::new();
+    if this_track.diff.is_some() {
+        targets.push((AlignTarget::DiffB(track_id), String::from("B to A")));
+    } else {
+        for (overlay_ix, overlay) in this_track.single.overlays.iter().enumerate() {
+            let label = selection_info::buffer_label(model, overlay.buffer_id);
+            targets.push((
+                AlignTarget::Overlay {
+                    track_id,
+                    overlay_ix,
+                },
+                format!("overlay {label} to this track"),
+            ));
+        }
+        for reference_track_id in model.tracks.tracks_order.iter().copied() {
+            if reference_track_id == track_id {
+                continue;
+            }
+            let label = selection_info::track_label(model, reference_track_id);
+            targets.push((
+                AlignTarget::Track {
+                    track_id,
+                    reference_track_id,
+                },
+                format!("to {label}"),
+            ));
+        }
+    }
+
+    if targets.is_empty() {
+        ui.label("No other tracks");
+    }
+    for (target, label) in targets {
+        if ui.button(label).clicked() {
+            model.actions.push(Action::AlignToReference(target));
+            ui.close_menu();
+        }
+    }
+}
+
 fn truncate_path_keep_basename_to_width(
     ui: &egui::Ui,
     path: &str,
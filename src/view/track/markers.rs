@@ -0,0 +1,35 @@
+use crate::{
+    model::{Model, config::ThemeColors},
+    view::{
+        ruler::markers::{marker_colors, marker_screen_x_range},
+        util::rpc,
+    },
+};
+
+/// Continue the marker lines of the time ruler over the track, with a band for regions
+pub fn ui_markers(ui: &mut egui::Ui, model: &Model, theme_colors: &ThemeColors) {
+    let rect = ui.min_rect();
+    for (_, marker, sample_ix) in model.markers.positioned(&model.tracks) {
+        let Some((start_x, end_x)) = marker_screen_x_range(model, marker, sample_ix, rect) else {
+            continue;
+        };
+        let (color, region_fill) = marker_colors(marker, theme_colors);
+        let line_color = color.gamma_multiply(0.6);
+        if let Some(end_x) = end_x {
+            let region = egui::Rect::from_x_y_ranges(
+                start_x..=end_x.max(start_x + 1.0),
+                (rect.top() + 1.0)..=rect.bottom(),
+            )
+            .intersect(rect);
+            ui.painter()
+                .rect(region, 0.0, region_fill, egui::Stroke::NONE);
+        }
+        for x in std::iter::once(start_x).chain(end_x) {
+            if rect.x_range().contains(x) {
+                let top = rpc(ui, [x, rect.top()].into());
+                let bottom = rpc(ui, [x, rect.bottom()].into());
+                ui.painter().line_segment([top, bottom], (1.0, line_color));
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{
+    audio::{
+        manager::{BufferId, STREAMED_BUFFER_HINT},
+        spectrogram::TileKey,
+    },
+    model::{
+        Model, config::SpectrogramConfig, config::ThemeColors, hover_info::HoverInfoE,
+        track::TrackId,
+    },
+    view::util::rpc,
+};
+use anyhow::Result;
+
+/// Minimum distance in points between frequency ruler labels
+const NR_PIXELS_PER_FREQUENCY_TICK: f32 = 40.0;
+
+/// Everything the spectrogram image depends on, it is only rebuilt when this changes
+#[derive(Debug, Clone, PartialEq)]
+struct ImageKey {
+    buffer_id: BufferId,
+    ix_rng: (f64, f64),
+    sample_ix_offset: f64,
+    size: [usize; 2],
+    config: SpectrogramConfig,
+    generation: u64,
+}
+
+/// Draw the track as spectrogram: an image with a column per pixel, taken from the cached
+/// tiles of the zoom level closest to the current samples per pixel.
+pub fn ui_spectrogram(
+    ui: &mut egui::Ui,
+    model: &mut Model,
+    track_id: TrackId,
+    rect: egui::Rect,
+) -> Result<()> {
+    let sample_ix_range = model
+        .tracks
+        .ruler
+        .ix_range()
+        .ok_or(anyhow::anyhow!("No time line"))?;
+    let track = model
+        .tracks
+        .get_track_mut(track_id)
+        .ok_or_else(|| anyhow::anyhow!("Track {:?} not found", track_id))?;
+    // keep the sample rect and screen rect in sync like for the waveform, hover and selection
+    // depend on them
+    track.set_ix_range(sample_ix_range, &model.audio)?;
+    track.set_screen_rect(rect.into());
+    let item = &track.single.item;
+    let (buffer_id, sample_ix_offset) = (item.buffer_id, item.sample_ix_offset);
+
+    let pixels_per_point = ui.ctx().pixels_per_point();
+    let size = [
+        (rect.width() * pixels_per_point).round() as usize,
+        (rect.height() * pixels_per_point).round() as usize,
+    ];
+    if size[0] == 0 || size[1] == 0 || sample_ix_range.is_empty() {
+        return Ok(());
+    }
+    if model.audio.is_streamed(buffer_id) {
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            STREAMED_BUFFER_HINT,
+            egui::TextStyle::Body.resolve(ui.style()),
+            ui.visuals().weak_text_color(),
+        );
+        return Ok(());
+    }
+
+    let key = ImageKey {
+        buffer_id,
+        ix_rng: (sample_ix_range.start, sample_ix_range.end),
+        sample_ix_offset,
+        size,
+        config: model.user_config.spectrogram.clone(),
+        generation: model.spectrograms.generation(),
+    };
+    let id = ui.id().with(("spectrogram_texture", track_id));
+    let cached: Option<(ImageKey, egui::TextureHandle)> = ui.data(|data| data.get_temp(id));
+    let texture = match cached {
+        Some((cached_key, texture)) if cached_key == key => texture,
+        cached => {
+            let image = build_image(model, &key)?;
+            let texture = match cached {
+                Some((_, mut texture)) => {
+                    texture.set(image, egui::TextureOptions::NEAREST);
+                    texture
+                }
+                None => ui.ctx().load_texture(
+                    format!("spectrogram_{track_id:?}"),
+                    image,
+                    egui::TextureOptions::NEAREST,
+                ),
+            };
+            ui.data_mut(|data| data.insert_temp(id, (key, texture.clone())));
+            texture
+        }
+    };
+
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    ui.painter()
+        .image(texture.id(), rect, uv, egui::Color32::WHITE);
+    Ok(())
+}
+
+fn build_image(model: &mut Model, key: &ImageKey) -> Result<egui::ColorImage> {
+    let buffer = model.audio.get_buffer(key.buffer_id)?;
+    let (nr_samples, sample_rate) = (buffer.nr_samples() as f64, buffer.sample_rate());
+    let config = &key.config;
+    let analysis = config.analysis();
+    let [width, height] = key.size;
+
+    let samples_per_pixel = (key.ix_rng.1 - key.ix_rng.0) / width as f64;
+    let samples_per_column = analysis.samples_per_column(samples_per_pixel);
+    let columns: Vec<Option<(TileKey, usize)>> = (0..width)
+        .map(|x| {
+            let track_ix = key.ix_rng.0 + (x as f64 + 0.5) * samples_per_pixel;
+            let buffer_ix = track_ix - key.sample_ix_offset;
+            (0.0..nr_samples)
+                .contains(&buffer_ix)
+                .then(|| TileKey::for_sample_ix(samples_per_column, buffer_ix as u64))
+        })
+        .collect();
+    // top row is the highest frequency
+    let bins: Vec<Option<usize>> = (0..height)
+        .map(|y| {
+            let frac = 1.0 - (y as f64 + 0.5) / height as f64;
+            analysis.frequency_bin(config.frequency_at(frac, sample_rate), sample_rate)
+        })
+        .collect();
+
+    let mut image = egui::ColorImage::new(key.size, egui::Color32::TRANSPARENT);
+    for (x, column) in columns.iter().enumerate() {
+        let Some((tile_key, column)) = *column else {
+            continue;
+        };
+        let Some(tile) =
+            model
+                .spectrograms
+                .get_or_request(key.buffer_id, tile_key, &model.audio, analysis)
+        else {
+            continue;
+        };
+        for (y, bin) in bins.iter().enumerate() {
+            if let Some(level) = bin.and_then(|bin| tile.level(column, bin)) {
+                image.pixels[y * width + x] = config.colormap.color(config.level_frac(level));
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Frequency and level under the mouse, drawn next to the cursor
+pub fn ui_hover_level(ui: &mut egui::Ui, model: &mut Model, track_id: TrackId, rect: egui::Rect) {
+    if !model.user_config.show_hover_info {
+        return;
+    }
+    let HoverInfoE::IsHovered(hover_info) = model.tracks.hover_info else {
+        return;
+    };
+    let pos: egui::Pos2 = (&hover_info.screen_pos).into();
+    if !rect.contains(pos) {
+        return;
+    }
+    let Some(track) = model.tracks.get_track(track_id) else {
+        return;
+    };
+    let item = &track.single.item;
+    let (buffer_id, sample_ix_offset) = (item.buffer_id, item.sample_ix_offset);
+    let Ok(buffer) = model.audio.get_buffer(buffer_id) else {
+        return;
+    };
+    let sample_rate = buffer.sample_rate();
+    let config = &model.user_config.spectrogram;
+    let analysis = config.analysis();
+    let frac = ((rect.bottom() - pos.y) / rect.height()) as f64;
+    let frequency = config.frequency_at(frac, sample_rate);
+
+    let buffer_ix = hover_info.sample_ix - sample_ix_offset;
+    // the image has a column per physical pixel
+    let pixels_per_point = ui.ctx().pixels_per_point() as f64;
+    let level = model.tracks.ruler.samples_per_pixel().and_then(|spp| {
+        if buffer_ix < 0.0 || buffer_ix >= buffer.nr_samples() as f64 {
+            return None;
+        }
+        let (tile_key, column) = TileKey::for_sample_ix(
+            analysis.samples_per_column(spp / pixels_per_point),
+            buffer_ix as u64,
+        );
+        let bin = analysis.frequency_bin(frequency, sample_rate)?;
+        let tile =
+            model
+                .spectrograms
+                .get_or_request(buffer_id, tile_key, &model.audio, analysis)?;
+        tile.level(column, bin)
+    });
+
+    let text = match level {
+        Some(level) => format!("{} {level:.1} dB", format_frequency(frequency)),
+        None => format_frequency(frequency),
+    };
+    let font_id = egui::TextStyle::Small.resolve(ui.style());
+    let color = ui.visuals().strong_text_color();
+    let galley = ui.fonts(|fonts| fonts.layout_no_wrap(text, font_id, color));
+    let text_rect =
+        egui::Rect::from_min_size(pos + egui::vec2(8.0, -8.0 - galley.size().y), galley.size());
+    let text_rect = text_rect.translate(egui::vec2(
+        (rect.right() - text_rect.right()).min(0.0),
+        (rect.top() - text_rect.top()).max(0.0),
+    ));
+    ui.painter()
+        .rect_filled(text_rect.expand(2.0), 2.0, ui.visuals().extreme_bg_color);
+    ui.painter().galley(text_rect.min, galley, color);
+}
+
+/// Frequency labels left of a spectrogram, in place of the sample value ruler
+pub fn ui_frequency_ruler(
+    ui: &mut egui::Ui,
+    model: &Model,
+    track_id: TrackId,
+    rect: egui::Rect,
+    theme_colors: &ThemeColors,
+) {
+    let Some(sample_rate) = model
+        .tracks
+        .get_track(track_id)
+        .and_then(|track| model.audio.get_buffer(track.single.item.buffer_id).ok())
+        .map(|buffer| buffer.sample_rate())
+    else {
+        return;
+    };
+    let config = &model.user_config.spectrogram;
+    let painter = ui.painter_at(rect);
+    let font_id = egui::TextStyle::Small.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+
+    let mut last_y = f32::INFINITY;
+    for frequency in frequency_ticks(config, sample_rate) {
+        let frac = config.frequency_frac(frequency, sample_rate) as f32;
+        let y = rect.bottom() - frac * rect.height();
+        // ticks are ordered from low to high, so from the bottom up
+        if last_y - y < NR_PIXELS_PER_FREQUENCY_TICK && last_y.is_finite() {
+            continue;
+        }
+        last_y = y;
+        let left = rpc(ui, egui::pos2(rect.right() - 6.0, y));
+        let right = rpc(ui, egui::pos2(rect.right(), y));
+        painter.line_segment([left, right], egui::Stroke::new(1.0, theme_colors.accent));
+        painter.text(
+            egui::pos2(
+                rect.right() - 8.0,
+                y.clamp(rect.top() + 6.0, rect.bottom() - 6.0),
+            ),
+            egui::Align2::RIGHT_CENTER,
+            format_frequency(frequency),
+            font_id.clone(),
+            text_color,
+        );
+    }
+}
+
+/// Candidate tick frequencies from low to high: 1-2-5 per decade on a log axis, multiples of
+/// 1 kHz on a linear axis
+fn frequency_ticks(config: &SpectrogramConfig, sample_rate: u32) -> Vec<f64> {
+    let nyquist = sample_rate as f64 / 2.0;
+    if config.log_frequency {
+        let mut ticks = Vec::new();
+        let mut decade = 10.0;
+        while decade <= nyquist {
+            for factor in [1.0, 2.0, 5.0] {
+                let frequency = decade * factor;
+                if frequency >= SpectrogramConfig::MIN_LOG_FREQUENCY && frequency <= nyquist {
+                    ticks.push(frequency);
+                }
+            }
+            decade *= 10.0;
+        }
+        ticks
+    } else {
+        (0..=(nyquist / 1000.0) as usize)
+            .map(|khz| khz as f64 * 1000.0)
+            .collect()
+    }
+}
+
+fn format_frequency(frequency: f64) -> String {
+    if frequency >= 1000.0 {
+        let khz = format!("{:.3}", frequency / 1000.0);
+        format!("{} kHz", khz.trim_end_matches('0').trim_end_matches('.'))
+    } else {
+        format!("{frequency:.0} Hz")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_frequency_trims_zero_decimals() {
+        assert_eq!(format_frequency(440.0), "440 Hz");
+        assert_eq!(format_frequency(2000.0), "2 kHz");
+        assert_eq!(format_frequency(20_000.0), "20 kHz");
+        assert_eq!(format_frequency(2500.0), "2.5 kHz");
+        assert_eq!(format_frequency(12345.0), "12.345 kHz");
+    }
+
+    #[test]
+    fn log_frequency_ticks_are_one_two_five_per_decade() {
+        let config = SpectrogramConfig {
+            log_frequency: true,
+            ..Default::default()
+        };
+
+        let ticks = frequency_ticks(&config, 48_000);
+
+        assert_eq!(ticks.first(), Some(&20.0));
+        assert_eq!(ticks.last(), Some(&20_000.0));
+        assert!(ticks.contains(&1000.0));
+        assert!(!ticks.contains(&10.0));
+    }
+}
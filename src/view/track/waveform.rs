@@ -6,12 +6,13 @@ use crate::{
     model::{
         Action, Model,
         config::ThemeColors,
-        ruler::{ValueLattice, sample_value_to_screen_y},
-        track::TrackId,
+        hover_info::HoverInfoE,
+        ruler::{ValueDisplayScale, ValueLattice, sample_value_to_screen_y},
+        track::{DisplayMode, TrackId},
     },
     rect::Rect,
     view::{
-        track::{hover, selection},
+        track::{hover, markers, selection, spectrogram},
         util::rpc,
         value_ruler2::NR_PIXELS_PER_VALUE_TICK,
     },
@@ -38,8 +39,20 @@ pub fn ui_waveform_canvas(
         egui::Sense::drag(),
     );
     handle_pan_drag(ui, model, track_id, &waveform_response);
-    ui_waveform(ui, model, track_id, rect, theme_colors)?;
+    let display_mode = model
+        .tracks
+        .get_track(track_id)
+        .map(|track| track.display_mode)
+        .unwrap_or_default();
+    match display_mode {
+        DisplayMode::Waveform => ui_waveform(ui, model, track_id, rect, theme_colors)?,
+        DisplayMode::Spectrogram => spectrogram::ui_spectrogram(ui, model, track_id, rect)?,
+    }
+    markers::ui_markers(ui, model, theme_colors);
     hover::ui_hover(ui, model, track_id, theme_colors);
+    if display_mode == DisplayMode::Spectrogram {
+        spectrogram::ui_hover_level(ui, model, track_id, rect);
+    }
     selection::ui_selection(ui, model, &waveform_response, theme_colors);
 
     Ok(())
@@ -98,8 +111,6 @@ fn ui_waveform(
     track.update_sample_view(&mut model.audio, display_scale)?;
     let sample_view = track.get_sample_view()?;
 
-    let color = theme_colors.waveform;
-    let line_color = color.linear_multiply(0.7);
     let screen_rect = track
         .screen_rect
         .ok_or_else(|| anyhow::anyhow!("screen_rect is missing"))?;
@@ -110,6 +121,68 @@ fn ui_waveform(
         .ok_or_else(|| anyhow::anyhow!("sample_rect is missing"))?;
     draw_value_grid(ui, sample_rect, screen_rect, display_scale);
 
+    let lane = Lane {
+        sample_rect,
+        screen_rect,
+        hover_info,
+        display_scale,
+    };
+    if let Some(diff) = track.diff.as_ref() {
+        // A and B first, so the residual stays visible on top
+        for (sample_view, color) in [
+            (diff.sample_view_a.as_ref(), theme_colors.diff_a),
+            (diff.sample_view_b.as_ref(), theme_colors.diff_b),
+        ] {
+            if let Some(sample_view) = sample_view {
+                draw_sample_view(ui, sample_view, &lane, color, color);
+            }
+        }
+    }
+    draw_sample_view(
+        ui,
+        sample_view,
+        &lane,
+        theme_colors.waveform,
+        theme_colors.waveform_hovered_sample,
+    );
+    for (overlay_ix, overlay) in track.single.overlays.iter().enumerate() {
+        if let Some(sample_view) = overlay.sample_view.as_ref() {
+            draw_sample_view(
+                ui,
+                sample_view,
+                &lane,
+                theme_colors.overlay(overlay_ix),
+                theme_colors.waveform_hovered_sample,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Mapping of a track's samples to the screen, shared by all sample views drawn on it
+struct Lane {
+    sample_rect: audio::SampleRect,
+    screen_rect: Rect,
+    hover_info: HoverInfoE,
+    display_scale: ValueDisplayScale,
+}
+
+fn draw_sample_view(
+    ui: &egui::Ui,
+    sample_view: &audio::sample::View,
+    lane: &Lane,
+    color: egui::Color32,
+    hovered_color: egui::Color32,
+) {
+    let Lane {
+        sample_rect,
+        screen_rect,
+        hover_info,
+        display_scale,
+    } = *lane;
+    let line_color = color.linear_multiply(0.7);
+
     match sample_view.data {
         ViewData::Single(ref single_view) => {
             if sample_view.samples_per_pixel < SINGLE_SAMPLE_DRAW_MAX_SPP {
@@ -126,11 +199,7 @@ fn ui_waveform(
                     let is_hovered = hover_info.sample_pos_is_hovered(pos.x.into());
                     let stroke_width = if is_hovered { 2.0 } else { 1.0 };
 
-                    let color = if is_hovered {
-                        theme_colors.waveform_hovered_sample
-                    } else {
-                        theme_colors.waveform
-                    };
+                    let color = if is_hovered { hovered_color } else { color };
                     let line_color = color.linear_multiply(0.7);
 
                     if pos.y < screen_rect.top() && pos_mid.y < screen_rect.top()
@@ -182,21 +251,18 @@ fn ui_waveform(
                 if !screen_rect.contains(min.into()) && !screen_rect.contains(max.into()) {
                     return;
                 }
-                let color = theme_colors.waveform;
                 ui.painter()
                     .line_segment([min, max], egui::Stroke::new(1.0, color));
             });
         }
     };
-
-    Ok(())
 }
 
 fn draw_value_grid(
     ui: &mut egui::Ui,
     sample_rect: audio::SampleRect,
     screen_rect: Rect,
-    display_scale: crate::model::ruler::ValueDisplayScale,
+    display_scale: ValueDisplayScale,
 ) {
     let Some(val_rng) = sample_rect.val_rng() else {
         return;
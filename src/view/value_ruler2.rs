@@ -4,7 +4,7 @@ use crate::model::hover_info::HoverInfoE;
 use crate::model::ruler::{
     TickType, ValueDisplayScale, ValueLattice, sample_value_to_screen_y, screen_y_to_sample_value,
 };
-use crate::model::track::Track;
+use crate::model::track::{Track, diff::DiffValues};
 use crate::model::{Action, track::TrackId};
 use egui::{Color32, FontId, Pos2, Rect, Stroke};
 
@@ -134,15 +134,36 @@ pub fn ui(
             hover_style,
         );
     }
-    draw_hover_value(
-        ui,
-        ctx.hover_info,
-        ctx.audio,
-        track,
-        rect,
-        &mut occupied,
-        hover_style,
-    );
+    if track.diff.is_some() {
+        draw_hover_diff_value(
+            ui,
+            ctx.hover_info,
+            ctx.audio,
+            track,
+            rect,
+            &mut occupied,
+            hover_style,
+        );
+    } else {
+        draw_hover_value(
+            ui,
+            ctx.hover_info,
+            ctx.audio,
+            track,
+            rect,
+            &mut occupied,
+            hover_style,
+        );
+        draw_hover_overlay_values(
+            ui,
+            ctx.hover_info,
+            ctx.audio,
+            track,
+            rect,
+            &mut occupied,
+            hover_style,
+        );
+    }
     draw_lattice_labels(ui, rect, &lattice, &mut occupied);
 }
 
@@ -180,7 +201,7 @@ fn handle_value_ruler_scroll(
     }
 }
 
-fn format_tick_label(value: f64, step: f64) -> String {
+pub(crate) fn format_tick_label(value: f64, step: f64) -> String {
     // Precision follows the active lattice step, not the absolute value. Without that, zooming
     // around 0.5 would stay stuck at one decimal place even when nearby ticks differ by 0.01.
     let decimals = decimals_for_step(step);
@@ -304,7 +325,7 @@ fn draw_hover_value(
         return;
     }
 
-    let sample_ix = hover_info.sample_ix.round() as i64;
+    let sample_ix = (hover_info.sample_ix - track.single.item.sample_ix_offset).round() as i64;
     if sample_ix < 0 {
         return;
     }
@@ -333,6 +354,22 @@ fn draw_hover_value(
                 format!("{sample_value:.3}\n{db:.3} dB"),
             )
         }
+        crate::audio::buffer::BufferE::F64(buffer) => {
+            let Some(sample_value) = buffer.data.get(sample_ix) else {
+                return;
+            };
+            // shortest representation that round trips, so no digit of the f64 value is hidden
+            let db = crate::audio::db::gain_to_db_f64(sample_value.abs());
+            (
+                sample_value_to_screen_y(
+                    (*sample_value).to_norm(buffer.bit_depth),
+                    val_rng,
+                    ruler_rect,
+                    style.display_scale,
+                ),
+                format!("{sample_value}\n{db:.3} dB"),
+            )
+        }
         crate::audio::buffer::BufferE::I16(buffer) => {
             let Some(sample_value) = buffer.data.get(sample_ix) else {
                 return;
@@ -377,6 +414,159 @@ fn draw_hover_value(
     occupied.push(label_rect);
 }
 
+/// Value and level of an overlay, f64 values with all their digits
+fn overlay_value_label(value: f64, is_f64: bool) -> String {
+    if is_f64 {
+        let db = crate::audio::db::gain_to_db_f64(value.abs());
+        format!("{value}\n{db:.1} dB")
+    } else {
+        let db = crate::audio::db::gain_to_db(value.abs() as f32);
+        format!("{value:.6}\n{db:.1} dB")
+    }
+}
+
+/// Hover labels of the items overlaid on a track, with a tick in the colour of each overlay.
+/// Labels are moved down when they would cover an earlier one, the ticks stay at the values.
+fn draw_hover_overlay_values(
+    ui: &egui::Ui,
+    hover_info: &HoverInfoE,
+    audio: &crate::audio::manager::AudioManager,
+    track: &Track,
+    rect: Rect,
+    occupied: &mut Vec<Rect>,
+    style: HoverValueStyle<'_>,
+) {
+    let HoverInfoE::IsHovered(hover_info) = hover_info else {
+        return;
+    };
+    let Some(sample_view) = track.single.item.sample_view.as_ref() else {
+        return;
+    };
+    if sample_view.samples_per_pixel >= 0.5 {
+        return;
+    }
+    let Some(val_rng) = track.single.item.sample_rect().and_then(|r| r.val_rng()) else {
+        return;
+    };
+    for (overlay_ix, overlay) in track.single.overlays.iter().enumerate() {
+        let Ok(Some(value)) = overlay.value_at(audio, hover_info.sample_ix) else {
+            continue;
+        };
+        let Some(y) = sample_value_to_screen_y(value, val_rng, rect.into(), style.display_scale)
+        else {
+            continue;
+        };
+        if y < rect.top() || y > rect.bottom() {
+            continue;
+        }
+        let color = style.theme_colors.overlay(overlay_ix);
+        let tick_line = [
+            Pos2::new(rect.right() - 10.0, y),
+            Pos2::new(rect.right(), y),
+        ];
+        ui.painter()
+            .line_segment(tick_line, Stroke::new(1.0, color));
+
+        let is_f64 = audio
+            .get_buffer_format(overlay.buffer_id)
+            .is_ok_and(|buffer| matches!(buffer.as_ref(), crate::audio::buffer::BufferE::F64(_)));
+        let label = overlay_value_label(value, is_f64);
+        let mut label_y = y;
+        let (mut label_rect, _, _) = layout_value_label(ui, rect, label_y, &label);
+        while let Some(other) = occupied.iter().find(|other| other.intersects(label_rect)) {
+            label_y += other.bottom() - label_rect.top() + 1.0;
+            let (next_rect, _, _) = layout_value_label(ui, rect, label_y, &label);
+            if next_rect == label_rect {
+                // clamped at the bottom of the ruler, overlap rather than loop forever
+                break;
+            }
+            label_rect = next_rect;
+        }
+        let label_rect = draw_value_label(ui, rect, label_y, label);
+        occupied.push(label_rect);
+    }
+}
+
+/// Hover label of a diff track: the values of A and B and their difference, placed at the
+/// residual value.
+fn draw_hover_diff_value(
+    ui: &egui::Ui,
+    hover_info: &HoverInfoE,
+    audio: &crate::audio::manager::AudioManager,
+    track: &Track,
+    rect: Rect,
+    occupied: &mut Vec<Rect>,
+    style: HoverValueStyle<'_>,
+) {
+    let HoverInfoE::IsHovered(hover_info) = hover_info else {
+        return;
+    };
+    let Some(diff) = track.diff.as_ref() else {
+        return;
+    };
+    let Some(sample_rect) = track.single.item.sample_rect() else {
+        return;
+    };
+    let Some(sample_view) = track.single.item.sample_view.as_ref() else {
+        return;
+    };
+    if sample_view.samples_per_pixel >= 0.5 {
+        return;
+    }
+    let Some(val_rng) = sample_rect.val_rng() else {
+        return;
+    };
+    let Ok(values) = diff.values_at(audio, hover_info.sample_ix.round()) else {
+        return;
+    };
+    let Some(y) =
+        sample_value_to_screen_y(values.residual, val_rng, rect.into(), style.display_scale)
+    else {
+        return;
+    };
+    if y < rect.top() || y > rect.bottom() {
+        return;
+    }
+
+    // Int sources show the difference in their LSBs, the smallest step they can differ by
+    let lsb_bit_depth = [diff.buffer_id_a, diff.buffer_id_b]
+        .into_iter()
+        .filter_map(|buffer_id| audio.get_buffer_format(buffer_id).ok())
+        .filter(|buffer| {
+            matches!(
+                buffer.as_ref(),
+                crate::audio::buffer::BufferE::I16(_) | crate::audio::buffer::BufferE::I32(_)
+            )
+        })
+        .map(|buffer| buffer.bit_depth())
+        .max();
+    let label = format_diff_label(values, lsb_bit_depth);
+    let label_rect = draw_hover_label(ui, rect, y, label, style.theme_colors.accent);
+    occupied.push(label_rect);
+}
+
+/// Values with the shortest precision that round-trips, so the smallest difference still shows
+fn format_diff_label(values: DiffValues, lsb_bit_depth: Option<u16>) -> String {
+    let format_value = |value: Option<f64>| match value {
+        Some(value) => format!("{value}"),
+        None => String::from("-"),
+    };
+    let lsbs = lsb_bit_depth
+        .map(|bit_depth| {
+            let factor = sample::convert::float2pcm_factor(bit_depth.clamp(1, 32) as u32) as f64;
+            let lsbs = (values.residual * factor * 1000.0).round() / 1000.0;
+            format!(" ({lsbs} LSB)")
+        })
+        .unwrap_or_default();
+    let db = crate::audio::db::gain_to_db_f64(values.residual.abs());
+    format!(
+        "A {}\nB {}\nA-B {}{lsbs}\n{db:.1} dB",
+        format_value(values.a),
+        format_value(values.b),
+        values.residual
+    )
+}
+
 fn draw_hover_value_from_y(
     ui: &egui::Ui,
     hover_info: &HoverInfoE,
@@ -404,7 +594,7 @@ fn draw_hover_value_from_y(
         None => return,
     };
 
-    let Ok(buffer) = audio.get_buffer(track.single.item.buffer_id) else {
+    let Ok(buffer) = audio.get_buffer_format(track.single.item.buffer_id) else {
         return;
     };
 
@@ -412,7 +602,7 @@ fn draw_hover_value_from_y(
     let Some(val_rng) = sample_rect.val_rng() else {
         return;
     };
-    let hover_label = match buffer {
+    let hover_label = match buffer.as_ref() {
         crate::audio::buffer::BufferE::F32(_) => {
             let Some(sample_value) =
                 screen_y_to_sample_value(hover_pos.y, val_rng, screen_rect, style.display_scale)
@@ -427,6 +617,20 @@ fn draw_hover_value_from_y(
             let db = crate::audio::db::gain_to_db(sample_value.abs() as f32);
             Some((y_ruler, format!("{sample_value:.3}\n{db:.3} dB")))
         }
+        crate::audio::buffer::BufferE::F64(_) => {
+            let Some(sample_value) =
+                screen_y_to_sample_value(hover_pos.y, val_rng, screen_rect, style.display_scale)
+            else {
+                return;
+            };
+            let Some(y_ruler) =
+                sample_value_to_screen_y(sample_value, val_rng, ruler_rect, style.display_scale)
+            else {
+                return;
+            };
+            let db = crate::audio::db::gain_to_db_f64(sample_value.abs());
+            Some((y_ruler, format!("{sample_value}\n{db:.3} dB")))
+        }
         crate::audio::buffer::BufferE::I16(_) => {
             let Some(sample_value) =
                 screen_y_to_sample_value(hover_pos.y, val_rng, screen_rect, style.display_scale)
@@ -480,12 +684,18 @@ fn draw_lattice_labels(
 ) {
     // Labels follow the lattice's chosen label cadence, which may be denser than the big-tick
     // cadence. For example, `0.05` can be labeled while still rendering as a mid tick.
-    for tick in lattice
+    // A dB lattice labels levels instead of sample values.
+    for (tick, label_value) in lattice
         .ticks
         .iter()
-        .filter(|tick| is_multiple_of(tick.sample_value, lattice.label_step))
+        .map(|tick| (tick, tick.db.unwrap_or(tick.sample_value)))
+        .filter(|(_, value)| value.is_infinite() || is_multiple_of(*value, lattice.label_step))
     {
-        let text = format_tick_label(tick.sample_value, lattice.label_step);
+        let text = if label_value.is_infinite() {
+            String::from("-∞")
+        } else {
+            format_tick_label(label_value, lattice.label_step)
+        };
         let (label_rect, _galleys, _color) = layout_value_label(ui, rect, tick.screen_y, &text);
         if occupied.iter().any(|r| r.intersects(label_rect)) {
             continue;
@@ -495,7 +705,7 @@ fn draw_lattice_labels(
     }
 }
 
-fn is_multiple_of(value: f64, step: f64) -> bool {
+pub(crate) fn is_multiple_of(value: f64, step: f64) -> bool {
     if step <= 0.0 {
         return false;
     }
@@ -505,7 +715,9 @@ fn is_multiple_of(value: f64, step: f64) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{decimals_for_step, format_tick_label};
+    use super::{
+        DiffValues, decimals_for_step, format_diff_label, format_tick_label, overlay_value_label,
+    };
 
     #[test]
     fn step_based_precision_allows_more_digits_away_from_zero() {
@@ -527,4 +739,40 @@ mod tests {
     fn negative_zero_label_is_normalized() {
         assert_eq!(format_tick_label(-0.0, 0.01), "0");
     }
+
+    #[test]
+    fn diff_label_shows_both_values_and_difference() {
+        let label = format_diff_label(
+            DiffValues {
+                a: Some(0.5),
+                b: None,
+                residual: 0.5,
+            },
+            None,
+        );
+        assert_eq!(label, "A 0.5\nB -\nA-B 0.5\n-6.0 dB");
+    }
+
+    #[test]
+    fn diff_label_shows_one_lsb_at_24_bit() {
+        let lsb = 1.0 / (1 << 23) as f64;
+        let label = format_diff_label(
+            DiffValues {
+                a: Some(0.5 + lsb),
+                b: Some(0.5),
+                residual: lsb,
+            },
+            Some(24),
+        );
+        assert_eq!(
+            label,
+            "A 0.5000001192092896\nB 0.5\nA-B 0.00000011920928955078125 (1 LSB)\n-138.5 dB"
+        );
+    }
+    #[test]
+    fn f64_overlay_label_keeps_all_digits() {
+        let value = 0.5 + 1e-12;
+        assert_eq!(overlay_value_label(value, true), "0.500000000001\n-6.0 dB");
+        assert_eq!(overlay_value_label(value, false), "0.500000\n-6.0 dB");
+    }
 }
@@ -0,0 +1,157 @@
+//! Broadcast Wave Format `bext` chunk (EBU Tech 3285).
+
+use anyhow::{Result, ensure};
+
+pub const BEXT: crate::wav::chunk::FourCC = *b"bext";
+
+const DESCRIPTION: std::ops::Range<usize> = 0..256;
+const ORIGINATOR: std::ops::Range<usize> = 256..288;
+const ORIGINATOR_REFERENCE: std::ops::Range<usize> = 288..320;
+const ORIGINATION_DATE: std::ops::Range<usize> = 320..330;
+const ORIGINATION_TIME: std::ops::Range<usize> = 330..338;
+const TIME_REFERENCE: std::ops::Range<usize> = 338..346;
+const VERSION: std::ops::Range<usize> = 346..348;
+/// Extended UMID, a basic UMID only uses the first 32 bytes
+const UMID: std::ops::Range<usize> = 348..412;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bext {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    /// "yyyy-mm-dd"
+    pub origination_date: String,
+    /// "hh:mm:ss"
+    pub origination_time: String,
+    /// Sample index of the first sample since midnight, at the sample rate of the file
+    pub time_reference: u64,
+    pub version: u16,
+    /// SMPTE 330M UMID, from version 1 on
+    pub umid: Option<Vec<u8>>,
+}
+
+impl Bext {
+    /// Time of the first sample since midnight in seconds
+    pub fn time_reference_s(&self, sample_rate: u32) -> f64 {
+        self.time_reference as f64 / sample_rate.max(1) as f64
+    }
+
+    pub fn umid_hex(&self) -> Option<String> {
+        self.umid
+            .as_ref()
+            .map(|umid| umid.iter().map(|byte| format!("{byte:02X}")).collect())
+    }
+}
+
+/// Parse the data of a `bext` chunk, the text fields are ASCII padded with zeros.
+pub fn parse_bext(data: &[u8]) -> Result<Bext> {
+    ensure!(
+        data.len() >= VERSION.end,
+        "bext chunk too small: {} bytes",
+        data.len()
+    );
+    let version = u16::from_le_bytes(data[VERSION].try_into()?);
+    let umid = data
+        .get(UMID)
+        .filter(|_| version >= 1)
+        .map(|umid| {
+            // a basic UMID leaves the extended part zero
+            let len = if umid[32..].iter().all(|byte| *byte == 0) {
+                32
+            } else {
+                umid.len()
+            };
+            umid[..len].to_vec()
+        })
+        .filter(|umid| umid.iter().any(|byte| *byte != 0));
+    Ok(Bext {
+        description: text(&data[DESCRIPTION]),
+        originator: text(&data[ORIGINATOR]),
+        originator_reference: text(&data[ORIGINATOR_REFERENCE]),
+        origination_date: text(&data[ORIGINATION_DATE]),
+        origination_time: text(&data[ORIGINATION_TIME]),
+        time_reference: u64::from_le_bytes(data[TIME_REFERENCE].try_into()?),
+        version,
+        umid,
+    })
+}
+
+fn text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+impl std::fmt::Display for Bext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.description.is_empty() {
+            writeln!(f, "description: {}", self.description)?;
+        }
+        if !self.originator.is_empty() {
+            writeln!(f, "originator: {}", self.originator)?;
+        }
+        if !self.originator_reference.is_empty() {
+            writeln!(f, "originator reference: {}", self.originator_reference)?;
+        }
+        writeln!(
+            f,
+            "origination: {} {}",
+            self.origination_date, self.origination_time
+        )?;
+        write!(f, "time reference: {}", self.time_reference)?;
+        if let Some(umid) = self.umid_hex() {
+            write!(f, "\nUMID: {umid}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn bext_data(description: &str, time_reference: u64, umid: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 602];
+        data[..description.len()].copy_from_slice(description.as_bytes());
+        data[ORIGINATOR][..7].copy_from_slice(b"Zoom F8");
+        data[ORIGINATION_DATE].copy_from_slice(b"2024-05-17");
+        data[ORIGINATION_TIME].copy_from_slice(b"14:03:21");
+        data[TIME_REFERENCE].copy_from_slice(&time_reference.to_le_bytes());
+        data[VERSION].copy_from_slice(&1u16.to_le_bytes());
+        data[UMID][..umid.len()].copy_from_slice(umid);
+        data
+    }
+
+    #[test]
+    fn fields_are_read_and_trimmed() {
+        let time_reference = 14 * 3600 * 48_000 + (1 << 32);
+        let data = bext_data("scene 12 take 3", time_reference, &[0x06, 0x0A, 0x2B]);
+
+        let bext = parse_bext(&data).unwrap();
+
+        assert_eq!(bext.description, "scene 12 take 3");
+        assert_eq!(bext.originator, "Zoom F8");
+        assert_eq!(bext.origination_date, "2024-05-17");
+        assert_eq!(bext.origination_time, "14:03:21");
+        assert_eq!(bext.time_reference, time_reference);
+        assert_eq!(bext.umid.as_ref().map(Vec::len), Some(32));
+        assert!(bext.umid_hex().unwrap().starts_with("060A2B00"));
+        assert_eq!(
+            bext.time_reference_s(48_000),
+            time_reference as f64 / 48_000.0
+        );
+    }
+
+    #[test]
+    fn version_0_has_no_umid() {
+        let mut data = bext_data("", 0, &[1; 64]);
+        data[VERSION].copy_from_slice(&0u16.to_le_bytes());
+
+        let bext = parse_bext(&data).unwrap();
+
+        assert_eq!(bext.umid, None);
+        assert!(parse_bext(&data[..300]).is_err());
+    }
+}
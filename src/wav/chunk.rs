@@ -0,0 +1,492 @@
+//! Minimal RIFF/WAVE chunk scanner.
+//!
+//! hound only exposes the basic `fmt ` fields and the sample data, so anything else we want from
+//! a wav file (extensible channel mask, metadata chunks, ...) is read here directly from the
+//! source.
+
+use crate::wav::bext::{BEXT, Bext, parse_bext};
+use crate::wav::cue::{self, CuePoint};
+use anyhow::{Context, Result, ensure};
+use std::io::{Read, Seek, SeekFrom};
+
+pub type FourCC = [u8; 4];
+
+pub const RIFF: FourCC = *b"RIFF";
+pub const RF64: FourCC = *b"RF64";
+pub const BW64: FourCC = *b"BW64";
+pub const WAVE: FourCC = *b"WAVE";
+pub const FMT: FourCC = *b"fmt ";
+pub const DS64: FourCC = *b"ds64";
+pub const DATA: FourCC = *b"data";
+
+/// 32-bit size of RF64/BW64 chunks whose real size is in the `ds64` chunk
+const SIZE_IN_DS64: u32 = 0xFFFF_FFFF;
+
+/// Largest chunk `read_chunk_data` reads into memory, metadata chunks are far smaller. A corrupt
+/// header could otherwise claim up to 16 EiB.
+pub const MAX_CHUNK_DATA_SIZE: u64 = 64 * 1024 * 1024;
+
+/// `wFormatTag` value for WAVE_FORMAT_EXTENSIBLE
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Location of a chunk inside the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub id: FourCC,
+    /// Size of the chunk data in bytes, without the header and pad byte
+    pub size: u64,
+    /// Absolute byte offset of the chunk data
+    pub data_offset: u64,
+}
+
+impl ChunkHeader {
+    pub fn id_str(&self) -> String {
+        String::from_utf8_lossy(&self.id).into_owned()
+    }
+}
+
+/// 64-bit sizes of an RF64/BW64 file, which don't fit the 32-bit chunk sizes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ds64 {
+    pub riff_size: u64,
+    pub data_size: u64,
+    /// Number of sample frames, not always filled in by writers
+    pub sample_count: u64,
+    /// Sizes of other chunks larger than 4 GB
+    pub table: Vec<(FourCC, u64)>,
+}
+
+impl Ds64 {
+    /// The real size of chunk `id` that has `SIZE_IN_DS64` as size
+    fn chunk_size(&self, id: FourCC) -> Option<u64> {
+        if id == DATA {
+            return Some(self.data_size);
+        }
+        self.table
+            .iter()
+            .find(|(table_id, _)| *table_id == id)
+            .map(|(_, size)| *size)
+    }
+}
+
+/// Parse the data of a `ds64` chunk
+pub fn parse_ds64(data: &[u8]) -> Result<Ds64> {
+    ensure!(
+        data.len() >= 28,
+        "ds64 chunk too small: {} bytes",
+        data.len()
+    );
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let table_len = u32::from_le_bytes(data[24..28].try_into()?) as usize;
+    let table = data[28..]
+        .chunks_exact(12)
+        .take(table_len)
+        .map(|entry| {
+            let id: FourCC = entry[0..4].try_into().unwrap();
+            (id, u64::from_le_bytes(entry[4..12].try_into().unwrap()))
+        })
+        .collect();
+    Ok(Ds64 {
+        riff_size: u64_at(0),
+        data_size: u64_at(8),
+        sample_count: u64_at(16),
+        table,
+    })
+}
+
+/// Whether the source starts with an RF64 or BW64 header instead of RIFF, hound can't read
+/// those.
+pub fn is_rf64<R: Read + Seek>(reader: &mut R) -> Result<bool> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut id: FourCC = [0; 4];
+    reader
+        .read_exact(&mut id)
+        .context("Failed to read RIFF header")?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(id == RF64 || id == BW64)
+}
+
+/// Chunk level information hound doesn't give us.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// `dwChannelMask` of a WAVE_FORMAT_EXTENSIBLE `fmt ` chunk
+    pub channel_mask: Option<u32>,
+    /// Broadcast Wave metadata
+    pub bext: Option<Bext>,
+    /// Cue points with their `adtl` labels, in the order of the `cue ` chunk
+    pub cue_points: Vec<CuePoint>,
+}
+
+/// List all top level chunks of a RIFF/WAVE source, or of an RF64/BW64 source with the sizes of
+/// large chunks taken from its `ds64` chunk.
+pub fn read_chunk_headers<R: Read + Seek>(reader: &mut R) -> Result<Vec<ChunkHeader>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut riff_header = [0u8; 12];
+    reader
+        .read_exact(&mut riff_header)
+        .context("Failed to read RIFF header")?;
+    let is_rf64 = riff_header[0..4] == RF64 || riff_header[0..4] == BW64;
+    ensure!(riff_header[0..4] == RIFF || is_rf64, "Not a RIFF file");
+    ensure!(riff_header[8..12] == WAVE, "Not a WAVE file");
+
+    let source_len = reader.seek(SeekFrom::End(0))?;
+    let mut offset = 12u64;
+    let mut headers = Vec::new();
+    let mut ds64: Option<Ds64> = None;
+    while offset + 8 <= source_len {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let id: FourCC = header[0..4].try_into()?;
+        let size = u32::from_le_bytes(header[4..8].try_into()?);
+        let data_offset = offset + 8;
+        let size = match &ds64 {
+            Some(ds64) if size == SIZE_IN_DS64 => ds64.chunk_size(id).with_context(|| {
+                format!(
+                    "No size for '{}' chunk in ds64",
+                    String::from_utf8_lossy(&id)
+                )
+            })?,
+            _ => size as u64,
+        };
+        // The ds64 chunk comes first in an RF64 file, the sizes of later chunks depend on it
+        let header = ChunkHeader {
+            id,
+            size,
+            data_offset,
+        };
+        if is_rf64 && id == DS64 && ds64.is_none() {
+            ds64 = Some(parse_ds64(&read_chunk_data(reader, &header)?)?);
+        }
+        // Chunks are word aligned, odd sizes are followed by a pad byte. A chunk running past the
+        // end, e.g. a ds64 size of u64::MAX, is the last one.
+        let next_offset = data_offset
+            .checked_add(size)
+            .and_then(|end| end.checked_add(size & 1))
+            .filter(|&next_offset| next_offset <= source_len);
+        let Some(next_offset) = next_offset else {
+            tracing::warn!(
+                "'{}' chunk of {size} bytes runs past the end of the source, not reading further",
+                header.id_str()
+            );
+            headers.push(header);
+            break;
+        };
+        headers.push(header);
+        offset = next_offset;
+    }
+
+    Ok(headers)
+}
+
+/// Read the data of a chunk into memory. Fails for chunks larger than `MAX_CHUNK_DATA_SIZE` or
+/// than what is left of the source, before allocating.
+pub fn read_chunk_data<R: Read + Seek>(reader: &mut R, header: &ChunkHeader) -> Result<Vec<u8>> {
+    ensure!(
+        header.size <= MAX_CHUNK_DATA_SIZE,
+        "'{}' chunk of {} bytes is too large to read",
+        header.id_str(),
+        header.size
+    );
+    let source_len = reader.seek(SeekFrom::End(0))?;
+    ensure!(
+        header.size <= source_len.saturating_sub(header.data_offset),
+        "'{}' chunk of {} bytes is larger than the rest of the file",
+        header.id_str(),
+        header.size
+    );
+    reader.seek(SeekFrom::Start(header.data_offset))?;
+    let mut data = vec![0u8; header.size as usize];
+    reader
+        .read_exact(&mut data)
+        .with_context(|| format!("Failed to read '{}' chunk", header.id_str()))?;
+    Ok(data)
+}
+
+/// Collect the chunk level metadata we support from a RIFF/WAVE source.
+pub fn read_metadata<R: Read + Seek>(reader: &mut R) -> Result<Metadata> {
+    let headers = read_chunk_headers(reader)?;
+    let mut metadata = Metadata::default();
+    let mut adtl_chunks = Vec::new();
+    for header in headers.iter() {
+        if header.id == FMT {
+            let data = read_chunk_data(reader, header)?;
+            metadata.channel_mask = parse_fmt_channel_mask(&data);
+        } else if header.id == BEXT {
+            // A bad bext chunk leaves the file without time reference, not without metadata
+            match read_chunk_data(reader, header).and_then(|data| parse_bext(&data)) {
+                Ok(bext) => metadata.bext = Some(bext),
+                Err(err) => tracing::warn!("Skipping bext chunk: {err:#}"),
+            }
+        } else if header.id == cue::CUE {
+            match read_chunk_data(reader, header).and_then(|data| cue::parse_cue(&data)) {
+                Ok(cue_points) => metadata.cue_points = cue_points,
+                Err(err) => tracing::warn!("Skipping cue chunk: {err:#}"),
+            }
+        } else if header.id == cue::LIST {
+            match read_chunk_data(reader, header) {
+                Ok(data) if data.starts_with(&cue::ADTL) => adtl_chunks.push(data),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Skipping LIST chunk: {err:#}"),
+            }
+        }
+    }
+    // The labels can come before the cue points they refer to
+    for data in adtl_chunks {
+        if let Err(err) = cue::apply_adtl(&data, &mut metadata.cue_points) {
+            tracing::warn!("Skipping labels of adtl chunk: {err:#}");
+        }
+    }
+    Ok(metadata)
+}
+
+/// Extract `dwChannelMask` from the data of a `fmt ` chunk, if it is WAVE_FORMAT_EXTENSIBLE.
+pub fn parse_fmt_channel_mask(fmt_data: &[u8]) -> Option<u32> {
+    // WAVEFORMATEX is 18 bytes, the extensible part adds at least 22 more bytes:
+    // wValidBitsPerSample (2), dwChannelMask (4), SubFormat GUID (16)
+    const CHANNEL_MASK_OFFSET: usize = 20;
+    if fmt_data.len() < CHANNEL_MASK_OFFSET + 4 {
+        return None;
+    }
+    let format_tag = u16::from_le_bytes([fmt_data[0], fmt_data[1]]);
+    if format_tag != WAVE_FORMAT_EXTENSIBLE {
+        return None;
+    }
+    let cb_size = u16::from_le_bytes([fmt_data[16], fmt_data[17]]);
+    if cb_size < 22 {
+        return None;
+    }
+    let mask_bytes = &fmt_data[CHANNEL_MASK_OFFSET..CHANNEL_MASK_OFFSET + 4];
+    Some(u32::from_le_bytes(mask_bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extensible_fmt(nr_channels: u16, channel_mask: u32) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        fmt.extend_from_slice(&nr_channels.to_le_bytes());
+        fmt.extend_from_slice(&48_000u32.to_le_bytes());
+        fmt.extend_from_slice(&(48_000u32 * 2 * nr_channels as u32).to_le_bytes());
+        fmt.extend_from_slice(&(2 * nr_channels).to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        fmt.extend_from_slice(&channel_mask.to_le_bytes());
+        // KSDATAFORMAT_SUBTYPE_PCM
+        fmt.extend_from_slice(&[
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38,
+            0x9B, 0x71,
+        ]);
+        fmt
+    }
+
+    fn riff(chunks: &[(FourCC, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&WAVE);
+        for (id, data) in chunks {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&RIFF);
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    #[test]
+    fn channel_mask_is_read_from_extensible_fmt() {
+        let fmt = extensible_fmt(6, 0x3F);
+        assert_eq!(parse_fmt_channel_mask(&fmt), Some(0x3F));
+    }
+
+    #[test]
+    fn plain_pcm_fmt_has_no_channel_mask() {
+        let mut fmt = extensible_fmt(2, 0x3);
+        fmt[0..2].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(parse_fmt_channel_mask(&fmt), None);
+        assert_eq!(parse_fmt_channel_mask(&fmt[..16]), None);
+    }
+
+    /// An RF64 source with the data size only in the ds64 chunk
+    fn rf64(data: &[u8]) -> Vec<u8> {
+        let mut ds64 = Vec::new();
+        ds64.extend_from_slice(&0u64.to_le_bytes());
+        ds64.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        ds64.extend_from_slice(&0u64.to_le_bytes());
+        ds64.extend_from_slice(&0u32.to_le_bytes());
+        let mut bytes = riff(&[(DS64, ds64), (FMT, extensible_fmt(2, 0x3))]);
+        bytes[0..4].copy_from_slice(&RF64);
+        bytes[4..8].copy_from_slice(&SIZE_IN_DS64.to_le_bytes());
+        bytes.extend_from_slice(&DATA);
+        bytes.extend_from_slice(&SIZE_IN_DS64.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn rf64_data_size_comes_from_ds64() {
+        let mut cursor = std::io::Cursor::new(rf64(&[7; 12]));
+        assert!(is_rf64(&mut cursor).unwrap());
+
+        let headers = read_chunk_headers(&mut cursor).unwrap();
+        let ids: Vec<_> = headers.iter().map(|h| h.id_str()).collect();
+        assert_eq!(ids, vec!["ds64", "fmt ", "data"]);
+        assert_eq!(headers[2].size, 12);
+        assert_eq!(
+            read_chunk_data(&mut cursor, &headers[2]).unwrap(),
+            vec![7; 12]
+        );
+
+        let metadata = read_metadata(&mut cursor).unwrap();
+        assert_eq!(metadata.channel_mask, Some(0x3));
+    }
+
+    #[test]
+    fn ds64_table_gives_sizes_of_other_chunks() {
+        let mut data = Vec::new();
+        for value in [1u64 << 33, 1 << 32, 1 << 30] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(b"axml");
+        data.extend_from_slice(&(5u64 << 32).to_le_bytes());
+
+        let ds64 = parse_ds64(&data).unwrap();
+
+        assert_eq!(ds64.data_size, 1 << 32);
+        assert_eq!(ds64.sample_count, 1 << 30);
+        assert_eq!(ds64.chunk_size(DATA), Some(1 << 32));
+        assert_eq!(ds64.chunk_size(*b"axml"), Some(5 << 32));
+        assert_eq!(ds64.chunk_size(*b"bext"), None);
+        assert!(parse_ds64(&data[..20]).is_err());
+    }
+
+    #[test]
+    fn chunk_headers_skip_pad_bytes() {
+        let bytes = riff(&[
+            (*b"odd ", vec![1, 2, 3]),
+            (FMT, extensible_fmt(2, 0x3)),
+            (*b"data", vec![0; 8]),
+        ]);
+        let mut cursor = std::io::Cursor::new(bytes);
+        let headers = read_chunk_headers(&mut cursor).unwrap();
+        let ids: Vec<_> = headers.iter().map(|h| h.id_str()).collect();
+        assert_eq!(ids, vec!["odd ", "fmt ", "data"]);
+        assert_eq!(headers[1].data_offset, 12 + 8 + 4 + 8);
+
+        let metadata = read_metadata(&mut cursor).unwrap();
+        assert_eq!(metadata.channel_mask, Some(0x3));
+    }
+
+    #[test]
+    fn chunk_claiming_more_than_the_file_is_not_read() {
+        let mut bytes = riff(&[(FMT, extensible_fmt(2, 0x3)), (*b"junk", vec![0; 4])]);
+        let size_offset = bytes.len() - 8;
+        bytes[size_offset..size_offset + 4].copy_from_slice(&0xFFFF_0000u32.to_le_bytes());
+        let mut cursor = std::io::Cursor::new(bytes);
+        let headers = read_chunk_headers(&mut cursor).unwrap();
+
+        assert_eq!(headers[1].size, 0xFFFF_0000);
+        assert!(read_chunk_data(&mut cursor, &headers[1]).is_err());
+        let huge = ChunkHeader {
+            size: MAX_CHUNK_DATA_SIZE + 1,
+            ..headers[0]
+        };
+        assert!(read_chunk_data(&mut cursor, &huge).is_err());
+    }
+
+    #[test]
+    fn ds64_size_of_u64_max_ends_the_chunks() {
+        let mut bytes = rf64(&[7; 12]);
+        // the data size of the ds64 chunk
+        bytes[20 + 8..20 + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&riff(&[(*b"junk", vec![0; 4])])[12..]);
+        let mut cursor = std::io::Cursor::new(bytes);
+
+        let headers = read_chunk_headers(&mut cursor).unwrap();
+
+        let ids: Vec<_> = headers.iter().map(|h| h.id_str()).collect();
+        assert_eq!(ids, vec!["ds64", "fmt ", "data"]);
+        assert_eq!(headers[2].size, u64::MAX);
+        assert!(read_chunk_data(&mut cursor, &headers[2]).is_err());
+    }
+
+    #[test]
+    fn bext_is_part_of_metadata() {
+        let bext = crate::wav::bext::tests::bext_data("take 1", 48_000, &[]);
+        let bytes = riff(&[
+            (FMT, extensible_fmt(2, 0x3)),
+            (BEXT, bext),
+            (DATA, vec![0; 8]),
+        ]);
+
+        let metadata = read_metadata(&mut std::io::Cursor::new(bytes)).unwrap();
+
+        let bext = metadata.bext.unwrap();
+        assert_eq!(bext.description, "take 1");
+        assert_eq!(bext.time_reference, 48_000);
+    }
+
+    #[test]
+    fn bad_bext_keeps_the_other_metadata() {
+        use crate::wav::cue::tests::cue_data;
+        let bytes = riff(&[
+            (FMT, extensible_fmt(2, 0x3)),
+            (BEXT, vec![0; 10]),
+            (cue::CUE, cue_data(&[(1, 3)])),
+            (DATA, vec![0; 8]),
+        ]);
+
+        let metadata = read_metadata(&mut std::io::Cursor::new(bytes)).unwrap();
+
+        assert_eq!(metadata.bext, None);
+        assert_eq!(metadata.channel_mask, Some(0x3));
+        assert_eq!(metadata.cue_points.len(), 1);
+    }
+
+    #[test]
+    fn truncated_cue_chunk_keeps_the_other_metadata() {
+        use crate::wav::cue::tests::cue_data;
+        let bext = crate::wav::bext::tests::bext_data("take 1", 48_000, &[]);
+        let mut cue = cue_data(&[(1, 3), (2, 5)]);
+        cue.truncate(cue.len() - 10);
+        let bytes = riff(&[
+            (FMT, extensible_fmt(2, 0x3)),
+            (BEXT, bext),
+            (cue::CUE, cue),
+            (DATA, vec![0; 8]),
+        ]);
+
+        let metadata = read_metadata(&mut std::io::Cursor::new(bytes)).unwrap();
+
+        assert!(metadata.cue_points.is_empty());
+        assert_eq!(metadata.channel_mask, Some(0x3));
+        assert_eq!(metadata.bext.unwrap().description, "take 1");
+    }
+
+    #[test]
+    fn cue_points_are_part_of_metadata() {
+        use crate::wav::cue::tests::{adtl_data, cue_data};
+        let bytes = riff(&[
+            (FMT, extensible_fmt(2, 0x3)),
+            (cue::LIST, adtl_data(&[(1, "click")], &[])),
+            (cue::LIST, b"INFOISFT".to_vec()),
+            (DATA, vec![0; 8]),
+            (cue::CUE, cue_data(&[(1, 3)])),
+        ]);
+
+        let metadata = read_metadata(&mut std::io::Cursor::new(bytes)).unwrap();
+
+        assert_eq!(metadata.cue_points.len(), 1);
+        assert_eq!(metadata.cue_points[0].sample_ix, 3);
+        assert_eq!(metadata.cue_points[0].label, "click");
+    }
+}
@@ -0,0 +1,187 @@
+//! Cue points from the `cue ` chunk and their labels from the `LIST` chunk of type `adtl`.
+
+use anyhow::{Result, ensure};
+
+pub const CUE: crate::wav::chunk::FourCC = *b"cue ";
+pub const LIST: crate::wav::chunk::FourCC = *b"LIST";
+pub const ADTL: crate::wav::chunk::FourCC = *b"adtl";
+const LABL: crate::wav::chunk::FourCC = *b"labl";
+const NOTE: crate::wav::chunk::FourCC = *b"note";
+const LTXT: crate::wav::chunk::FourCC = *b"ltxt";
+
+/// Size of a cue point entry in the `cue ` chunk
+const CUE_POINT_SIZE: usize = 24;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CuePoint {
+    /// `dwName`, the id the `adtl` sub-chunks refer to
+    pub id: u32,
+    /// Sample frame index of the cue point in the data chunk
+    pub sample_ix: u64,
+    /// Number of sample frames of a labelled region, from an `ltxt` sub-chunk
+    pub length: Option<u64>,
+    /// Text of the `labl` sub-chunk, or of the `ltxt` sub-chunk when there is no `labl`
+    pub label: String,
+    /// Text of the `note` sub-chunk
+    pub note: String,
+}
+
+impl CuePoint {
+    /// Text shown for the cue point: its label, or its note without label
+    pub fn text(&self) -> &str {
+        if self.label.is_empty() {
+            &self.note
+        } else {
+            &self.label
+        }
+    }
+}
+
+/// Parse the data of a `cue ` chunk, without labels.
+pub fn parse_cue(data: &[u8]) -> Result<Vec<CuePoint>> {
+    ensure!(data.len() >= 4, "cue chunk too small: {} bytes", data.len());
+    let nr_cue_points = u32::from_le_bytes(data[0..4].try_into()?) as usize;
+    let cue_points: Vec<_> = data[4..]
+        .chunks_exact(CUE_POINT_SIZE)
+        .take(nr_cue_points)
+        .map(|entry| {
+            let u32_at =
+                |offset: usize| u32::from_le_bytes(entry[offset..offset + 4].try_into().unwrap());
+            CuePoint {
+                id: u32_at(0),
+                // dwSampleOffset, dwPosition is the play order position and is often left zero
+                sample_ix: u32_at(20) as u64,
+                ..Default::default()
+            }
+        })
+        .collect();
+    ensure!(
+        cue_points.len() == nr_cue_points,
+        "cue chunk has {} of {nr_cue_points} cue points",
+        cue_points.len()
+    );
+    Ok(cue_points)
+}
+
+/// Add the labels, notes and region lengths of the data of a `LIST` chunk of type `adtl` to the
+/// cue points they refer to. Sub-chunks for unknown cue points are ignored.
+pub fn apply_adtl(data: &[u8], cue_points: &mut [CuePoint]) -> Result<()> {
+    ensure!(
+        data.len() >= 4 && data[0..4] == ADTL,
+        "Not an adtl LIST chunk"
+    );
+    let mut offset = 4;
+    while offset + 8 <= data.len() {
+        let id: crate::wav::chunk::FourCC = data[offset..offset + 4].try_into()?;
+        let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into()?) as usize;
+        let start = offset + 8;
+        let sub = &data[start..(start + size).min(data.len())];
+        // Sub-chunks are word aligned like chunks
+        offset = start + size + (size & 1);
+        if sub.len() < 4 {
+            continue;
+        }
+        let cue_id = u32::from_le_bytes(sub[0..4].try_into()?);
+        let Some(cue_point) = cue_points.iter_mut().find(|cue| cue.id == cue_id) else {
+            continue;
+        };
+        match id {
+            LABL => cue_point.label = text(&sub[4..]),
+            NOTE => cue_point.note = text(&sub[4..]),
+            // dwName, dwSampleLength, dwPurposeID, wCountry, wLanguage, wDialect, wCodePage
+            LTXT if sub.len() >= 20 => {
+                let length = u32::from_le_bytes(sub[4..8].try_into()?) as u64;
+                cue_point.length = (length > 0).then_some(length);
+                if cue_point.label.is_empty() {
+                    cue_point.label = text(&sub[20..]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Data of a `cue ` chunk with the given (id, sample index) cue points
+    pub(crate) fn cue_data(cue_points: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = (cue_points.len() as u32).to_le_bytes().to_vec();
+        for (id, sample_ix) in cue_points {
+            data.extend_from_slice(&id.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(b"data");
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&sample_ix.to_le_bytes());
+        }
+        data
+    }
+
+    fn sub_chunk(data: &mut Vec<u8>, id: &[u8; 4], payload: &[u8]) {
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+    }
+
+    /// Data of a `LIST` chunk of type `adtl` with a `labl` per (id, label) and an `ltxt` per
+    /// (id, region length)
+    pub(crate) fn adtl_data(labels: &[(u32, &str)], regions: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = ADTL.to_vec();
+        for (id, label) in labels {
+            let mut payload = id.to_le_bytes().to_vec();
+            payload.extend_from_slice(label.as_bytes());
+            payload.push(0);
+            sub_chunk(&mut data, &LABL, &payload);
+        }
+        for (id, length) in regions {
+            let mut payload = id.to_le_bytes().to_vec();
+            payload.extend_from_slice(&length.to_le_bytes());
+            payload.extend_from_slice(b"rgn ");
+            payload.extend_from_slice(&[0; 8]);
+            payload.extend_from_slice(b"region text\0");
+            sub_chunk(&mut data, &LTXT, &payload);
+        }
+        data
+    }
+
+    #[test]
+    fn cue_points_are_labelled_from_adtl() {
+        let mut cue_points = parse_cue(&cue_data(&[(1, 480), (2, 96_000), (3, 7)])).unwrap();
+        apply_adtl(
+            &adtl_data(&[(1, "onset"), (9, "unknown cue")], &[(2, 4800), (3, 0)]),
+            &mut cue_points,
+        )
+        .unwrap();
+
+        assert_eq!(cue_points.len(), 3);
+        assert_eq!(cue_points[0].sample_ix, 480);
+        assert_eq!(cue_points[0].label, "onset");
+        assert_eq!(cue_points[0].length, None);
+        assert_eq!(cue_points[1].sample_ix, 96_000);
+        assert_eq!(cue_points[1].label, "region text");
+        assert_eq!(cue_points[1].length, Some(4800));
+        assert_eq!(cue_points[2].length, None);
+    }
+
+    #[test]
+    fn truncated_cue_chunk_is_refused() {
+        let data = cue_data(&[(1, 480), (2, 960)]);
+        assert!(parse_cue(&data[..30]).is_err());
+        assert!(parse_cue(&data[..2]).is_err());
+        assert!(apply_adtl(b"INFO", &mut []).is_err());
+    }
+}
@@ -1,6 +1,10 @@
 use crate::{
     audio::{self, manager::BufferId},
-    wav::read::ChIx,
+    wav::{
+        bext::Bext,
+        cue::CuePoint,
+        read::{ChIx, ReadConfig},
+    },
 };
 use slotmap::new_key_type;
 use std::{collections::BTreeMap, path::PathBuf};
@@ -25,6 +29,12 @@ pub struct File {
     pub path: Option<PathBuf>,
     /// Number of samples per channel
     pub nr_samples: u64,
+    /// Broadcast Wave metadata, when the file has a `bext` chunk
+    pub bext: Option<Box<Bext>>,
+    /// Cue points of the `cue ` chunk with their `adtl` labels
+    pub cue_points: Vec<CuePoint>,
+    /// How the file was read from disk, None for files from memory
+    pub read_config: Option<ReadConfig>,
 }
 
 impl std::fmt::Display for File {
@@ -39,6 +49,9 @@ impl std::fmt::Display for File {
             write!(f, ", layout: {:?}", layout)?;
         }
         write!(f, ", nr_samples: {}", self.nr_samples)?;
+        if !self.cue_points.is_empty() {
+            write!(f, ", nr_cue_points: {}", self.cue_points.len())?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,226 @@
+//! Audacity label tracks: one `start\tend\tlabel` line per label, in seconds, and CSV export.
+
+use crate::wav::cue::CuePoint;
+use anyhow::{Context, Result, anyhow};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+    /// Audacity label track export
+    Audacity,
+    /// Sample indices and seconds, with a header
+    Csv,
+}
+
+impl LabelFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            LabelFormat::Audacity => "txt",
+            LabelFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Parse an Audacity label track. Labels with equal start and end are points, others regions.
+/// The spectral selection lines Audacity adds below a label (starting with `\`) are skipped.
+pub fn parse_audacity_labels(text: &str, sample_rate: u32) -> Result<Vec<CuePoint>> {
+    let to_sample_ix = |seconds: &str, line_nr: usize| -> Result<u64> {
+        let seconds: f64 = seconds
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid time '{seconds}' on line {line_nr}"))?;
+        anyhow::ensure!(seconds >= 0.0, "Negative time {seconds} on line {line_nr}");
+        Ok((seconds * sample_rate as f64).round() as u64)
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('\\'))
+        .map(|(ix, line)| {
+            let line_nr = ix + 1;
+            let mut fields = line.splitn(3, '\t');
+            let start = fields.next().unwrap_or_default();
+            let end = fields
+                .next()
+                .ok_or_else(|| anyhow!("Missing end time on line {line_nr}"))?;
+            let start_ix = to_sample_ix(start, line_nr)?;
+            let end_ix = to_sample_ix(end, line_nr)?;
+            Ok(CuePoint {
+                id: line_nr as u32,
+                sample_ix: start_ix.min(end_ix),
+                length: (end_ix != start_ix).then(|| start_ix.abs_diff(end_ix)),
+                label: fields.next().unwrap_or_default().trim_end().to_string(),
+                note: String::new(),
+            })
+        })
+        .collect()
+}
+
+/// Audacity label track of the cue points, with 6 decimals like Audacity writes them
+pub fn audacity_labels(cue_points: &[CuePoint], sample_rate: u32) -> String {
+    let seconds = |sample_ix: u64| sample_ix as f64 / sample_rate.max(1) as f64;
+    cue_points
+        .iter()
+        .map(|cue| {
+            let end_ix = cue.sample_ix + cue.length.unwrap_or(0);
+            format!(
+                "{:.6}\t{:.6}\t{}\n",
+                seconds(cue.sample_ix),
+                seconds(end_ix),
+                cue.label
+            )
+        })
+        .collect()
+}
+
+/// CSV with the start and end of each cue point as sample index and in seconds
+pub fn csv_labels(cue_points: &[CuePoint], sample_rate: u32) -> String {
+    let seconds = |sample_ix: u64| sample_ix as f64 / sample_rate.max(1) as f64;
+    let mut csv = String::from("label,start_sample,end_sample,start_s,end_s\n");
+    for cue in cue_points {
+        let end_ix = cue.sample_ix + cue.length.unwrap_or(0);
+        csv.push_str(&format!(
+            "{},{},{},{:.6},{:.6}\n",
+            csv_field(&cue.label),
+            cue.sample_ix,
+            end_ix,
+            seconds(cue.sample_ix),
+            seconds(end_ix)
+        ));
+    }
+    csv
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Label track next to a wav file, `<stem>.markers.txt`, where its markers are exported to and
+/// read back from. A plain `<stem>.txt` is left alone, it's too often an unrelated note.
+pub fn label_file_path(wav_path: &Path) -> PathBuf {
+    let stem = wav_path.file_stem().unwrap_or_default().to_string_lossy();
+    wav_path.with_file_name(format!(
+        "{stem}.markers.{}",
+        LabelFormat::Audacity.extension()
+    ))
+}
+
+/// Labels that don't repeat one of `cue_points`, e.g. the cue points of a file whose markers were
+/// exported to its label track
+pub fn without_cue_points(labels: Vec<CuePoint>, cue_points: &[CuePoint]) -> Vec<CuePoint> {
+    labels
+        .into_iter()
+        .filter(|label| {
+            !cue_points.iter().any(|cue_point| {
+                cue_point.sample_ix == label.sample_ix
+                    && cue_point.length == label.length
+                    && cue_point.text() == label.text()
+            })
+        })
+        .collect()
+}
+
+pub fn read_audacity_labels(path: &Path, sample_rate: u32) -> Result<Vec<CuePoint>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read labels '{}'", path.display()))?;
+    parse_audacity_labels(&text, sample_rate)
+        .with_context(|| format!("Failed to parse labels '{}'", path.display()))
+}
+
+pub fn write_labels(
+    path: &Path,
+    format: LabelFormat,
+    cue_points: &[CuePoint],
+    sample_rate: u32,
+) -> Result<()> {
+    let text = match format {
+        LabelFormat::Audacity => audacity_labels(cue_points, sample_rate),
+        LabelFormat::Csv => csv_labels(cue_points, sample_rate),
+    };
+    std::fs::write(path, text)
+        .with_context(|| format!("Failed to write labels '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: &str = "0.500000\t0.500000\tclick\n\
+                          1.000000\t1.250000\tdropout, left\n\
+                          \\\t20.0\t4000.0\n\
+                          \n\
+                          2.000000\t2.000000\t\n";
+
+    #[test]
+    fn audacity_labels_become_points_and_regions() {
+        let cue_points = parse_audacity_labels(LABELS, 48_000).unwrap();
+
+        assert_eq!(cue_points.len(), 3);
+        assert_eq!(cue_points[0].sample_ix, 24_000);
+        assert_eq!(cue_points[0].length, None);
+        assert_eq!(cue_points[0].label, "click");
+        assert_eq!(cue_points[1].sample_ix, 48_000);
+        assert_eq!(cue_points[1].length, Some(12_000));
+        assert_eq!(cue_points[1].label, "dropout, left");
+        assert_eq!(cue_points[2].label, "");
+    }
+
+    #[test]
+    fn audacity_labels_round_trip() {
+        let cue_points = parse_audacity_labels(LABELS, 44_100).unwrap();
+        let text = audacity_labels(&cue_points, 44_100);
+        let fields = |cue_points: &[CuePoint]| {
+            cue_points
+                .iter()
+                .map(|cue| (cue.sample_ix, cue.length, cue.label.clone()))
+                .collect::<Vec<_>>()
+        };
+        let read_back = parse_audacity_labels(&text, 44_100).unwrap();
+        assert_eq!(fields(&read_back), fields(&cue_points));
+        assert!(text.starts_with("0.500000\t0.500000\tclick\n"));
+    }
+
+    #[test]
+    fn csv_has_samples_and_seconds() {
+        let cue_points = parse_audacity_labels(LABELS, 48_000).unwrap();
+        let csv = csv_labels(&cue_points, 48_000);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "label,start_sample,end_sample,start_s,end_s");
+        assert_eq!(lines[1], "click,24000,24000,0.500000,0.500000");
+        assert_eq!(lines[2], "\"dropout, left\",48000,60000,1.000000,1.250000");
+    }
+
+    #[test]
+    fn labels_repeating_a_cue_point_are_skipped() {
+        let cue_points = vec![CuePoint {
+            id: 1,
+            sample_ix: 24_000,
+            note: "click".into(),
+            ..Default::default()
+        }];
+        let labels = parse_audacity_labels(LABELS, 48_000).unwrap();
+
+        let labels = without_cue_points(labels, &cue_points);
+
+        let texts: Vec<_> = labels.iter().map(CuePoint::text).collect();
+        assert_eq!(texts, ["dropout, left", ""]);
+    }
+
+    #[test]
+    fn label_file_is_named_after_the_wav_stem() {
+        assert_eq!(
+            label_file_path(Path::new("/data/take.1.wav")),
+            Path::new("/data/take.1.markers.txt")
+        );
+    }
+
+    #[test]
+    fn invalid_labels_are_refused() {
+        assert!(parse_audacity_labels("abc\t1.0\tx\n", 48_000).is_err());
+        assert!(parse_audacity_labels("1.0\n", 48_000).is_err());
+        assert!(parse_audacity_labels("-1.0\t1.0\tx\n", 48_000).is_err());
+    }
+}
@@ -1,5 +1,12 @@
+pub mod bext;
+pub mod chunk;
+pub mod cue;
 pub mod file;
 pub mod file2;
+pub mod labels;
 pub mod read;
+pub mod reader;
+pub mod stream;
+pub mod write;
 
 pub use read::*;
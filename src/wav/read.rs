@@ -3,7 +3,14 @@ use crate::audio::manager::Buffers;
 use crate::audio::sample;
 // use crate::audio::{BufferPool, SampleBuffer};
 use crate::audio::SampleType;
+use crate::audio::{self, channel::mask::ids_from_wave_channel_mask};
+use crate::wav::bext::Bext;
+use crate::wav::chunk;
+use crate::wav::cue::CuePoint;
 use crate::wav::file2::{Channel, File};
+use crate::wav::labels;
+use crate::wav::reader::{WavReader, WavSample};
+use crate::wav::stream::{self, StreamedChannel};
 use anyhow::{Result, ensure};
 use hound;
 use std::borrow::Cow;
@@ -20,7 +27,7 @@ use thousands::Separable;
 pub type ChIx = usize; // Channel index
 
 /// File-based read options (path + optional filters).
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ReadConfig {
     /// Path to wav file to read
     pub filepath: PathBuf,
@@ -45,9 +52,11 @@ pub struct ReadConfigBytes {
 
 /// Shared subset of read options for both file and byte sources.
 #[derive(Debug, Clone)]
-struct ReadOptions {
-    ch_ixs: Option<Vec<ChIx>>,
-    sample_range: sample::OptIxRange,
+pub(crate) struct ReadOptions {
+    pub(crate) ch_ixs: Option<Vec<ChIx>>,
+    pub(crate) sample_range: sample::OptIxRange,
+    /// Stream the samples from the file on disk instead of reading them
+    pub(crate) stream: bool,
 }
 
 /// Fully decoded data, ready to be integrated into the model.
@@ -59,16 +68,27 @@ pub struct LoadedFile {
     pub bit_depth: u16,
     pub sample_rate: u32,
     pub layout: Option<crate::audio::Layout>,
+    /// Speaker position per read channel, when the file has a channel mask
+    pub channel_ids: BTreeMap<ChIx, audio::Id>,
     pub path: Option<PathBuf>,
     /// Number of samples per channel
     pub nr_samples: u64,
+    /// Broadcast Wave metadata, when the file has a `bext` chunk
+    pub bext: Option<Box<Bext>>,
+    /// Cue points of the `cue ` chunk and of a label track next to the file, relative to the
+    /// first read sample
+    pub cue_points: Vec<CuePoint>,
+    /// How the file was read from disk, None for files from memory
+    pub read_config: Option<ReadConfig>,
+    /// Channels too large to keep in memory, streamed from disk instead of read into `channels`
+    pub streamed: BTreeMap<ChIx, StreamedChannel>,
 }
 
 pub type LoadId = u64;
 
 /// Message from loader to UI thread with the decoded file or an error.
 pub enum LoadResult {
-    Ok(LoadedFile),
+    Ok(Box<LoadedFile>),
     Err {
         load_id: LoadId,
         error: anyhow::Error,
@@ -175,6 +195,7 @@ impl From<&ReadConfig> for ReadOptions {
         Self {
             ch_ixs: value.ch_ixs.clone(),
             sample_range: value.sample_range,
+            stream: false,
         }
     }
 }
@@ -184,10 +205,63 @@ impl From<&ReadConfigBytes> for ReadOptions {
         Self {
             ch_ixs: value.ch_ixs.clone(),
             sample_range: value.sample_range,
+            stream: false,
         }
     }
 }
 
+/// Header level information of a wav file, read without the sample data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    pub sample_type: SampleType,
+    pub bit_depth: u16,
+    pub sample_rate: u32,
+    pub nr_channels: usize,
+    /// Number of samples per channel
+    pub nr_samples: u64,
+    pub layout: Option<crate::audio::Layout>,
+    /// Speaker position per channel in file order, when the file has a channel mask
+    pub channel_ids: Vec<audio::Id>,
+}
+
+impl FileInfo {
+    pub fn duration_s(&self) -> f64 {
+        self.nr_samples as f64 / self.sample_rate as f64
+    }
+}
+
+pub fn read_file_info(filepath: &std::path::Path) -> Result<FileInfo> {
+    let reader = open_wav_file(filepath)?;
+    let spec = reader.spec();
+    let metadata = std::fs::File::open(filepath)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| chunk::read_metadata(&mut std::io::BufReader::new(file)));
+    let metadata = metadata_or_default(metadata, &filepath.display().to_string());
+
+    let channel_ids = file_channel_ids(metadata.channel_mask, spec.channels as usize);
+    let layout = (!channel_ids.is_empty()).then(|| audio::Layout::from_ids(&channel_ids));
+    Ok(FileInfo {
+        path: filepath.to_path_buf(),
+        sample_type: spec.sample_format.into(),
+        bit_depth: spec.bits_per_sample,
+        sample_rate: spec.sample_rate,
+        nr_channels: spec.channels as usize,
+        nr_samples: reader.duration(),
+        layout,
+        channel_ids,
+    })
+}
+
+pub(crate) fn open_wav_file(
+    filepath: &std::path::Path,
+) -> Result<WavReader<std::io::BufReader<std::fs::File>>> {
+    std::fs::File::open(filepath)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| WavReader::new(std::io::BufReader::new(file)))
+        .map_err(|err| anyhow::anyhow!("Failed to open wav file '{}': {}", filepath.display(), err))
+}
+
 // TODO: think of better name :)
 pub fn read_to_file(config: &ReadConfig, buffers: &mut Buffers) -> Result<File> {
     let loaded = read_to_loaded_file(config)?;
@@ -202,21 +276,65 @@ pub fn read_to_loaded_file_with_progress(
     config: &ReadConfig,
     load_id: LoadId,
     progress: Option<&LoadProgressAtomic>,
+) -> Result<LoadedFile> {
+    read_path_to_loaded_file(config, false, load_id, progress)
+}
+
+/// Like `read_to_loaded_file_with_progress`, but the channels are streamed from disk when their
+/// samples would take more than `stream_threshold` bytes in memory
+pub fn read_or_stream_loaded_file(
+    config: &ReadConfig,
+    stream_threshold: u64,
+    load_id: LoadId,
+    progress: Option<&LoadProgressAtomic>,
+) -> Result<LoadedFile> {
+    let info = read_file_info(&config.filepath)?;
+    let stream = stream::in_memory_size(&info, config) > stream_threshold;
+    read_path_to_loaded_file(config, stream, load_id, progress)
+}
+
+fn read_path_to_loaded_file(
+    config: &ReadConfig,
+    stream: bool,
+    load_id: LoadId,
+    progress: Option<&LoadProgressAtomic>,
 ) -> Result<LoadedFile> {
     let Some(filepath) = config.filepath.to_str() else {
         return Err(anyhow::anyhow!("Invalid filepath"));
     };
-    let options = ReadOptions::from(config);
-    let reader = hound::WavReader::open(&config.filepath)
-        .map_err(|err| anyhow::anyhow!("Failed to open wav file '{}': {}", filepath, err))?;
+    let options = ReadOptions {
+        stream,
+        ..ReadOptions::from(config)
+    };
+    let reader = open_wav_file(&config.filepath)?;
+    let metadata = std::fs::File::open(&config.filepath)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| chunk::read_metadata(&mut std::io::BufReader::new(file)));
+    let mut metadata = metadata_or_default(metadata, filepath);
+    // An Audacity label track next to the file adds to its cue points, without repeating them
+    let label_path = labels::label_file_path(&config.filepath);
+    if label_path.is_file() {
+        match labels::read_audacity_labels(&label_path, reader.spec().sample_rate) {
+            Ok(cue_points) => {
+                let cue_points = labels::without_cue_points(cue_points, &metadata.cue_points);
+                metadata.cue_points.extend(cue_points);
+            }
+            Err(err) => tracing::warn!("{err:#}"),
+        }
+    }
     read_to_loaded_file_from_reader(
         reader,
+        metadata,
         &options,
         load_id,
         progress,
         filepath,
         Some(PathBuf::from(&config.filepath)),
     )
+    .map(|file| LoadedFile {
+        read_config: Some(config.clone()),
+        ..file
+    })
 }
 
 // Same pipeline as file-based reading, but from an in-memory cursor.
@@ -227,10 +345,12 @@ pub fn read_bytes_to_loaded_file_with_progress(
 ) -> Result<LoadedFile> {
     let options = ReadOptions::from(config);
     let label = config.name.as_deref().unwrap_or("bytes");
-    let reader = hound::WavReader::new(std::io::Cursor::new(&config.bytes))
+    let reader = WavReader::new(std::io::Cursor::new(&config.bytes))
         .map_err(|err| anyhow::anyhow!("Failed to open wav bytes '{}': {}", label, err))?;
+    let metadata = chunk::read_metadata(&mut std::io::Cursor::new(&config.bytes));
     read_to_loaded_file_from_reader(
         reader,
+        metadata_or_default(metadata, label),
         &options,
         load_id,
         progress,
@@ -239,9 +359,29 @@ pub fn read_bytes_to_loaded_file_with_progress(
     )
 }
 
+// Missing metadata shouldn't prevent us from showing the samples.
+fn metadata_or_default(metadata: Result<chunk::Metadata>, source_label: &str) -> chunk::Metadata {
+    metadata.unwrap_or_else(|err| {
+        tracing::warn!("Failed to read wav chunks of '{}': {}", source_label, err);
+        chunk::Metadata::default()
+    })
+}
+
+/// Channel ids for all channels in the file, in file order, derived from the channel mask.
+///
+/// Channels beyond the speakers in the mask don't get an id.
+fn file_channel_ids(channel_mask: Option<u32>, nr_channels: usize) -> Vec<audio::Id> {
+    let mut ids = channel_mask
+        .map(ids_from_wave_channel_mask)
+        .unwrap_or_default();
+    ids.truncate(nr_channels);
+    ids
+}
+
 // Shared implementation for file paths and byte buffers.
 fn read_to_loaded_file_from_reader<R: std::io::Read + std::io::Seek>(
-    mut reader: hound::WavReader<R>,
+    mut reader: WavReader<R>,
+    metadata: chunk::Metadata,
     options: &ReadOptions,
     load_id: LoadId,
     progress: Option<&LoadProgressAtomic>,
@@ -264,13 +404,76 @@ fn read_to_loaded_file_from_reader<R: std::io::Read + std::io::Seek>(
         reader.duration().separate_with_commas()
     );
 
+    let (chix_buffers, streamed) = match path.as_deref().filter(|_| options.stream) {
+        Some(filepath) => (
+            BTreeMap::new(),
+            stream::stream_channels(filepath, options, progress)?,
+        ),
+        None => (
+            read_buffers_e(&mut reader, options, progress)?,
+            BTreeMap::new(),
+        ),
+    };
+
+    if let Some(progress) = progress {
+        progress.set_stage(LoadStage::Finalizing, 1);
+        progress.set_current(1);
+    }
+
+    let file_ids = file_channel_ids(metadata.channel_mask, spec.channels as usize);
+    let layout = (!file_ids.is_empty()).then(|| audio::Layout::from_ids(&file_ids));
+    let channel_ids = chix_buffers
+        .keys()
+        .chain(streamed.keys())
+        .filter_map(|ch_ix| file_ids.get(*ch_ix).map(|id| (*ch_ix, *id)))
+        .collect();
+    let sample_range = options
+        .sample_range
+        .to_ix_range(0, reader.duration() as i64);
+    let cue_points = cue_points_in_range(metadata.cue_points, sample_range);
+
+    let file = LoadedFile {
+        load_id,
+        channels: chix_buffers,
+        layout,
+        channel_ids,
+        sample_rate: spec.sample_rate,
+        bit_depth: spec.bits_per_sample,
+        sample_type: spec.sample_format.into(),
+        path,
+        nr_samples: reader.duration(),
+        bext: metadata.bext.map(Box::new),
+        cue_points,
+        read_config: None,
+        streamed,
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    tracing::trace!("Read wav data in {:?}: {}", start.elapsed(), file);
+    #[cfg(target_arch = "wasm32")]
+    tracing::trace!("Read wav data: {}", file);
+
+    Ok(file)
+}
+
+/// Read the samples of the selected channels and range into buffers of the sample type of the file
+pub(crate) fn read_buffers_e<R: std::io::Read + std::io::Seek>(
+    reader: &mut WavReader<R>,
+    options: &ReadOptions,
+    progress: Option<&LoadProgressAtomic>,
+) -> Result<BTreeMap<ChIx, BufferE>> {
+    let spec = reader.spec();
     // read samples into appropriate type and associate with channel index
     let chix_buffers: BTreeMap<ChIx, BufferE> = match spec.sample_format {
         hound::SampleFormat::Float => match spec.bits_per_sample {
             bit_depth if bit_depth <= 32 => convert_samples(
-                read_to_buffers::<f32, _>(&mut reader, options, progress)?,
+                read_to_buffers::<f32, _>(reader, options, progress)?,
                 BufferE::F32,
             ),
+            64 => convert_samples(
+                read_to_buffers::<f64, _>(reader, options, progress)?,
+                BufferE::F64,
+            ),
             _ => {
                 return Err(anyhow::anyhow!(
                     "Unsupported bit depth for float: {}",
@@ -280,11 +483,11 @@ fn read_to_loaded_file_from_reader<R: std::io::Read + std::io::Seek>(
         },
         hound::SampleFormat::Int => match spec.bits_per_sample {
             bit_depth if bit_depth <= 16 => convert_samples(
-                read_to_buffers::<i16, _>(&mut reader, options, progress)?,
+                read_to_buffers::<i16, _>(reader, options, progress)?,
                 BufferE::I16,
             ),
             bit_depth if bit_depth <= 32 => convert_samples(
-                read_to_buffers::<i32, _>(&mut reader, options, progress)?,
+                read_to_buffers::<i32, _>(reader, options, progress)?,
                 BufferE::I32,
             ),
             _ => {
@@ -296,28 +499,7 @@ fn read_to_loaded_file_from_reader<R: std::io::Read + std::io::Seek>(
         },
     };
 
-    if let Some(progress) = progress {
-        progress.set_stage(LoadStage::Finalizing, 1);
-        progress.set_current(1);
-    }
-
-    let file = LoadedFile {
-        load_id,
-        channels: chix_buffers,
-        layout: None, // TODO: first need to extend hound to 'publish' the wavextended channel mask?
-        sample_rate: spec.sample_rate,
-        bit_depth: spec.bits_per_sample,
-        sample_type: spec.sample_format.into(),
-        path,
-        nr_samples: reader.duration() as u64,
-    };
-
-    #[cfg(not(target_arch = "wasm32"))]
-    tracing::trace!("Read wav data in {:?}: {}", start.elapsed(), file);
-    #[cfg(target_arch = "wasm32")]
-    tracing::trace!("Read wav data: {}", file);
-
-    Ok(file)
+    Ok(chix_buffers)
 }
 
 // TODO: maybe use Vec<(ChIx, Vec<T>)> instead of HashMap<ChIx, Vec<T>>?
@@ -331,15 +513,42 @@ fn convert_samples<T>(
         .collect()
 }
 
+/// Keep the cue points inside the read range, with their sample index relative to its start.
+/// Regions overlapping the range are clipped to it.
+fn cue_points_in_range(cue_points: Vec<CuePoint>, sample_range: sample::IxRange) -> Vec<CuePoint> {
+    let range_start = sample_range.start.max(0) as u64;
+    let range_end = sample_range.end.max(0) as u64;
+    cue_points
+        .into_iter()
+        .filter_map(|cue| match cue.length {
+            Some(length) if length > 0 => {
+                let start = cue.sample_ix.max(range_start);
+                let end = cue.sample_ix.saturating_add(length).min(range_end);
+                (start < end).then(|| CuePoint {
+                    sample_ix: start - range_start,
+                    length: Some(end - start),
+                    ..cue
+                })
+            }
+            _ => (range_start..range_end)
+                .contains(&cue.sample_ix)
+                .then(|| CuePoint {
+                    sample_ix: cue.sample_ix - range_start,
+                    ..cue
+                }),
+        })
+        .collect()
+}
+
 /// Reads interleaved samples into buffers, honoring channel/range filters.
 fn read_to_buffers<S, R>(
-    reader: &mut hound::WavReader<R>,
+    reader: &mut WavReader<R>,
     options: &ReadOptions,
     progress: Option<&LoadProgressAtomic>,
 ) -> Result<BTreeMap<ChIx, Buffer<S>>>
 where
     R: std::io::Read + std::io::Seek,
-    S: crate::audio::sample::Sample + hound::Sample,
+    S: crate::audio::sample::Sample + WavSample,
 {
     let nr_channels = reader.spec().channels as usize;
     let reader_duration = reader.duration() as i64;
@@ -353,7 +562,7 @@ where
 
     // Seek to the start position
     if sample_range.start > 0 {
-        reader.seek(sample_range.start as u32)?;
+        reader.seek(sample_range.start as u64)?;
     }
 
     // Read the desired number of interleaved samples
@@ -482,7 +691,11 @@ impl std::fmt::Display for LoadedFile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "LoadedFile:")?;
         write!(f, " path: {:?}", self.path)?;
-        write!(f, ", nr_channels: {}", self.channels.len())?;
+        write!(
+            f,
+            ", nr_channels: {}",
+            self.channels.len() + self.streamed.len()
+        )?;
         write!(f, ", sample_type: {:?}", self.sample_type)?;
         write!(f, ", bit_depth: {}", self.bit_depth)?;
         write!(f, ", sample_rate: {}", self.sample_rate)?;
@@ -495,7 +708,9 @@ impl std::fmt::Display for LoadedFile {
 }
 
 impl LoadedFile {
+    /// Move the buffers into `buffers`. Files of `read_to_loaded_file` are never streamed.
     pub fn into_file(self, buffers: &mut Buffers) -> File {
+        let channel_ids = self.channel_ids;
         File {
             // move buffers to storage and store it's id in file
             channels: self
@@ -506,8 +721,8 @@ impl LoadedFile {
                         ch_ix,
                         Channel {
                             ch_ix,
-                            buffer_id: buffers.insert(buffer),
-                            channel_id: None,
+                            buffer_id: buffers.insert(std::sync::Arc::new(buffer)),
+                            channel_id: channel_ids.get(&ch_ix).copied(),
                         },
                     )
                 })
@@ -518,6 +733,9 @@ impl LoadedFile {
             sample_type: self.sample_type,
             path: self.path,
             nr_samples: self.nr_samples,
+            bext: self.bext,
+            cue_points: self.cue_points,
+            read_config: self.read_config,
         }
     }
 }
@@ -898,6 +1116,111 @@ mod tests {
     #[allow(unused_imports)]
     use crate::util;
 
+    #[test]
+    fn rf64_bytes_are_read_with_channel_and_range_filter() {
+        let spec = hound::WavSpec {
+            channels: 3,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples: Vec<i16> = (0..300).collect();
+        let bytes = crate::wav::reader::tests::rf64_bytes(spec, &samples, chunk::RF64);
+        let config = ReadConfigBytes::new(Some("big.wav".into()), bytes)
+            .with_ch_ixs([0, 2])
+            .with_sample_range(sample::OptIxRange {
+                start: Some(10),
+                end: Some(20),
+            });
+
+        let loaded = read_bytes_to_loaded_file_with_progress(&config, 0, None).unwrap();
+
+        assert_eq!(loaded.nr_samples, 100);
+        assert_eq!(loaded.sample_rate, 48_000);
+        assert_eq!(loaded.channels.keys().copied().collect::<Vec<_>>(), [0, 2]);
+        let BufferE::I16(buffer) = &loaded.channels[&2] else {
+            panic!("expected i16 samples");
+        };
+        assert_eq!(
+            buffer.data,
+            (10..20).map(|ix| ix * 3 + 2).collect::<Vec<i16>>()
+        );
+    }
+
+    #[test]
+    fn bw64_float_file_is_read() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 96_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let samples: Vec<f32> = (0..64).map(|ix| ix as f32 / 64.0).collect();
+        let bytes = crate::wav::reader::tests::rf64_bytes(spec, &samples, chunk::BW64);
+        let path = std::env::temp_dir().join(format!("wavalyze_bw64_{}.wav", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let info = read_file_info(&path).unwrap();
+        let loaded = read_to_loaded_file(&ReadConfig::new(&path).with_ch_ixs([1]));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(info.nr_samples, 32);
+        assert_eq!(info.nr_channels, 2);
+        assert_eq!(info.sample_type, SampleType::Float);
+        let loaded = loaded.unwrap();
+        let BufferE::F32(buffer) = &loaded.channels[&1] else {
+            panic!("expected f32 samples");
+        };
+        assert_eq!(buffer.data.len(), 32);
+        assert_eq!(buffer.data[3], 7.0 / 64.0);
+    }
+
+    #[test]
+    fn label_track_next_to_file_is_read_within_range() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples: Vec<i16> = (0..100).collect();
+        let bytes = crate::wav::reader::tests::rf64_bytes(spec, &samples, chunk::RF64);
+        let path = std::env::temp_dir().join(format!("wavalyze_labels_{}.wav", std::process::id()));
+        let label_path = labels::label_file_path(&path);
+        std::fs::write(&path, bytes).unwrap();
+        std::fs::write(
+            &label_path,
+            "0.005\t0.005\tbefore\n0.005\t0.015\tstraddling\n0.020\t0.030\tglitch\n\
+             0.045\t0.060\tcut\n0.070\t0.080\tafter\n",
+        )
+        .unwrap();
+
+        let loaded = read_to_loaded_file(&ReadConfig::new(&path).with_sample_range(
+            sample::OptIxRange {
+                start: Some(10),
+                end: Some(50),
+            },
+        ));
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&label_path).unwrap();
+
+        let cue_points: Vec<_> = loaded
+            .unwrap()
+            .cue_points
+            .into_iter()
+            .map(|cue| (cue.label, cue.sample_ix, cue.length))
+            .collect();
+        // regions overlapping the range are clipped to it
+        assert_eq!(
+            cue_points,
+            [
+                ("straddling".to_string(), 0, Some(5)),
+                ("glitch".to_string(), 10, Some(10)),
+                ("cut".to_string(), 35, Some(5)),
+            ]
+        );
+    }
+
     // #[test]
     // fn test_read_wav_file_to_float() {
     //     #[allow(dead_code)]
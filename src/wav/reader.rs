@@ -0,0 +1,346 @@
+//! Reader for the sample data of RIFF/WAVE files and of RF64 and BW64 files, the 64-bit variants
+//! for files larger than 4 GB.
+//!
+//! hound rejects RF64/BW64 headers and 64-bit float samples, so the `fmt ` and `data` chunks are
+//! located with the chunk scanner. Integer and 32-bit float samples are decoded with hound's
+//! `Sample::read`, which gives the same values as `hound::WavReader` for the same data.
+
+use crate::wav::chunk;
+use anyhow::{Context, Result, ensure};
+use std::io::{Read, Seek, SeekFrom};
+
+/// `wFormatTag` values, also the first two bytes of the extensible SubFormat GUID
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+
+/// Sample types the data chunk can be decoded to
+pub trait WavSample: Sized {
+    /// Read one sample of `nr_bytes` bytes with `nr_bits` valid bits
+    fn read<R: Read>(
+        reader: &mut R,
+        format: hound::SampleFormat,
+        nr_bytes: u16,
+        nr_bits: u16,
+    ) -> Result<Self>;
+}
+
+macro_rules! impl_wav_sample_with_hound {
+    ($($t:ty),*) => {
+        $(
+            impl WavSample for $t {
+                fn read<R: Read>(
+                    reader: &mut R,
+                    format: hound::SampleFormat,
+                    nr_bytes: u16,
+                    nr_bits: u16,
+                ) -> Result<Self> {
+                    <$t as hound::Sample>::read(reader, format, nr_bytes, nr_bits)
+                        .map_err(|err| anyhow::anyhow!("{err}"))
+                }
+            }
+        )*
+    };
+}
+impl_wav_sample_with_hound!(i16, i32, f32);
+
+impl WavSample for f64 {
+    fn read<R: Read>(
+        reader: &mut R,
+        format: hound::SampleFormat,
+        nr_bytes: u16,
+        nr_bits: u16,
+    ) -> Result<Self> {
+        ensure!(
+            format == hound::SampleFormat::Float && nr_bytes == 8 && nr_bits == 64,
+            "Can't read {nr_bits} bit {format:?} samples as f64"
+        );
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+}
+
+pub struct WavReader<R> {
+    reader: R,
+    spec: hound::WavSpec,
+    /// Bytes per sample of one channel, can be larger than the bits per sample need
+    nr_bytes_per_sample: u16,
+    data_offset: u64,
+    /// Number of samples per channel
+    nr_samples: u64,
+    /// Sample index per channel the next read starts at
+    position: u64,
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let headers = chunk::read_chunk_headers(&mut reader)?;
+        let find = |id: chunk::FourCC| {
+            headers
+                .iter()
+                .find(|header| header.id == id)
+                .with_context(|| format!("No '{}' chunk", String::from_utf8_lossy(&id)))
+        };
+        let fmt = find(chunk::FMT)?;
+        let (spec, block_align) = parse_fmt(&chunk::read_chunk_data(&mut reader, fmt)?)?;
+        let data = find(chunk::DATA)?;
+
+        // A capture that was cut off has less data than its header says
+        let source_len = reader.seek(SeekFrom::End(0))?;
+        let data_size = data.size.min(source_len.saturating_sub(data.data_offset));
+        reader.seek(SeekFrom::Start(data.data_offset))?;
+        Ok(Self {
+            reader,
+            spec,
+            nr_bytes_per_sample: block_align / spec.channels,
+            data_offset: data.data_offset,
+            nr_samples: data_size / block_align as u64,
+            position: 0,
+        })
+    }
+
+    pub fn spec(&self) -> hound::WavSpec {
+        self.spec
+    }
+
+    /// Number of samples per channel
+    pub fn duration(&self) -> u64 {
+        self.nr_samples
+    }
+
+    /// Continue reading at `sample_ix` per channel
+    pub fn seek(&mut self, sample_ix: u64) -> Result<()> {
+        ensure!(
+            sample_ix <= self.nr_samples,
+            "Can't seek to sample {sample_ix}, the file has {} samples",
+            self.nr_samples
+        );
+        let block_align = self.nr_bytes_per_sample as u64 * self.spec.channels as u64;
+        self.reader
+            .seek(SeekFrom::Start(self.data_offset + sample_ix * block_align))?;
+        self.position = sample_ix;
+        Ok(())
+    }
+
+    /// Interleaved samples from the current position to the end of the data
+    pub fn samples<S: WavSample>(&mut self) -> impl Iterator<Item = Result<S>> {
+        let nr_values = (self.nr_samples - self.position) * self.spec.channels as u64;
+        self.position = self.nr_samples;
+        let (format, nr_bytes, nr_bits) = (
+            self.spec.sample_format,
+            self.nr_bytes_per_sample,
+            self.spec.bits_per_sample,
+        );
+        let reader = &mut self.reader;
+        (0..nr_values).map(move |_| S::read(reader, format, nr_bytes, nr_bits))
+    }
+}
+
+/// Spec and block align from the data of a `fmt ` chunk
+fn parse_fmt(data: &[u8]) -> Result<(hound::WavSpec, u16)> {
+    ensure!(
+        data.len() >= 16,
+        "fmt chunk too small: {} bytes",
+        data.len()
+    );
+    let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let mut format_tag = u16_at(0);
+    let channels = u16_at(2);
+    let sample_rate = u32::from_le_bytes(data[4..8].try_into()?);
+    let block_align = u16_at(12);
+    let mut bits_per_sample = u16_at(14);
+    if format_tag == chunk::WAVE_FORMAT_EXTENSIBLE {
+        ensure!(data.len() >= 26, "Extensible fmt chunk too small");
+        // wValidBitsPerSample, then the channel mask and the SubFormat GUID. Like hound, fall back
+        // to the container size when the valid bits are missing.
+        if u16_at(18) > 0 {
+            bits_per_sample = u16_at(18);
+        }
+        format_tag = u16_at(24);
+    }
+    let sample_format = match format_tag {
+        WAVE_FORMAT_PCM => hound::SampleFormat::Int,
+        WAVE_FORMAT_IEEE_FLOAT => hound::SampleFormat::Float,
+        _ => anyhow::bail!("Unsupported wav format 0x{format_tag:04X}"),
+    };
+    ensure!(channels > 0, "fmt chunk has no channels");
+    ensure!(
+        block_align > 0 && block_align.is_multiple_of(channels),
+        "Invalid block align {block_align} for {channels} channels"
+    );
+    ensure!(
+        bits_per_sample > 0 && bits_per_sample <= block_align / channels * 8,
+        "Invalid bits per sample {bits_per_sample} for block align {block_align}"
+    );
+    Ok((
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        },
+        block_align,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Write `samples` with hound, then turn the RIFF header into an RF64 (or BW64) header with a
+    /// `ds64` chunk in place of the `JUNK` chunk, as a converting writer would.
+    pub(crate) fn rf64_bytes<S: hound::Sample + Copy>(
+        spec: hound::WavSpec,
+        samples: &[S],
+        id: chunk::FourCC,
+    ) -> Vec<u8> {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let wav = cursor.into_inner();
+
+        let mut cursor = std::io::Cursor::new(&wav);
+        let headers = chunk::read_chunk_headers(&mut cursor).unwrap();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&id);
+        bytes.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        bytes.extend_from_slice(&chunk::WAVE);
+        for header in headers {
+            let data = chunk::read_chunk_data(&mut cursor, &header).unwrap();
+            if header.id == chunk::DATA {
+                let mut ds64 = Vec::new();
+                ds64.extend_from_slice(&0u64.to_le_bytes());
+                ds64.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                ds64.extend_from_slice(&0u64.to_le_bytes());
+                ds64.extend_from_slice(&0u32.to_le_bytes());
+                // ds64 has to come first, so put it right after the header
+                let mut with_ds64 = bytes[..12].to_vec();
+                with_ds64.extend_from_slice(&chunk::DS64);
+                with_ds64.extend_from_slice(&(ds64.len() as u32).to_le_bytes());
+                with_ds64.extend_from_slice(&ds64);
+                with_ds64.extend_from_slice(&bytes[12..]);
+                bytes = with_ds64;
+                bytes.extend_from_slice(&chunk::DATA);
+                bytes.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&header.id);
+                bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            }
+            bytes.extend_from_slice(&data);
+            if data.len() % 2 == 1 {
+                bytes.push(0);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_the_same_samples_as_hound() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44_100,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples: Vec<i32> = (0..200).map(|ix| (ix - 100) * 40_000).collect();
+        let bytes = rf64_bytes(spec, &samples, chunk::RF64);
+
+        let mut reader = WavReader::new(std::io::Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(reader.spec(), spec);
+        assert_eq!(reader.duration(), 100);
+        let read: Vec<i32> = reader.samples().collect::<Result<_>>().unwrap();
+        assert_eq!(read, samples);
+
+        reader.seek(90).unwrap();
+        let read: Vec<i32> = reader.samples().collect::<Result<_>>().unwrap();
+        assert_eq!(read, samples[180..]);
+        assert!(reader.seek(101).is_err());
+    }
+
+    #[test]
+    fn truncated_data_is_read_up_to_the_end_of_the_source() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let samples: Vec<f32> = (0..64).map(|ix| ix as f32 / 64.0).collect();
+        let mut bytes = rf64_bytes(spec, &samples, chunk::BW64);
+        bytes.truncate(bytes.len() - 4 * 10 - 2);
+
+        let mut reader = WavReader::new(std::io::Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(reader.duration(), 53);
+        let read: Vec<f32> = reader.samples().collect::<Result<_>>().unwrap();
+        assert_eq!(read, samples[..53]);
+    }
+
+    #[test]
+    fn riff_reads_the_same_samples_as_hound() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48_000,
+            bits_per_sample: 8,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for sample in -128..128 {
+            writer.write_sample(sample as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        let bytes = cursor.into_inner();
+        let expected: Vec<i16> = hound::WavReader::new(std::io::Cursor::new(&bytes))
+            .unwrap()
+            .samples()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let mut reader = WavReader::new(std::io::Cursor::new(&bytes)).unwrap();
+
+        let read: Vec<i16> = reader.samples().collect::<Result<_>>().unwrap();
+        assert_eq!(read, expected);
+        assert_eq!(read[0], -128);
+    }
+
+    #[test]
+    fn float64_riff_is_read_at_full_precision() {
+        let samples = [0.1f64, -1.0 / 3.0, f64::MIN_POSITIVE];
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&3u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&48_000u32.to_le_bytes());
+        fmt.extend_from_slice(&(48_000u32 * 8).to_le_bytes());
+        fmt.extend_from_slice(&8u16.to_le_bytes());
+        fmt.extend_from_slice(&64u16.to_le_bytes());
+        let data: Vec<u8> = samples.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&chunk::RIFF);
+        bytes.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&chunk::WAVE);
+        for (id, chunk_data) in [(chunk::FMT, &fmt), (chunk::DATA, &data)] {
+            bytes.extend_from_slice(&id);
+            bytes.extend_from_slice(&(chunk_data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(chunk_data);
+        }
+
+        let mut reader = WavReader::new(std::io::Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(reader.spec().bits_per_sample, 64);
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+        let read: Vec<f64> = reader.samples().collect::<Result<_>>().unwrap();
+        assert_eq!(read, samples);
+        reader.seek(0).unwrap();
+        assert!(reader.samples::<f32>().next().unwrap().is_err());
+    }
+
+    #[test]
+    fn non_wav_is_refused() {
+        assert!(WavReader::new(std::io::Cursor::new(b"not a wav file".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,590 @@
+//! Streams the samples of files too large to keep in memory from disk.
+//!
+//! The thumbnails of a streamed file are built in one pass over the file, block by block. When
+//! zoomed in further than their finest level, the samples of a channel around its visible range
+//! are read on the worker thread of the file, meanwhile its thumbnail is shown. What was read is
+//! kept per channel up to `WINDOW_BUDGET`.
+
+use crate::{
+    audio::{
+        SampleType,
+        buffer::BufferE,
+        sample,
+        thumbnail::{ThumbnailBuilderE, ThumbnailConfig, ThumbnailE},
+    },
+    wav::read::{
+        ChIx, FileInfo, LoadProgressAtomic, LoadStage, ReadConfig, ReadOptions, open_wav_file,
+        read_buffers_e,
+    },
+};
+use anyhow::{Context, Result, ensure};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock, PoisonError, mpsc::Sender},
+};
+
+/// Finest thumbnail level of streamed buffers. Coarser than for buffers in memory, so the
+/// thumbnails of a long multichannel recording stay small, zooming in further reads from disk.
+pub const THUMBNAIL_SAMPLES_PER_PIXEL: u64 = 1024;
+
+/// Samples per channel read at once while building the thumbnails, a multiple of
+/// `THUMBNAIL_SAMPLES_PER_PIXEL`
+const BLOCK_LEN: sample::Ix = 256 * 1024;
+
+/// Bytes of samples kept per streamed file, the least recently used reads are dropped beyond it
+const WINDOW_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Bytes the samples that `config` reads from the file would take in memory
+pub fn in_memory_size(info: &FileInfo, config: &ReadConfig) -> u64 {
+    let nr_channels = config
+        .ch_ixs
+        .as_ref()
+        .map_or(info.nr_channels, |ch_ixs| ch_ixs.len());
+    let nr_samples = config
+        .sample_range
+        .to_ix_range(0, info.nr_samples as sample::Ix)
+        .len()
+        .max(0) as u64;
+    let bytes_per_sample = match info.sample_type {
+        SampleType::Float if info.bit_depth <= 32 => 4,
+        SampleType::Float => 8,
+        SampleType::Int if info.bit_depth <= 16 => 2,
+        SampleType::Int => 4,
+    };
+    nr_channels as u64 * nr_samples * bytes_per_sample
+}
+
+/// Samples of a streamed channel read from disk
+#[derive(Debug)]
+struct Window {
+    ch_ix: ChIx,
+    /// Relative to the first streamed sample
+    ix_range: sample::IxRange,
+    buffer: Arc<BufferE>,
+    /// `Windows::nr_uses` when the window was last used, the least recently used is dropped first
+    last_use: u64,
+}
+
+impl Window {
+    fn holds(&self, ch_ix: ChIx, ix_range: sample::IxRange) -> bool {
+        self.ch_ix == ch_ix
+            && self.ix_range.start <= ix_range.start
+            && ix_range.end <= self.ix_range.end
+    }
+}
+
+/// A failed read is tried again once the source was asked for samples this many times since,
+/// twice as many after each next failure
+const RETRY_AFTER_NR_USES: u64 = 64;
+
+/// Most doublings of `RETRY_AFTER_NR_USES` after reads that keep failing
+const MAX_RETRY_SHIFT: u32 = 10;
+
+/// A read that failed, requests within its range wait until `retry_at` before reading again
+#[derive(Debug)]
+struct Failure {
+    ch_ix: ChIx,
+    ix_range: sample::IxRange,
+    nr_failures: u32,
+    /// `Windows::nr_uses` from which the range is read again
+    retry_at: u64,
+}
+
+/// What was read of a streamed file and what is being read
+#[derive(Debug, Default)]
+struct Windows {
+    windows: Vec<Window>,
+    /// Reads waiting for the worker, at most one per channel, a later request replaces it
+    queued: Vec<(ChIx, sample::IxRange)>,
+    /// Read the worker is busy with
+    reading: Option<(ChIx, sample::IxRange)>,
+    failed: Vec<Failure>,
+    nr_uses: u64,
+    /// Number of finished reads, views drawn before the last one may show the thumbnail instead
+    nr_reads: u64,
+}
+
+impl Windows {
+    /// Whether the samples of `ix_range` are queued or being read, or failed to read recently
+    fn is_requested(&self, ch_ix: ChIx, ix_range: sample::IxRange) -> bool {
+        let holds = |read_ch_ix: ChIx, read_range: sample::IxRange| {
+            read_ch_ix == ch_ix
+                && read_range.start <= ix_range.start
+                && ix_range.end <= read_range.end
+        };
+        self.reading
+            .iter()
+            .chain(&self.queued)
+            .any(|&(read_ch_ix, read_range)| holds(read_ch_ix, read_range))
+            || self.failed.iter().any(|failure| {
+                self.nr_uses < failure.retry_at && holds(failure.ch_ix, failure.ix_range)
+            })
+    }
+
+    /// Queue a read, dropping the queued read of the same channel it supersedes
+    fn queue(&mut self, ch_ix: ChIx, ix_range: sample::IxRange) {
+        self.queued
+            .retain(|(queued_ch_ix, _)| *queued_ch_ix != ch_ix);
+        self.queued.push((ch_ix, ix_range));
+    }
+
+    /// Start the read that was queued first
+    fn next_read(&mut self) -> Option<(ChIx, sample::IxRange)> {
+        if self.queued.is_empty() {
+            return None;
+        }
+        let read = self.queued.remove(0);
+        self.reading = Some(read);
+        Some(read)
+    }
+
+    /// Keep the samples of a finished read, dropping the least recently used beyond the budget
+    fn finish_read(&mut self, ch_ix: ChIx, ix_range: sample::IxRange, result: Result<BufferE>) {
+        self.reading = None;
+        let overlaps = |failure: &Failure| {
+            failure.ch_ix == ch_ix
+                && failure.ix_range.start < ix_range.end
+                && ix_range.start < failure.ix_range.end
+        };
+        let buffer = match result {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                tracing::warn!("Failed to read streamed samples: {err:#}");
+                let nr_failures = self
+                    .failed
+                    .iter()
+                    .filter(|failure| overlaps(failure))
+                    .map(|failure| failure.nr_failures)
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                self.failed.retain(|failure| !overlaps(failure));
+                let shift = (nr_failures - 1).min(MAX_RETRY_SHIFT);
+                self.failed.push(Failure {
+                    ch_ix,
+                    ix_range,
+                    nr_failures,
+                    retry_at: self.nr_uses + (RETRY_AFTER_NR_USES << shift),
+                });
+                return;
+            }
+        };
+        self.failed.retain(|failure| !overlaps(failure));
+        self.nr_uses += 1;
+        self.nr_reads += 1;
+        self.windows.push(Window {
+            ch_ix,
+            ix_range,
+            buffer: Arc::new(buffer),
+            last_use: self.nr_uses,
+        });
+        let size = |windows: &[Window]| {
+            windows
+                .iter()
+                .map(|window| window.buffer.size_in_bytes())
+                .sum::<usize>()
+        };
+        while self.windows.len() > 1 && size(&self.windows) > WINDOW_BUDGET {
+            let Some(lru_ix) = self
+                .windows
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, window)| window.last_use)
+                .map(|(ix, _)| ix)
+            else {
+                break;
+            };
+            self.windows.swap_remove(lru_ix);
+        }
+    }
+}
+
+/// Where the samples of a stream source are read from, shared with its worker
+#[derive(Debug, Clone)]
+struct StreamFile {
+    path: PathBuf,
+    /// Samples of the file that are streamed, index 0 of the streamed buffers is its start
+    sample_range: sample::IxRange,
+}
+
+impl StreamFile {
+    fn read_window(&self, ch_ix: ChIx, ix_range: sample::IxRange) -> Result<BufferE> {
+        let mut reader = open_wav_file(&self.path)?;
+        let options = ReadOptions {
+            ch_ixs: Some(vec![ch_ix]),
+            sample_range: sample::OptIxRange {
+                start: Some(self.sample_range.start + ix_range.start),
+                end: Some(self.sample_range.start + ix_range.end),
+            },
+            stream: false,
+        };
+        read_buffers_e(&mut reader, &options, None)?
+            .remove(&ch_ix)
+            .with_context(|| format!("Channel {ch_ix} not read from {}", self.path.display()))
+    }
+
+    /// Read the queued requests one by one until nothing is queued
+    fn read_queued(&self, windows: &Mutex<Windows>) {
+        let lock = || windows.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            // Not a `while let`, that would hold the lock while reading
+            let Some((ch_ix, ix_range)) = lock().next_read() else {
+                break;
+            };
+            let result = self.read_window(ch_ix, ix_range);
+            lock().finish_read(ch_ix, ix_range, result);
+        }
+    }
+}
+
+/// The part of a file on disk that is streamed, shared by its streamed channels.
+///
+/// Samples are read by one worker thread per source, started on the first request and stopped
+/// when the source is dropped.
+#[derive(Debug)]
+pub struct StreamSource {
+    file: StreamFile,
+    windows: Arc<Mutex<Windows>>,
+    /// Wakes the worker when a read is queued
+    worker: OnceLock<Sender<()>>,
+}
+
+impl StreamSource {
+    fn new(path: PathBuf, sample_range: sample::IxRange) -> Self {
+        Self {
+            file: StreamFile { path, sample_range },
+            windows: Arc::default(),
+            worker: OnceLock::new(),
+        }
+    }
+
+    pub fn nr_samples(&self) -> usize {
+        self.file.sample_range.len().max(0) as usize
+    }
+
+    fn windows(&self) -> std::sync::MutexGuard<'_, Windows> {
+        self.windows.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Buffer of `ch_ix` holding the samples of `ix_range`, with the index of its first sample.
+    /// None while they are read from disk, together with the samples around them.
+    pub fn read(
+        &self,
+        ch_ix: ChIx,
+        ix_range: sample::IxRange,
+    ) -> Option<(Arc<BufferE>, sample::Ix)> {
+        let nr_samples = self.nr_samples() as sample::Ix;
+        let start = ix_range.start.clamp(0, nr_samples);
+        let end = ix_range.end.clamp(start, nr_samples);
+        let ix_range = sample::IxRange { start, end };
+        let mut windows = self.windows();
+        windows.nr_uses += 1;
+        let nr_uses = windows.nr_uses;
+        if let Some(window) = windows
+            .windows
+            .iter_mut()
+            .find(|window| window.holds(ch_ix, ix_range))
+        {
+            window.last_use = nr_uses;
+            return Some((window.buffer.clone(), window.ix_range.start));
+        }
+        if windows.is_requested(ch_ix, ix_range) {
+            return None;
+        }
+        // Read one visible range more on both sides, so panning doesn't read every frame
+        let margin = end - start;
+        let read_range = sample::IxRange {
+            start: (start - margin).max(0),
+            end: (end + margin).min(nr_samples),
+        };
+        windows.queue(ch_ix, read_range);
+        drop(windows);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let worker = self.worker.get_or_init(|| {
+                let (tx, rx) = std::sync::mpsc::channel::<()>();
+                let file = self.file.clone();
+                let windows = self.windows.clone();
+                // Stops when the source, holding the sender, is dropped
+                std::thread::spawn(move || {
+                    while rx.recv().is_ok() {
+                        file.read_queued(&windows);
+                    }
+                });
+                tx
+            });
+            let _ = worker.send(());
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.file.read_queued(&self.windows);
+        None
+    }
+
+    /// Normalized value of a sample of `ch_ix`, only when it was read
+    pub fn read_value(&self, ch_ix: ChIx, sample_ix: usize) -> Option<f64> {
+        let sample_ix = sample_ix as sample::Ix;
+        let windows = self.windows();
+        let window = windows.windows.iter().find(|window| {
+            window.holds(
+                ch_ix,
+                sample::IxRange {
+                    start: sample_ix,
+                    end: sample_ix + 1,
+                },
+            )
+        })?;
+        window
+            .buffer
+            .norm_value(usize::try_from(sample_ix - window.ix_range.start).ok()?)
+    }
+
+    /// Whether samples are being read from disk or wait to be read
+    pub fn is_reading(&self) -> bool {
+        let windows = self.windows();
+        windows.reading.is_some() || !windows.queued.is_empty()
+    }
+
+    /// Number of reads that finished
+    pub fn nr_reads(&self) -> u64 {
+        self.windows().nr_reads
+    }
+
+    /// Memory held by the samples that were read
+    pub fn size_in_bytes(&self) -> usize {
+        self.windows()
+            .windows
+            .iter()
+            .map(|window| window.buffer.size_in_bytes())
+            .sum()
+    }
+}
+
+/// One channel of a streamed file
+#[derive(Debug, Clone)]
+pub struct StreamedBuffer {
+    pub source: Arc<StreamSource>,
+    pub ch_ix: ChIx,
+}
+
+impl StreamedBuffer {
+    pub fn nr_samples(&self) -> usize {
+        self.source.nr_samples()
+    }
+
+    pub fn read(&self, ix_range: sample::IxRange) -> Option<(Arc<BufferE>, sample::Ix)> {
+        self.source.read(self.ch_ix, ix_range)
+    }
+
+    pub fn read_value(&self, sample_ix: usize) -> Option<f64> {
+        self.source.read_value(self.ch_ix, sample_ix)
+    }
+}
+
+/// A streamed channel with what is kept of it in memory
+#[derive(Debug, Clone)]
+pub struct StreamedChannel {
+    pub buffer: StreamedBuffer,
+    /// Sample rate, bit depth and sample type of the channel, without samples
+    pub format: BufferE,
+    pub thumbnail: ThumbnailE,
+}
+
+/// Build the thumbnails of the selected channels in one pass over the file, without keeping its
+/// samples in memory
+pub(crate) fn stream_channels(
+    filepath: &std::path::Path,
+    options: &ReadOptions,
+    progress: Option<&LoadProgressAtomic>,
+) -> Result<BTreeMap<ChIx, StreamedChannel>> {
+    let mut reader = open_wav_file(filepath)?;
+    let duration = reader.duration() as sample::Ix;
+    let sample_range = options.sample_range.to_ix_range(0, duration);
+    ensure!(
+        0 <= sample_range.start && sample_range.start <= sample_range.end,
+        "invalid sample range {sample_range:?}"
+    );
+    ensure!(
+        sample_range.end <= duration,
+        "sample range end {} is larger than file duration {duration}",
+        sample_range.end,
+    );
+    let ch_ixs = options
+        .ch_ixs
+        .clone()
+        .unwrap_or_else(|| (0..reader.spec().channels as ChIx).collect());
+
+    if let Some(progress) = progress {
+        progress.set_stage(LoadStage::Thumbnail, sample_range.len() as u64);
+    }
+    let config = ThumbnailConfig {
+        samples_per_pixel_delta: THUMBNAIL_SAMPLES_PER_PIXEL,
+        ..ThumbnailConfig::default()
+    };
+    let mut channels: BTreeMap<ChIx, (BufferE, ThumbnailBuilderE)> = BTreeMap::new();
+    let mut block_start = sample_range.start;
+    while block_start < sample_range.end {
+        let block_end = (block_start + BLOCK_LEN).min(sample_range.end);
+        let block_options = ReadOptions {
+            ch_ixs: Some(ch_ixs.clone()),
+            sample_range: sample::OptIxRange {
+                start: Some(block_start),
+                end: Some(block_end),
+            },
+            stream: false,
+        };
+        for (ch_ix, buffer) in read_buffers_e(&mut reader, &block_options, None)? {
+            let (_, builder) = channels.entry(ch_ix).or_insert_with(|| {
+                (
+                    buffer.without_samples(),
+                    ThumbnailBuilderE::like_buffer_e(&buffer, config.clone()),
+                )
+            });
+            builder.push(&buffer)?;
+        }
+        block_start = block_end;
+        if let Some(progress) = progress {
+            progress.set_current((block_start - sample_range.start) as u64);
+        }
+    }
+
+    let source = Arc::new(StreamSource::new(filepath.to_path_buf(), sample_range));
+    Ok(channels
+        .into_iter()
+        .map(|(ch_ix, (format, builder))| {
+            let channel = StreamedChannel {
+                buffer: StreamedBuffer {
+                    source: source.clone(),
+                    ch_ix,
+                },
+                format,
+                thumbnail: builder.finish(),
+            };
+            (ch_ix, channel)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::Buffer;
+
+    #[test]
+    fn streamed_file_reads_only_the_visible_samples() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = std::env::temp_dir().join(format!("wavalyze_stream_{}.wav", std::process::id()));
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for ix in 0..(BLOCK_LEN as i32 + 100) {
+            writer.write_sample((ix % 1000) as i16).unwrap();
+            writer.write_sample(-((ix % 1000) as i16)).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let config = ReadConfig::new(&path).with_sample_range(sample::OptIxRange {
+            start: Some(10),
+            end: None,
+        });
+        let info = crate::wav::read::read_file_info(&path).unwrap();
+        assert_eq!(
+            in_memory_size(&info, &config),
+            2 * 2 * (BLOCK_LEN as u64 + 90)
+        );
+        let channels = stream_channels(&path, &ReadOptions::from(&config), None).unwrap();
+        let channel = &channels[&1];
+        let visible = sample::IxRange {
+            start: 100,
+            end: 110,
+        };
+        assert!(channel.buffer.read(visible).is_none());
+        while channel.buffer.source.is_reading() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let (buffer, start) = channel.buffer.read(visible).unwrap();
+        // another channel at another position doesn't replace what was read
+        assert!(
+            channels[&0]
+                .buffer
+                .read(sample::IxRange {
+                    start: 100_000,
+                    end: 100_010,
+                })
+                .is_none()
+        );
+        while channel.buffer.source.is_reading() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(channel.buffer.nr_samples(), BLOCK_LEN as usize + 90);
+        assert_eq!(channel.format, BufferE::I16(Buffer::new(48_000, 16)));
+        assert_eq!(
+            channel.thumbnail.get_smallest_samples_per_pixel(),
+            Some(THUMBNAIL_SAMPLES_PER_PIXEL)
+        );
+        // the visible range and as much on both sides, of the requested channel only
+        assert_eq!(start, 90);
+        let BufferE::I16(buffer) = buffer.as_ref() else {
+            panic!("expected i16 samples");
+        };
+        assert_eq!(buffer.data, (100..130).map(|ix| -ix).collect::<Vec<i16>>());
+        assert_eq!(channel.buffer.source.size_in_bytes(), 2 * 30 + 2 * 30);
+        assert!(channel.buffer.read(visible).is_some());
+        assert_eq!(channel.buffer.read_value(90), Some(-100.0 / 32768.0));
+        assert_eq!(channel.buffer.read_value(200), None);
+        // sample 100_010 of the file
+        assert_eq!(
+            channels[&0].buffer.read_value(100_000),
+            Some(10.0 / 32768.0)
+        );
+    }
+    fn range(start: sample::Ix, end: sample::Ix) -> sample::IxRange {
+        sample::IxRange { start, end }
+    }
+
+    #[test]
+    fn later_request_of_a_channel_replaces_its_queued_read() {
+        let mut windows = Windows::default();
+        windows.queue(0, range(0, 10));
+        windows.queue(1, range(0, 10));
+        windows.queue(0, range(50, 60));
+
+        assert!(!windows.is_requested(0, range(0, 10)));
+        assert_eq!(windows.next_read(), Some((1, range(0, 10))));
+        assert!(windows.is_requested(1, range(2, 8)));
+        assert_eq!(windows.next_read(), Some((0, range(50, 60))));
+        assert_eq!(windows.next_read(), None);
+    }
+
+    #[test]
+    fn failed_read_is_tried_again_after_a_growing_backoff() {
+        let mut windows = Windows::default();
+        let fail = |windows: &mut Windows| {
+            windows.queue(0, range(0, 10));
+            windows.next_read().unwrap();
+            windows.finish_read(0, range(0, 10), Err(anyhow::anyhow!("file is gone")));
+        };
+        fail(&mut windows);
+        assert!(windows.is_requested(0, range(2, 8)));
+        windows.nr_uses += RETRY_AFTER_NR_USES;
+        assert!(!windows.is_requested(0, range(2, 8)));
+
+        fail(&mut windows);
+        windows.nr_uses += RETRY_AFTER_NR_USES;
+        assert!(windows.is_requested(0, range(2, 8)));
+        windows.nr_uses += RETRY_AFTER_NR_USES;
+        assert!(!windows.is_requested(0, range(2, 8)));
+
+        windows.queue(0, range(0, 10));
+        windows.next_read().unwrap();
+        windows.finish_read(0, range(0, 10), Ok(BufferE::I16(Buffer::new(48_000, 16))));
+        assert!(windows.failed.is_empty());
+        assert_eq!(windows.nr_reads, 1);
+    }
+}
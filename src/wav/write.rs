@@ -0,0 +1,393 @@
+//! Write sample ranges of buffers to a RIFF/WAVE file, in the format of the source or converted.
+
+use crate::{
+    audio::{
+        SampleType,
+        buffer::BufferE,
+        sample::convert::{RequantizeConfig, Requantizer},
+    },
+    wav::chunk,
+};
+use anyhow::{Context, Result, ensure};
+use std::{
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const FACT: chunk::FourCC = *b"fact";
+
+/// Number of samples per channel converted and written at once
+const BLOCK_SIZE: usize = 1 << 14;
+
+/// Sample formats a file can be written in, ordered from least to most precise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum WavFormat {
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+}
+
+impl WavFormat {
+    pub const ALL: [WavFormat; 5] = [
+        WavFormat::Int16,
+        WavFormat::Int24,
+        WavFormat::Int32,
+        WavFormat::Float32,
+        WavFormat::Float64,
+    ];
+
+    /// The format that holds the samples of `buffer` without loss
+    pub fn of_buffer(buffer: &BufferE) -> Self {
+        match buffer {
+            BufferE::F32(_) => WavFormat::Float32,
+            BufferE::F64(_) => WavFormat::Float64,
+            BufferE::I16(_) => WavFormat::Int16,
+            BufferE::I32(buffer) if buffer.bit_depth <= 24 => WavFormat::Int24,
+            BufferE::I32(_) => WavFormat::Int32,
+        }
+    }
+
+    pub fn sample_type(self) -> SampleType {
+        match self {
+            WavFormat::Int16 | WavFormat::Int24 | WavFormat::Int32 => SampleType::Int,
+            WavFormat::Float32 | WavFormat::Float64 => SampleType::Float,
+        }
+    }
+
+    pub fn bit_depth(self) -> u16 {
+        match self {
+            WavFormat::Int16 => 16,
+            WavFormat::Int24 => 24,
+            WavFormat::Int32 | WavFormat::Float32 => 32,
+            WavFormat::Float64 => 64,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WavFormat::Int16 => "16-bit int",
+            WavFormat::Int24 => "24-bit int",
+            WavFormat::Int32 => "32-bit int",
+            WavFormat::Float32 => "32-bit float",
+            WavFormat::Float64 => "64-bit float",
+        }
+    }
+
+    fn nr_bytes(self) -> u16 {
+        self.bit_depth() / 8
+    }
+
+    fn format_tag(self) -> u16 {
+        match self.sample_type() {
+            SampleType::Int => WAVE_FORMAT_PCM,
+            SampleType::Float => WAVE_FORMAT_IEEE_FLOAT,
+        }
+    }
+}
+
+/// One channel of the file: the samples in `ix_rng` of `buffer`, starting `offset` samples
+/// into the file. The channel is silent where the buffer doesn't cover the file.
+#[derive(Debug, Clone)]
+pub struct ChannelSource<'a> {
+    pub buffer: &'a BufferE,
+    pub ix_rng: Range<usize>,
+    pub offset: usize,
+}
+
+impl ChannelSource<'_> {
+    /// Normalized samples for the file sample indices `block`
+    fn values(&self, block: Range<usize>) -> Vec<f64> {
+        let mut values = vec![0.0; block.len()];
+        let start = block.start.max(self.offset);
+        let end = block.end.min(self.offset + self.ix_rng.len());
+        if start < end {
+            let buffer_start = self.ix_rng.start + start - self.offset;
+            let buffer_values = self
+                .buffer
+                .norm_values(buffer_start..buffer_start + end - start);
+            let values_start = start - block.start;
+            values[values_start..values_start + buffer_values.len()]
+                .copy_from_slice(&buffer_values);
+        }
+        values
+    }
+}
+
+/// Sample rate and sample format of the file to write
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WriteSpec {
+    pub sample_rate: u32,
+    pub format: WavFormat,
+    /// How samples are brought to an int format with a lower resolution than the source
+    pub requantize: RequantizeConfig,
+}
+
+/// Converts the normalized samples of one channel to the bytes of the file format
+struct Encoder {
+    format: WavFormat,
+    requantizer: Requantizer,
+}
+
+impl Encoder {
+    /// Samples that fit the format are written unchanged, only a reduction of the resolution
+    /// is dithered or noise shaped.
+    fn new(spec: &WriteSpec, source: &BufferE, ch_ix: usize) -> Self {
+        let reduces_resolution = match source {
+            BufferE::F32(_) | BufferE::F64(_) => true,
+            BufferE::I16(_) | BufferE::I32(_) => source.bit_depth() > spec.format.bit_depth(),
+        };
+        let config = if reduces_resolution {
+            spec.requantize
+        } else {
+            RequantizeConfig::default()
+        };
+        Self {
+            format: spec.format,
+            requantizer: Requantizer::new(spec.format.bit_depth() as u32, &config, ch_ix),
+        }
+    }
+
+    /// Append the normalized `value`
+    fn encode(&mut self, value: f64, bytes: &mut Vec<u8>) {
+        match self.format {
+            WavFormat::Int16 => {
+                bytes.extend_from_slice(&(self.requantizer.requantize(value) as i16).to_le_bytes())
+            }
+            WavFormat::Int24 => {
+                bytes.extend_from_slice(&self.requantizer.requantize(value).to_le_bytes()[..3])
+            }
+            WavFormat::Int32 => {
+                bytes.extend_from_slice(&self.requantizer.requantize(value).to_le_bytes())
+            }
+            WavFormat::Float32 => bytes.extend_from_slice(&(value as f32).to_le_bytes()),
+            WavFormat::Float64 => bytes.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+/// Write `nr_samples` samples per channel, interleaved in the order of `channels`
+pub fn write_wav_to<W: Write>(
+    writer: &mut W,
+    spec: &WriteSpec,
+    channels: &[ChannelSource<'_>],
+    nr_samples: usize,
+) -> Result<()> {
+    let WriteSpec {
+        sample_rate,
+        format,
+        ..
+    } = *spec;
+    ensure!(!channels.is_empty(), "No channels to write");
+    ensure!(
+        channels.len() <= u16::MAX as usize,
+        "Can't write {} channels",
+        channels.len()
+    );
+    let nr_channels = channels.len() as u16;
+    let block_align = nr_channels * format.nr_bytes();
+    let data_size = nr_samples as u64 * block_align as u64;
+    let is_float = format.sample_type() == SampleType::Float;
+    // Float files have a cbSize in `fmt ` and a `fact` chunk with the number of samples
+    let fmt_size: u32 = if is_float { 18 } else { 16 };
+    let fact_size: u32 = if is_float { 12 } else { 0 };
+    let riff_size = 4 + 8 + fmt_size as u64 + fact_size as u64 + 8 + data_size;
+    ensure!(
+        riff_size <= u32::MAX as u64,
+        "{data_size} bytes of samples don't fit in a wav file, export a shorter range"
+    );
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&chunk::RIFF);
+    header.extend_from_slice(&(riff_size as u32).to_le_bytes());
+    header.extend_from_slice(&chunk::WAVE);
+    header.extend_from_slice(&chunk::FMT);
+    header.extend_from_slice(&fmt_size.to_le_bytes());
+    header.extend_from_slice(&format.format_tag().to_le_bytes());
+    header.extend_from_slice(&nr_channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&format.bit_depth().to_le_bytes());
+    if is_float {
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&FACT);
+        header.extend_from_slice(&4u32.to_le_bytes());
+        header.extend_from_slice(&(nr_samples.min(u32::MAX as usize) as u32).to_le_bytes());
+    }
+    header.extend_from_slice(&chunk::DATA);
+    header.extend_from_slice(&(data_size as u32).to_le_bytes());
+    writer.write_all(&header)?;
+
+    let mut encoders: Vec<Encoder> = channels
+        .iter()
+        .enumerate()
+        .map(|(ch_ix, channel)| Encoder::new(spec, channel.buffer, ch_ix))
+        .collect();
+    let mut bytes = Vec::with_capacity(BLOCK_SIZE * block_align as usize);
+    for block_start in (0..nr_samples).step_by(BLOCK_SIZE) {
+        let block = block_start..(block_start + BLOCK_SIZE).min(nr_samples);
+        let values: Vec<Vec<f64>> = channels
+            .iter()
+            .map(|channel| channel.values(block.clone()))
+            .collect();
+        bytes.clear();
+        for sample_ix in 0..block.len() {
+            for (channel_values, encoder) in values.iter().zip(&mut encoders) {
+                encoder.encode(channel_values[sample_ix], &mut bytes);
+            }
+        }
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a wav file at `path`, see `write_wav_to`
+pub fn write_wav(
+    path: &Path,
+    spec: &WriteSpec,
+    channels: &[ChannelSource<'_>],
+    nr_samples: usize,
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create '{}'", path.display()))?;
+    write_wav_to(&mut BufWriter::new(file), spec, channels, nr_samples)
+        .with_context(|| format!("Failed to write '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::{
+            buffer::Buffer,
+            sample::convert::{NoiseShaping, Rounding},
+        },
+        wav::read::{ReadConfigBytes, read_bytes_to_loaded_file_with_progress},
+    };
+
+    fn write_to_bytes(
+        format: WavFormat,
+        channels: &[ChannelSource<'_>],
+        nr_samples: usize,
+    ) -> Vec<u8> {
+        let spec = WriteSpec {
+            sample_rate: 48_000,
+            format,
+            requantize: RequantizeConfig::default(),
+        };
+        let mut bytes = Vec::new();
+        write_wav_to(&mut bytes, &spec, channels, nr_samples).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn int24_samples_are_written_unchanged_with_silence_around() {
+        let mut buffer = Buffer::<i32>::new(48_000, 24);
+        buffer.data = vec![-8_388_608, -1, 0, 1, 8_388_607];
+        let buffer = BufferE::I32(buffer);
+        let channel = ChannelSource {
+            buffer: &buffer,
+            ix_rng: 1..5,
+            offset: 2,
+        };
+
+        let bytes = write_to_bytes(WavFormat::of_buffer(&buffer), &[channel], 8);
+        let config = ReadConfigBytes::new(None, bytes);
+        let loaded = read_bytes_to_loaded_file_with_progress(&config, 0, None).unwrap();
+
+        assert_eq!(loaded.bit_depth, 24);
+        assert_eq!(loaded.nr_samples, 8);
+        let BufferE::I32(read) = &loaded.channels[&0] else {
+            panic!("expected i32 samples");
+        };
+        assert_eq!(read.data, [0, 0, -1, 0, 1, 8_388_607, 0, 0]);
+    }
+
+    #[test]
+    fn float_is_converted_to_int16_rounded_and_clipped() {
+        let mut left = Buffer::<f32>::new(48_000, 32);
+        left.data = vec![0.5, -1.0, 1.0, 2.0];
+        let mut right = Buffer::<f64>::new(48_000, 64);
+        right.data = vec![1.0 / 65536.0, -3.0 / 65536.0, -2.0, 0.0];
+        let (left, right) = (BufferE::F32(left), BufferE::F64(right));
+        let channels = [&right, &left].map(|buffer| ChannelSource {
+            buffer,
+            ix_rng: 0..4,
+            offset: 0,
+        });
+
+        let bytes = write_to_bytes(WavFormat::Int16, &channels, 4);
+        let config = ReadConfigBytes::new(None, bytes);
+        let loaded = read_bytes_to_loaded_file_with_progress(&config, 0, None).unwrap();
+
+        let samples = |ch_ix| match &loaded.channels[&ch_ix] {
+            BufferE::I16(buffer) => buffer.data.clone(),
+            _ => panic!("expected i16 samples"),
+        };
+        assert_eq!(samples(0), [1, -2, -32768, 0]);
+        assert_eq!(samples(1), [16384, -32768, 32767, 32767]);
+    }
+
+    #[test]
+    fn dither_only_applies_when_the_resolution_is_reduced() {
+        let mut pcm = Buffer::<i16>::new(48_000, 16);
+        pcm.data = vec![3; 64];
+        let mut float = Buffer::<f32>::new(48_000, 32);
+        float.data = vec![3.0 / 32768.0; 64];
+        let (pcm, float) = (BufferE::I16(pcm), BufferE::F32(float));
+        let channels = [&pcm, &float].map(|buffer| ChannelSource {
+            buffer,
+            ix_rng: 0..64,
+            offset: 0,
+        });
+        let spec = WriteSpec {
+            sample_rate: 48_000,
+            format: WavFormat::Int16,
+            requantize: RequantizeConfig {
+                rounding: Rounding::Tpdf,
+                noise_shaping: NoiseShaping::SecondOrder,
+                seed: 5,
+            },
+        };
+        let mut bytes = Vec::new();
+        write_wav_to(&mut bytes, &spec, &channels, 64).unwrap();
+        let config = ReadConfigBytes::new(None, bytes);
+        let loaded = read_bytes_to_loaded_file_with_progress(&config, 0, None).unwrap();
+
+        let samples = |ch_ix| match &loaded.channels[&ch_ix] {
+            BufferE::I16(buffer) => buffer.data.clone(),
+            _ => panic!("expected i16 samples"),
+        };
+        assert_eq!(samples(0), vec![3; 64]);
+        let dithered = samples(1);
+        assert_ne!(dithered, vec![3; 64]);
+        let mean = dithered.iter().map(|&value| value as f64).sum::<f64>() / 64.0;
+        assert!((mean - 3.0).abs() < 0.2, "mean {mean}");
+    }
+
+    #[test]
+    fn float64_is_written_with_fact_chunk() {
+        let mut buffer = Buffer::<f64>::new(48_000, 64);
+        buffer.data = vec![0.1, -0.2, 1e-12];
+        let buffer = BufferE::F64(buffer);
+        let channel = ChannelSource {
+            buffer: &buffer,
+            ix_rng: 0..3,
+            offset: 0,
+        };
+
+        let bytes = write_to_bytes(WavFormat::Float64, &[channel], 3);
+        let headers = chunk::read_chunk_headers(&mut std::io::Cursor::new(&bytes)).unwrap();
+        assert!(headers.iter().any(|header| header.id == FACT));
+        let config = ReadConfigBytes::new(None, bytes);
+        let loaded = read_bytes_to_loaded_file_with_progress(&config, 0, None).unwrap();
+
+        assert_eq!(loaded.channels[&0], buffer);
+    }
+}